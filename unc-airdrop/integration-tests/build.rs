@@ -0,0 +1,19 @@
+use std::path::Path;
+
+static CONTRACT_DIR: &str = "..";
+
+fn main() {
+    let contract_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(CONTRACT_DIR);
+    // Tell Cargo to rerun this script if the contract source changes.
+    println!("cargo:rerun-if-changed={}", contract_dir.join("src").to_str().expect("valid UTF-8 path"));
+    // Run build.sh. The flags and the wrapper (e.g. clippy-driver) Cargo passes to build scripts would
+    // override those of build.sh and change the committed res/airdrop.wasm.
+    let status = std::process::Command::new("bash")
+        .arg("build.sh")
+        .current_dir(&contract_dir)
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .env_remove("RUSTC_WORKSPACE_WRAPPER")
+        .status()
+        .expect("failed to execute build.sh");
+    assert!(status.success());
+}
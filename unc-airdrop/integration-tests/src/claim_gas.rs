@@ -0,0 +1,65 @@
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use anyhow::Result;
+use common::gas::call_gas_burnt;
+use common::{claimed_amount, init_contract_and_drop};
+use serde_json::json;
+use unc_workspaces::types::{Gas, KeyType, SecretKey, UncToken};
+
+/// Gas a typical wallet attaches to a function call.
+const WALLET_GAS: Gas = Gas::from_tgas(30);
+
+/// Static gas the account creation callbacks used to be attached with.
+const OLD_ON_CREATE_ACCOUNT_CALLBACK_GAS: Gas = Gas::from_tgas(13);
+
+/// Tests the gas attached to the account creation callbacks.
+#[tokio::main]
+async fn main() -> Result<()> {
+    test_create_account_and_claim_with_wallet_gas().await?;
+    test_create_account_and_claim_below_old_callback_gas().await?;
+    Ok(())
+}
+
+/// `create_account_and_claim` succeeds with the gas a wallet attaches by default.
+async fn test_create_account_and_claim_with_wallet_gas() -> Result<()> {
+    let (worker, contract, claimer) = init_contract_and_drop().await?;
+    let new_account_id = format!("bob.{}", contract.id());
+
+    let claimed = claimed_amount(claimer
+        .call(contract.id(), "create_account_and_claim")
+        .args_json(json!({
+            "new_account_id": new_account_id,
+            "new_public_key": SecretKey::from_random(KeyType::ED25519).public_key().to_string(),
+        }))
+        .gas(WALLET_GAS)
+        .transact()
+        .await?)?;
+    assert!(!claimed.is_zero());
+
+    let account = worker.view_account(&new_account_id.parse()?).await?;
+    assert!(account.balance > UncToken::from_attounc(0));
+
+    Ok(())
+}
+
+/// `create_account_and_claim` succeeds with less gas than the call itself burns plus the old
+/// static callback gas, so the old static value would have failed with the same gas.
+async fn test_create_account_and_claim_below_old_callback_gas() -> Result<()> {
+    let (_, contract, claimer) = init_contract_and_drop().await?;
+    let prepaid_gas = Gas::from_tgas(15);
+
+    let result = claimer
+        .call(contract.id(), "create_account_and_claim")
+        .args_json(json!({
+            "new_account_id": format!("bob.{}", contract.id()),
+            "new_public_key": SecretKey::from_random(KeyType::ED25519).public_key().to_string(),
+        }))
+        .gas(prepaid_gas)
+        .transact()
+        .await?;
+    assert!(call_gas_burnt(&result).saturating_add(OLD_ON_CREATE_ACCOUNT_CALLBACK_GAS) > prepaid_gas);
+    assert!(!claimed_amount(result)?.is_zero());
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//! Gas and storage benchmarks of the main methods. They are ignored by default, run them with
+//! `cargo test --test bench -- --ignored --nocapture` to print the numbers.
+//! The ceilings are generous estimates. Once a run has printed the numbers, tighten each one to about 20%
+//! above its measured value, so that a regression fails the run.
+mod common;
+
+use anyhow::Result;
+use common::gas::{assert_gas_below, total_gas_burnt};
+use common::{create_account, fund_drop_key, init_contract, init_contract_and_drop, DROP_BALANCE};
+use serde_json::json;
+use unc_workspaces::types::{Gas, KeyType, SecretKey, UncToken};
+use unc_workspaces::{Account, Contract};
+
+/// Deposit attached to `create_account_advanced`, enough for the storage of a 50 KB contract.
+const CREATE_ACCOUNT_DEPOSIT: UncToken = UncToken::from_unc(2);
+
+/// Size of the contract deployed by `create_account_advanced`.
+const CONTRACT_BYTES: usize = 50 * 1024;
+
+/// Storage a funded key may take in the contract, with its access key.
+const MAX_STORAGE_BYTES_PER_KEY: u64 = 1_500;
+
+fn random_public_key() -> String {
+    SecretKey::from_random(KeyType::ED25519).public_key().to_string()
+}
+
+/// Returns a valid wasm module of `size` bytes: an empty module padded with a custom section.
+fn padded_contract(size: usize) -> Vec<u8> {
+    // Header, custom section id, 3 bytes of section size, 1 byte of name length and the name
+    let payload_size = size - 8 - 1 - 3;
+    let mut wasm = b"\0asm\x01\0\0\0\0".to_vec();
+    // Padded LEB128, so that the size always takes 3 bytes
+    wasm.extend([
+        (payload_size & 0x7f) as u8 | 0x80,
+        ((payload_size >> 7) & 0x7f) as u8 | 0x80,
+        (payload_size >> 14) as u8,
+    ]);
+    wasm.extend([1, b'p']);
+    wasm.resize(size, 0);
+    wasm
+}
+
+/// Calls `create_account_advanced` from `funder` and returns the gas it burnt.
+async fn create_account_advanced_gas(
+    contract: &Contract,
+    funder: &Account,
+    name: &str,
+    options: serde_json::Value,
+) -> Result<Gas> {
+    let result = funder
+        .call(contract.id(), "create_account_advanced")
+        .args_json(json!({ "new_account_id": format!("{}.{}", name, contract.id()), "options": options }))
+        .deposit(CREATE_ACCOUNT_DEPOSIT)
+        .max_gas()
+        .transact()
+        .await?;
+    let gas = total_gas_burnt(&result)?;
+    assert!(result.json::<bool>()?, "{} wasn't created", name);
+    Ok(gas)
+}
+
+/// Funds `public_key` from the contract account and returns the gas burnt.
+async fn send_gas(contract: &Contract, public_key: &str) -> Result<Gas> {
+    let result = contract
+        .call("send")
+        .args_json(json!({ "public_key": public_key }))
+        .deposit(DROP_BALANCE)
+        .transact()
+        .await?;
+    total_gas_burnt(&result)
+}
+
+#[tokio::test]
+#[ignore]
+async fn bench_send() -> Result<()> {
+    let (_, contract) = init_contract().await?;
+    let public_key = random_public_key();
+    let storage_before = contract.view_account().await?.storage_usage;
+
+    assert_gas_below("send of a new key", send_gas(&contract, &public_key).await?, Gas::from_tgas(20));
+    let storage_per_key = contract.view_account().await?.storage_usage - storage_before;
+    println!("storage per key: {} bytes (ceiling {})", storage_per_key, MAX_STORAGE_BYTES_PER_KEY);
+    assert!(storage_per_key <= MAX_STORAGE_BYTES_PER_KEY);
+
+    assert_gas_below("send top-up", send_gas(&contract, &public_key).await?, Gas::from_tgas(15));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn bench_claim() -> Result<()> {
+    let (worker, contract, claimer) = init_contract_and_drop().await?;
+    let receiver = create_account(&worker, "receiver", UncToken::from_unc(1)).await?;
+    let result = claimer
+        .call(contract.id(), "claim")
+        .args_json(json!({ "account_id": receiver.id() }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert_gas_below("claim", total_gas_burnt(&result)?, Gas::from_tgas(30));
+
+    let claimer = fund_drop_key(&worker, &contract).await?;
+    let result = claimer
+        .call(contract.id(), "create_account_and_claim")
+        .args_json(json!({
+            "new_account_id": format!("bob.{}", contract.id()),
+            "new_public_key": random_public_key(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert_gas_below("create_account_and_claim", total_gas_burnt(&result)?, Gas::from_tgas(50));
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore]
+async fn bench_create_account_advanced() -> Result<()> {
+    let (worker, contract) = init_contract().await?;
+    let funder = create_account(&worker, "funder", UncToken::from_unc(20)).await?;
+
+    for (keys, ceiling) in [(1, 50), (5, 60), (20, 100)] {
+        let full_access_keys: Vec<String> = (0..keys).map(|_| random_public_key()).collect();
+        let gas = create_account_advanced_gas(
+            &contract,
+            &funder,
+            &format!("keys{}", keys),
+            json!({ "full_access_keys": full_access_keys }),
+        )
+        .await?;
+        assert_gas_below(&format!("create_account_advanced with {} keys", keys), gas, Gas::from_tgas(ceiling));
+    }
+
+    let gas = create_account_advanced_gas(
+        &contract,
+        &funder,
+        "code",
+        json!({ "full_access_keys": [random_public_key()], "contract_bytes": padded_contract(CONTRACT_BYTES) }),
+    )
+    .await?;
+    assert_gas_below("create_account_advanced with a 50 KB contract", gas, Gas::from_tgas(150));
+    Ok(())
+}
@@ -0,0 +1,297 @@
+//! Failure branches of the callbacks, which need the account creation or the transfer to actually fail.
+mod common;
+
+use anyhow::Result;
+use common::{claimed_amount, create_account, contract_wasm, init_contract, init_contract_and_drop, key_balance};
+use serde_json::json;
+use unc_workspaces::types::{KeyType, SecretKey, UncToken};
+
+/// Number of times the owner can retry a failed `create_account_advanced`, see MAX_CREATE_ACCOUNT_RETRIES.
+const MAX_CREATE_ACCOUNT_RETRIES: u32 = 3;
+
+/// Upper bound of the gas fees paid by the transactions of a test.
+const MAX_FEES: UncToken = UncToken::from_milliunc(100);
+
+fn random_public_key() -> String {
+    SecretKey::from_random(KeyType::ED25519).public_key().to_string()
+}
+
+/// `create_account_and_claim` to an existing account fails the creation, which puts the balance back on the key.
+#[tokio::test]
+async fn test_create_account_and_claim_existing_account() -> Result<()> {
+    let (_, contract, claimer) = init_contract_and_drop().await?;
+    let existing = contract
+        .as_account()
+        .create_subaccount("bob")
+        .initial_balance(UncToken::from_unc(1))
+        .transact()
+        .await?
+        .into_result()?;
+    let existing_balance = existing.view_account().await?.balance;
+    let balance = key_balance(&contract, &claimer).await?;
+
+    let claimed = claimed_amount(claimer
+        .call(contract.id(), "create_account_and_claim")
+        .args_json(json!({
+            "new_account_id": existing.id(),
+            "new_public_key": random_public_key(),
+        }))
+        .max_gas()
+        .transact()
+        .await?)?;
+    assert!(claimed.is_zero());
+
+    assert_eq!(key_balance(&contract, &claimer).await?, balance);
+    assert_eq!(existing.view_account().await?.balance, existing_balance);
+    Ok(())
+}
+
+/// `create_account_and_claim` retried with a new name after the first name was taken: the allowance of the key
+/// covers both attempts.
+#[tokio::test]
+async fn test_create_account_and_claim_retry_with_new_name() -> Result<()> {
+    let (worker, contract, claimer) = init_contract_and_drop().await?;
+    let existing = contract
+        .as_account()
+        .create_subaccount("bob")
+        .initial_balance(UncToken::from_unc(1))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let claimed = claimed_amount(claimer
+        .call(contract.id(), "create_account_and_claim")
+        .args_json(json!({
+            "new_account_id": existing.id(),
+            "new_public_key": random_public_key(),
+        }))
+        .max_gas()
+        .transact()
+        .await?)?;
+    assert!(claimed.is_zero());
+    let info = contract
+        .view("get_key_information")
+        .args_json(json!({ "key": claimer.secret_key().public_key().to_string() }))
+        .await?
+        .json::<serde_json::Value>()?;
+    assert_eq!(info["remaining_attempts"], 1);
+
+    let new_account_id = format!("alice.{}", contract.id());
+    let claimed = claimed_amount(claimer
+        .call(contract.id(), "create_account_and_claim")
+        .args_json(json!({
+            "new_account_id": new_account_id,
+            "new_public_key": random_public_key(),
+        }))
+        .max_gas()
+        .transact()
+        .await?)?;
+    assert!(!claimed.is_zero());
+    assert!(worker.view_account(&new_account_id.parse()?).await.is_ok());
+    Ok(())
+}
+
+/// `create_account` of an existing account fails the creation, which refunds the funder.
+#[tokio::test]
+async fn test_create_account_existing_account() -> Result<()> {
+    let (worker, contract) = init_contract().await?;
+    let funder = create_account(&worker, "funder", UncToken::from_unc(20)).await?;
+    let existing = contract
+        .as_account()
+        .create_subaccount("bob")
+        .initial_balance(UncToken::from_unc(1))
+        .transact()
+        .await?
+        .into_result()?;
+    let funder_balance = funder.view_account().await?.balance;
+
+    let creation = funder
+        .call(contract.id(), "create_account")
+        .args_json(json!({
+            "new_account_id": existing.id(),
+            "new_public_key": random_public_key(),
+        }))
+        .deposit(UncToken::from_unc(10))
+        .max_gas()
+        .transact()
+        .await?
+        .json::<serde_json::Value>()?;
+    assert_eq!(creation["success"], json!(false));
+    assert_eq!(creation["account_id"], json!(existing.id()));
+
+    let spent = funder_balance.saturating_sub(funder.view_account().await?.balance);
+    assert!(spent < MAX_FEES);
+    Ok(())
+}
+
+/// `claim` to an account deleted after the claim was signed: the transfer fails and its deposit is refunded to
+/// the contract, while the key is already removed since the callback of `claim` only reports it in its receipt.
+#[tokio::test]
+async fn test_claim_to_deleted_account() -> Result<()> {
+    let (worker, contract, claimer) = init_contract_and_drop().await?;
+    let receiver = create_account(&worker, "receiver", UncToken::from_unc(1)).await?;
+    let receiver_id = receiver.id().clone();
+    receiver.delete_account(worker.root_account()?.id()).await?.into_result()?;
+    let contract_balance = contract.view_account().await?.balance;
+
+    let claimed = claimer
+        .call(contract.id(), "claim")
+        .args_json(json!({ "account_id": receiver_id }))
+        .max_gas()
+        .transact()
+        .await?
+        .json::<serde_json::Value>()?;
+    assert_eq!(claimed["account_id"], receiver_id.as_str());
+    assert_eq!(claimed["amount"], "0");
+
+    assert!(worker.view_account(&receiver_id).await.is_err());
+    let key_exists = contract
+        .view("key_exists")
+        .args_json(json!({ "key": claimer.secret_key().public_key().to_string() }))
+        .await?
+        .json::<bool>()?;
+    assert!(!key_exists);
+    assert!(contract.view_account().await?.balance > contract_balance.saturating_sub(MAX_FEES));
+    Ok(())
+}
+
+/// `create_account_advanced` with a contract whose storage the deposit doesn't cover fails the creation,
+/// which is queued for a retry and refunded to the funder once the retries are exhausted.
+#[tokio::test]
+async fn test_create_account_advanced_deploy_above_deposit() -> Result<()> {
+    let (worker, contract) = init_contract().await?;
+    let funder = create_account(&worker, "funder", UncToken::from_unc(10)).await?;
+    let new_account_id = format!("bob.{}", contract.id());
+    let funder_balance = funder.view_account().await?.balance;
+    // The storage of the code takes more than 1 UNC
+    let deposit = UncToken::from_milliunc(500);
+
+    let created = funder
+        .call(contract.id(), "create_account_advanced")
+        .args_json(json!({
+            "new_account_id": new_account_id,
+            "options": {
+                "full_access_keys": [random_public_key()],
+                "contract_bytes": contract_wasm()?,
+            },
+        }))
+        .deposit(deposit)
+        .max_gas()
+        .transact()
+        .await?
+        .json::<bool>()?;
+    assert!(!created);
+    assert!(worker.view_account(&new_account_id.parse()?).await.is_err());
+    // The deposit is kept for the retries
+    let spent = funder_balance.saturating_sub(funder.view_account().await?.balance);
+    assert!(spent >= deposit);
+
+    for _ in 0..MAX_CREATE_ACCOUNT_RETRIES {
+        let created = contract
+            .call("retry_create_account")
+            .args_json(json!({ "account_id": new_account_id }))
+            .deposit(UncToken::from_attounc(1))
+            .max_gas()
+            .transact()
+            .await?
+            .json::<bool>()?;
+        assert!(!created);
+    }
+
+    let spent = funder_balance.saturating_sub(funder.view_account().await?.balance);
+    assert!(spent < MAX_FEES);
+    let retried = contract
+        .call("retry_create_account")
+        .args_json(json!({ "account_id": new_account_id }))
+        .deposit(UncToken::from_attounc(1))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(retried.is_failure());
+    Ok(())
+}
+
+/// Two `create_account_and_claim` submitted back-to-back with the same drop key: whether the second one lands
+/// while the first creation is in progress or after the key was removed, only one account gets the balance.
+#[tokio::test]
+async fn test_back_to_back_claims_pay_once() -> Result<()> {
+    let (worker, contract, claimer) = init_contract_and_drop().await?;
+    let new_account_ids = [format!("alice.{}", contract.id()), format!("bob.{}", contract.id())];
+
+    let mut claims = Vec::new();
+    for new_account_id in &new_account_ids {
+        let claim = claimer
+            .call(contract.id(), "create_account_and_claim")
+            .args_json(json!({
+                "new_account_id": new_account_id,
+                "new_public_key": random_public_key(),
+            }))
+            .max_gas()
+            .transact_async()
+            .await?;
+        claims.push(claim);
+    }
+    for claim in claims {
+        // The second claim either gets `ClaimInProgress` or fails once the access key is deleted
+        let _ = claim.await?;
+    }
+
+    let mut created = 0;
+    for new_account_id in &new_account_ids {
+        if worker.view_account(&new_account_id.parse()?).await.is_ok() {
+            created += 1;
+        }
+    }
+    assert_eq!(created, 1);
+    let key_exists = contract
+        .view("key_exists")
+        .args_json(json!({ "key": claimer.secret_key().public_key().to_string() }))
+        .await?
+        .json::<bool>()?;
+    assert!(!key_exists);
+    Ok(())
+}
+
+/// `create_accounts` with one account that already exists: the other accounts are created and only the amount
+/// of the failed creation is refunded to the funder.
+#[tokio::test]
+async fn test_create_accounts_partial_failure() -> Result<()> {
+    let (worker, contract) = init_contract().await?;
+    let funder = create_account(&worker, "funder", UncToken::from_unc(20)).await?;
+    let existing = contract
+        .as_account()
+        .create_subaccount("bob")
+        .initial_balance(UncToken::from_unc(1))
+        .transact()
+        .await?
+        .into_result()?;
+    let existing_balance = existing.view_account().await?.balance;
+    let new_account_ids = [format!("alice.{}", contract.id()), format!("carol.{}", contract.id())];
+    let funder_balance = funder.view_account().await?.balance;
+
+    let scheduled = funder
+        .call(contract.id(), "create_accounts")
+        .args_json(json!({
+            "requests": [
+                { "new_account_id": new_account_ids[0], "new_public_key": random_public_key(), "amount": UncToken::from_unc(2) },
+                { "new_account_id": existing.id(), "new_public_key": random_public_key(), "amount": UncToken::from_unc(5) },
+                { "new_account_id": new_account_ids[1], "new_public_key": random_public_key(), "amount": UncToken::from_unc(3) },
+            ],
+        }))
+        .deposit(UncToken::from_unc(10))
+        .max_gas()
+        .transact()
+        .await?
+        .json::<u64>()?;
+    assert_eq!(scheduled, 3);
+
+    for new_account_id in &new_account_ids {
+        assert!(worker.view_account(&new_account_id.parse()?).await.is_ok());
+    }
+    assert_eq!(existing.view_account().await?.balance, existing_balance);
+    // Only the amounts of the created accounts are spent
+    let spent = funder_balance.saturating_sub(funder.view_account().await?.balance);
+    assert!(spent >= UncToken::from_unc(5));
+    assert!(spent < UncToken::from_unc(5).saturating_add(MAX_FEES));
+    Ok(())
+}
@@ -0,0 +1,24 @@
+//! Gas accounting of the sandbox executions, shared by the benchmarks and the gas tests.
+
+use anyhow::{ensure, Result};
+use unc_workspaces::result::ExecutionFinalResult;
+use unc_workspaces::types::Gas;
+
+/// Returns the gas burnt by the whole execution, the transaction and all the receipts it generated, e.g. the
+/// callbacks. Fails if any of them failed, so that a failure isn't measured as a cheap call.
+pub fn total_gas_burnt(result: &ExecutionFinalResult) -> Result<Gas> {
+    ensure!(result.failures().is_empty(), "Execution failed: {:?}", result.failures());
+    Ok(result.total_gas_burnt)
+}
+
+/// Returns the gas burnt by the transaction and the receipt of the method call, without the receipts it
+/// generated.
+pub fn call_gas_burnt(result: &ExecutionFinalResult) -> Gas {
+    result.outcome().gas_burnt.saturating_add(result.receipt_outcomes()[0].gas_burnt)
+}
+
+/// Prints the gas of `name` and asserts it is below `ceiling`.
+pub fn assert_gas_below(name: &str, gas: Gas, ceiling: Gas) {
+    println!("{}: {:.2} Tgas (ceiling {} Tgas)", name, gas.as_gas() as f64 / 1e12, ceiling.as_tgas());
+    assert!(gas <= ceiling, "{} burnt {} gas, more than {}", name, gas.as_gas(), ceiling.as_gas());
+}
@@ -0,0 +1,83 @@
+//! Sandbox fixtures shared by the integration tests and examples.
+#![allow(dead_code)]
+
+pub mod gas;
+
+use anyhow::Result;
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use unc_workspaces::network::Sandbox;
+use unc_workspaces::types::{KeyType, SecretKey, UncToken};
+use unc_workspaces::result::ExecutionFinalResult;
+use unc_workspaces::{Account, Contract, Worker};
+
+static CONTRACT_WASM_FILEPATH: &str = "../res/airdrop.wasm";
+
+/// Balance the drop key is funded with by `init_contract_and_drop`.
+pub const DROP_BALANCE: UncToken = UncToken::from_unc(5);
+
+/// Reads the compiled contract.
+pub fn contract_wasm() -> Result<Vec<u8>> {
+    let wasm_filepath = Path::new(env!("CARGO_MANIFEST_DIR")).join(CONTRACT_WASM_FILEPATH);
+    Ok(fs::read(wasm_filepath)?)
+}
+
+/// Deploys and initializes the contract in a new sandbox. The contract account is the owner.
+pub async fn init_contract() -> Result<(Worker<Sandbox>, Contract)> {
+    // Create a sandboxed environment.
+    // NOTE: Each call will create a new sandboxed environment
+    let worker = unc_workspaces::sandbox().await?;
+    let contract = worker.dev_deploy(&contract_wasm()?).await?;
+    contract.call("new_default").transact().await?.into_result()?;
+    Ok((worker, contract))
+}
+
+/// Deploys the contract and funds a drop key with DROP_BALANCE, returning an account signing with the drop key.
+pub async fn init_contract_and_drop() -> Result<(Worker<Sandbox>, Contract, Account)> {
+    let (worker, contract) = init_contract().await?;
+    let claimer = fund_drop_key(&worker, &contract).await?;
+    Ok((worker, contract, claimer))
+}
+
+/// Funds a new drop key with DROP_BALANCE, returning an account signing with it.
+pub async fn fund_drop_key(worker: &Worker<Sandbox>, contract: &Contract) -> Result<Account> {
+    let drop_key = SecretKey::from_random(KeyType::ED25519);
+    contract
+        .as_account()
+        .call(contract.id(), "send")
+        .args_json(json!({
+            "public_key": drop_key.public_key().to_string(),
+        }))
+        .deposit(DROP_BALANCE)
+        .transact()
+        .await?
+        .into_result()?;
+    Ok(Account::from_secret_key(contract.id().clone(), drop_key, worker))
+}
+
+/// Returns the balance the contract holds for the key `claimer` signs with.
+pub async fn key_balance(contract: &Contract, claimer: &Account) -> Result<UncToken> {
+    Ok(contract
+        .view("get_key_balance")
+        .args_json(json!({ "key": claimer.secret_key().public_key().to_string() }))
+        .await?
+        .json::<UncToken>()?)
+}
+
+/// Returns the amount a claim resolved to, failing if it was rejected.
+pub fn claimed_amount(result: ExecutionFinalResult) -> Result<UncToken> {
+    let outcome = result.json::<serde_json::Value>()?;
+    Ok(serde_json::from_value(outcome["Claimed"].clone())?)
+}
+
+/// Creates an account funded with `balance` under the root account.
+pub async fn create_account(worker: &Worker<Sandbox>, name: &str, balance: UncToken) -> Result<Account> {
+    Ok(worker
+        .root_account()?
+        .create_subaccount(name)
+        .initial_balance(balance)
+        .transact()
+        .await?
+        .into_result()?)
+}
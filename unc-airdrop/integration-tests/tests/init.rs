@@ -0,0 +1,27 @@
+//! Deployment checks, which need the contract to be deployed without being initialized.
+mod common;
+
+use anyhow::Result;
+use common::contract_wasm;
+
+/// `ping` works before the contract is initialized, and reports it once `new_default` is called.
+#[tokio::test]
+async fn test_ping_before_and_after_init() -> Result<()> {
+    let worker = unc_workspaces::sandbox().await?;
+    let contract = worker.dev_deploy(&contract_wasm()?).await?;
+
+    let status = contract.view("ping").await?.json::<serde_json::Value>()?;
+    assert_eq!(status["initialized"], false);
+    let failure = contract
+        .view("get_key_count")
+        .await
+        .expect_err("The state isn't initialized");
+    assert!(failure.to_string().contains("call `new` or `new_default` first"));
+
+    contract.call("new_default").transact().await?.into_result()?;
+    let status = contract.view("ping").await?.json::<serde_json::Value>()?;
+    assert_eq!(status["initialized"], true);
+    assert_eq!(status["keys"], 0);
+    assert_eq!(status["total_unclaimed"], "0");
+    Ok(())
+}
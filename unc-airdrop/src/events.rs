@@ -0,0 +1,61 @@
+use unc_sdk::serde::Serialize;
+use unc_sdk::serde_json;
+
+use crate::*;
+
+/// Name of the standard the events follow, see `log_event`.
+pub const EVENT_STANDARD: &str = "airdrop";
+
+/// Version of the format of the events.
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// Events logged by the contract for indexers, see `log_event`.
+#[derive(Serialize)]
+#[serde(crate = "unc_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum AirDropEvent<'a> {
+    AccountBlocked { account_id: &'a AccountId },
+    AccountUnblocked { account_id: &'a AccountId },
+    AutoPaused { window: u64, claims: u64 },
+    DropOwnershipTransferred { drop_id: u64, old_funder: &'a AccountId, new_funder: &'a AccountId },
+    DropCompleted { drop_id: u64, funder: &'a AccountId, refunded: U128 },
+    DropToppedUp { public_key: &'a PublicKey, old_balance: U128, new_balance: U128 },
+    FtRegistrationFailed { account_id: &'a AccountId, ft_contract_id: &'a AccountId },
+    OperationFailed {
+        kind: FailureKind,
+        public_key: Option<&'a PublicKey>,
+        account_id: Option<&'a AccountId>,
+        amount: U128,
+    },
+    OwnerProposed { owner_id: &'a AccountId, new_owner: &'a AccountId },
+    OwnershipAccepted { old_owner: &'a AccountId, new_owner: &'a AccountId },
+    OwnerProposalCancelled { new_owner: &'a AccountId },
+    Reclaimed { public_key: &'a PublicKey, to: &'a AccountId, amount: U128 },
+    TokensStranded { ft_contract_id: &'a AccountId, amount: U128 },
+}
+
+impl AirDropEvent<'_> {
+    pub fn emit(&self) {
+        log_event(self);
+    }
+}
+
+/// An event wrapped with the standard and version fields of NEP-297.
+#[derive(Serialize)]
+#[serde(crate = "unc_sdk::serde")]
+struct EventLog<'a, T: Serialize> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a T,
+}
+
+/// Logs the event as `EVENT_JSON:{"standard": "airdrop", "version": "1.0.0", "event": ..., "data": ...}`.
+pub fn log_event<T: Serialize>(event: &T) {
+    let log = EventLog {
+        standard: EVENT_STANDARD,
+        version: EVENT_STANDARD_VERSION,
+        event,
+    };
+    env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(&log).unwrap()));
+}
@@ -0,0 +1,38 @@
+use unc_sdk::json_types::U128;
+use unc_sdk::{ext_contract, AccountId, Gas, UncToken};
+
+/// Deposit attached to `storage_deposit` to register the receiver on the FT contract: 0.00125 UNC,
+/// the storage cost of an account on the standard FT implementation.
+pub const FT_STORAGE_DEPOSIT: UncToken = UncToken::from_attounc(1_250_000_000_000_000_000_000);
+
+/// Gas attached to `storage_deposit`.
+pub const FT_STORAGE_DEPOSIT_GAS: Gas = Gas::from_tgas(10);
+
+/// Gas attached to `ft_transfer`.
+pub const FT_TRANSFER_GAS: Gas = Gas::from_tgas(10);
+
+/// Gas attached to `ft_transfer_call`, which also runs `ft_on_transfer` on the receiver and resolves the transfer.
+pub const FT_TRANSFER_CALL_GAS: Gas = Gas::from_tgas(50);
+
+/// Gas attached to the deposit of `claim_wrapped` on the wrap contract.
+pub const WRAP_DEPOSIT_GAS: Gas = Gas::from_tgas(10);
+
+/// Wrapped UNC interface of the wrap contract, the equivalent of `near_deposit` on wNEAR: mints wrapped tokens
+/// to the caller for the attached deposit. The tokens are then moved with `ft_transfer` of NEP-141.
+#[ext_contract(ext_wrap)]
+pub trait WrappedUnc {
+    fn unc_deposit(&mut self);
+}
+
+/// Storage management interface of NEP-145, used to register the receiver before transferring tokens.
+#[ext_contract(ext_ft_storage)]
+pub trait FtStorageManagement {
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>);
+}
+
+/// Fungible token interface of NEP-141.
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+    fn ft_transfer_call(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>, msg: String) -> U128;
+}
@@ -0,0 +1,50 @@
+//! Interface of the contract for other contracts, e.g. to use it as an account factory like the root linkdrop.
+//! Depend on this crate with the `interface-only` feature to get it without the contract itself:
+//!
+//! ```no_run
+//! use airdrop::interface::ext_airdrop;
+//! use unc_sdk::{Gas, Promise, UncToken};
+//!
+//! fn create_account(airdrop_id: unc_sdk::AccountId, new_public_key: unc_sdk::PublicKey) -> Promise {
+//!     ext_airdrop::ext(airdrop_id)
+//!         .with_attached_deposit(UncToken::from_unc(1))
+//!         .with_static_gas(Gas::from_tgas(30))
+//!         .create_account("alice.airdrop".parse().unwrap(), new_public_key, None)
+//! }
+//! ```
+use unc_sdk::json_types::U128;
+use unc_sdk::{ext_contract, AccountId, PublicKey, UncToken};
+
+use crate::models::{CreateAccountOptions, CreationResult, KeyInfo};
+
+#[ext_contract(ext_airdrop)]
+pub trait AirDropInterface {
+    /// Creates `new_account_id` with a full access key and funds it with the attached deposit.
+    /// A failed creation is refunded to `refund_to`, or else to the caller.
+    fn create_account(
+        &mut self,
+        new_account_id: AccountId,
+        new_public_key: PublicKey,
+        refund_to: Option<AccountId>,
+    ) -> CreationResult;
+
+    /// Creates `new_account_id` with the keys and contract of `options`. Returns whether it was created.
+    fn create_account_advanced(&mut self, new_account_id: AccountId, options: CreateAccountOptions) -> bool;
+
+    /// Funds `public_key` with the attached deposit. Returns the balance of the key.
+    fn send(
+        &mut self,
+        public_key: PublicKey,
+        tag: Option<String>,
+        idempotency_key: Option<String>,
+        expiration: Option<u64>,
+        methods: Option<String>,
+        new_accounts_only: Option<bool>,
+    ) -> U128;
+
+    /// Returns information about the key, or fails if it isn't funded.
+    fn get_key_information(&self, key: PublicKey) -> KeyInfo;
+
+    /// Returns the balance of the key, or fails if it isn't funded.
+    fn get_key_balance(&self, key: PublicKey) -> UncToken;
+}
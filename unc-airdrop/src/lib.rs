@@ -1,35 +1,560 @@
+// With `interface-only` only the interface is built, which leaves most of the helpers of the contract unused
+#![cfg_attr(feature = "interface-only", allow(dead_code, unused_imports))]
+
 use borsh::{BorshDeserialize, BorshSerialize};
-use unc_sdk::store::LookupMap;
-use unc_sdk::json_types::U128;
+#[allow(deprecated)]
+use unc_sdk::store::{LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
+use unc_sdk::json_types::{Base58CryptoHash, Base64VecU8, U128};
 use unc_sdk::{
-    env, ext_contract, unc_bindgen, AccountId, Allowance, Gas, PanicOnDefault, Promise, PromiseResult, PublicKey, UncToken
+    env, ext_contract, unc_bindgen, AccountId, Allowance, BorshStorageKey, CryptoHash, CurveType, Gas, Promise, PromiseOrValue, PromiseResult, PublicKey, UncToken
 };
 
-mod models;
+mod events;
+pub mod ft;
+pub mod interface;
+mod merkle;
+pub mod models;
+pub mod staking;
+mod storage;
+use events::*;
+use ft::*;
 use models::*;
+use staking::*;
+use storage::*;
 
-#[unc_bindgen]
-#[derive(PanicOnDefault, BorshDeserialize, BorshSerialize)]
+/// Prefixes of the contract collections. Each variant serializes to its single byte discriminant,
+/// which keeps the prefixes of collections created before this enum (e.g. `b"a"` for the accounts).
+#[derive(BorshSerialize, BorshStorageKey)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+enum StorageKey {
+    Accounts = b'a',
+    Allowances = b'l',
+    Codes = b'h',
+    KeyTags = b't',
+    PendingCreations = b'p',
+    MerkleDrops = b'm',
+    MerkleClaimed = b'c',
+    BlockedAccounts = b'b',
+    KeyBalances = b'k',
+    ClaimHistory = b'r',
+    Drips = b'd',
+    IdempotencyKeys = b'i',
+    FundedAt = b'f',
+    KeyExpirations = b'e',
+    DropConfigs = b'o',
+    KeyDrops = b'g',
+    DropsByFunder = b'u',
+    Operators = b'x',
+    FtBalances = b'z',
+    FailedAttempts = b'y',
+    ScheduledActions = b's',
+    UsedNonces = b'n',
+    StorageStakes = b'w',
+    ClaimLocks = b'q',
+    CallReceivers = b'v',
+    ClaimedAccounts = b'j',
+    Reservations = b'R',
+    Failures = b'F',
+    Relayers = b'S',
+    DropClaims = b'D',
+    AdminKeys = b'A',
+    NewAccountKeys = b'N',
+    KeyMethods = b'M',
+    FtContracts = b'T',
+    StrandedTokens = b'U',
+    CodeCommitments = b'C',
+    KeysByTag = b'K',
+}
+
+#[cfg(not(feature = "interface-only"))]
+#[unc_bindgen(contract_metadata(
+    link = "https://github.com/zwong91/contracts-examples",
+    standard(standard = "nep330", version = "1.1.0"),
+    standard(standard = "nep452", version = "1.0.0"),
+))]
+#[derive(BorshDeserialize, BorshSerialize)]
 pub struct AirDrop {
+    pub owner_id: AccountId,
+    /// Balances of the keys funded before `key_balances` was introduced.
+    /// An entry is moved to `key_balances` when its key is funded again and removed when it is claimed.
     pub accounts: LookupMap<PublicKey, UncToken>,
+    /// Estimate of the allowance left on each funded access key.
+    pub allowances: LookupMap<PublicKey, UncToken>,
+    /// Balances claimable with a redemption code, keyed by the sha256 of the code.
+    pub codes: LookupMap<CryptoHash, UncToken>,
+    /// Tags of the funded keys that have one.
+    #[allow(deprecated)]
+    pub key_tags: UnorderedMap<PublicKey, String>,
+    /// Keys of each tag in `key_tags`, for `get_keys_by_tag`.
+    pub keys_by_tag: LookupMap<String, Vec<PublicKey>>,
+    pub pending_creations: LookupMap<AccountId, PendingCreation>,
+    pub merkle_drops: LookupMap<u64, MerkleDrop>,
+    /// Bitmap of the claimed leaves of each Merkle drop, keyed by drop id and word index.
+    pub merkle_claimed: LookupMap<(u64, u64), u64>,
+    pub next_merkle_drop_id: u64,
+    /// Accounts that cannot receive claims or be created by the contract.
+    #[allow(deprecated)]
+    pub blocked_accounts: UnorderedSet<AccountId>,
+    pub pending_sweep: Option<PendingSweep>,
+    /// Time in nanoseconds that must pass between proposing and confirming a sweep.
+    pub sweep_delay_ns: u64,
+    /// Whether claims are disabled, either by the owner or automatically on abnormal claim volume.
+    pub paused: bool,
+    /// Maximum number of key claims per window of CLAIM_WINDOW_BLOCKS blocks, claims above it are rejected.
+    pub max_claims_per_window: Option<u64>,
+    /// Number of key claims per window above which the contract pauses itself.
+    pub auto_pause_threshold: Option<u64>,
+    /// Index of the current window, i.e. `block_height / CLAIM_WINDOW_BLOCKS`.
+    pub claim_window: u64,
+    /// Number of key claims attempted in the current window.
+    pub claims_in_window: u64,
+    /// When set, accounts created by the contract must end with this suffix (e.g. `.mydrop.unc`).
+    pub required_suffix: Option<String>,
+    /// Balances of the funded keys in attoUNC, keyed by the sha256 of the borsh-serialized public key.
+    /// Compared to `accounts` this saves the length prefix and the curve type of the key in every entry.
+    pub key_balances: LookupMap<CryptoHash, u128>,
+    /// Append-only log of the successful key claims, in the order they happened.
+    pub claim_history: Vector<ClaimRecord>,
+    /// Maximum number of keys, full and limited, that `create_account_advanced` can add to an account.
+    pub max_keys_per_account: u64,
+    /// Maximum length in bytes of the contract deployed by `create_account_advanced`.
+    pub max_contract_bytes: u64,
+    /// Release schedules of the keys whose balance is claimed in several drips, keyed like `key_balances`.
+    pub drips: LookupMap<CryptoHash, Drip>,
+    /// Idempotency keys of the `send` calls already processed, per caller.
+    pub idempotency_keys: LookupSet<(AccountId, String)>,
+    /// Link to the source code of the deployed contract when it differs from the one built into the code,
+    /// returned by `get_source_metadata`.
+    pub source_link: Option<String>,
+    /// Time in nanoseconds after funding before a key can be claimed.
+    pub claim_cooldown_ns: u64,
+    /// Block timestamp at which each key got funded, keyed like `key_balances`. Before `get_key_information`
+    /// returned it, it was only stored for the keys funded while a cooldown was set.
+    pub funded_at: LookupMap<CryptoHash, u64>,
+    /// Cumulative counters returned by `get_stats`.
+    pub stats: Stats,
+    /// Block timestamps in nanoseconds from which the funded keys that have one can no longer be claimed.
+    #[allow(deprecated)]
+    pub key_expirations: UnorderedMap<PublicKey, u64>,
+    pub drop_configs: LookupMap<u64, DropConfig>,
+    pub next_drop_id: u64,
+    /// Drop of each key funded with `send_to_drop`, keyed like `key_balances`.
+    pub key_drops: LookupMap<CryptoHash, u64>,
+    /// Ids of the drops of each funder.
+    pub drops_by_funder: LookupMap<AccountId, Vec<u64>>,
+    /// Sum of the balances stored with `internal_set_key_balance` and not taken since.
+    pub key_liability: UncToken,
+    /// Sum of the allowance estimates in `allowances`.
+    pub reserved_allowances: UncToken,
+    /// Accounts allowed to pause the contract and block accounts on top of the owner.
+    #[allow(deprecated)]
+    pub operators: UnorderedSet<AccountId>,
+    /// Fungible tokens of the keys that hold some, keyed like `key_balances`.
+    pub ft_balances: LookupMap<CryptoHash, FtBalance>,
+    /// Account proposed by the owner to become the owner once it accepts, see `propose_owner`.
+    pub pending_owner: Option<AccountId>,
+    /// Number of consecutive failed claims of the keys that have some, keyed like `key_balances`.
+    pub failed_attempts: LookupMap<CryptoHash, u32>,
+    /// Owner actions waiting for their timelock, keyed by action id.
+    #[allow(deprecated)]
+    pub scheduled_actions: UnorderedMap<u64, ScheduledAction>,
+    pub next_action_id: u64,
+    /// Minimum time in nanoseconds between scheduling an owner action and its execution.
+    pub timelock_delay_ns: u64,
+    /// Sha256 of the code staged with an executed `StageUpgrade` action.
+    pub staged_code_hash: Option<CryptoHash>,
+    /// Number of keys with a balance stored with `internal_set_key_balance` and not taken since.
+    pub key_count: u64,
+    /// Minimum balance of a new key once its allowance is taken from the deposit.
+    pub min_drop_amount: UncToken,
+    /// Nonces used by `claim_signed`, per key hashed like `key_balances`. Kept once the key is claimed
+    /// so a signature can't be replayed if the key is funded again.
+    pub used_nonces: LookupSet<(CryptoHash, u64)>,
+    /// Cost of the storage taken by each key funded with `send` or `send_signed`, keyed like `key_balances`.
+    /// It is taken from the deposit and paid out with the balance once the key is removed.
+    pub storage_stakes: LookupMap<CryptoHash, UncToken>,
+    /// Block height at which `create_account_and_claim`, `claim_ft_with_registration` and `claim_ft_call` locked
+    /// the keys whose claim hasn't resolved yet, keyed like `key_balances`. The balance of a locked key stays
+    /// stored until the creation succeeds, and a locked key can't be reclaimed.
+    pub claim_locks: LookupMap<CryptoHash, u64>,
+    /// Fee in basis points taken by `send` from what is sent once the allowance is taken.
+    pub fee_bps: u16,
+    /// Fees taken by `send` and not withdrawn yet. They aren't part of `key_liability`.
+    pub fees_collected: UncToken,
+    /// Whether keys and codes can be funded. Unlike `paused` it leaves the claims enabled, e.g. for a wind-down.
+    pub funding_enabled: bool,
+    /// Contracts `claim_and_call` can call, with the field of the call arguments that gets the claiming account.
+    #[allow(deprecated)]
+    pub call_receivers: UnorderedMap<AccountId, String>,
+    /// Accounts that received a successful key claim, see `has_claimed`.
+    pub claimed_accounts: LookupSet<AccountId>,
+    /// Deposit attached to each `storage_deposit` of the `ft_registrations` of `create_account_advanced`.
+    pub ft_registration_deposit: UncToken,
+    /// Account ids reserved for the keys funded with `reserve_account`, which can only create that account.
+    pub reservations: LookupMap<PublicKey, Reservation>,
+    /// Allowance a claim attempt of an ED25519 key can use up. `send` gives the access key of a new key
+    /// `max_claim_attempts` times this, taken from the deposit.
+    pub ed25519_allowance: UncToken,
+    /// Allowance a claim attempt of a SECP256K1 key can use up, see `ed25519_allowance`.
+    pub secp256k1_allowance: UncToken,
+    /// Index in `claim_history` up to which `migrate_chunk` added the claiming accounts to `claimed_accounts`.
+    pub migration_cursor: u64,
+    /// The last MAX_FAILURE_RECORDS failed operations, overwritten oldest first, see `get_recent_failures`.
+    pub failures: Vector<FailureRecord>,
+    /// Number of failed operations recorded since the deployment, the next one going at this index
+    /// modulo MAX_FAILURE_RECORDS.
+    pub failure_count: u64,
+    /// Block timestamp at which `claim_cooldown_ns` was last set. The keys funded before aren't subject to it.
+    pub claim_cooldown_set_at: u64,
+    /// Number of claim attempts the allowance of a new access key is sized for, so that a wallet can retry
+    /// `create_account_and_claim` with another name after a failed attempt.
+    pub max_claim_attempts: u32,
+    /// Deposit attached to the `storage_deposit` of `claim_ft_with_registration`, taken from the key balance.
+    pub ft_storage_deposit: UncToken,
+    /// Gas attached to the `storage_deposit` of `claim_ft_with_registration`.
+    pub ft_storage_deposit_gas: Gas,
+    /// Accounts allowed to fund keys from `sponsor_pool` with `sponsor_key`.
+    pub relayers: LookupSet<AccountId>,
+    /// Balance deposited with `fund_sponsor_pool` that `sponsor_key` moves into keys. Not owed to any key yet.
+    pub sponsor_pool: UncToken,
+    /// Comma separated subset of CLAIM_METHODS the access keys of new keys can call, all of them if unset.
+    pub default_claim_methods: Option<String>,
+    /// Indices in `claim_history` of the claims of each drop, keyed by drop id and the number of the claim
+    /// in the drop, so that the claims of a drop can be exported in order.
+    pub drop_claims: LookupMap<(u64, u64), u32>,
+    /// Nanoseconds after which new keys expire when `send` isn't given an expiration, none if unset.
+    pub default_expiry_ns: Option<u64>,
+    /// Maximum number of funded keys, unlimited if unset.
+    pub max_keys: Option<u64>,
+    /// Wrap contract `claim_wrapped` deposits the balances in, disabled if unset.
+    pub wrap_contract_id: Option<AccountId>,
+    /// Full access keys of the owner and the operators on the contract account, which the claim methods
+    /// refuse to be signed with, see `add_admin_key`.
+    #[allow(deprecated)]
+    pub admin_keys: UnorderedSet<PublicKey>,
+    /// What `send` does with a deposit larger than needed.
+    pub over_deposit_policy: OverDepositPolicy,
+    /// Keys funded with `send` that can only be claimed to new accounts, keyed like `key_balances`. The keys
+    /// of a drop follow `new_accounts_only` of the drop instead.
+    pub new_account_keys: LookupSet<CryptoHash>,
+    /// Comma separated claim methods of the keys funded with `methods` by `send`, keyed like `key_balances`.
+    pub key_methods: LookupMap<CryptoHash, String>,
+    /// FT contracts `ft_on_transfer` accepts tokens from, see `add_ft_contract`.
+    pub ft_contracts: LookupSet<AccountId>,
+    /// Tokens of each FT contract that couldn't be released to the claiming account of their key, which the
+    /// owner can withdraw with `withdraw_stranded_tokens`.
+    pub stranded_tokens: LookupMap<AccountId, u128>,
+    /// Commitments made with `commit_code`, keyed by the sha256 of the code followed by the receiving account id.
+    pub code_commitments: LookupMap<CryptoHash, CodeCommitment>,
+    /// Sum of what is owed outside the key balances: the unclaimed amounts and storage reserves of the Merkle
+    /// drops, the balances of the codes, the deposits of the code commitments, the pending creations with
+    /// their storage cost and the FT registration deposits of the creations in flight.
+    pub other_liability: UncToken,
+}
+
+/// Called by the methods that load the state when there is none, like `PanicOnDefault` but naming the init call.
+#[cfg(not(feature = "interface-only"))]
+impl Default for AirDrop {
+    fn default() -> Self {
+        panic!("The contract is not initialized, call `new` or `new_default` first")
+    }
+}
+
+/// State of the first version of the contract, which `migrate` converts.
+#[cfg(not(feature = "interface-only"))]
+#[derive(BorshDeserialize, BorshSerialize)]
+struct OldAirDrop {
+    accounts: LookupMap<PublicKey, UncToken>,
 }
 
-/// Access key allowance for airdrop keys.
-const ACCESS_KEY_ALLOWANCE: UncToken = UncToken::from_attounc(1_000_000_000_000_000_000_000_000);
+/// Storage key of the contract state, as written by `unc_sdk`.
+#[cfg(not(feature = "interface-only"))]
+const STATE_KEY: &[u8] = b"STATE";
+
+/// Default allowance of a claim attempt, see `ed25519_allowance` and `secp256k1_allowance`: 1 UNC, what a
+/// `create_account_and_claim` with the maximum gas takes from an access key at the pessimistic gas price.
+const CLAIM_ATTEMPT_ALLOWANCE: UncToken = UncToken::from_attounc(1_000_000_000_000_000_000_000_000);
+
+/// Default number of claim attempts the allowance of a new access key is sized for.
+pub const DEFAULT_MAX_CLAIM_ATTEMPTS: u32 = 2;
+
+/// Gas price assumed when estimating how much allowance a claim attempt burns.
+const CLAIM_GAS_PRICE_ESTIMATE: u128 = 100_000_000;
+
+/// Minimum gas attached to the callback from account creation.
+/// On top of it, the callback gets all the gas left unused by the call that creates the account.
+pub const MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS: Gas = Gas::from_tgas(5);
+
+/// Gas reserved by `create_accounts` for every account: scheduling the creation and the floor of its callback.
+pub const CREATE_ACCOUNTS_GAS_PER_ACCOUNT: Gas = Gas::from_tgas(10);
+
+/// Gas estimate of a claim paying out the native token, without the account creation callback.
+pub const CLAIM_BASE_GAS: Gas = Gas::from_tgas(15);
+
+/// Maximum number of times a failed `create_account_advanced` can be retried.
+pub const MAX_CREATE_ACCOUNT_RETRIES: u32 = 3;
+
+/// Minimum length in bytes of a redemption code, since codes have less entropy than keys.
+pub const MIN_CODE_LENGTH: usize = 16;
+
+/// Default time between proposing and confirming a sweep: 24 hours.
+pub const DEFAULT_SWEEP_DELAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Default minimum time between scheduling and executing an owner action: 24 hours.
+pub const DEFAULT_TIMELOCK_DELAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Default minimum balance of a new key: 0.01 UNC, below which claiming costs more than it's worth.
+pub const DEFAULT_MIN_DROP_AMOUNT: UncToken = UncToken::from_milliunc(10);
+
+/// Minimum balance of a new key whatever `min_drop_amount` is, so a funded key always has something to claim.
+pub const MIN_NET_CREDIT: UncToken = UncToken::from_attounc(1);
+
+/// Maximum fee `send` can take: 1000 basis points, i.e. 10%.
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+/// Maximum length in bytes of a key tag.
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// Number of entries returned by the paginated view methods, e.g. `get_keys_by_tag`, when no `limit` is given.
+pub const DEFAULT_PAGE_LIMIT: u64 = 100;
+
+/// Maximum length in bytes of the metadata of a drop.
+pub const MAX_DROP_METADATA_LENGTH: usize = 1024;
+
+/// Maximum length in bytes of an idempotency key.
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 64;
+
+/// Maximum number of keys `claim_all` claims in one call.
+pub const MAX_CLAIM_ALL_KEYS: usize = 20;
+
+/// Number of consecutive failed claims after which a key is locked until the owner resets it.
+pub const MAX_FAILED_CLAIM_ATTEMPTS: u32 = 3;
+
+/// Maximum number of keys `add_admin_key` can register.
+pub const MAX_ADMIN_KEYS: u32 = 10;
+
+/// Number of blocks after which the owner can unlock a key whose account creation never resolved,
+/// see `unlock_stale_claim`.
+pub const CLAIM_LOCK_TIMEOUT_BLOCKS: u64 = 100;
+
+/// Number of blocks after which the committer can cancel a commitment that wasn't claimed, see
+/// `cancel_commitment`.
+pub const CODE_COMMITMENT_TIMEOUT_BLOCKS: u64 = 100;
+
+/// Number of failed operations kept in `failures` for support.
+pub const MAX_FAILURE_RECORDS: u64 = 1_000;
 
-/// Gas attached to the callback from account creation.
-pub const ON_CREATE_ACCOUNT_CALLBACK_GAS: Gas = Gas::from_gas(13_000_000_000_000);
+/// Maximum number of keys `get_keys_information` can be queried for at once.
+pub const MAX_KEYS_PER_QUERY: usize = 100;
 
-/// Methods callable by the function call access key
-const ACCESS_KEY_METHOD_NAMES: &str = "claim,create_account_and_claim";
+/// Number of blocks in a window over which key claims are counted for rate limiting.
+pub const CLAIM_WINDOW_BLOCKS: u64 = 100;
+
+/// Default maximum number of keys `create_account_advanced` can add to an account.
+pub const DEFAULT_MAX_KEYS_PER_ACCOUNT: u64 = 20;
+
+/// Default maximum length in bytes of the contract deployed by `create_account_advanced`: 4 MiB.
+pub const DEFAULT_MAX_CONTRACT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Maximum number of FT contracts `create_account_advanced` registers the new account with.
+pub const MAX_FT_REGISTRATIONS: u64 = 10;
+
+/// Gas estimate of `create_account_advanced` and its callback, without a contract to deploy.
+pub const CREATE_ACCOUNT_ADVANCED_GAS: Gas = Gas::from_tgas(20);
+
+/// Gas estimate of deploying a contract, per byte of code.
+pub const DEPLOY_GAS_PER_BYTE: u64 = 75_000_000;
+
+/// Magic bytes every wasm module starts with.
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Methods the access keys added by the contract can call. Each of them checks `check_claim_context` first.
+/// The access keys of new keys get all of them unless the owner set `default_claim_methods`, and a drop can
+/// allow a subset of them, see `create_drop`.
+pub const CLAIM_METHODS: &[&str] = &[
+    "claim",
+    "create_account_and_claim",
+    "claim_ft_with_registration",
+    "claim_ft_call",
+    "claim_and_call",
+    "create_account_and_claim_derived",
+    "claim_wrapped",
+];
+
+/// Claim methods that create the account they claim to, the only ones the access keys of keys for new accounts
+/// only can call.
+pub const NEW_ACCOUNT_CLAIM_METHODS: &[&str] = &["create_account_and_claim", "create_account_and_claim_derived"];
 
 #[ext_contract(ext_self)]
 pub trait ExtAirDrop {
     /// Callback after plain account creation.
-    fn on_account_created(&mut self, predecessor_account_id: AccountId, amount: U128) -> bool;
+    fn on_account_created(
+        &mut self,
+        predecessor_account_id: AccountId,
+        new_account_id: AccountId,
+        amount: U128,
+    ) -> CreationResult;
 
     /// Callback after creating account and claiming airdrop.
-    fn on_account_created_and_claimed(&mut self, amount: U128) -> bool;
+    fn on_account_created_and_claimed(
+        &mut self,
+        new_account_id: AccountId,
+        amount: U128,
+        staking: Option<StakingIntent>,
+    ) -> U128;
+
+    /// Callback after advanced account creation or a retry of it.
+    fn on_account_created_advanced(
+        &mut self,
+        predecessor_account_id: AccountId,
+        new_account_id: AccountId,
+        amount: U128,
+        options: CreateAccountOptions,
+        ft_registration_deposit: U128,
+        retries: u32,
+    ) -> bool;
+
+    /// Callback after registering a new account with an FT contract.
+    fn on_ft_registered(&mut self, new_account_id: AccountId, ft_contract_id: AccountId, deposit: U128) -> bool;
+}
+
+/// Gas the callback of `create_account_advanced` needs on top of MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS
+/// to register the new account with `count` FT contracts and check each registration.
+fn ft_registrations_gas(count: u64) -> Gas {
+    Gas::from_gas(
+        FT_STORAGE_DEPOSIT_GAS
+            .saturating_add(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+            .as_gas()
+            .saturating_mul(count),
+    )
+}
+
+/// Asserts that gas is left for the account creation once the callback floor is reserved,
+/// so that a call with too little gas fails early instead of in the callback.
+fn assert_creation_gas() {
+    assert!(
+        env::prepaid_gas().saturating_sub(env::used_gas()) > MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS,
+        "Not enough gas attached for the account creation callback"
+    );
+}
+
+/// Key of a public key in `key_balances`.
+fn key_hash(public_key: &PublicKey) -> CryptoHash {
+    env::sha256_array(&borsh::to_vec(public_key).unwrap())
+}
+
+/// Commitment of `commit_code` to claim `code` to `account_id`: the sha256 of the code followed by the account id.
+fn code_commitment(code: &str, account_id: &AccountId) -> CryptoHash {
+    env::sha256_array(&[code.as_bytes(), account_id.as_bytes()].concat())
+}
+
+/// Cost of the storage of a commitment in `code_commitments`.
+fn code_commitment_cost() -> UncToken {
+    env::storage_byte_cost().saturating_mul(u128::from(CODE_COMMITMENT_STORAGE_BYTES))
+}
+
+/// Asserts that exactly 1 attoUNC is attached, which requires the transaction to be signed with
+/// a full access key since function call access keys can't attach deposits.
+fn assert_one_atto() {
+    if env::attached_deposit() != UncToken::from_attounc(1) {
+        panic!("{}", AirdropError::OneAttoRequired);
+    }
+}
+
+/// Panics with `ArithmeticOverflow` when the result of a balance operation doesn't fit in a u128,
+/// rather than capping it and losing track of funds.
+fn overflow(operation: &str) -> ! {
+    panic!("{}", AirdropError::ArithmeticOverflow(operation.to_string()))
+}
+
+/// Bytes of the key data whose hex makes the account id of `create_account_and_claim_derived`, which keeps
+/// room for the parent account id within the 64 characters of an account id.
+pub const DERIVED_ACCOUNT_ID_KEY_BYTES: usize = 16;
+
+/// Account id created by `create_account_and_claim_derived` for the key: the hex of the first
+/// DERIVED_ACCOUNT_ID_KEY_BYTES bytes of its data, as a sub-account of `parent`.
+pub fn derived_account_id(public_key: &PublicKey, parent: &AccountId) -> AccountId {
+    let hex: String = public_key.as_bytes()[1..]
+        .iter()
+        .take(DERIVED_ACCOUNT_ID_KEY_BYTES)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}.{}", hex, parent).parse().expect("Invalid account id")
+}
+
+/// Message signed by the key for `claim_signed`: the borsh serialization of `(contract_id, account_id, nonce)`,
+/// where `contract_id` is the account of the airdrop contract, so that a signature can't be replayed on
+/// another deployment the key is funded on.
+pub fn signed_claim_message(contract_id: &AccountId, account_id: &AccountId, nonce: u64) -> Vec<u8> {
+    borsh::to_vec(&(contract_id, account_id, nonce)).unwrap()
+}
+
+/// Adds the function call access key with `allowance` a drop is claimed with.
+fn add_drop_access_key(promise: Promise, public_key: PublicKey, allowance: UncToken, method_names: &str) -> Promise {
+    promise.add_access_key_allowance(
+        public_key,
+        Allowance::limited(allowance).unwrap_or(Allowance::Unlimited),
+        env::current_account_id(),
+        method_names.to_string(),
+    )
+}
+
+/// Transfers `amount` tokens of `ft_contract_id` to `receiver_id`, stranding them with `on_tokens_released`
+/// if the transfer fails.
+#[cfg(not(feature = "interface-only"))]
+fn internal_transfer_tokens(ft_contract_id: AccountId, receiver_id: AccountId, amount: u128) -> Promise {
+    ext_ft::ext(ft_contract_id.clone())
+        .with_attached_deposit(UncToken::from_attounc(1))
+        .with_static_gas(FT_TRANSFER_GAS)
+        .ft_transfer(receiver_id, U128(amount), None)
+        .then(
+            AirDrop::ext(env::current_account_id())
+                .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                .on_tokens_released(ft_contract_id, U128(amount))
+        )
+}
+
+/// Adds the keys and the contract of `options` to the creation of an account, for `create_account_advanced`
+/// and `create_account_and_claim`. The options are validated by `internal_validate_create_account_options`.
+fn build_creation_batch(mut promise: Promise, options: &CreateAccountOptions) -> Promise {
+    for key in options.full_access_keys.iter().flatten() {
+        promise = promise.add_full_access_key(key.clone());
+    }
+    for key_info in options.limited_access_keys.iter().flatten() {
+        promise = promise.add_access_key_allowance(
+            key_info.public_key.clone(),
+            Allowance::limited(key_info.allowance).unwrap_or(Allowance::Unlimited),
+            key_info.receiver_id.clone(),
+            key_info.method_names.clone(),
+        );
+    }
+    if let Some(bytes) = &options.contract_bytes {
+        promise = promise.deploy_contract(bytes.clone());
+    }
+    promise
+}
+
+/// Asserts that the comma separated method names are all claim methods, so they can be allowed on drop keys.
+fn assert_claim_method_names(method_names: &str) {
+    for method_name in method_names.split(',') {
+        assert!(
+            CLAIM_METHODS.contains(&method_name),
+            "Method {} can't be allowed on drop keys",
+            method_name
+        );
+    }
+}
+
+/// Whether the account id is an implicit account, i.e. the 64 lowercase hex characters of an ED25519
+/// public key. Such accounts come into existence when they receive a transfer and can't be created
+/// with a `CreateAccount` action.
+fn is_implicit_account_id(account_id: &AccountId) -> bool {
+    account_id.len() == 64
+        && account_id
+            .as_bytes()
+            .iter()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(byte))
 }
 
 fn is_promise_success() -> bool {
@@ -38,500 +563,10561 @@ fn is_promise_success() -> bool {
         1,
         "Contract expected a result on the callback"
     );
-    match env::promise_result(0) {
-        PromiseResult::Successful(_) => true,
-        _ => false,
-    }
+    matches!(env::promise_result(0), PromiseResult::Successful(_))
 }
 
+#[cfg(not(feature = "interface-only"))]
 #[unc_bindgen]
 impl AirDrop {
-    /// Initializes the contract with an empty map for the accounts
+    /// Initializes the contract with `owner_id` as its owner and the settings of `config`, the defaults for
+    /// the ones it doesn't give.
+    #[init]
+    pub fn new(owner_id: AccountId, config: Option<Config>) -> Self {
+        assert!(!env::state_exists(), "The contract is already initialized");
+        let mut contract = Self::internal_new(owner_id);
+        contract.internal_apply_config(config.unwrap_or_default());
+        contract
+    }
+
+    /// Returns the state of a new contract with the default settings, for `new` and `migrate`.
+    #[allow(deprecated)]
+    fn internal_new(owner_id: AccountId) -> Self {
+        Self {
+            owner_id,
+            accounts: LookupMap::new(StorageKey::Accounts),
+            allowances: LookupMap::new(StorageKey::Allowances),
+            codes: LookupMap::new(StorageKey::Codes),
+            key_tags: UnorderedMap::new(StorageKey::KeyTags),
+            keys_by_tag: LookupMap::new(StorageKey::KeysByTag),
+            pending_creations: LookupMap::new(StorageKey::PendingCreations),
+            merkle_drops: LookupMap::new(StorageKey::MerkleDrops),
+            merkle_claimed: LookupMap::new(StorageKey::MerkleClaimed),
+            next_merkle_drop_id: 0,
+            blocked_accounts: UnorderedSet::new(StorageKey::BlockedAccounts),
+            pending_sweep: None,
+            sweep_delay_ns: DEFAULT_SWEEP_DELAY_NS,
+            paused: false,
+            max_claims_per_window: None,
+            auto_pause_threshold: None,
+            claim_window: 0,
+            claims_in_window: 0,
+            required_suffix: None,
+            key_balances: LookupMap::new(StorageKey::KeyBalances),
+            claim_history: Vector::new(StorageKey::ClaimHistory),
+            max_keys_per_account: DEFAULT_MAX_KEYS_PER_ACCOUNT,
+            max_contract_bytes: DEFAULT_MAX_CONTRACT_BYTES,
+            drips: LookupMap::new(StorageKey::Drips),
+            idempotency_keys: LookupSet::new(StorageKey::IdempotencyKeys),
+            source_link: None,
+            claim_cooldown_ns: 0,
+            funded_at: LookupMap::new(StorageKey::FundedAt),
+            stats: Stats::default(),
+            key_expirations: UnorderedMap::new(StorageKey::KeyExpirations),
+            drop_configs: LookupMap::new(StorageKey::DropConfigs),
+            next_drop_id: 0,
+            key_drops: LookupMap::new(StorageKey::KeyDrops),
+            drops_by_funder: LookupMap::new(StorageKey::DropsByFunder),
+            key_liability: UncToken::from_attounc(0),
+            reserved_allowances: UncToken::from_attounc(0),
+            operators: UnorderedSet::new(StorageKey::Operators),
+            ft_balances: LookupMap::new(StorageKey::FtBalances),
+            pending_owner: None,
+            failed_attempts: LookupMap::new(StorageKey::FailedAttempts),
+            scheduled_actions: UnorderedMap::new(StorageKey::ScheduledActions),
+            next_action_id: 0,
+            timelock_delay_ns: DEFAULT_TIMELOCK_DELAY_NS,
+            staged_code_hash: None,
+            key_count: 0,
+            min_drop_amount: DEFAULT_MIN_DROP_AMOUNT,
+            used_nonces: LookupSet::new(StorageKey::UsedNonces),
+            storage_stakes: LookupMap::new(StorageKey::StorageStakes),
+            claim_locks: LookupMap::new(StorageKey::ClaimLocks),
+            fee_bps: 0,
+            fees_collected: UncToken::from_attounc(0),
+            funding_enabled: true,
+            call_receivers: UnorderedMap::new(StorageKey::CallReceivers),
+            claimed_accounts: LookupSet::new(StorageKey::ClaimedAccounts),
+            ft_registration_deposit: FT_STORAGE_DEPOSIT,
+            reservations: LookupMap::new(StorageKey::Reservations),
+            ed25519_allowance: CLAIM_ATTEMPT_ALLOWANCE,
+            secp256k1_allowance: CLAIM_ATTEMPT_ALLOWANCE,
+            migration_cursor: 0,
+            failures: Vector::new(StorageKey::Failures),
+            failure_count: 0,
+            claim_cooldown_set_at: 0,
+            max_claim_attempts: DEFAULT_MAX_CLAIM_ATTEMPTS,
+            ft_storage_deposit: FT_STORAGE_DEPOSIT,
+            ft_storage_deposit_gas: FT_STORAGE_DEPOSIT_GAS,
+            relayers: LookupSet::new(StorageKey::Relayers),
+            sponsor_pool: UncToken::from_attounc(0),
+            default_claim_methods: None,
+            drop_claims: LookupMap::new(StorageKey::DropClaims),
+            default_expiry_ns: None,
+            max_keys: None,
+            wrap_contract_id: None,
+            admin_keys: UnorderedSet::new(StorageKey::AdminKeys),
+            over_deposit_policy: OverDepositPolicy::CreditAll,
+            new_account_keys: LookupSet::new(StorageKey::NewAccountKeys),
+            key_methods: LookupMap::new(StorageKey::KeyMethods),
+            ft_contracts: LookupSet::new(StorageKey::FtContracts),
+            stranded_tokens: LookupMap::new(StorageKey::StrandedTokens),
+            code_commitments: LookupMap::new(StorageKey::CodeCommitments),
+            other_liability: UncToken::from_attounc(0),
+        }
+    }
+
+    /// Initializes the contract with the default settings. The calling account becomes the owner.
     #[init]
-    pub fn new() -> Self {
-        Self { 
-            accounts: LookupMap::new(b"a") 
+    pub fn new_default() -> Self {
+        Self::new(env::predecessor_account_id(), None)
+    }
+
+    /// Returns whether the contract is initialized, with its version and a summary of its state, for deployment
+    /// tooling to check it. Doesn't load the state like the other methods, so it works before `new` too. The
+    /// summary is left empty if the state can't be read, e.g. until `migrate` is called after an upgrade.
+    pub fn ping() -> ContractStatus {
+        let state = env::storage_read(STATE_KEY).and_then(|state| Self::try_from_slice(&state).ok());
+        ContractStatus {
+            initialized: env::state_exists(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            paused: state.as_ref().is_some_and(|contract| contract.paused),
+            keys: state.as_ref().map_or(0, |contract| contract.key_count),
+            total_unclaimed: U128(state.map_or(0, |contract| contract.key_liability.as_attounc())),
+        }
+    }
+
+    /// Reloads the contract state after an upgrade of the code.
+    /// The storage prefixes are unchanged, so existing collections stay readable without rewriting them.
+    /// The state of the first version, which only had the balances of the keys, gets the default settings
+    /// and the contract account as owner, which can hand it over with `propose_owner`.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(STATE_KEY).expect("No state to migrate");
+        if let Ok(contract) = Self::try_from_slice(&state) {
+            return contract;
+        }
+        let old = OldAirDrop::try_from_slice(&state).expect("Unknown state layout");
+        let mut contract = Self::internal_new(env::current_account_id());
+        contract.accounts = old.accounts;
+        contract
+    }
+
+    /// Adds the accounts of up to `limit` records of `claim_history` to `claimed_accounts`, resuming where the
+    /// previous call stopped, for the claims recorded before `claimed_accounts` existed. The contract stays
+    /// usable in between, new claims being added to both. Only the owner can migrate.
+    /// Returns whether the whole history is migrated.
+    #[payable]
+    pub fn migrate_chunk(&mut self, limit: u64) -> bool {
+        self.assert_owner();
+        assert_one_atto();
+        let end = std::cmp::min(self.migration_cursor.saturating_add(limit), self.claim_history.len() as u64);
+        for index in self.migration_cursor..end {
+            let account_id = self.claim_history[index as u32].account_id.clone();
+            self.claimed_accounts.insert(account_id);
         }
+        self.migration_cursor = end;
+        end == self.claim_history.len() as u64
     }
 
-    /// Allows given public key to claim sent balance.
-    /// Takes ACCESS_KEY_ALLOWANCE as fee from deposit to cover account creation via an access key.
+    /// Allows given public key to claim sent balance, optionally tagging the key (e.g. with a campaign tier).
+    /// With an `expiration` (a block timestamp in nanoseconds) the key can no longer be claimed from then on.
+    /// Takes the allowance of the curve of the key as fee from deposit to cover account creation via an access key, and the
+    /// cost of the storage of a new key as its storage stake, which is paid out with the balance.
+    /// `fee_bps` of what is left after the allowance, or of the whole deposit of a top-up, goes to `fees_collected`.
+    /// Sending to a key that still has a balance tops it up with the whole deposit like `top_up`, since it
+    /// already has an access key, and returns the new balance. Keys of a drop or a reservation can only be
+    /// topped up by their funder, who is also the only one who can change their tag.
+    /// `methods` overrides the comma separated claim methods the access key of a new key can call, e.g.
+    /// `create_account_and_claim` only, see `get_key_methods`. It is ignored on top-ups.
+    /// With `new_accounts_only` a new key can only be claimed to a new account: its access key can only call
+    /// NEW_ACCOUNT_CLAIM_METHODS, and the other claims of the key fail with `NewAccountRequired`. It can't be
+    /// combined with `methods` and is ignored on top-ups.
+    /// A retried call with the same `idempotency_key` refunds the deposit and returns the balance of the key
+    /// instead of crediting it again.
+    /// Returns the balance of the key. The access key of a new key is added by a separate receipt.
     #[payable]
-    pub fn send(&mut self, public_key: PublicKey) -> Promise {
+    pub fn send(
+        &mut self,
+        public_key: PublicKey,
+        tag: Option<String>,
+        idempotency_key: Option<String>,
+        expiration: Option<u64>,
+        methods: Option<String>,
+        new_accounts_only: Option<bool>,
+    ) -> U128 {
+        self.assert_funding_enabled();
+        if let Some(idempotency_key) = idempotency_key {
+            assert!(
+                idempotency_key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH,
+                "Idempotency key must be at most {} bytes long",
+                MAX_IDEMPOTENCY_KEY_LENGTH
+            );
+            if !self.idempotency_keys.insert((env::predecessor_account_id(), idempotency_key)) {
+                Promise::new(env::predecessor_account_id()).transfer(env::attached_deposit());
+                let balance = self.internal_key_balance(&public_key).unwrap_or(UncToken::from_unc(0));
+                return U128(balance.as_attounc());
+            }
+        }
+        let deposit = self.internal_send_deposit();
+        if self.internal_key_balance(&public_key).is_some() {
+            self.assert_can_top_up(&public_key);
+        }
+        let balance = self.internal_take_key_balance(&public_key);
+        if balance.is_some() {
+            assert!(
+                deposit > UncToken::from_attounc(0),
+                "Attached deposit must be greater than 0"
+            );
+        } else {
+            let allowance = self.internal_curve_allowance(&public_key);
+            assert!(
+                deposit > allowance,
+                "Attached deposit must be more than the access key allowance of {} attoUNC, got {}",
+                allowance.as_attounc(),
+                deposit.as_attounc()
+            );
+            let required = self.internal_min_send_deposit(&public_key);
+            assert!(
+                deposit >= required,
+                "Attached deposit must be at least {} attoUNC, got {}",
+                required.as_attounc(),
+                deposit.as_attounc()
+            );
+        }
+        let storage_before = self.internal_key_storage_usage();
+        if let Some(tag) = tag {
+            assert!(
+                tag.len() <= MAX_TAG_LENGTH,
+                "Tag must be at most {} bytes long",
+                MAX_TAG_LENGTH
+            );
+            assert!(
+                balance.is_none()
+                    || self.internal_key_funder(&public_key) == Some(env::predecessor_account_id()),
+                "Only the funder of the key can change its tag"
+            );
+            self.internal_set_key_tag(&public_key, tag);
+        }
+        if let Some(methods) = &methods {
+            assert_claim_method_names(methods);
+        }
+        let new_accounts_only = new_accounts_only.unwrap_or(false);
         assert!(
-            env::attached_deposit() > ACCESS_KEY_ALLOWANCE,
-            "Attached deposit must be greater than ACCESS_KEY_ALLOWANCE"
+            !(new_accounts_only && methods.is_some()),
+            "Methods can't be set on a key for new accounts only"
         );
-        let pk = public_key.into();
-        let zero = UncToken::from_unc(0);
-        let value = self.accounts.get(&pk).unwrap_or(&zero);
-        self.accounts.insert(
-            pk.to_owned(),
-            value.saturating_add(env::attached_deposit()).saturating_sub(ACCESS_KEY_ALLOWANCE),
-        );
-        Promise::new(env::current_account_id()).add_access_key_allowance(
-            pk,
-            Allowance::limited(ACCESS_KEY_ALLOWANCE).unwrap_or(Allowance::Unlimited),
-            env::current_account_id(),
-            ACCESS_KEY_METHOD_NAMES.to_string(),
-        )
+        let expiration = match expiration {
+            None if balance.is_none() => self.default_expiry_ns.map(|expiry_ns| {
+                env::block_timestamp().checked_add(expiry_ns).unwrap_or_else(|| overflow("default expiry"))
+            }),
+            expiration => expiration,
+        };
+        if let Some(expiration) = expiration {
+            assert!(expiration > env::block_timestamp(), "Expiration must be in the future");
+            self.key_expirations.insert(public_key.clone(), expiration);
+        }
+        let pk = public_key;
+        if let Some(balance) = balance {
+            return U128(self.internal_top_up(&pk, balance, deposit).as_attounc());
+        }
+        let allowance = self.internal_new_key_allowance(&pk, deposit);
+        self.internal_set_allowance(&pk, allowance);
+        if new_accounts_only {
+            self.new_account_keys.insert(key_hash(&pk));
+        }
+        if let Some(methods) = methods {
+            self.key_methods.insert(key_hash(&pk), methods);
+        }
+        let method_names = self.internal_key_method_names(&pk);
+        let access_key_bytes = access_key_storage_bytes(
+            pk.as_bytes().len() as u64,
+            Some((&env::current_account_id(), &method_names)),
+        );
+        let fee = self.internal_collect_fee(deposit.checked_sub(allowance).unwrap_or_else(|| overflow("send fee")));
+        let fees = allowance.checked_add(fee).unwrap_or_else(|| overflow("send fee"));
+        let balance = self.internal_fund_new_key(&pk, deposit, fees, storage_before, access_key_bytes);
+        self.internal_record_drop(balance, true);
+        add_drop_access_key(Promise::new(env::current_account_id()), pk, allowance, &method_names);
+        U128(balance.as_attounc())
     }
 
-    /// Claim tokens for specific account that are attached to the public key this tx is signed with.
-    pub fn claim(&mut self, account_id: AccountId) -> Promise {
-        assert_eq!(
-            env::predecessor_account_id(),
-            env::current_account_id(),
-            "Claim only can come from this account"
-        );
+    /// Adds the attached deposit, less the fee, to the balance of a funded key without touching its access
+    /// key. Keys of a drop or a reservation can only be topped up by their funder.
+    /// Returns the new balance of the key.
+    #[payable]
+    pub fn top_up(&mut self, public_key: PublicKey) -> U128 {
+        self.assert_funding_enabled();
         assert!(
-            env::is_valid_account_id(account_id.as_bytes()),
-            "Invalid account id"
+            env::attached_deposit() > UncToken::from_attounc(0),
+            "Attached deposit must be greater than 0"
         );
-        let amount = self
-            .accounts
-            .remove(&env::signer_account_pk())
-            .expect("Unexpected public key");
-        Promise::new(env::current_account_id()).delete_key(env::signer_account_pk());
-        Promise::new(account_id).transfer(amount)
+        self.assert_can_top_up(&public_key);
+        let old_balance = self.internal_take_key_balance(&public_key).expect("Key is missing");
+        U128(self.internal_top_up(&public_key, old_balance, env::attached_deposit()).as_attounc())
     }
 
-    /// Create new account and and claim tokens to it.
-    pub fn create_account_and_claim(
-        &mut self,
-        new_account_id: AccountId,
-        new_public_key: PublicKey,
-    ) -> Promise {
-        assert_eq!(
-            env::predecessor_account_id(),
-            env::current_account_id(),
-            "Create account and claim only can come from this account"
+    /// Funds a new key with `amount` taken from `sponsor_pool` instead of an attached deposit, and adds its
+    /// access key like `send`, e.g. for a faucet onboarding users without UNC. Only relayers can sponsor keys.
+    pub fn sponsor_key(&mut self, public_key: PublicKey, amount: U128) -> Promise {
+        assert!(
+            self.relayers.contains(&env::predecessor_account_id()),
+            "Only relayers can sponsor keys"
         );
+        self.assert_funding_enabled();
+        assert!(self.internal_key_balance(&public_key).is_none(), "Key is already funded");
+        let amount = UncToken::from_attounc(amount.0);
+        self.sponsor_pool = self
+            .sponsor_pool
+            .checked_sub(amount)
+            .unwrap_or_else(|| panic!("Sponsor pool doesn't cover the amount"));
+        let required = self.internal_min_send_deposit(&public_key);
         assert!(
-            env::is_valid_account_id(new_account_id.as_bytes()),
+            amount >= required,
+            "Amount must be at least {} attoUNC, got {}",
+            required.as_attounc(),
+            amount.as_attounc()
+        );
+        let storage_before = self.internal_key_storage_usage();
+        let allowance = self.internal_new_key_allowance(&public_key, amount);
+        self.internal_set_allowance(&public_key, allowance);
+        let method_names = self.internal_key_method_names(&public_key);
+        let access_key_bytes = access_key_storage_bytes(
+            public_key.as_bytes().len() as u64,
+            Some((&env::current_account_id(), &method_names)),
+        );
+        let balance = self.internal_fund_new_key(&public_key, amount, allowance, storage_before, access_key_bytes);
+        self.internal_record_drop(balance, true);
+        add_drop_access_key(Promise::new(env::current_account_id()), public_key, allowance, &method_names)
+    }
+
+    /// Claim tokens for specific account that are attached to the public key this tx is signed with.
+    /// The account can be an implicit account, which the transfer creates if it doesn't exist yet.
+    /// Resolves to a `ClaimOutcome::Claimed` receipt of the transfer, or returns a `ClaimOutcome::Rejected`
+    /// error instead of paying out if the claim limits are exceeded.
+    pub fn claim(&mut self, account_id: AccountId) -> PromiseOrValue<ClaimOutcome> {
+        if let Err(error) = self.check_claim_context("claim") {
+            return PromiseOrValue::Value(ClaimOutcome::Rejected(error));
+        }
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
             "Invalid account id"
         );
-        let amount = self
-            .accounts
-            .remove(&env::signer_account_pk())
-            .expect("Unexpected public key");
-        Promise::new(new_account_id)
-            .create_account()
-            .add_full_access_key(new_public_key.into())
-            .transfer(amount)
+        self.assert_not_blocked(&account_id);
+        if let Err(error) = self.internal_track_claim() {
+            return PromiseOrValue::Value(ClaimOutcome::Rejected(error));
+        }
+        let public_key = env::signer_account_pk();
+        if self.claim_locks.contains_key(&key_hash(&public_key)) {
+            return PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::ClaimInProgress));
+        }
+        self.assert_claimable(&public_key);
+        let balance = self.internal_key_balance(&public_key).expect("Unexpected public key");
+        self.assert_drip_due(&public_key);
+        let (_, payout) = self.internal_compute_claim(&public_key, balance);
+        // The key stays locked with its balance until the callback resolves the transfer
+        self.claim_locks.insert(key_hash(&public_key), env::block_height());
+        self.charge_claim_allowance(&public_key);
+        Promise::new(account_id.clone())
+            .transfer(payout)
             .then(
                 Self::ext(env::current_account_id())
-                    .with_static_gas(ON_CREATE_ACCOUNT_CALLBACK_GAS)
-                    .on_account_created_and_claimed(amount.into())
+                    .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                    .on_claimed(account_id, payout, public_key, env::block_timestamp())
             )
+            .into()
     }
 
-    /// Create new account without airdrop and deposit passed funds (used for creating sub accounts directly).
-    #[payable]
-    pub fn create_account(
+    /// Callback after the transfer of `claim`. Unlocks the key, and takes the claim from its balance if the
+    /// transfer succeeded. Returns the receipt of the claim, with the amount transferred, or 0 if the transfer
+    /// failed.
+    #[private]
+    pub fn on_claimed(
         &mut self,
-        new_account_id: AccountId,
-        new_public_key: PublicKey,
-    ) -> Promise {
+        account_id: AccountId,
+        amount: UncToken,
+        public_key: PublicKey,
+        timestamp: u64,
+    ) -> ClaimOutcome {
+        self.claim_locks.remove(&key_hash(&public_key));
+        let transfer_succeeded = is_promise_success();
+        if transfer_succeeded {
+            // The owner can unlock a stale claim, after which the key may have been claimed again
+            if self.internal_key_balance(&public_key).is_some() {
+                self.internal_claim_balance(public_key.clone(), &account_id);
+            }
+            self.claimed_accounts.insert(account_id.clone());
+        } else {
+            // In case of failure, the balance is still on the key.
+            *self.failed_attempts.entry(key_hash(&public_key)).or_insert(0) += 1;
+            self.internal_record_failure(FailureKind::Claim, Some(public_key.clone()), Some(account_id.clone()), amount);
+        }
+        ClaimOutcome::Claimed(ClaimReceipt {
+            account_id,
+            amount: U128(if transfer_succeeded { amount.as_attounc() } else { 0 }),
+            public_key,
+            timestamp,
+        })
+    }
+
+    /// Claims the balance of the key this tx is signed with into a call of `method_name` on
+    /// `receiver_contract`, with the balance as its deposit, on behalf of `account_id`: the account id is
+    /// added to the JSON object `args` under the field set for the contract with `add_call_receiver`.
+    /// If the call fails, the balance is transferred to `account_id` instead.
+    pub fn claim_and_call(
+        &mut self,
+        account_id: AccountId,
+        receiver_contract: AccountId,
+        method_name: String,
+        args: Base64VecU8,
+        gas: Gas,
+    ) -> PromiseOrValue<ClaimAmountOutcome> {
+        if let Err(error) = self.check_claim_context("claim_and_call") {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+        }
         assert!(
-            env::is_valid_account_id(new_account_id.as_bytes()),
+            env::is_valid_account_id(account_id.as_bytes()),
             "Invalid account id"
         );
-        let amount = env::attached_deposit();
-        Promise::new(new_account_id)
-            .create_account()
-            .add_full_access_key(new_public_key.into())
-            .transfer(amount)
+        self.assert_not_blocked(&account_id);
+        let account_field = self.call_receivers.get(&receiver_contract).expect("Receiver contract is not allowed");
+        let mut args: unc_sdk::serde_json::Map<String, unc_sdk::serde_json::Value> =
+            unc_sdk::serde_json::from_slice(&args.0).expect("Args must be a JSON object");
+        args.insert(account_field.clone(), account_id.to_string().into());
+        assert!(
+            env::prepaid_gas().saturating_sub(env::used_gas()) > gas.saturating_add(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS),
+            "Not enough gas attached for the call and its callback"
+        );
+        if let Err(error) = self.internal_track_claim() {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+        }
+        let public_key = env::signer_account_pk();
+        if self.claim_locks.contains_key(&key_hash(&public_key)) {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(ClaimError::ClaimInProgress));
+        }
+        self.assert_claimable(&public_key);
+        self.charge_claim_allowance(&public_key);
+        let payout = self.internal_claim_balance(public_key, &account_id);
+        Promise::new(receiver_contract)
+            .function_call(method_name, unc_sdk::serde_json::to_vec(&args).unwrap(), payout, gas)
             .then(
                 Self::ext(env::current_account_id())
-                    .with_static_gas(ON_CREATE_ACCOUNT_CALLBACK_GAS)
-                    .on_account_created(
-                        env::predecessor_account_id(),
-                        amount.into()
-                    )
+                    .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                    .on_claim_called(account_id, payout)
             )
+            .into()
     }
 
-    /// Create new account without airdrop and deposit passed funds (used for creating sub accounts directly).
-    #[payable]
-    pub fn create_account_advanced(
-        &mut self,
-        new_account_id: AccountId,
-        options: CreateAccountOptions,
-    ) -> Promise {
-        let is_some_option = options.contract_bytes.is_some() || options.full_access_keys.is_some() || options.limited_access_keys.is_some();
-        assert!(is_some_option, "Cannot create account with no options. Please specify either contract bytes, full access keys, or limited access keys.");
-
-        let amount = env::attached_deposit();
-
-        // Initiate a new promise on the new account we're creating and transfer it any attached deposit
-        let mut promise = Promise::new(new_account_id).create_account().transfer(amount);
-        
-        // If there are any full access keys in the options, loop through and add them to the promise
-        if let Some(full_access_keys) = options.full_access_keys {
-            for key in full_access_keys {
-                promise = promise.add_full_access_key(key.clone());
-            }
+    /// Callback after the call of `claim_and_call`. If it failed, its deposit is refunded to the contract,
+    /// so it is transferred to `account_id`. Returns the amount claimed either way.
+    pub fn on_claim_called(&mut self, account_id: AccountId, amount: UncToken) -> ClaimAmountOutcome {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        let call_succeeded = is_promise_success();
+        if call_succeeded {
+            self.claimed_accounts.insert(account_id);
+        } else {
+            self.internal_record_failure(
+                FailureKind::ClaimAndCall,
+                Some(env::signer_account_pk()),
+                Some(account_id.clone()),
+                amount,
+            );
+            Promise::new(account_id).transfer(amount);
         }
+        ClaimAmountOutcome::Claimed(U128(amount.as_attounc()))
+    }
 
-        // If there are any function call access keys in the options, loop through and add them to the promise
-        if let Some(limited_access_keys) = options.limited_access_keys {
-            for key_info in limited_access_keys {
-                promise = promise.add_access_key_allowance(key_info.public_key.clone(), Allowance::limited(key_info.allowance).unwrap_or(Allowance::Unlimited), key_info.receiver_id.clone(), key_info.method_names.clone());
-            }
+    /// Claims the balance of the key this tx is signed with as wrapped UNC: it is deposited in the wrap contract
+    /// set with `set_wrap_contract`, and the wrapped tokens minted to the contract are transferred to
+    /// `account_id`, which must be registered on the wrap contract. The contract must be registered there too.
+    /// If the deposit fails, the balance is transferred to `account_id` instead.
+    pub fn claim_wrapped(&mut self, account_id: AccountId) -> PromiseOrValue<ClaimAmountOutcome> {
+        if let Err(error) = self.check_claim_context("claim_wrapped") {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
         }
-
-        // If there are any contract bytes, we should deploy the contract to the account
-        if let Some(bytes) = options.contract_bytes {
-            promise = promise.deploy_contract(bytes);
-        };
-
-        // Callback if anything went wrong, refund the predecessor for their attached deposit
-        promise.then(
-            Self::ext(env::current_account_id())
-                .with_static_gas(ON_CREATE_ACCOUNT_CALLBACK_GAS)
-                .on_account_created(
-                    env::predecessor_account_id(),
-                    amount.into()
-                )
-        )
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
+            "Invalid account id"
+        );
+        self.assert_not_blocked(&account_id);
+        let wrap_contract_id = self.wrap_contract_id.clone().expect("Wrapped claims are not enabled");
+        if let Err(error) = self.internal_track_claim() {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+        }
+        let public_key = env::signer_account_pk();
+        if self.claim_locks.contains_key(&key_hash(&public_key)) {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(ClaimError::ClaimInProgress));
+        }
+        self.assert_claimable(&public_key);
+        self.charge_claim_allowance(&public_key);
+        let payout = self.internal_claim_balance(public_key, &account_id);
+        ext_wrap::ext(wrap_contract_id.clone())
+            .with_attached_deposit(payout)
+            .with_static_gas(WRAP_DEPOSIT_GAS)
+            .unc_deposit()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(FT_TRANSFER_GAS.saturating_add(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS.saturating_mul(2)))
+                    .with_unused_gas_weight(1)
+                    .on_wrapped(account_id, wrap_contract_id, payout)
+            )
+            .into()
     }
 
-    /// Callback after executing `create_account` or `create_account_advanced`.
-    pub fn on_account_created(&mut self, predecessor_account_id: AccountId, amount: UncToken) -> bool {
+    /// Callback after the deposit of `claim_wrapped`. Forwards the wrapped tokens to `account_id`, or transfers
+    /// the refunded deposit to it if the deposit failed and returns the amount claimed.
+    pub fn on_wrapped(
+        &mut self,
+        account_id: AccountId,
+        wrap_contract_id: AccountId,
+        amount: UncToken,
+    ) -> PromiseOrValue<ClaimAmountOutcome> {
         assert_eq!(
             env::predecessor_account_id(),
             env::current_account_id(),
             "Callback can only be called from the contract"
         );
-        let creation_succeeded = is_promise_success();
-        if !creation_succeeded {
-            // In case of failure, send funds back.
-            Promise::new(predecessor_account_id).transfer(amount.into());
+        if !is_promise_success() {
+            self.internal_record_failure(
+                FailureKind::ClaimWrapped,
+                Some(env::signer_account_pk()),
+                Some(account_id.clone()),
+                amount,
+            );
+            Promise::new(account_id).transfer(amount);
+            return PromiseOrValue::Value(ClaimAmountOutcome::Claimed(U128(amount.as_attounc())));
         }
-        creation_succeeded
+        ext_ft::ext(wrap_contract_id)
+            .with_attached_deposit(UncToken::from_attounc(1))
+            .with_static_gas(FT_TRANSFER_GAS)
+            .ft_transfer(account_id.clone(), U128(amount.as_attounc()), None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                    .on_wrapped_transferred(account_id, amount)
+            )
+            .into()
     }
 
-    /// Callback after execution `create_account_and_claim`.
-    pub fn on_account_created_and_claimed(&mut self, amount: UncToken) -> bool {
+    /// Callback after the transfer of the wrapped tokens of `claim_wrapped`. If it failed, e.g. because
+    /// `account_id` isn't registered on the wrap contract, the tokens stay with the contract and the failure
+    /// is recorded for support. Returns the amount claimed, or 0 if the transfer failed.
+    pub fn on_wrapped_transferred(&mut self, account_id: AccountId, amount: UncToken) -> ClaimAmountOutcome {
         assert_eq!(
             env::predecessor_account_id(),
             env::current_account_id(),
             "Callback can only be called from the contract"
         );
-        let creation_succeeded = is_promise_success();
-        if creation_succeeded {
-            Promise::new(env::current_account_id()).delete_key(env::signer_account_pk());
+        let transfer_succeeded = is_promise_success();
+        if transfer_succeeded {
+            self.claimed_accounts.insert(account_id);
         } else {
-            // In case of failure, put the amount back.
-            self.accounts
-                .insert(env::signer_account_pk(), amount.into());
+            self.internal_record_failure(
+                FailureKind::ClaimWrapped,
+                Some(env::signer_account_pk()),
+                Some(account_id),
+                amount,
+            );
+            return ClaimAmountOutcome::Claimed(U128(0));
         }
-        creation_succeeded
+        ClaimAmountOutcome::Claimed(U128(amount.as_attounc()))
+    }
+
+    /// Like `send`, but the balance is released in drips of at most `drip_amount`, one per `interval_ns`
+    /// nanoseconds, so the key is claimed several times. With an `interval_ns` of 0, `drip_amount` only caps
+    /// what each claim releases. The allowance of the access key covers a claim per drip, so `drip_amount`
+    /// can't be so small that the allowance takes the whole deposit. Only new keys can be funded with drips,
+    /// keys that have a balance are topped up with `top_up`.
+    #[payable]
+    pub fn send_drip(
+        &mut self,
+        public_key: PublicKey,
+        drip_amount: U128,
+        interval_ns: u64,
+        tag: Option<String>,
+    ) -> U128 {
+        assert!(drip_amount.0 > 0, "Drip amount must be positive");
+        assert!(
+            self.internal_key_balance(&public_key).is_none(),
+            "Key already has a balance, top it up with top_up"
+        );
+        let drip_amount = UncToken::from_attounc(drip_amount.0);
+        assert!(
+            self.internal_drip_allowance(&public_key, drip_amount, env::attached_deposit())
+                .and_then(|allowance| allowance.checked_add(self.internal_min_drop_amount()))
+                .is_some_and(|required| required <= env::attached_deposit()),
+            "Drip amount is too small: the allowance of a claim per drip would take the whole deposit"
+        );
+        self.drips.insert(
+            key_hash(&public_key),
+            Drip {
+                amount: drip_amount,
+                interval_ns,
+                last_claim_ns: None,
+            },
+        );
+        self.send(public_key, tag, None, None, None, None)
+    }
+
+    /// Adds the tokens transferred with `ft_transfer_call` to the key whose public key is `msg`, which must
+    /// be funded with `send` first to pay for its access key. Only the FT contracts added with `add_ft_contract`
+    /// can credit tokens, the transfers of the others are refunded. A key holds the tokens of a single FT contract.
+    /// The key claims them with `claim_ft_with_registration`, or they are released to the account its balance is
+    /// claimed to.
+    #[allow(unused_variables)]
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        assert!(
+            self.ft_contracts.contains(&env::predecessor_account_id()),
+            "Token contract is not allowed"
+        );
+        let public_key: PublicKey = msg.parse().expect("Message must be the public key to fund");
+        assert!(
+            self.internal_key_balance(&public_key).is_some(),
+            "Key must be funded with send first"
+        );
+        self.internal_credit_tokens(&public_key, env::predecessor_account_id(), amount.0);
+        PromiseOrValue::Value(U128(0))
+    }
+
+    /// Claims the tokens of the key this tx is signed with to `account_id`, registering the account on
+    /// the FT contract first with a `storage_deposit` of `ft_storage_deposit` taken from the key balance.
+    /// If the registration or the transfer fails, the tokens are put back on the key.
+    pub fn claim_ft_with_registration(&mut self, account_id: AccountId) -> PromiseOrValue<ClaimAmountOutcome> {
+        if let Err(error) = self.check_claim_context("claim_ft_with_registration") {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+        }
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
+            "Invalid account id"
+        );
+        self.assert_not_blocked(&account_id);
+        if let Err(error) = self.internal_track_claim() {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+        }
+        let public_key = env::signer_account_pk();
+        if self.claim_locks.contains_key(&key_hash(&public_key)) {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(ClaimError::ClaimInProgress));
+        }
+        self.assert_claimable(&public_key);
+        let tokens = self.ft_balances.remove(&key_hash(&public_key)).expect("Key has no tokens");
+        let balance = self
+            .internal_take_key_balance(&public_key)
+            .expect("Unexpected public key");
+        let storage_deposit = self.ft_storage_deposit;
+        assert!(
+            balance >= storage_deposit,
+            "Key balance doesn't cover the storage deposit"
+        );
+        let balance = balance.checked_sub(storage_deposit).unwrap_or_else(|| overflow("FT storage deposit"));
+        self.internal_set_key_balance(&public_key, balance);
+        if let Some(drop_id) = self.key_drops.get(&key_hash(&public_key)) {
+            let drop = self.drop_configs.get_mut(drop_id).unwrap();
+            drop.remaining = drop.remaining.checked_sub(storage_deposit).unwrap_or_else(|| overflow("drop remaining"));
+        }
+        // The key stays locked until the callbacks put back what wasn't claimed
+        self.claim_locks.insert(key_hash(&public_key), env::block_height());
+        self.charge_claim_allowance(&public_key);
+        ext_ft_storage::ext(tokens.ft_contract_id.clone())
+            .with_attached_deposit(storage_deposit)
+            .with_static_gas(self.ft_storage_deposit_gas)
+            .storage_deposit(Some(account_id.clone()), Some(true))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(FT_TRANSFER_GAS.saturating_add(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS))
+                    .with_unused_gas_weight(1)
+                    .on_ft_storage_deposited(public_key, account_id, tokens.ft_contract_id, tokens.amount, storage_deposit)
+            )
+            .into()
+    }
+
+    /// Callback after the `storage_deposit` of `claim_ft_with_registration`, transfers the tokens
+    /// if the account got registered, or returns 0 tokens claimed.
+    pub fn on_ft_storage_deposited(
+        &mut self,
+        public_key: PublicKey,
+        account_id: AccountId,
+        ft_contract_id: AccountId,
+        amount: U128,
+        storage_deposit: UncToken,
+    ) -> PromiseOrValue<ClaimAmountOutcome> {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        if !is_promise_success() {
+            self.claim_locks.remove(&key_hash(&public_key));
+            // The storage deposit is refunded to the contract, so it goes back to the key with the tokens
+            let balance = self.internal_take_key_balance(&public_key).unwrap_or(UncToken::from_attounc(0));
+            let balance = balance.checked_add(storage_deposit).unwrap_or_else(|| overflow("FT storage refund"));
+            self.internal_set_key_balance(&public_key, balance);
+            if let Some(drop_id) = self.key_drops.get(&key_hash(&public_key)) {
+                let drop = self.drop_configs.get_mut(drop_id).unwrap();
+                drop.remaining = drop.remaining.checked_add(storage_deposit).unwrap_or_else(|| overflow("drop remaining"));
+            }
+            self.internal_credit_tokens(&public_key, ft_contract_id, amount.0);
+            return PromiseOrValue::Value(ClaimAmountOutcome::Claimed(U128(0)));
+        }
+        ext_ft::ext(ft_contract_id.clone())
+            .with_attached_deposit(UncToken::from_attounc(1))
+            .with_static_gas(FT_TRANSFER_GAS)
+            .ft_transfer(account_id, amount, None)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                    .on_ft_transferred(public_key, ft_contract_id, amount)
+            )
+            .into()
+    }
+
+    /// Callback after the `ft_transfer` of `claim_ft_with_registration`, puts the tokens back on the key
+    /// if the transfer failed. Returns the tokens claimed, or 0 if the transfer failed.
+    pub fn on_ft_transferred(&mut self, public_key: PublicKey, ft_contract_id: AccountId, amount: U128) -> ClaimAmountOutcome {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        self.claim_locks.remove(&key_hash(&public_key));
+        if !is_promise_success() {
+            self.internal_credit_tokens(&public_key, ft_contract_id, amount.0);
+            return ClaimAmountOutcome::Claimed(U128(0));
+        }
+        ClaimAmountOutcome::Claimed(amount)
+    }
+
+    /// Claims the tokens of the key this tx is signed with to the contract `account_id` with `ft_transfer_call`,
+    /// passing `msg` to its `ft_on_transfer`. The account must already be registered on the FT contract.
+    /// The tokens the receiver doesn't use are put back on the key.
+    pub fn claim_ft_call(&mut self, account_id: AccountId, msg: String) -> PromiseOrValue<ClaimAmountOutcome> {
+        if let Err(error) = self.check_claim_context("claim_ft_call") {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+        }
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
+            "Invalid account id"
+        );
+        self.assert_not_blocked(&account_id);
+        if let Err(error) = self.internal_track_claim() {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+        }
+        let public_key = env::signer_account_pk();
+        if self.claim_locks.contains_key(&key_hash(&public_key)) {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(ClaimError::ClaimInProgress));
+        }
+        self.assert_claimable(&public_key);
+        let tokens = self.ft_balances.remove(&key_hash(&public_key)).expect("Key has no tokens");
+        self.claim_locks.insert(key_hash(&public_key), env::block_height());
+        self.charge_claim_allowance(&public_key);
+        ext_ft::ext(tokens.ft_contract_id.clone())
+            .with_attached_deposit(UncToken::from_attounc(1))
+            .with_static_gas(FT_TRANSFER_CALL_GAS)
+            .ft_transfer_call(account_id, tokens.amount, None, msg)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                    .on_ft_transfer_called(public_key, tokens.ft_contract_id, tokens.amount)
+            )
+            .into()
+    }
+
+    /// Callback after the `ft_transfer_call` of `claim_ft_call`, puts the tokens the receiver didn't use back
+    /// on the key, or all of them if the transfer failed. Returns the tokens used as the amount claimed.
+    pub fn on_ft_transfer_called(
+        &mut self,
+        public_key: PublicKey,
+        ft_contract_id: AccountId,
+        amount: U128,
+    ) -> ClaimAmountOutcome {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        self.claim_locks.remove(&key_hash(&public_key));
+        let used = match env::promise_result(0) {
+            // The FT contract returns the amount used, which can't be more than what was transferred
+            PromiseResult::Successful(value) => unc_sdk::serde_json::from_slice::<U128>(&value)
+                .map(|used| used.0.min(amount.0))
+                .unwrap_or(amount.0),
+            PromiseResult::Failed => 0,
+        };
+        let unused = amount.0 - used;
+        if unused > 0 {
+            self.internal_credit_tokens(&public_key, ft_contract_id, unused);
+        }
+        ClaimAmountOutcome::Claimed(U128(used))
+    }
+
+    /// Returns the comma separated claim methods the access key of the funded key can call, or `None` if the
+    /// key isn't funded.
+    pub fn get_key_methods(&self, public_key: PublicKey) -> Option<String> {
+        self.internal_key_balance(&public_key)
+            .map(|_| self.internal_key_method_names(&public_key))
+    }
+
+    /// Returns the fungible tokens the key can claim, if any.
+    pub fn get_key_tokens(&self, key: PublicKey) -> Option<&FtBalance> {
+        self.ft_balances.get(&key_hash(&key))
+    }
+
+    /// Creates a campaign whose keys are funded with `send_to_drop`. Its keys can only be claimed from
+    /// `starts_at` and until `ends_at` (block timestamps in nanoseconds) and while it isn't paused.
+    /// The caller becomes the funder of the drop. With `auto_withdraw` the drop is deleted once its last key
+    /// is claimed, and its storage cost and leftover balance are refunded to the funder.
+    /// `allowed_methods` restricts the methods the keys of the drop can call to a comma separated subset
+    /// of `claim` and `create_account_and_claim`, e.g. to force the creation of new accounts.
+    /// With `new_accounts_only` the keys can only be claimed to new accounts, like the keys `send` funds with
+    /// `new_accounts_only`. It can't be combined with `allowed_methods`.
+    /// Returns the id of the new drop.
+    pub fn create_drop(
+        &mut self,
+        metadata: Option<String>,
+        starts_at: Option<u64>,
+        ends_at: Option<u64>,
+        auto_withdraw: Option<bool>,
+        allowed_methods: Option<String>,
+        new_accounts_only: Option<bool>,
+    ) -> u64 {
+        self.assert_drop_metadata(&metadata);
+        if let Some(allowed_methods) = &allowed_methods {
+            assert_claim_method_names(allowed_methods);
+        }
+        let new_accounts_only = new_accounts_only.unwrap_or(false);
+        assert!(
+            !(new_accounts_only && allowed_methods.is_some()),
+            "Methods can't be set on a drop for new accounts only"
+        );
+        if let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at) {
+            assert!(starts_at < ends_at, "Drop must start before it ends");
+        }
+        let drop_id = self.next_drop_id;
+        self.next_drop_id += 1;
+        self.drop_configs.insert(
+            drop_id,
+            DropConfig {
+                funder: env::predecessor_account_id(),
+                metadata,
+                starts_at,
+                ends_at,
+                paused: false,
+                keys_added: 0,
+                keys_claimed: 0,
+                keys_reclaimed: 0,
+                claims: 0,
+                claimed: UncToken::from_attounc(0),
+                refunded: UncToken::from_attounc(0),
+                remaining: UncToken::from_attounc(0),
+                auto_withdraw: auto_withdraw.unwrap_or(false),
+                allowed_methods,
+                new_accounts_only,
+            },
+        );
+        self.drops_by_funder
+            .entry(env::predecessor_account_id())
+            .or_default()
+            .push(drop_id);
+        drop_id
+    }
+
+    /// Like `send`, but adds the key to the drop `drop_id`. Only the funder of the drop can add keys to it,
+    /// and a key can only be topped up in the drop it was added to.
+    #[payable]
+    pub fn send_to_drop(&mut self, drop_id: u64, public_key: PublicKey) -> U128 {
+        self.internal_funded_drop(drop_id);
+        let previous_balance = self.internal_key_balance(&public_key);
+        if previous_balance.is_some() {
+            assert_eq!(
+                self.key_drops.get(&key_hash(&public_key)),
+                Some(&drop_id),
+                "Key belongs to another drop"
+            );
+        }
+        if previous_balance.is_none() {
+            // Linked first so the access key is added with the methods of the drop
+            self.key_drops.insert(key_hash(&public_key), drop_id);
+        }
+        let balance = self.send(public_key.clone(), None, None, None, None, None);
+        if previous_balance.is_none() {
+            // Top-ups of keys already in the drop are accounted for by `send`
+            let drop = self.drop_configs.get_mut(&drop_id).unwrap();
+            drop.remaining = drop
+                .remaining
+                .checked_add(UncToken::from_attounc(balance.0))
+                .unwrap_or_else(|| overflow("drop remaining"));
+            drop.keys_added += 1;
+        }
+        balance
+    }
+
+    /// Stops the claims of the keys of the drop until it is unpaused. Only the funder can pause it.
+    pub fn pause_drop(&mut self, drop_id: u64) {
+        self.internal_funded_drop(drop_id).paused = true;
+    }
+
+    /// Resumes the claims of the keys of a paused drop. Only the funder can unpause it.
+    pub fn unpause_drop(&mut self, drop_id: u64) {
+        self.internal_funded_drop(drop_id).paused = false;
+    }
+
+    /// Replaces the metadata of the drop. Only the funder can update it.
+    pub fn update_drop_metadata(&mut self, drop_id: u64, metadata: Option<String>) {
+        self.assert_drop_metadata(&metadata);
+        self.internal_funded_drop(drop_id).metadata = metadata;
+    }
+
+    /// Makes `new_funder` the funder of the drop, e.g. when an agency hands a campaign over to its client.
+    /// From then on only `new_funder` can manage the drop. Only the funder can transfer it.
+    #[payable]
+    pub fn transfer_drop_ownership(&mut self, drop_id: u64, new_funder: AccountId) {
+        assert_one_atto();
+        assert_ne!(new_funder, env::current_account_id(), "Cannot transfer a drop to the contract");
+        self.assert_not_blocked(&new_funder);
+        let drop = self.internal_funded_drop(drop_id);
+        let old_funder = std::mem::replace(&mut drop.funder, new_funder.clone());
+        self.internal_remove_funder_drop(&old_funder, drop_id);
+        self.drops_by_funder.entry(new_funder.clone()).or_default().push(drop_id);
+        AirDropEvent::DropOwnershipTransferred {
+            drop_id,
+            old_funder: &old_funder,
+            new_funder: &new_funder,
+        }
+        .emit();
+    }
+
+    /// Returns the ids of the drops funded by `funder`.
+    pub fn get_drops_by_funder(&self, funder: AccountId) -> Vec<u64> {
+        self.drops_by_funder.get(&funder).cloned().unwrap_or_default()
+    }
+
+    /// Returns the config of a drop with its counters.
+    pub fn get_drop(&self, drop_id: u64) -> Option<&DropConfig> {
+        self.drop_configs.get(&drop_id)
+    }
+
+    /// Returns up to `limit` claims of the keys of the drop, starting at `from_index`, in the order they were
+    /// made, e.g. for an accounting export. The claims of a drop deleted by `auto_withdraw` are no longer
+    /// exported.
+    pub fn export_claims(&self, drop_id: u64, from_index: u64, limit: u64) -> Vec<ClaimExportRow> {
+        let drop = self.drop_configs.get(&drop_id).expect("No such drop");
+        let end = std::cmp::min(from_index.saturating_add(limit), drop.claims);
+        (from_index..end)
+            .map(|index| ClaimExportRow::from(&self.claim_history[self.drop_claims[&(drop_id, index)]]))
+            .collect()
+    }
+
+    /// Returns what was claimed from and reclaimed from the keys of the drop, and what is left to claim.
+    pub fn get_drop_totals(&self, drop_id: u64) -> DropTotals {
+        let drop = self.drop_configs.get(&drop_id).expect("No such drop");
+        DropTotals {
+            claimed: U128(drop.claimed.as_attounc()),
+            refunded: U128(drop.refunded.as_attounc()),
+            outstanding: U128(drop.remaining.as_attounc()),
+        }
+    }
+
+    /// Returns the nanoseconds left before the next drip of the key can be claimed, 0 if it can be claimed now.
+    pub fn time_until_next_drip(&self, public_key: PublicKey) -> u64 {
+        let drip = self.drips.get(&key_hash(&public_key)).expect("Key has no drip");
+        drip.next_claim_ns().saturating_sub(env::block_timestamp())
+    }
+
+    /// Claims the balance of `public_key` to `account_id` without a transaction signed by the key,
+    /// e.g. when the recipient lost it. Only the owner can force a claim.
+    #[payable]
+    pub fn force_claim(&mut self, public_key: PublicKey, account_id: AccountId) -> Promise {
+        self.assert_owner();
+        assert_one_atto();
+        self.assert_not_blocked(&account_id);
+        self.assert_not_claim_locked(&public_key);
+        let amount = self
+            .internal_take_key_balance(&public_key)
+            .expect("Unexpected public key");
+        self.internal_record_claim(public_key.clone(), account_id.clone(), amount, false);
+        self.internal_count_key_claimed(&public_key);
+        let storage_stake = self.internal_forget_key(&public_key);
+        Promise::new(env::current_account_id()).delete_key(public_key);
+        Promise::new(account_id.clone())
+            .transfer(amount.checked_add(storage_stake).unwrap_or_else(|| overflow("claim")))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                    .on_force_claimed(account_id)
+            )
+    }
+
+    /// Callback after the transfer of `force_claim`. Returns whether the transfer succeeded, in which case the
+    /// account counts as claimed.
+    #[private]
+    pub fn on_force_claimed(&mut self, account_id: AccountId) -> bool {
+        let transfer_succeeded = is_promise_success();
+        if transfer_succeeded {
+            self.claimed_accounts.insert(account_id);
+        }
+        transfer_succeeded
+    }
+
+    /// Moves the balance and everything tracked about an unclaimed key to `new_public_key`, e.g. when the old
+    /// key is suspected compromised. The old access key is deleted and the new one gets the allowance `send`
+    /// would give it, paid with what is left of the old allowance and, if that is not enough, the balance.
+    /// Only the owner can rotate keys.
+    #[payable]
+    pub fn rotate_key(&mut self, old_public_key: PublicKey, new_public_key: PublicKey) -> Promise {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(
+            self.internal_key_balance(&new_public_key).is_none(),
+            "New public key already has a balance"
+        );
+        self.assert_not_claim_locked(&old_public_key);
+        let balance = self
+            .internal_take_key_balance(&old_public_key)
+            .expect("Unexpected public key");
+        self.internal_move_key_state(&old_public_key, &new_public_key);
+        let old_allowance = self.allowances.get(&old_public_key).copied().unwrap_or(UncToken::from_attounc(0));
+        self.internal_remove_allowance(&old_public_key);
+        let allowance = self.internal_new_key_allowance(&new_public_key, balance);
+        let balance = balance
+            .checked_add(old_allowance)
+            .and_then(|balance| balance.checked_sub(allowance))
+            .filter(|balance| !balance.is_zero())
+            .expect("Key balance doesn't cover the allowance of the new access key");
+        self.internal_set_key_balance(&new_public_key, balance);
+        self.internal_set_allowance(&new_public_key, allowance);
+        let method_names = self.internal_key_method_names(&new_public_key);
+        add_drop_access_key(
+            Promise::new(env::current_account_id()).delete_key(old_public_key),
+            new_public_key,
+            allowance,
+            &method_names,
+        )
+    }
+
+    /// Moves `amount` of the balance of `from_public_key` to `to_public_key`, e.g. to reorganize allocations
+    /// before a launch. Moving the whole balance also moves the storage stake of the source key, which is then
+    /// forgotten and its access key deleted. A destination that isn't funded yet gets an access key with the
+    /// allowance `send` would give it, taken with its storage stake from the moved amount. The keys of drops can't be used, so that
+    /// the drop balances stay right. Only the owner can move balances. Returns the new balance of `to_public_key`.
+    #[payable]
+    pub fn move_balance(&mut self, from_public_key: PublicKey, to_public_key: PublicKey, amount: U128) -> U128 {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(from_public_key != to_public_key, "Keys must be different");
+        assert!(amount.0 > 0, "Amount must be positive");
+        for key in [&from_public_key, &to_public_key] {
+            self.assert_not_claim_locked(key);
+            assert!(!self.key_drops.contains_key(&key_hash(key)), "Balances of drop keys can't be moved");
+        }
+        let amount = UncToken::from_attounc(amount.0);
+        let balance = self
+            .internal_take_key_balance(&from_public_key)
+            .expect("Unexpected public key");
+        assert!(
+            amount <= balance,
+            "Source key has {} attoUNC, less than {}",
+            balance.as_attounc(),
+            amount.as_attounc()
+        );
+        let amount = if amount == balance {
+            let storage_stake = self.internal_forget_key(&from_public_key);
+            Promise::new(env::current_account_id()).delete_key(from_public_key);
+            amount.checked_add(storage_stake).unwrap_or_else(|| overflow("move balance"))
+        } else {
+            self.internal_set_key_balance(
+                &from_public_key,
+                balance.checked_sub(amount).unwrap_or_else(|| overflow("move balance")),
+            );
+            amount
+        };
+
+        if let Some(balance) = self.internal_take_key_balance(&to_public_key) {
+            let new_balance = balance.checked_add(amount).unwrap_or_else(|| overflow("move balance"));
+            self.internal_set_key_balance(&to_public_key, new_balance);
+            return U128(new_balance.as_attounc());
+        }
+        let storage_before = self.internal_key_storage_usage();
+        let allowance = self.internal_new_key_allowance(&to_public_key, amount);
+        assert!(amount > allowance, "Amount doesn't cover the allowance of the new access key");
+        self.internal_set_allowance(&to_public_key, allowance);
+        let method_names = self.internal_key_method_names(&to_public_key);
+        let access_key_bytes = access_key_storage_bytes(
+            to_public_key.as_bytes().len() as u64,
+            Some((&env::current_account_id(), &method_names)),
+        );
+        let new_balance = self.internal_fund_new_key(&to_public_key, amount, allowance, storage_before, access_key_bytes);
+        add_drop_access_key(Promise::new(env::current_account_id()), to_public_key, allowance, &method_names);
+        U128(new_balance.as_attounc())
+    }
+
+    /// Allows the redemption code hashing to `code_hash` (sha256) to claim the attached deposit.
+    /// Unlike `send` no access key is added, so no allowance is taken from the deposit.
+    #[payable]
+    pub fn send_with_code(&mut self, code_hash: Base58CryptoHash) {
+        self.assert_funding_enabled();
+        assert!(
+            env::attached_deposit() > UncToken::from_attounc(0),
+            "Attached deposit must be greater than 0"
+        );
+        let code_hash = CryptoHash::from(code_hash);
+        let zero = UncToken::from_unc(0);
+        let value = self.codes.get(&code_hash).unwrap_or(&zero);
+        let is_new = value.is_zero();
+        let value = value.checked_add(env::attached_deposit()).unwrap_or_else(|| overflow("code top-up"));
+        self.codes.insert(code_hash, value);
+        self.internal_add_liability(env::attached_deposit());
+        self.internal_record_drop(env::attached_deposit(), is_new);
+    }
+
+    /// Funds an ED25519 key claimed with `claim_signed` instead of a transaction signed with the key.
+    /// Unlike `send` no access key is added, so only the storage stake is taken from the deposit.
+    /// Sending to a key that still has a balance tops it up. Returns the balance of the key.
+    #[payable]
+    pub fn send_signed(&mut self, public_key: PublicKey) -> U128 {
+        self.assert_funding_enabled();
+        assert!(
+            matches!(public_key.curve_type(), CurveType::ED25519),
+            "Only ED25519 keys can sign claims"
+        );
+        let deposit = env::attached_deposit();
+        let balance = self.internal_take_key_balance(&public_key);
+        let is_new = balance.is_none();
+        let required = if is_new {
+            self.internal_min_drop_amount()
+        } else {
+            UncToken::from_attounc(1)
+        };
+        assert!(
+            deposit >= required,
+            "Attached deposit must be at least {} attoUNC, got {}",
+            required.as_attounc(),
+            deposit.as_attounc()
+        );
+        let balance = match balance {
+            Some(balance) => {
+                let balance = balance.checked_add(deposit).unwrap_or_else(|| overflow("send signed"));
+                self.internal_set_key_balance(&public_key, balance);
+                self.internal_record_drop(deposit, false);
+                balance
+            }
+            None => {
+                let storage_before = self.internal_key_storage_usage();
+                let balance = self.internal_fund_new_key(&public_key, deposit, UncToken::from_attounc(0), storage_before, 0);
+                self.internal_record_drop(balance, true);
+                balance
+            }
+        };
+        U128(balance.as_attounc())
+    }
+
+    /// Claims the balance of `public_key` to `account_id` with an ED25519 `signature` of
+    /// `signed_claim_message(contract_id, account_id, nonce)` by the key, `contract_id` being the account of
+    /// this contract. Can be called by anyone, e.g. a relayer sponsoring the gas. A nonce can only be used
+    /// once per key.
+    /// Resolves to the amount claimed, or returns a `ClaimAmountOutcome::Rejected` error instead of paying out
+    /// if the claim limits are exceeded.
+    pub fn claim_signed(
+        &mut self,
+        account_id: AccountId,
+        public_key: PublicKey,
+        signature: Vec<u8>,
+        nonce: u64,
+    ) -> PromiseOrValue<ClaimAmountOutcome> {
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
+            "Invalid account id"
+        );
+        self.assert_not_blocked(&account_id);
+        self.internal_verify_claim_signature(&public_key, &account_id, signature, nonce);
+        if let Err(error) = self.internal_track_claim() {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+        }
+        if self.claim_locks.contains_key(&key_hash(&public_key)) {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(ClaimError::ClaimInProgress));
+        }
+        let payout = self.internal_lock_signed_key(&public_key);
+        Promise::new(account_id.clone())
+            .transfer(payout)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                    .on_signed_claimed(account_id, vec![public_key], payout)
+            )
+            .into()
+    }
+
+    /// Claims the balances of several keys to `account_id` in one transfer, each key with an ED25519
+    /// signature of `signed_claim_message(contract_id, account_id, nonce)` like `claim_signed`, e.g. for a recipient
+    /// funded by several campaigns. `signatures[i]` is the signature of `public_keys[i]`. Keys without a
+    /// balance are skipped. Can be called by anyone.
+    /// Resolves to the amount claimed, or returns a `ClaimAmountOutcome::Rejected` error instead of paying out
+    /// if the claim limits are exceeded.
+    pub fn claim_all(
+        &mut self,
+        account_id: AccountId,
+        public_keys: Vec<PublicKey>,
+        signatures: Vec<Vec<u8>>,
+        nonce: u64,
+    ) -> PromiseOrValue<ClaimAmountOutcome> {
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
+            "Invalid account id"
+        );
+        self.assert_not_blocked(&account_id);
+        assert!(public_keys.len() == signatures.len(), "Each key needs a signature");
+        assert!(
+            public_keys.len() <= MAX_CLAIM_ALL_KEYS,
+            "At most {} keys can be claimed at once",
+            MAX_CLAIM_ALL_KEYS
+        );
+        let mut keys = Vec::new();
+        for (public_key, signature) in public_keys.into_iter().zip(signatures) {
+            if self.internal_key_balance(&public_key).is_none() {
+                continue;
+            }
+            self.internal_verify_claim_signature(&public_key, &account_id, signature, nonce);
+            assert!(!self.claim_locks.contains_key(&key_hash(&public_key)), "Key has a claim in progress");
+            keys.push(public_key);
+        }
+        assert!(!keys.is_empty(), "None of the keys is funded");
+        // Every key counts towards the claim limits, checked before any balance is taken
+        for _ in &keys {
+            if let Err(error) = self.internal_track_claim() {
+                return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+            }
+        }
+        let mut payout = UncToken::from_attounc(0);
+        for public_key in &keys {
+            let claimed = self.internal_lock_signed_key(public_key);
+            payout = payout.checked_add(claimed).unwrap_or_else(|| overflow("claim all"));
+        }
+        Promise::new(account_id.clone())
+            .transfer(payout)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                    .with_unused_gas_weight(1)
+                    .on_signed_claimed(account_id, keys, payout)
+            )
+            .into()
+    }
+
+    /// Callback after the transfer of `claim_signed` and `claim_all`. Unlocks the keys, and takes their balances
+    /// if the transfer succeeded. Returns the amount claimed, or 0 if the transfer failed.
+    #[private]
+    pub fn on_signed_claimed(
+        &mut self,
+        account_id: AccountId,
+        public_keys: Vec<PublicKey>,
+        amount: UncToken,
+    ) -> ClaimAmountOutcome {
+        for public_key in &public_keys {
+            self.claim_locks.remove(&key_hash(public_key));
+        }
+        if !is_promise_success() {
+            // In case of failure, the balances are still on the keys. The nonces stay used.
+            return ClaimAmountOutcome::Claimed(U128(0));
+        }
+        for public_key in public_keys {
+            // The owner can unlock a stale claim, after which the key may have been claimed again
+            if self.internal_key_balance(&public_key).is_some() {
+                self.internal_claim_signed_key(public_key, &account_id);
+            }
+        }
+        self.claimed_accounts.insert(account_id);
+        ClaimAmountOutcome::Claimed(U128(amount.as_attounc()))
+    }
+
+    /// Commits to claiming a code to an account before revealing the code with `claim_with_code`, so that
+    /// the claim can't be redirected by someone who reads the code from the pending transaction.
+    /// `commitment` is the sha256 of the code followed by the account id. The attached deposit pays for the
+    /// storage of the commitment and is paid out with the claim. Can be called by anyone, e.g. a relayer, who
+    /// gets the deposit back with `cancel_commitment` if the commitment isn't claimed.
+    #[payable]
+    pub fn commit_code(&mut self, commitment: Base58CryptoHash) {
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit >= code_commitment_cost(),
+            "Attached deposit must be at least {} attoUNC, got {}",
+            code_commitment_cost().as_attounc(),
+            deposit.as_attounc()
+        );
+        let code_commitment = CodeCommitment {
+            committer: env::predecessor_account_id(),
+            committed_at: env::block_height(),
+            deposit,
+        };
+        assert!(
+            self.code_commitments.insert(commitment.into(), code_commitment).is_none(),
+            "Commitment already made"
+        );
+        self.internal_add_liability(deposit);
+    }
+
+    /// Cancels a commitment of `commit_code` that wasn't claimed within CODE_COMMITMENT_TIMEOUT_BLOCKS blocks,
+    /// e.g. because the code was claimed to another account, and refunds its deposit. Only the committer can
+    /// cancel it.
+    pub fn cancel_commitment(&mut self, commitment: Base58CryptoHash) -> Promise {
+        let commitment = CryptoHash::from(commitment);
+        let code_commitment = self.code_commitments.get(&commitment).expect("No such commitment");
+        assert_eq!(
+            code_commitment.committer,
+            env::predecessor_account_id(),
+            "Only the committer can cancel the commitment"
+        );
+        assert!(
+            env::block_height() >= code_commitment.committed_at.saturating_add(CODE_COMMITMENT_TIMEOUT_BLOCKS),
+            "Commitment has not timed out yet"
+        );
+        let code_commitment = self.code_commitments.remove(&commitment).unwrap();
+        self.internal_release_liability(code_commitment.deposit);
+        Promise::new(code_commitment.committer).transfer(code_commitment.deposit)
+    }
+
+    /// Claim tokens sent with `send_with_code` to the given account. Can be called by anyone, e.g. a relayer.
+    /// The claim to `account_id` must be committed with `commit_code` in an earlier block, since the code
+    /// is revealed in the transaction.
+    pub fn claim_with_code(&mut self, code: String, account_id: AccountId) -> Promise {
+        assert!(
+            code.len() >= MIN_CODE_LENGTH,
+            "Code must be at least {} bytes long",
+            MIN_CODE_LENGTH
+        );
+        self.assert_not_paused();
+        self.assert_not_blocked(&account_id);
+        let amount = self
+            .codes
+            .remove(&env::sha256_array(code.as_bytes()))
+            .expect("Unknown code");
+        let CodeCommitment { committed_at, deposit, .. } = self
+            .code_commitments
+            .remove(&code_commitment(&code, &account_id))
+            .expect("Claim must be committed with commit_code first");
+        assert!(
+            committed_at < env::block_height(),
+            "Claim must be committed in an earlier block"
+        );
+        self.stats.total_drops_claimed += 1;
+        self.stats.total_unc_claimed.0 = self.stats.total_unc_claimed.0
+            .checked_add(amount.as_attounc())
+            .unwrap_or_else(|| overflow("total claimed"));
+        let payout = amount.checked_add(deposit).unwrap_or_else(|| overflow("code claim"));
+        self.internal_release_liability(payout);
+        Promise::new(account_id).transfer(payout)
+    }
+
+    /// Create new account and and claim tokens to it.
+    /// With `options`, the account also gets their full and limited access keys and contract, like with
+    /// `create_account_advanced`, except for FT registrations. With `keyless` set in the options, it gets
+    /// neither `new_public_key` nor any other full access key.
+    /// With `staking`, `staking.amount` of the balance is staked in the liquid staking pool `staking.pool_id`
+    /// once the account is created and the minted shares are transferred to it, or the amount itself if the
+    /// pool fails.
+    /// Resolves to the amount claimed, or returns a `ClaimAmountOutcome::Rejected` error instead of creating
+    /// the account if the claim limits are exceeded.
+    pub fn create_account_and_claim(
+        &mut self,
+        new_account_id: AccountId,
+        new_public_key: PublicKey,
+        staking: Option<StakingIntent>,
+        options: Option<CreateAccountOptions>,
+    ) -> PromiseOrValue<ClaimAmountOutcome> {
+        self.internal_create_account_and_claim(new_account_id, new_public_key, staking, options, "create_account_and_claim")
+    }
+
+    /// Like `create_account_and_claim`, but the account id is derived from the key this tx is signed with,
+    /// see `derived_account_id`, so scripted drops can't collide on account ids.
+    pub fn create_account_and_claim_derived(
+        &mut self,
+        parent: AccountId,
+        new_public_key: PublicKey,
+    ) -> PromiseOrValue<ClaimAmountOutcome> {
+        let new_account_id = derived_account_id(&env::signer_account_pk(), &parent);
+        self.internal_create_account_and_claim(new_account_id, new_public_key, None, None, "create_account_and_claim_derived")
+    }
+
+    /// Like `send`, but the key can only claim with `create_account_and_claim` to create `account_id`,
+    /// e.g. a name pre-allocated for a branded campaign. The reservation ends with the key: once it is
+    /// claimed, expired or refunded to the caller with `refund_reservation`.
+    #[payable]
+    pub fn reserve_account(
+        &mut self,
+        account_id: AccountId,
+        public_key: PublicKey,
+        expiration: Option<u64>,
+    ) -> U128 {
+        assert!(
+            !is_implicit_account_id(&account_id),
+            "Implicit accounts can't be reserved"
+        );
+        self.assert_not_blocked(&account_id);
+        self.assert_required_suffix(&account_id);
+        assert!(self.internal_key_balance(&public_key).is_none(), "Key is already funded");
+        // Reserved first so the access key is added with the reserved methods only
+        self.reservations.insert(
+            public_key.clone(),
+            Reservation { account_id, funder: env::predecessor_account_id() },
+        );
+        self.send(public_key, None, None, expiration, None, None)
+    }
+
+    /// Refunds the balance of a key funded with `reserve_account` to its funder and deletes its access key.
+    /// Only the funder can refund it, as long as the account isn't created.
+    pub fn refund_reservation(&mut self, public_key: PublicKey) -> Promise {
+        let funder = self.reservations.get(&public_key).expect("Key has no reservation").funder.clone();
+        assert!(funder == env::predecessor_account_id(), "Only the funder of the reservation can refund it");
+        self.assert_not_claim_locked(&public_key);
+        self.internal_release_tokens(&public_key, &funder);
+        let amount = self.internal_reclaim_key(&public_key);
+        AirDropEvent::Reclaimed { public_key: &public_key, to: &funder, amount: U128(amount.as_attounc()) }.emit();
+        Promise::new(env::current_account_id()).delete_key(public_key);
+        Promise::new(funder).transfer(amount)
+    }
+
+    /// Returns the account id reserved for the key, unless the key has expired.
+    pub fn get_reserved_name(&self, public_key: PublicKey) -> Option<AccountId> {
+        if self.key_expirations.get(&public_key).is_some_and(|expiration| env::block_timestamp() >= *expiration) {
+            return None;
+        }
+        self.reservations.get(&public_key).map(|reservation| reservation.account_id.clone())
+    }
+
+    /// Create new account without airdrop and deposit passed funds (used for creating sub accounts directly).
+    /// The outcome is the `CreationResult` returned by the `on_account_created` callback, which callers
+    /// should read from the result of the transaction.
+    /// A failed creation is refunded to `refund_to`, e.g. the payer behind a relayer, or else to the caller.
+    #[payable]
+    pub fn create_account(
+        &mut self,
+        new_account_id: AccountId,
+        new_public_key: PublicKey,
+        refund_to: Option<AccountId>,
+    ) -> Promise {
+        assert!(
+            env::is_valid_account_id(new_account_id.as_bytes()),
+            "Invalid account id"
+        );
+        self.assert_not_blocked(&new_account_id);
+        self.assert_required_suffix(&new_account_id);
+        assert_creation_gas();
+        let amount = env::attached_deposit();
+        Promise::new(new_account_id.clone())
+            .create_account()
+            .add_full_access_key(new_public_key)
+            .transfer(amount)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                    .with_unused_gas_weight(1)
+                    .on_account_created(
+                        refund_to.unwrap_or_else(env::predecessor_account_id),
+                        new_account_id,
+                        amount
+                    )
+            )
+    }
+
+    /// Creates several accounts like `create_account`, each funded with its own amount. The amounts must add
+    /// up to the attached deposit. A failed creation refunds only its amount to the caller.
+    /// Returns the number of creations scheduled.
+    #[payable]
+    pub fn create_accounts(&mut self, requests: Vec<CreateAccountRequest>) -> u64 {
+        assert!(!requests.is_empty(), "No accounts to create");
+        let max = env::prepaid_gas().saturating_sub(env::used_gas()).as_gas() / CREATE_ACCOUNTS_GAS_PER_ACCOUNT.as_gas();
+        if requests.len() as u64 > max {
+            panic!("{}", AirdropError::BatchTooLarge(max));
+        }
+        let mut total = UncToken::from_attounc(0);
+        for request in &requests {
+            assert!(
+                env::is_valid_account_id(request.new_account_id.as_bytes()),
+                "Invalid account id"
+            );
+            self.assert_not_blocked(&request.new_account_id);
+            self.assert_required_suffix(&request.new_account_id);
+            total = total
+                .checked_add(UncToken::from_attounc(request.amount.0))
+                .unwrap_or_else(|| overflow("create accounts total"));
+        }
+        assert_eq!(total, env::attached_deposit(), "Amounts must add up to the attached deposit");
+
+        let count = requests.len() as u64;
+        for request in requests {
+            let amount = UncToken::from_attounc(request.amount.0);
+            Promise::new(request.new_account_id.clone())
+                .create_account()
+                .add_full_access_key(request.new_public_key)
+                .transfer(amount)
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                        .with_unused_gas_weight(1)
+                        .on_account_created(env::predecessor_account_id(), request.new_account_id, amount)
+                );
+        }
+        count
+    }
+
+    /// Create new account without airdrop and deposit passed funds (used for creating sub accounts directly).
+    #[payable]
+    pub fn create_account_advanced(
+        &mut self,
+        new_account_id: AccountId,
+        options: CreateAccountOptions,
+    ) -> Promise {
+        let report = match self.internal_validate_create_account_options(&new_account_id, &options) {
+            Ok(report) => report,
+            Err(error) => panic!("{}", error),
+        };
+        if options.ft_registrations.as_ref().is_some_and(|ft_registrations| !ft_registrations.is_empty()) {
+            assert!(
+                env::attached_deposit().as_attounc() >= report.minimum_deposit.0,
+                "Attached deposit must cover the FT registrations and the storage of the account: {} attoUNC",
+                report.minimum_deposit.0
+            );
+        }
+
+        self.internal_create_account_advanced(
+            new_account_id,
+            options,
+            env::predecessor_account_id(),
+            env::attached_deposit(),
+            0,
+        )
+    }
+
+    /// Re-attempts a `create_account_advanced` call that previously failed.
+    /// Only the owner can retry, at most MAX_CREATE_ACCOUNT_RETRIES times per account.
+    #[payable]
+    pub fn retry_create_account(&mut self, account_id: AccountId) -> Promise {
+        self.assert_owner();
+        assert_one_atto();
+        let pending = self
+            .pending_creations
+            .remove(&account_id)
+            .expect("No pending creation for this account");
+        assert!(
+            pending.retries < MAX_CREATE_ACCOUNT_RETRIES,
+            "Maximum number of retries reached"
+        );
+        self.assert_not_blocked(&account_id);
+        // The storage of the entry is freed, so its cost goes back to the deposit
+        let amount = pending.amount.checked_add(pending.storage_cost).unwrap_or_else(|| overflow("retry"));
+        self.internal_release_liability(amount);
+        self.internal_create_account_advanced(
+            account_id,
+            pending.options,
+            pending.funder,
+            amount,
+            pending.retries + 1,
+        )
+    }
+
+    /// Cancels a `create_account_advanced` call queued for a retry and refunds its deposit to the funder.
+    /// Only the funder can cancel it.
+    pub fn cancel_pending_creation(&mut self, account_id: AccountId) -> Promise {
+        let pending = self
+            .pending_creations
+            .get(&account_id)
+            .expect("No pending creation for this account");
+        assert!(
+            pending.funder == env::predecessor_account_id(),
+            "Only the funder of the creation can cancel it"
+        );
+        let pending = self.pending_creations.remove(&account_id).unwrap();
+        let refund = pending.amount.checked_add(pending.storage_cost).unwrap_or_else(|| overflow("refund"));
+        self.internal_release_liability(refund);
+        Promise::new(pending.funder).transfer(refund)
+    }
+
+    /// Builds the account creation promise for `create_account_advanced` and its retries.
+    fn internal_create_account_advanced(
+        &mut self,
+        new_account_id: AccountId,
+        options: CreateAccountOptions,
+        funder: AccountId,
+        amount: UncToken,
+        retries: u32,
+    ) -> Promise {
+        assert_creation_gas();
+        // The deposits of the FT registrations stay on the contract until the callback registers the account
+        let ft_registration_deposit = self.ft_registration_deposit;
+        let ft_registrations = options.ft_registrations.as_ref().map_or(0, |ft_registrations| ft_registrations.len() as u64);
+        let registration_deposits = ft_registration_deposit
+            .checked_mul(ft_registrations.into())
+            .unwrap_or_else(|| overflow("FT registrations"));
+        let transfer = amount
+            .checked_sub(registration_deposits)
+            .expect("Attached deposit must cover the FT registrations");
+        self.internal_add_liability(registration_deposits);
+        // Initiate a new promise on the new account we're creating and transfer it any attached deposit,
+        // then add the keys and the contract of the options
+        let promise = build_creation_batch(
+            Promise::new(new_account_id.clone()).create_account().transfer(transfer),
+            &options,
+        );
+
+        // Callback to register the account with the FT contracts, or if anything went wrong, queue the creation
+        // for a retry or refund the funder
+        promise.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS.saturating_add(ft_registrations_gas(ft_registrations)))
+                .with_unused_gas_weight(1)
+                .on_account_created_advanced(
+                    funder,
+                    new_account_id,
+                    amount,
+                    options,
+                    ft_registration_deposit,
+                    retries
+                )
+        )
+    }
+
+    /// Callback after executing `create_account`. A failed creation is refunded to `refund_to`.
+    pub fn on_account_created(
+        &mut self,
+        refund_to: AccountId,
+        new_account_id: AccountId,
+        amount: UncToken,
+    ) -> CreationResult {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        let creation_succeeded = is_promise_success();
+        if creation_succeeded {
+            self.stats.total_accounts_created += 1;
+        } else {
+            self.internal_record_failure(FailureKind::AccountCreation, None, Some(new_account_id.clone()), amount);
+            // In case of failure, send funds back.
+            Promise::new(refund_to).transfer(amount);
+        }
+        CreationResult {
+            account_id: new_account_id,
+            amount: U128(amount.as_attounc()),
+            success: creation_succeeded,
+        }
+    }
+
+    /// Callback after executing `create_account_advanced` or `retry_create_account`. A created account is
+    /// registered with the FT contracts of the options, each with `ft_registration_deposit` attached.
+    /// In case of failure the creation is queued for a retry, or refunded once the retries are exhausted.
+    pub fn on_account_created_advanced(
+        &mut self,
+        predecessor_account_id: AccountId,
+        new_account_id: AccountId,
+        amount: UncToken,
+        options: CreateAccountOptions,
+        ft_registration_deposit: UncToken,
+        retries: u32,
+    ) -> bool {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        let registrations = options.ft_registrations.as_ref().map_or(0, |ft_registrations| ft_registrations.len() as u128);
+        self.internal_release_liability(
+            ft_registration_deposit.checked_mul(registrations).unwrap_or_else(|| overflow("FT registrations"))
+        );
+        let creation_succeeded = is_promise_success();
+        if creation_succeeded {
+            self.stats.total_accounts_created += 1;
+            for ft_contract_id in options.ft_registrations.into_iter().flatten() {
+                ext_ft_storage::ext(ft_contract_id.clone())
+                    .with_attached_deposit(ft_registration_deposit)
+                    .with_static_gas(FT_STORAGE_DEPOSIT_GAS)
+                    .storage_deposit(Some(new_account_id.clone()), None)
+                    .then(
+                        Self::ext(env::current_account_id())
+                            .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                            .on_ft_registered(new_account_id.clone(), ft_contract_id, ft_registration_deposit)
+                    );
+            }
+        } else {
+            // Only one creation per account can wait for a retry, any other one is refunded. The storage of the
+            // queued creation, up to the length of its contract, is paid from its deposit.
+            if retries < MAX_CREATE_ACCOUNT_RETRIES && !self.pending_creations.contains_key(&new_account_id) {
+                self.pending_creations.flush();
+                let storage_before = env::storage_usage();
+                self.pending_creations.insert(
+                    new_account_id.clone(),
+                    PendingCreation {
+                        funder: predecessor_account_id.clone(),
+                        amount,
+                        options,
+                        retries,
+                        // The placeholder takes as many bytes as the final value
+                        storage_cost: UncToken::from_attounc(0),
+                    },
+                );
+                self.pending_creations.flush();
+                let storage_cost = env::storage_byte_cost()
+                    .saturating_mul(u128::from(env::storage_usage().saturating_sub(storage_before)));
+                match amount.checked_sub(storage_cost) {
+                    Some(pending_amount) if !pending_amount.is_zero() => {
+                        let pending = self.pending_creations.get_mut(&new_account_id).unwrap();
+                        pending.amount = pending_amount;
+                        pending.storage_cost = storage_cost;
+                        self.internal_add_liability(amount);
+                    }
+                    _ => {
+                        self.pending_creations.remove(&new_account_id);
+                        Promise::new(predecessor_account_id).transfer(amount);
+                    }
+                }
+            } else {
+                Promise::new(predecessor_account_id).transfer(amount);
+            }
+        }
+        creation_succeeded
+    }
+
+    /// Callback after registering an account created by `create_account_advanced` with an FT contract.
+    /// A failed registration is logged and its deposit is sent to the account, which is kept.
+    pub fn on_ft_registered(&mut self, new_account_id: AccountId, ft_contract_id: AccountId, deposit: UncToken) -> bool {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        let registered = is_promise_success();
+        if !registered {
+            AirDropEvent::FtRegistrationFailed { account_id: &new_account_id, ft_contract_id: &ft_contract_id }.emit();
+            Promise::new(new_account_id).transfer(deposit);
+        }
+        registered
+    }
+
+    /// Callback after execution `create_account_and_claim`. Unlocks the key, and removes the `amount` paid
+    /// to the new account from its balance if the creation succeeded, then stakes the part of it in `staking`.
+    /// Returns the amount claimed, or 0 if the creation failed.
+    pub fn on_account_created_and_claimed(
+        &mut self,
+        new_account_id: AccountId,
+        amount: UncToken,
+        staking: Option<StakingIntent>,
+    ) -> ClaimAmountOutcome {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        let public_key = env::signer_account_pk();
+        self.claim_locks.remove(&key_hash(&public_key));
+        let creation_succeeded = is_promise_success();
+        if creation_succeeded {
+            self.internal_record_claim(public_key.clone(), new_account_id.clone(), amount, true);
+            self.claimed_accounts.insert(new_account_id.clone());
+            self.stats.total_accounts_created += 1;
+            let balance = self.internal_take_key_balance(&public_key).unwrap_or(UncToken::from_attounc(0));
+            let remaining = balance.checked_sub(amount).unwrap_or_else(|| overflow("claim"));
+            if remaining.is_zero() {
+                self.internal_count_key_claimed(&public_key);
+                self.internal_release_tokens(&public_key, &new_account_id);
+                let storage_stake = self.internal_forget_key(&public_key);
+                Promise::new(env::current_account_id()).delete_key(public_key);
+                if !storage_stake.is_zero() {
+                    Promise::new(new_account_id.clone()).transfer(storage_stake);
+                }
+            } else {
+                // The key was topped up while locked
+                self.internal_set_key_balance(&public_key, remaining);
+            }
+            if let Some(staking) = staking {
+                let stake = UncToken::from_attounc(staking.amount.0);
+                ext_staking_pool::ext(staking.pool_id.clone())
+                    .with_attached_deposit(stake)
+                    .with_static_gas(DEPOSIT_AND_STAKE_GAS)
+                    .deposit_and_stake()
+                    .then(
+                        Self::ext(env::current_account_id())
+                            .with_static_gas(FT_TRANSFER_GAS.saturating_add(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS))
+                            .on_staked(new_account_id, staking.pool_id, stake)
+                    );
+            }
+        } else {
+            // In case of failure, the balance is still on the key.
+            *self.failed_attempts.entry(key_hash(&public_key)).or_insert(0) += 1;
+            self.internal_record_failure(
+                FailureKind::CreateAccountAndClaim,
+                Some(public_key),
+                Some(new_account_id),
+                amount,
+            );
+            return ClaimAmountOutcome::Claimed(U128(0));
+        }
+        ClaimAmountOutcome::Claimed(U128(amount.as_attounc()))
+    }
+
+    /// Callback after the `deposit_and_stake` of `create_account_and_claim`. Transfers the shares minted for
+    /// the contract to the new account, or the `amount` meant to be staked if the pool failed.
+    /// Returns whether the amount was staked.
+    pub fn on_staked(&mut self, new_account_id: AccountId, pool_id: AccountId, amount: UncToken) -> bool {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                let shares: U128 = unc_sdk::serde_json::from_slice(&value).expect("Pool must return the minted shares");
+                ext_ft::ext(pool_id)
+                    .with_attached_deposit(UncToken::from_attounc(1))
+                    .with_static_gas(FT_TRANSFER_GAS)
+                    .ft_transfer(new_account_id, shares, None);
+                true
+            }
+            PromiseResult::Failed => {
+                // The deposit is refunded to the contract
+                Promise::new(new_account_id).transfer(amount);
+                false
+            }
+        }
+    }
+
+    /// Creates a drop for a list of `leaf_count` `(account_id, amount)` pairs committed to by the Merkle `root`,
+    /// without funding a key per recipient. The attached deposit must cover `total` and the storage of the
+    /// drop record and of one word of its claim bitmap per leaf, and what it has on top is refunded.
+    /// After `expiration` (a block timestamp in nanoseconds) the unclaimed remainder can be refunded.
+    /// Returns the id of the new drop.
+    #[payable]
+    pub fn create_merkle_drop(
+        &mut self,
+        root: Base58CryptoHash,
+        total: U128,
+        leaf_count: u64,
+        expiration: Option<u64>,
+    ) -> u64 {
+        let total = UncToken::from_attounc(total.0);
+        assert!(total > UncToken::from_attounc(0), "Total must be positive");
+        assert!(leaf_count > 0, "Leaf count must be positive");
+        if let Some(expiration) = expiration {
+            assert!(expiration > env::block_timestamp(), "Expiration must be in the future");
+        }
+        let drop_id = self.next_merkle_drop_id;
+        self.next_merkle_drop_id += 1;
+        self.merkle_drops.flush();
+        let storage_before = env::storage_usage();
+        self.merkle_drops.insert(
+            drop_id,
+            MerkleDrop {
+                funder: env::predecessor_account_id(),
+                root: root.into(),
+                total,
+                claimed: UncToken::from_attounc(0),
+                expiration,
+                // The placeholder takes as many bytes as the final value
+                storage_reserve: UncToken::from_attounc(0),
+            },
+        );
+        self.merkle_drops.flush();
+        // Every claim writes at most one word of the bitmap
+        let storage_bytes = env::storage_usage()
+            .saturating_sub(storage_before)
+            .saturating_add(leaf_count.saturating_mul(MERKLE_WORD_STORAGE_BYTES));
+        let storage_reserve = env::storage_byte_cost()
+            .checked_mul(u128::from(storage_bytes))
+            .unwrap_or_else(|| overflow("Merkle drop storage"));
+        let required = total.checked_add(storage_reserve).unwrap_or_else(|| overflow("Merkle drop deposit"));
+        assert!(
+            env::attached_deposit() >= required,
+            "Attached deposit must cover the total of the drop and its storage: {} attoUNC",
+            required.as_attounc()
+        );
+        self.merkle_drops.get_mut(&drop_id).unwrap().storage_reserve = storage_reserve;
+        self.internal_add_liability(required);
+        let excess = env::attached_deposit().saturating_sub(required);
+        if !excess.is_zero() {
+            Promise::new(env::predecessor_account_id()).transfer(excess);
+        }
+        self.internal_record_drop(total, true);
+        drop_id
+    }
+
+    /// Claims `amount` from a Merkle drop to `account_id`.
+    /// `proof` is the list of sibling hashes from the `sha256(0x00 || account_id || amount)` leaf up to the root.
+    /// If the transfer fails, e.g. because `account_id` doesn't exist, the leaf can be claimed again.
+    pub fn claim_merkle(
+        &mut self,
+        drop_id: u64,
+        account_id: AccountId,
+        amount: U128,
+        proof: Vec<Base58CryptoHash>,
+    ) -> Promise {
+        self.assert_not_paused();
+        self.assert_not_blocked(&account_id);
+        let drop = self.merkle_drops.get_mut(&drop_id).expect("No such drop");
+        if let Some(expiration) = drop.expiration {
+            assert!(env::block_timestamp() < expiration, "Drop has expired");
+        }
+        let proof: Vec<CryptoHash> = proof.into_iter().map(CryptoHash::from).collect();
+        let index = merkle::verify_proof(&drop.root, merkle::leaf_hash(&account_id, amount.0), &proof)
+            .expect("Invalid proof");
+
+        let amount = UncToken::from_attounc(amount.0);
+        let claimed = drop
+            .claimed
+            .checked_add(amount)
+            .filter(|claimed| *claimed <= drop.total)
+            .expect("Not enough funds left in the drop");
+        drop.claimed = claimed;
+
+        let word_key = (drop_id, index / 64);
+        let bit = 1u64 << (index % 64);
+        let word = self.merkle_claimed.get(&word_key).copied();
+        assert_eq!(word.unwrap_or(0) & bit, 0, "Already claimed");
+        let mut released = amount;
+        if word.is_none() {
+            let word_cost = env::storage_byte_cost().saturating_mul(u128::from(MERKLE_WORD_STORAGE_BYTES));
+            drop.storage_reserve = drop
+                .storage_reserve
+                .checked_sub(word_cost)
+                .expect("Drop has no storage left for more claims");
+            // The word is paid from the reserve and locked for its storage
+            released = released.checked_add(word_cost).unwrap_or_else(|| overflow("Merkle claim"));
+        }
+        self.merkle_claimed.insert(word_key, word.unwrap_or(0) | bit);
+
+        let funder = drop.funder.clone();
+        self.internal_release_liability(released);
+        Promise::new(account_id.clone()).transfer(amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+                .on_merkle_claimed(drop_id, funder, account_id, index, U128(amount.as_attounc()))
+        )
+    }
+
+    /// Callback after the transfer of `claim_merkle`. A failed claim is undone so that the leaf can be
+    /// claimed again, or its amount goes to the funder if the drop was refunded in the meantime.
+    pub fn on_merkle_claimed(
+        &mut self,
+        drop_id: u64,
+        funder: AccountId,
+        account_id: AccountId,
+        index: u64,
+        amount: U128,
+    ) -> bool {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        let transfer_succeeded = is_promise_success();
+        if transfer_succeeded {
+            self.stats.total_unc_claimed.0 = self.stats.total_unc_claimed.0
+                .checked_add(amount.0)
+                .unwrap_or_else(|| overflow("total claimed"));
+            return true;
+        }
+        let amount = UncToken::from_attounc(amount.0);
+        self.internal_record_failure(FailureKind::MerkleClaim, None, Some(account_id), amount);
+        match self.merkle_drops.get_mut(&drop_id) {
+            Some(drop) => {
+                drop.claimed = drop.claimed.checked_sub(amount).unwrap_or_else(|| overflow("Merkle claim"));
+                let word_key = (drop_id, index / 64);
+                let word = self.merkle_claimed.get(&word_key).copied().unwrap_or(0);
+                // The word stays written, its storage is already paid
+                self.merkle_claimed.insert(word_key, word & !(1u64 << (index % 64)));
+                self.internal_add_liability(amount);
+            }
+            None => {
+                // The drop was refunded without this claim, so its amount goes to the funder too
+                Promise::new(funder).transfer(amount);
+            }
+        }
+        false
+    }
+
+    /// Sends the unclaimed remainder of an expired Merkle drop and its storage reserve back to its funder
+    /// and removes the drop.
+    pub fn refund_merkle_drop(&mut self, drop_id: u64) -> Promise {
+        let drop = self.merkle_drops.get(&drop_id).expect("No such drop");
+        let expiration = drop.expiration.expect("Drop never expires");
+        assert!(env::block_timestamp() >= expiration, "Drop has not expired yet");
+        let drop = self.merkle_drops.remove(&drop_id).unwrap();
+        let refund = drop.total.checked_sub(drop.claimed).unwrap_or_else(|| overflow("Merkle drop refund"));
+        self.stats.total_drops_refunded += 1;
+        self.stats.total_unc_refunded.0 = self.stats.total_unc_refunded.0
+            .checked_add(refund.as_attounc())
+            .unwrap_or_else(|| overflow("total refunded"));
+        let refund = refund.checked_add(drop.storage_reserve).unwrap_or_else(|| overflow("Merkle drop refund"));
+        self.internal_release_liability(refund);
+        Promise::new(drop.funder).transfer(refund)
+    }
+
+    /// Returns the balance of the contract split into the storage stake, what is owed to the funded keys,
+    /// the allowances left on their access keys, the sponsor pool and what is owed outside the keys.
+    pub fn get_balance_breakdown(&self) -> BalanceBreakdown {
+        BalanceBreakdown {
+            account_balance: U128(env::account_balance().as_attounc()),
+            locked: self.get_storage_cost(),
+            key_liability: U128(self.key_liability.as_attounc()),
+            reserved_allowances: U128(self.reserved_allowances.as_attounc()),
+            sponsor_pool: U128(self.sponsor_pool.as_attounc()),
+            other_liability: U128(self.other_liability.as_attounc()),
+        }
+    }
+
+    /// Returns the bytes of storage used by the contract account: its state, code and access keys.
+    pub fn get_storage_usage(&self) -> u64 {
+        env::storage_usage()
+    }
+
+    /// Returns the cost of the storage used by the contract account, which is locked from its balance.
+    pub fn get_storage_cost(&self) -> U128 {
+        U128(env::storage_byte_cost().saturating_mul(u128::from(env::storage_usage())).as_attounc())
+    }
+
+    /// Returns the cumulative counters of the drops, claims and account creations.
+    pub fn get_stats(&self) -> Stats {
+        self.stats.clone()
+    }
+
+    /// Returns information about a Merkle drop.
+    pub fn get_merkle_drop(&self, drop_id: u64) -> Option<MerkleDropInfo> {
+        self.merkle_drops.get(&drop_id).map(MerkleDropInfo::from)
+    }
+
+    /// Returns the balance associated with given key.
+    pub fn get_key_balance(&self, key: PublicKey) -> UncToken {
+        self.internal_key_balance(&key).expect("Key is missing")
+    }
+
+    /// Prevents the account from receiving claims or being created by the contract.
+    /// Existing key balances are untouched and stay claimable to other accounts.
+    /// Only the owner or an operator can block.
+    #[payable]
+    pub fn block_account(&mut self, account_id: AccountId) -> bool {
+        self.assert_owner_or_operator();
+        assert_one_atto();
+        let added = self.blocked_accounts.insert(account_id.clone());
+        if added {
+            AirDropEvent::AccountBlocked { account_id: &account_id }.emit();
+        }
+        added
+    }
+
+    /// Lifts a block set by `block_account`. Only the owner or an operator can unblock.
+    #[payable]
+    pub fn unblock_account(&mut self, account_id: AccountId) -> bool {
+        self.assert_owner_or_operator();
+        assert_one_atto();
+        let removed = self.blocked_accounts.remove(&account_id);
+        if removed {
+            AirDropEvent::AccountUnblocked { account_id: &account_id }.emit();
+        }
+        removed
+    }
+
+    /// Returns whether the account is blocked.
+    pub fn is_blocked(&self, account_id: AccountId) -> bool {
+        self.blocked_accounts.contains(&account_id)
+    }
+
+    /// Returns up to `limit` blocked accounts, starting at `from_index`.
+    pub fn get_blocked(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<AccountId> {
+        self.blocked_accounts
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Allows the account to pause and unpause the contract and to block and unblock accounts.
+    /// Only the owner can add operators. Returns whether the account wasn't an operator yet.
+    #[payable]
+    pub fn add_operator(&mut self, account_id: AccountId) -> bool {
+        self.assert_owner();
+        assert_one_atto();
+        self.operators.insert(account_id)
+    }
+
+    /// Revokes the operator role of the account. Only the owner can remove operators.
+    /// Returns whether the account was an operator.
+    #[payable]
+    pub fn remove_operator(&mut self, account_id: AccountId) -> bool {
+        self.assert_owner();
+        assert_one_atto();
+        self.operators.remove(&account_id)
+    }
+
+    /// Returns whether the account is an operator.
+    pub fn is_operator(&self, account_id: AccountId) -> bool {
+        self.operators.contains(&account_id)
+    }
+
+    /// Registers a full access key of the contract account, e.g. one the owner or an operator signs
+    /// with. The claim methods return `NotADropKey` when signed with it, even if the key was funded by mistake.
+    /// Only the owner can add admin keys, up to MAX_ADMIN_KEYS. Returns whether the key wasn't registered yet.
+    #[payable]
+    pub fn add_admin_key(&mut self, public_key: PublicKey) -> bool {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(
+            self.admin_keys.contains(&public_key) || self.admin_keys.len() < MAX_ADMIN_KEYS,
+            "At most {} admin keys can be registered",
+            MAX_ADMIN_KEYS
+        );
+        self.admin_keys.insert(public_key)
+    }
+
+    /// Unregisters a key added with `add_admin_key`. Only the owner can remove admin keys.
+    /// Returns whether the key was registered.
+    #[payable]
+    pub fn remove_admin_key(&mut self, public_key: PublicKey) -> bool {
+        self.assert_owner();
+        assert_one_atto();
+        self.admin_keys.remove(&public_key)
+    }
+
+    /// Returns the keys registered with `add_admin_key`.
+    pub fn get_admin_keys(&self) -> Vec<PublicKey> {
+        self.admin_keys.iter().cloned().collect()
+    }
+
+    /// Allows the account to fund keys from the sponsor pool with `sponsor_key`. Only the owner can add
+    /// relayers. Returns whether the account wasn't a relayer yet.
+    #[payable]
+    pub fn add_relayer(&mut self, account_id: AccountId) -> bool {
+        self.assert_owner();
+        assert_one_atto();
+        self.relayers.insert(account_id)
+    }
+
+    /// Revokes the relayer role of the account. Only the owner can remove relayers.
+    /// Returns whether the account was a relayer.
+    #[payable]
+    pub fn remove_relayer(&mut self, account_id: AccountId) -> bool {
+        self.assert_owner();
+        assert_one_atto();
+        self.relayers.remove(&account_id)
+    }
+
+    /// Returns whether the account is a relayer.
+    pub fn is_relayer(&self, account_id: AccountId) -> bool {
+        self.relayers.contains(&account_id)
+    }
+
+    /// Allows `claim_and_call` to call `receiver_contract`, adding the claiming account to the call arguments
+    /// under `account_field`. Only the owner can add receivers.
+    #[payable]
+    pub fn add_call_receiver(&mut self, receiver_contract: AccountId, account_field: String) {
+        self.assert_owner();
+        assert_one_atto();
+        self.call_receivers.insert(receiver_contract, account_field);
+    }
+
+    /// Stops `claim_and_call` from calling `receiver_contract`. Only the owner can remove receivers.
+    /// Returns whether the contract was allowed.
+    #[payable]
+    pub fn remove_call_receiver(&mut self, receiver_contract: AccountId) -> bool {
+        self.assert_owner();
+        assert_one_atto();
+        self.call_receivers.remove(&receiver_contract).is_some()
+    }
+
+    /// Returns up to `limit` contracts `claim_and_call` can call with their account field,
+    /// starting at `from_index`.
+    pub fn get_call_receivers(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<(AccountId, String)> {
+        self.call_receivers
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .map(|(receiver_contract, account_field)| (receiver_contract.clone(), account_field.clone()))
+            .collect()
+    }
+
+    /// Returns up to `limit` operators, starting at `from_index`.
+    pub fn get_operators(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<AccountId> {
+        self.operators
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .cloned()
+            .collect()
+    }
+
+    /// Proposes to sweep the funds of the contract not owed to anyone to `to`, leaving the storage,
+    /// the unclaimed balances, the sponsor pool and the fees. Can be confirmed once `sweep_delay_ns` has passed.
+    #[payable]
+    pub fn propose_sweep(&mut self, to: AccountId) {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(self.pending_sweep.is_none(), "A sweep is already pending");
+        self.pending_sweep = Some(PendingSweep {
+            to,
+            proposed_at: env::block_timestamp(),
+        });
+    }
+
+    /// Executes the pending sweep once its delay has passed.
+    #[payable]
+    pub fn confirm_sweep(&mut self) -> Promise {
+        self.assert_owner();
+        assert_one_atto();
+        let pending = self.pending_sweep.as_ref().expect("No pending sweep");
+        assert!(
+            env::block_timestamp() >= pending.proposed_at.saturating_add(self.sweep_delay_ns),
+            "Sweep delay has not passed yet"
+        );
+        let pending = self.pending_sweep.take().unwrap();
+        // The keys stay funded, so the sweep leaves what they and the other liabilities are owed
+        Promise::new(pending.to).transfer(UncToken::from_attounc(self.internal_available_balance()))
+    }
+
+    /// Clears the pending sweep.
+    #[payable]
+    pub fn cancel_sweep(&mut self) {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(self.pending_sweep.take().is_some(), "No pending sweep");
+    }
+
+    /// Returns the pending sweep, if any.
+    pub fn get_pending_sweep(&self) -> Option<&PendingSweep> {
+        self.pending_sweep.as_ref()
+    }
+
+    /// Returns up to `limit` funded keys with the given tag, skipping the first `from_index` ones.
+    pub fn get_keys_by_tag(&self, tag: String, from_index: Option<u64>, limit: Option<u64>) -> Vec<PublicKey> {
+        self.keys_by_tag
+            .get(&tag)
+            .map(|keys| {
+                keys.iter()
+                    .skip(from_index.unwrap_or(0) as usize)
+                    .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Removes up to `limit` expired keys, deletes their access keys and sends their balances to `to` in a
+    /// single transfer. Only the owner can reclaim keys.
+    /// Returns the number of keys reclaimed.
+    #[payable]
+    pub fn reclaim_all_expired(&mut self, limit: u64, to: AccountId) -> u64 {
+        self.assert_owner();
+        assert_one_atto();
+        let expired: Vec<PublicKey> = self
+            .key_expirations
+            .iter()
+            .filter(|(_, expiration)| **expiration <= env::block_timestamp())
+            .map(|(key, _)| key.clone())
+            // Keys with a claim in progress are left to their callback
+            .filter(|key| !self.claim_locks.contains_key(&key_hash(key)))
+            .take(limit as usize)
+            .collect();
+        if expired.is_empty() {
+            return 0;
+        }
+        let mut refund = UncToken::from_attounc(0);
+        let mut delete_keys = Promise::new(env::current_account_id());
+        for key in &expired {
+            let amount = self.internal_reclaim_key(key);
+            AirDropEvent::Reclaimed { public_key: key, to: &to, amount: U128(amount.as_attounc()) }.emit();
+            refund = refund.checked_add(amount).unwrap_or_else(|| overflow("reclaim"));
+            delete_keys = delete_keys.delete_key(key.clone());
+        }
+        if !refund.is_zero() {
+            Promise::new(to).transfer(refund);
+        }
+        expired.len() as u64
+    }
+
+    /// Reclaims the balance of a key to `to` at any time, e.g. a key funded by mistake, and deletes its
+    /// access key. Only the owner can reclaim keys.
+    #[payable]
+    pub fn owner_reclaim(&mut self, public_key: PublicKey, to: AccountId) -> Promise {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(self.internal_key_balance(&public_key).is_some(), "Unexpected public key");
+        self.assert_not_claim_locked(&public_key);
+        self.internal_release_tokens(&public_key, &to);
+        let amount = self.internal_reclaim_key(&public_key);
+        AirDropEvent::Reclaimed { public_key: &public_key, to: &to, amount: U128(amount.as_attounc()) }.emit();
+        Promise::new(env::current_account_id()).delete_key(public_key);
+        Promise::new(to).transfer(amount)
+    }
+
+    /// Unlocks a key whose claim locked it at least CLAIM_LOCK_TIMEOUT_BLOCKS blocks ago without its callback
+    /// resolving it. The owner should first check that the account wasn't created or the tokens transferred,
+    /// since the balance of the key can be claimed again once unlocked.
+    #[payable]
+    pub fn unlock_stale_claim(&mut self, public_key: PublicKey) {
+        self.assert_owner();
+        assert_one_atto();
+        let locked_at = *self.claim_locks.get(&key_hash(&public_key)).expect("Key has no claim in progress");
+        assert!(
+            env::block_height() >= locked_at.saturating_add(CLAIM_LOCK_TIMEOUT_BLOCKS),
+            "Claim lock has not timed out yet"
+        );
+        self.claim_locks.remove(&key_hash(&public_key));
+    }
+
+    /// Unlocks a key locked after MAX_FAILED_CLAIM_ATTEMPTS failed claims by resetting its count of failed
+    /// claims. Only the owner can reset it.
+    #[payable]
+    pub fn reset_key_attempts(&mut self, public_key: PublicKey) {
+        self.assert_owner();
+        assert_one_atto();
+        self.failed_attempts.remove(&key_hash(&public_key));
+    }
+
+    /// Returns the keys that expire before `timestamp_ns`, with their balance.
+    pub fn get_keys_expiring_before(
+        &self,
+        timestamp_ns: u64,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<(PublicKey, U128)> {
+        self.key_expirations
+            .iter()
+            .filter(|(_, expiration)| **expiration < timestamp_ns)
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .map(|(key, _)| {
+                let balance = self.internal_key_balance(key).unwrap_or(UncToken::from_attounc(0));
+                (key.clone(), U128(balance.as_attounc()))
+            })
+            .collect()
+    }
+
+    /// Sets the claim limits, see `max_claims_per_window` and `auto_pause_threshold`. Only the owner can set them.
+    #[payable]
+    pub fn set_claim_limits(&mut self, max_claims_per_window: Option<u64>, auto_pause_threshold: Option<u64>) {
+        self.assert_owner();
+        assert_one_atto();
+        self.max_claims_per_window = max_claims_per_window;
+        self.auto_pause_threshold = auto_pause_threshold;
+    }
+
+    /// Disables claims. Only the owner or an operator can pause.
+    #[payable]
+    pub fn pause(&mut self) {
+        self.assert_owner_or_operator();
+        assert_one_atto();
+        self.paused = true;
+    }
+
+    /// Enables claims again and resets the claim counter of the current window.
+    /// Only the owner or an operator can unpause.
+    #[payable]
+    pub fn unpause(&mut self) {
+        self.assert_owner_or_operator();
+        assert_one_atto();
+        self.paused = false;
+        self.claims_in_window = 0;
+    }
+
+    /// Returns whether claims are disabled.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Enables or disables the funding of keys and codes with `send` and its variants, while claims keep working.
+    /// Only the owner can set it.
+    #[payable]
+    pub fn set_funding_enabled(&mut self, funding_enabled: bool) {
+        self.assert_owner();
+        assert_one_atto();
+        self.funding_enabled = funding_enabled;
+    }
+
+    /// Returns whether keys and codes can be funded.
+    pub fn is_funding_enabled(&self) -> bool {
+        self.funding_enabled
+    }
+
+    /// Sets the time in nanoseconds after funding before a key can be claimed, 0 to disable it.
+    /// Only the owner can set it.
+    #[payable]
+    pub fn set_claim_cooldown(&mut self, claim_cooldown_ns: u64) {
+        self.assert_owner();
+        assert_one_atto();
+        self.internal_set_claim_cooldown(claim_cooldown_ns);
+    }
+
+    /// Sets the minimum balance of a new key once its allowance is taken from the deposit.
+    /// A new key always gets at least 1 attoUNC. Only the owner can set it.
+    #[payable]
+    pub fn set_min_drop_amount(&mut self, min_drop_amount: U128) {
+        self.assert_owner();
+        assert_one_atto();
+        self.min_drop_amount = UncToken::from_attounc(min_drop_amount.0);
+    }
+
+    /// Sets the allowance of a claim attempt of the ED25519 keys `send` adds. Only the owner can set it.
+    #[payable]
+    pub fn set_ed25519_allowance(&mut self, allowance: U128) {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(allowance.0 > 0, "Allowance must be positive");
+        self.ed25519_allowance = UncToken::from_attounc(allowance.0);
+    }
+
+    /// Sets the allowance of a claim attempt of the SECP256K1 keys `send` adds. Only the owner can set it.
+    #[payable]
+    pub fn set_secp256k1_allowance(&mut self, allowance: U128) {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(allowance.0 > 0, "Allowance must be positive");
+        self.secp256k1_allowance = UncToken::from_attounc(allowance.0);
+    }
+
+    /// Sets the number of claim attempts the allowance of the access keys `send` adds is sized for.
+    /// Only the owner can set it.
+    #[payable]
+    pub fn set_max_claim_attempts(&mut self, max_claim_attempts: u32) {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(max_claim_attempts > 0, "Max claim attempts must be positive");
+        self.max_claim_attempts = max_claim_attempts;
+    }
+
+    pub fn get_max_claim_attempts(&self) -> u32 {
+        self.max_claim_attempts
+    }
+
+    /// Sets the comma separated claim methods the access keys of new keys can call, or all of CLAIM_METHODS
+    /// with `None`. Keys of drops and reservations keep their own methods. Only the owner can set it.
+    #[payable]
+    pub fn set_default_claim_methods(&mut self, method_names: Option<String>) {
+        self.assert_owner();
+        assert_one_atto();
+        if let Some(method_names) = &method_names {
+            assert_claim_method_names(method_names);
+        }
+        self.default_claim_methods = method_names;
+    }
+
+    /// Returns the comma separated claim methods the access keys of new keys can call.
+    pub fn get_default_claim_methods(&self) -> String {
+        self.default_claim_methods.clone().unwrap_or_else(|| CLAIM_METHODS.join(","))
+    }
+
+    /// Sets the nanoseconds after which the keys `send` adds without an expiration expire, or none with `None`
+    /// or 0. Keys that are already funded keep their expiration. Only the owner can set it.
+    #[payable]
+    pub fn set_default_expiry_ns(&mut self, default_expiry_ns: Option<u64>) {
+        self.assert_owner();
+        assert_one_atto();
+        self.default_expiry_ns = default_expiry_ns.filter(|expiry_ns| *expiry_ns > 0);
+    }
+
+    pub fn get_default_expiry_ns(&self) -> Option<u64> {
+        self.default_expiry_ns
+    }
+
+    /// Sets the maximum number of funded keys, counted with `get_key_count`, or lifts it with `None`. Funding a
+    /// new key beyond it fails, while top-ups still work. Only the owner can set it.
+    #[payable]
+    pub fn set_max_keys(&mut self, max_keys: Option<u64>) {
+        self.assert_owner();
+        assert_one_atto();
+        self.max_keys = max_keys;
+    }
+
+    pub fn get_max_keys(&self) -> Option<u64> {
+        self.max_keys
+    }
+
+    /// Sets what `send` does with a deposit larger than needed: credit it all to the key, or use at most the
+    /// given amount and refund the rest to the sender. Only the owner can set it.
+    #[payable]
+    pub fn set_over_deposit_policy(&mut self, policy: OverDepositPolicy) {
+        self.assert_owner();
+        assert_one_atto();
+        if let OverDepositPolicy::RefundExcess(max_deposit) = &policy {
+            assert!(max_deposit.0 > 0, "Maximum deposit must be greater than 0");
+        }
+        self.over_deposit_policy = policy;
+    }
+
+    pub fn get_over_deposit_policy(&self) -> OverDepositPolicy {
+        self.over_deposit_policy.clone()
+    }
+
+    /// Sets the fee `send` takes, in basis points of what is sent. Only the owner can set it.
+    #[payable]
+    pub fn set_fee_bps(&mut self, fee_bps: u16) {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(fee_bps <= MAX_FEE_BPS, "Fee must be at most {} basis points", MAX_FEE_BPS);
+        self.fee_bps = fee_bps;
+    }
+
+    /// Returns the fee `send` takes, in basis points of what is sent.
+    pub fn get_fee_bps(&self) -> u16 {
+        self.fee_bps
+    }
+
+    /// Returns the fees collected by `send` and not withdrawn yet.
+    pub fn get_fees_collected(&self) -> U128 {
+        U128(self.fees_collected.as_attounc())
+    }
+
+    /// Sends the fees collected by `send` to `to`. Only the owner can withdraw them.
+    #[payable]
+    pub fn withdraw_fees(&mut self, to: AccountId) -> Promise {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(!self.fees_collected.is_zero(), "No fees to withdraw");
+        let fees = std::mem::replace(&mut self.fees_collected, UncToken::from_attounc(0));
+        Promise::new(to).transfer(fees)
+    }
+
+    /// Adds the attached deposit to the pool `sponsor_key` funds keys from. Returns the new pool balance.
+    #[payable]
+    pub fn fund_sponsor_pool(&mut self) -> U128 {
+        assert!(
+            env::attached_deposit() > UncToken::from_attounc(0),
+            "Attached deposit must be greater than 0"
+        );
+        self.sponsor_pool = self
+            .sponsor_pool
+            .checked_add(env::attached_deposit())
+            .unwrap_or_else(|| overflow("sponsor pool"));
+        U128(self.sponsor_pool.as_attounc())
+    }
+
+    /// Sends `amount` of the sponsor pool to `to`. Only the owner can withdraw it.
+    #[payable]
+    pub fn withdraw_sponsor_pool(&mut self, amount: U128, to: AccountId) -> Promise {
+        self.assert_owner();
+        assert_one_atto();
+        let amount = UncToken::from_attounc(amount.0);
+        self.sponsor_pool = self
+            .sponsor_pool
+            .checked_sub(amount)
+            .unwrap_or_else(|| panic!("Sponsor pool doesn't cover the amount"));
+        Promise::new(to).transfer(amount)
+    }
+
+    pub fn get_sponsor_pool(&self) -> U128 {
+        U128(self.sponsor_pool.as_attounc())
+    }
+
+    /// Sets the deposit attached to each FT registration of `create_account_advanced`. Only the owner can set it.
+    #[payable]
+    pub fn set_ft_registration_deposit(&mut self, deposit: U128) {
+        self.assert_owner();
+        assert_one_atto();
+        self.ft_registration_deposit = UncToken::from_attounc(deposit.0);
+    }
+
+    /// Returns the deposit attached to each FT registration of `create_account_advanced`.
+    pub fn get_ft_registration_deposit(&self) -> U128 {
+        U128(self.ft_registration_deposit.as_attounc())
+    }
+
+    /// Sets the deposit and the gas attached to the `storage_deposit` of `claim_ft_with_registration`,
+    /// since token contracts charge different amounts for the storage of an account. Only the owner can set them.
+    #[payable]
+    pub fn set_ft_storage_deposit(&mut self, deposit: U128, gas: Gas) {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(gas.as_gas() > 0, "Gas must be positive");
+        self.ft_storage_deposit = UncToken::from_attounc(deposit.0);
+        self.ft_storage_deposit_gas = gas;
+    }
+
+    /// Sets the wrap contract `claim_wrapped` deposits the balances in, or disables `claim_wrapped` with `None`.
+    /// Only the owner can set it.
+    #[payable]
+    pub fn set_wrap_contract(&mut self, wrap_contract_id: Option<AccountId>) {
+        self.assert_owner();
+        assert_one_atto();
+        self.wrap_contract_id = wrap_contract_id;
+    }
+
+    pub fn get_wrap_contract(&self) -> Option<AccountId> {
+        self.wrap_contract_id.clone()
+    }
+
+    /// Allows `ft_on_transfer` to credit the tokens of `ft_contract_id` to keys. Only the owner can add it.
+    /// Returns whether the contract wasn't allowed yet.
+    #[payable]
+    pub fn add_ft_contract(&mut self, ft_contract_id: AccountId) -> bool {
+        self.assert_owner();
+        assert_one_atto();
+        self.ft_contracts.insert(ft_contract_id)
+    }
+
+    /// Stops `ft_on_transfer` from accepting the tokens of `ft_contract_id`. The tokens already on keys can still
+    /// be claimed. Only the owner can remove it. Returns whether the contract was allowed.
+    #[payable]
+    pub fn remove_ft_contract(&mut self, ft_contract_id: AccountId) -> bool {
+        self.assert_owner();
+        assert_one_atto();
+        self.ft_contracts.remove(&ft_contract_id)
+    }
+
+    pub fn is_ft_contract(&self, ft_contract_id: AccountId) -> bool {
+        self.ft_contracts.contains(&ft_contract_id)
+    }
+
+    /// Returns the tokens of `ft_contract_id` that couldn't be released to the account a key was claimed to.
+    pub fn get_stranded_tokens(&self, ft_contract_id: AccountId) -> U128 {
+        U128(self.stranded_tokens.get(&ft_contract_id).copied().unwrap_or(0))
+    }
+
+    /// Transfers the stranded tokens of `ft_contract_id` to `receiver_id`, which must be registered on the FT
+    /// contract. They are stranded again if the transfer fails. Only the owner can withdraw them.
+    #[payable]
+    pub fn withdraw_stranded_tokens(&mut self, ft_contract_id: AccountId, receiver_id: AccountId) -> Promise {
+        self.assert_owner();
+        assert_one_atto();
+        let amount = self.stranded_tokens.remove(&ft_contract_id).expect("No stranded tokens");
+        internal_transfer_tokens(ft_contract_id, receiver_id, amount)
+    }
+
+    /// Callback after the `ft_transfer` of the tokens released with the balance of a key or withdrawn with
+    /// `withdraw_stranded_tokens`, strands them if the transfer failed.
+    pub fn on_tokens_released(&mut self, ft_contract_id: AccountId, amount: U128) -> bool {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Callback can only be called from the contract"
+        );
+        let transfer_succeeded = is_promise_success();
+        if !transfer_succeeded {
+            self.internal_strand_tokens(ft_contract_id, amount.0);
+        }
+        transfer_succeeded
+    }
+
+    /// Returns the deposit attached to the `storage_deposit` of `claim_ft_with_registration`.
+    pub fn get_ft_storage_deposit(&self) -> U128 {
+        U128(self.ft_storage_deposit.as_attounc())
+    }
+
+    /// Returns the gas attached to the `storage_deposit` of `claim_ft_with_registration`.
+    pub fn get_ft_storage_deposit_gas(&self) -> Gas {
+        self.ft_storage_deposit_gas
+    }
+
+    /// Sets the maximum number of keys and contract length accepted by `create_account_advanced`.
+    /// Only the owner can set them.
+    #[payable]
+    pub fn set_create_account_limits(&mut self, max_keys_per_account: u64, max_contract_bytes: u64) {
+        self.assert_owner();
+        assert_one_atto();
+        self.max_keys_per_account = max_keys_per_account;
+        self.max_contract_bytes = max_contract_bytes;
+    }
+
+    /// Restricts the accounts created by the contract to the ones ending with `required_suffix`,
+    /// or lifts the restriction with `None`. Only the owner can set it.
+    #[payable]
+    pub fn set_required_suffix(&mut self, required_suffix: Option<String>) {
+        self.assert_owner();
+        assert_one_atto();
+        self.required_suffix = required_suffix;
+    }
+
+    /// Returns whether the account received a successful key claim. Unlike `key_exists` it is about the
+    /// receiving account, e.g. to keep a user from claiming a drop again to the same account.
+    /// Claims recorded before `claimed_accounts` existed only count once `migrate_chunk` reached them.
+    pub fn has_claimed(&self, account_id: AccountId) -> bool {
+        self.claimed_accounts.contains(&account_id)
+    }
+
+    /// Returns up to `limit` records of the claim history, starting at `from_index`.
+    pub fn get_claim_history(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<&ClaimRecord> {
+        self.claim_history
+            .iter()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .collect()
+    }
+
+    /// Returns up to `limit` of the last MAX_FAILURE_RECORDS failed operations, most recent first,
+    /// starting at `from_index`.
+    pub fn get_recent_failures(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<&FailureRecord> {
+        let len = self.failures.len() as u64;
+        let from_index = from_index.unwrap_or(0);
+        let end = std::cmp::min(from_index.saturating_add(limit.unwrap_or(DEFAULT_PAGE_LIMIT)), len);
+        (from_index..end)
+            .map(|index| &self.failures[((self.failure_count - 1 - index) % MAX_FAILURE_RECORDS) as u32])
+            .collect()
+    }
+
+    /// Returns the gas to attach to `claim` or `create_account_and_claim`.
+    /// This is CLAIM_BASE_GAS plus the floor of the account creation callback: 20 Tgas.
+    /// `claim_ft_with_registration` needs more for its calls to the FT contract.
+    pub fn estimate_claim_gas(&self) -> Gas {
+        CLAIM_BASE_GAS.saturating_add(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+    }
+
+    /// Estimates the deposits needed to `send` `amount_per_key` to each of `num_keys` keys, with tags of
+    /// `with_metadata_bytes` bytes (0 for no tag): the amounts and the access key allowances, plus the cost
+    /// of the storage taken by the keys.
+    pub fn get_required_deposit(&self, num_keys: u64, amount_per_key: U128, with_metadata_bytes: u64) -> U128 {
+        let mut bytes_per_key = KEY_STORAGE_BYTES
+            + access_key_storage_bytes(
+                ED25519_PUBLIC_KEY_BYTES,
+                Some((&env::current_account_id(), &self.get_default_claim_methods())),
+            );
+        if with_metadata_bytes > 0 {
+            bytes_per_key += TAG_STORAGE_BYTES + with_metadata_bytes;
+        }
+        let deposit = env::storage_byte_cost()
+            .checked_mul(bytes_per_key.into())
+            .and_then(|storage_cost| storage_cost.checked_add(self.internal_attempts_allowance(self.ed25519_allowance)))
+            .and_then(|fees| fees.checked_add(UncToken::from_attounc(amount_per_key.0)))
+            .and_then(|per_key| per_key.checked_mul(num_keys.into()))
+            .unwrap_or_else(|| overflow("required deposit"));
+        U128(deposit.as_attounc())
+    }
+
+    /// Previews splitting `total` evenly across `count` new ED25519 keys sent without tag or expiration, each
+    /// with one `send` of its share: what each key is credited, the allowances taken and what is left over.
+    pub fn preview_batch_split(&self, count: u64, total: U128) -> BatchPreview {
+        assert!(count > 0, "Count must be greater than 0");
+        let share = UncToken::from_attounc(total.0 / u128::from(count));
+        let allowance = self.internal_attempts_allowance(self.ed25519_allowance);
+        let bytes_per_key = KEY_STORAGE_BYTES
+            + access_key_storage_bytes(
+                ED25519_PUBLIC_KEY_BYTES,
+                Some((&env::current_account_id(), &self.get_default_claim_methods())),
+            );
+        let storage_stake = env::storage_byte_cost()
+            .checked_mul(bytes_per_key.into())
+            .unwrap_or_else(|| overflow("storage stake"));
+        // Saturates since a share below the allowance and the stake credits nothing
+        let after_allowance = share.saturating_sub(allowance);
+        let per_key_net = after_allowance
+            .saturating_sub(self.internal_fee(after_allowance))
+            .saturating_sub(storage_stake);
+        BatchPreview {
+            per_key_net: U128(per_key_net.as_attounc()),
+            allowance_total: U128(
+                allowance.checked_mul(count.into()).unwrap_or_else(|| overflow("allowance total")).as_attounc(),
+            ),
+            remainder: U128(total.0 % u128::from(count)),
+        }
+    }
+
+    /// Estimates the minimum deposit `create_account_advanced` needs to cover the storage of the new account
+    /// with its keys and contract, and its FT registrations.
+    pub fn get_account_creation_cost(&self, options: Option<CreateAccountOptions>) -> U128 {
+        let mut bytes = ACCOUNT_STORAGE_BYTES;
+        let mut ft_registrations = 0;
+        if let Some(options) = options {
+            for key in options.full_access_keys.iter().flatten() {
+                bytes += access_key_storage_bytes(key.as_bytes().len() as u64, None);
+            }
+            for key_info in options.limited_access_keys.iter().flatten() {
+                bytes += access_key_storage_bytes(
+                    key_info.public_key.as_bytes().len() as u64,
+                    Some((&key_info.receiver_id, &key_info.method_names)),
+                );
+            }
+            bytes += options.contract_bytes.map_or(0, |contract_bytes| contract_bytes.len() as u64);
+            ft_registrations = options.ft_registrations.map_or(0, |ft_registrations| ft_registrations.len() as u64);
+        }
+        let cost = env::storage_byte_cost()
+            .checked_mul(bytes.into())
+            .and_then(|storage_cost| {
+                self.ft_registration_deposit
+                    .checked_mul(ft_registrations.into())
+                    .and_then(|registrations| storage_cost.checked_add(registrations))
+            })
+            .unwrap_or_else(|| overflow("account creation cost"));
+        U128(cost.as_attounc())
+    }
+
+    /// Checks the options of a `create_account_advanced` call without creating the account, and reports
+    /// the deposit and gas it needs along with warnings about likely mistakes.
+    #[handle_result]
+    pub fn validate_create_account_options(
+        &self,
+        new_account_id: AccountId,
+        options: CreateAccountOptions,
+    ) -> Result<ValidationReport, AirdropError> {
+        self.internal_validate_create_account_options(&new_account_id, &options)
+    }
+
+    /// Returns the version, source link and implemented standards of the contract (NEP-330).
+    /// Same as the `contract_source_metadata` view generated by `unc_bindgen` from the crate version
+    /// and the `contract_metadata` attribute, except for the link once the owner has updated it.
+    pub fn get_source_metadata(&self) -> ContractSourceMetadata {
+        let mut metadata: ContractSourceMetadata = unc_sdk::serde_json::from_str(CONTRACT_SOURCE_METADATA)
+            .expect("Invalid contract source metadata");
+        if let Some(link) = &self.source_link {
+            metadata.link = Some(link.clone());
+        }
+        metadata
+    }
+
+    /// Updates the source link returned by `get_source_metadata`, e.g. when the repository moves,
+    /// or goes back to the one built into the code with `None`. Only the owner can set it.
+    #[payable]
+    pub fn set_source_metadata(&mut self, link: Option<String>) {
+        self.assert_owner();
+        assert_one_atto();
+        self.source_link = link;
+    }
+
+    /// Proposes `new_owner` as the next owner, which becomes the owner once it calls `accept_ownership`.
+    /// A new proposal replaces the pending one. Only the owner can propose.
+    #[payable]
+    pub fn propose_owner(&mut self, new_owner: AccountId) {
+        self.assert_owner();
+        assert_one_atto();
+        self.internal_propose_owner(new_owner);
+    }
+
+    /// Makes the caller the owner. Only the account proposed with `propose_owner` can accept.
+    #[payable]
+    pub fn accept_ownership(&mut self) {
+        assert_one_atto();
+        assert_eq!(
+            self.pending_owner.as_ref(),
+            Some(&env::predecessor_account_id()),
+            "Only the proposed owner can accept the ownership"
+        );
+        let new_owner = self.pending_owner.take().unwrap();
+        let old_owner = std::mem::replace(&mut self.owner_id, new_owner);
+        AirDropEvent::OwnershipAccepted { old_owner: &old_owner, new_owner: &self.owner_id }.emit();
+    }
+
+    /// Withdraws the pending ownership proposal. Only the owner can cancel it.
+    #[payable]
+    pub fn cancel_ownership_proposal(&mut self) {
+        self.assert_owner();
+        assert_one_atto();
+        let new_owner = self.pending_owner.take().expect("No pending owner");
+        AirDropEvent::OwnerProposalCancelled { new_owner: &new_owner }.emit();
+    }
+
+    /// Schedules an owner action to be executed with `execute_action` from `eta`, a block timestamp in
+    /// nanoseconds at least `timelock_delay_ns` from now. Returns the id of the action. Only the owner can
+    /// schedule actions. Only the actions of `OwnerAction` go through the timelock: pausing and unpausing,
+    /// the owner setters such as `set_claim_cooldown`, `set_fee_bps`, `set_min_drop_amount` and
+    /// `set_create_account_limits`, and `withdraw_fees` and `withdraw_sponsor_pool` take effect immediately.
+    #[payable]
+    pub fn schedule_action(&mut self, action: OwnerAction, eta: u64) -> u64 {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(
+            eta >= env::block_timestamp().saturating_add(self.timelock_delay_ns),
+            "Eta is earlier than the timelock delay"
+        );
+        let id = self.next_action_id;
+        self.next_action_id += 1;
+        self.scheduled_actions.insert(id, ScheduledAction { id, action, eta });
+        id
+    }
+
+    /// Executes a scheduled action once its eta has passed. Only the owner can execute actions.
+    #[payable]
+    pub fn execute_action(&mut self, action_id: u64) {
+        self.assert_owner();
+        assert_one_atto();
+        let scheduled = self.scheduled_actions.get(&action_id).expect("No scheduled action with this id");
+        assert!(env::block_timestamp() >= scheduled.eta, "Action is still timelocked");
+        let action = self.scheduled_actions.remove(&action_id).unwrap().action;
+        match action {
+            OwnerAction::SetTimelockDelay { timelock_delay_ns } => {
+                self.timelock_delay_ns = timelock_delay_ns;
+            }
+            OwnerAction::WithdrawTreasury { to, amount } => {
+                assert!(amount.0 <= self.internal_available_balance(), "Amount exceeds the funds not owed to the keys");
+                Promise::new(to).transfer(UncToken::from_attounc(amount.0));
+            }
+            OwnerAction::StageUpgrade { code_hash } => {
+                self.staged_code_hash = Some(code_hash.into());
+            }
+            OwnerAction::SetOwner { new_owner } => {
+                self.internal_propose_owner(new_owner);
+            }
+        }
+    }
+
+    /// Removes a scheduled action before it is executed. Only the owner can cancel actions.
+    #[payable]
+    pub fn cancel_action(&mut self, action_id: u64) {
+        self.assert_owner();
+        assert_one_atto();
+        assert!(self.scheduled_actions.remove(&action_id).is_some(), "No scheduled action with this id");
+    }
+
+    /// Returns up to `limit` scheduled actions, skipping the first `from_index` ones.
+    pub fn get_scheduled_actions(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<&ScheduledAction> {
+        self.scheduled_actions
+            .values()
+            .skip(from_index.unwrap_or(0) as usize)
+            .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+            .collect()
+    }
+
+    /// Returns the sha256 of the code staged for the next upgrade, if any.
+    pub fn get_staged_code_hash(&self) -> Option<Base58CryptoHash> {
+        self.staged_code_hash.map(Base58CryptoHash::from)
+    }
+
+    /// Returns the account proposed to become the owner, if any.
+    pub fn get_pending_owner(&self) -> Option<&AccountId> {
+        self.pending_owner.as_ref()
+    }
+
+    /// Returns the account the contract is owned by.
+    pub fn get_owner_id(&self) -> &AccountId {
+        &self.owner_id
+    }
+
+    /// Returns information associated with a given key.
+    /// Part of the airdrop NEP
+    #[handle_result]
+    pub fn get_key_information(&self, key: PublicKey) -> Result<KeyInfo, &'static str> {
+        self.internal_key_info(&key).ok_or("Key is missing")
+    }
+
+    /// Returns the information of each of the keys, in the same order, or `None` for the missing ones.
+    /// At most MAX_KEYS_PER_QUERY keys can be queried at once.
+    pub fn get_keys_information(&self, keys: Vec<PublicKey>) -> Vec<Option<KeyInfo>> {
+        assert!(
+            keys.len() <= MAX_KEYS_PER_QUERY,
+            "At most {} keys can be queried at once",
+            MAX_KEYS_PER_QUERY
+        );
+        keys.iter().map(|key| self.internal_key_info(key)).collect()
+    }
+
+    /// Returns whether the key has a balance to claim.
+    pub fn key_exists(&self, key: PublicKey) -> bool {
+        self.internal_key_balance(&key).is_some()
+    }
+
+    /// Returns whether the key can be claimed right now: it exists, the contract isn't paused, the key
+    /// isn't locked or expired, its drop accepts claims and its cooldown and drip interval have elapsed.
+    pub fn can_claim(&self, public_key: PublicKey) -> bool {
+        self.internal_key_balance(&public_key).is_some()
+            && !self.paused
+            && self.internal_claim_error(&public_key).is_none()
+            && self.drips.get(&key_hash(&public_key))
+                .is_none_or(|drip| env::block_timestamp() >= drip.next_claim_ns())
+    }
+
+    /// Simulates a `claim` of the key to `receiver_id` without changing the state, e.g. for a wallet to show
+    /// the payout before the user signs. The payout is computed like the claim computes it, and `errors`
+    /// lists the checks the claim would fail right now. Fails if the key isn't funded.
+    #[handle_result]
+    pub fn simulate_claim(
+        &self,
+        public_key: PublicKey,
+        receiver_id: Option<AccountId>,
+    ) -> Result<ClaimSimulation, AirdropError> {
+        let balance = self.internal_key_balance(&public_key).ok_or(AirdropError::KeyMissing)?;
+        let mut errors = Vec::new();
+        if self.paused {
+            errors.push("Contract is paused".to_string());
+        }
+        if receiver_id.is_some_and(|receiver_id| self.blocked_accounts.contains(&receiver_id)) {
+            errors.push(AirdropError::AccountBlocked.to_string());
+        }
+        if let Some(error) = self.internal_claim_error(&public_key) {
+            errors.push(error);
+        }
+        if self.drips.get(&key_hash(&public_key)).is_some_and(|drip| env::block_timestamp() < drip.next_claim_ns()) {
+            errors.push("Drip interval has not elapsed yet".to_string());
+        }
+        let (_, payout) = self.internal_compute_claim(&public_key, balance);
+        Ok(ClaimSimulation {
+            payout: U128(payout.as_attounc()),
+            requires_new_account: !self
+                .internal_key_method_names(&public_key)
+                .split(',')
+                .any(|method_name| method_name == "claim"),
+            errors,
+        })
+    }
+
+    /// Returns the number of funded keys.
+    pub fn get_key_count(&self) -> u64 {
+        self.key_count
+    }
+
+    fn internal_key_info(&self, key: &PublicKey) -> Option<KeyInfo> {
+        self.internal_key_balance(key).map(|balance| KeyInfo {
+            balance: U128::from(balance.as_attounc()),
+            allowance_remaining: U128::from(
+                self.allowances.get(key).map_or(0, |allowance| allowance.as_attounc())
+            ),
+            tag: self.key_tags.get(key).cloned(),
+            failed_attempts: self.failed_attempts.get(&key_hash(key)).copied().unwrap_or(0),
+            remaining_attempts: self.max_claim_attempts
+                .saturating_sub(self.failed_attempts.get(&key_hash(key)).copied().unwrap_or(0)),
+            max_per_claim: self.drips.get(&key_hash(key)).map(|drip| U128(drip.amount.as_attounc())),
+            expiry_ns: self.key_expirations.get(key).copied(),
+            disabled: self.failed_attempts.get(&key_hash(key)).is_some_and(|attempts| *attempts >= MAX_FAILED_CLAIM_ATTEMPTS)
+                || self.key_drops.get(&key_hash(key)).is_some_and(|drop_id| self.drop_configs[drop_id].paused),
+            created_at_ns: self.funded_at.get(&key_hash(key)).copied().unwrap_or(0),
+            claimable: self.can_claim(key.clone()),
+            status: self.internal_key_status(key),
+            new_accounts_only: self.internal_new_accounts_only(key),
+        })
+    }
+
+    /// Returns the balance of the key, whether it is stored in `key_balances` or still in `accounts`.
+    fn internal_key_balance(&self, key: &PublicKey) -> Option<UncToken> {
+        match self.key_balances.get(&key_hash(key)) {
+            Some(balance) => Some(UncToken::from_attounc(*balance)),
+            None => self.accounts.get(key).copied(),
+        }
+    }
+
+    /// Removes the balance of the key from wherever it is stored and returns it.
+    fn internal_take_key_balance(&mut self, key: &PublicKey) -> Option<UncToken> {
+        let Some(balance) = self.key_balances.remove(&key_hash(key)) else {
+            // Balances stored before `key_liability` and `key_count` were tracked were never added to them
+            return self.accounts.remove(key);
+        };
+        let balance = UncToken::from_attounc(balance);
+        self.key_liability = self.key_liability.checked_sub(balance).unwrap_or_else(|| overflow("key liability"));
+        self.key_count = self.key_count.checked_sub(1).unwrap_or_else(|| overflow("key count"));
+        Some(balance)
+    }
+
+    /// Stores the balance of the key in `key_balances`. Callers take the previous balance first,
+    /// so a key never has entries in both maps.
+    fn internal_set_key_balance(&mut self, key: &PublicKey, balance: UncToken) {
+        self.key_balances.insert(key_hash(key), balance.as_attounc());
+        self.key_liability = self.key_liability.checked_add(balance).unwrap_or_else(|| overflow("key liability"));
+        self.key_count += 1;
+    }
+
+    /// Counts a key claim in the current window and checks it against the claim limits.
+    /// Rejected claims are reported as errors rather than panics so they are still counted.
+    fn internal_track_claim(&mut self) -> Result<(), ClaimError> {
+        self.assert_not_paused();
+        let window = env::block_height() / CLAIM_WINDOW_BLOCKS;
+        if window != self.claim_window {
+            self.claim_window = window;
+            self.claims_in_window = 0;
+        }
+        self.claims_in_window += 1;
+        if self.auto_pause_threshold.is_some_and(|threshold| self.claims_in_window > threshold) {
+            self.paused = true;
+            AirDropEvent::AutoPaused { window, claims: self.claims_in_window }.emit();
+            return Err(ClaimError::AutoPaused);
+        }
+        if self.max_claims_per_window.is_some_and(|max| self.claims_in_window > max) {
+            return Err(ClaimError::RateLimited);
+        }
+        Ok(())
+    }
+
+    /// Asserts that `signature` is the ED25519 signature of
+    /// `signed_claim_message(current_account_id, account_id, nonce)` by the key, and uses up the nonce.
+    fn internal_verify_claim_signature(
+        &mut self,
+        public_key: &PublicKey,
+        account_id: &AccountId,
+        signature: Vec<u8>,
+        nonce: u64,
+    ) {
+        assert!(
+            matches!(public_key.curve_type(), CurveType::ED25519),
+            "Only ED25519 keys can sign claims"
+        );
+        let signature: [u8; 64] = signature.try_into().expect("Signature must be 64 bytes");
+        let key_bytes: [u8; 32] = public_key.as_bytes()[1..].try_into().unwrap();
+        assert!(
+            env::ed25519_verify(&signature, &signed_claim_message(&env::current_account_id(), account_id, nonce), &key_bytes),
+            "Invalid signature"
+        );
+        assert!(
+            self.used_nonces.insert((key_hash(public_key), nonce)),
+            "Nonce already used"
+        );
+    }
+
+    /// Locks a key claimed with a signature until the transfer of its balance resolves.
+    /// Returns the amount to pay out, with the storage stake of the key.
+    fn internal_lock_signed_key(&mut self, public_key: &PublicKey) -> UncToken {
+        assert!(
+            !self.drips.contains_key(&key_hash(public_key)),
+            "Drip keys can only be claimed with claim"
+        );
+        assert!(
+            !self.internal_new_accounts_only(public_key),
+            "Key can only be claimed to a new account with create_account_and_claim or create_account_and_claim_derived"
+        );
+        self.assert_claimable(public_key);
+        let balance = self.internal_key_balance(public_key).expect("Unexpected public key");
+        self.claim_locks.insert(key_hash(public_key), env::block_height());
+        self.internal_compute_claim(public_key, balance).1
+    }
+
+    /// Takes the whole balance of a key claimed with a signature once it is paid out to `account_id`,
+    /// and forgets the key.
+    fn internal_claim_signed_key(&mut self, public_key: PublicKey, account_id: &AccountId) {
+        let amount = self
+            .internal_take_key_balance(&public_key)
+            .expect("Unexpected public key");
+        self.internal_release_tokens(&public_key, account_id);
+        self.internal_record_claim(public_key.clone(), account_id.clone(), amount, false);
+        self.internal_count_key_claimed(&public_key);
+        // Keys funded with `send` also have an access key to delete
+        let has_access_key = self.allowances.contains_key(&public_key);
+        // The storage stake was paid out with the balance
+        let _ = self.internal_forget_key(&public_key);
+        if has_access_key {
+            Promise::new(env::current_account_id()).delete_key(public_key);
+        }
+    }
+
+    /// Validation shared by `create_account_advanced` and `validate_create_account_options`.
+    fn internal_validate_create_account_options(
+        &self,
+        new_account_id: &AccountId,
+        options: &CreateAccountOptions,
+    ) -> Result<ValidationReport, AirdropError> {
+        if !env::is_valid_account_id(new_account_id.as_bytes()) {
+            return Err(AirdropError::InvalidAccountId);
+        }
+        if self.blocked_accounts.contains(new_account_id) {
+            return Err(AirdropError::AccountBlocked);
+        }
+        if let Some(suffix) = &self.required_suffix {
+            if !new_account_id.as_str().ends_with(suffix.as_str()) {
+                return Err(AirdropError::MissingSuffix(suffix.clone()));
+            }
+        }
+        let is_some_option = options.contract_bytes.is_some() || options.full_access_keys.is_some() || options.limited_access_keys.is_some();
+        if !is_some_option {
+            return Err(AirdropError::NoOptions);
+        }
+        if options.keyless == Some(true)
+            && options.full_access_keys.as_ref().is_some_and(|full_access_keys| !full_access_keys.is_empty())
+        {
+            return Err(AirdropError::KeylessWithFullAccessKeys);
+        }
+
+        let mut warnings = Vec::new();
+        let full_access_keys = options.full_access_keys.iter().flatten();
+        let limited_access_keys = options.limited_access_keys.iter().flatten();
+        let mut keys: Vec<&PublicKey> = full_access_keys.clone().collect();
+        keys.extend(limited_access_keys.clone().map(|key_info| &key_info.public_key));
+        if keys.len() as u64 > self.max_keys_per_account {
+            return Err(AirdropError::TooManyKeys(self.max_keys_per_account));
+        }
+        for (i, key) in keys.iter().enumerate() {
+            if keys[..i].contains(key) {
+                return Err(AirdropError::DuplicateKey((*key).clone()));
+            }
+        }
+        for key_info in limited_access_keys {
+            let is_valid_method_name = |method_name: &str| {
+                !method_name.is_empty()
+                    && method_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            };
+            if !key_info.method_names.is_empty()
+                && !key_info.method_names.split(',').all(is_valid_method_name)
+            {
+                return Err(AirdropError::InvalidMethodNames(key_info.method_names.clone()));
+            }
+            if key_info.method_names.is_empty() {
+                warnings.push(format!(
+                    "Key {} can call any method of {}",
+                    String::from(&key_info.public_key),
+                    key_info.receiver_id
+                ));
+            }
+            if key_info.allowance.is_zero() {
+                warnings.push(format!(
+                    "Key {} has an allowance of 0, which makes it unlimited",
+                    String::from(&key_info.public_key)
+                ));
+            }
+        }
+
+        let ft_registrations = options.ft_registrations.as_ref().map_or(0, |ft_registrations| ft_registrations.len() as u64);
+        if ft_registrations > MAX_FT_REGISTRATIONS {
+            return Err(AirdropError::TooManyFtRegistrations(MAX_FT_REGISTRATIONS));
+        }
+
+        let mut estimated_gas = CREATE_ACCOUNT_ADVANCED_GAS.saturating_add(ft_registrations_gas(ft_registrations));
+        if let Some(bytes) = &options.contract_bytes {
+            if bytes.len() as u64 > self.max_contract_bytes {
+                return Err(AirdropError::ContractTooLarge(self.max_contract_bytes));
+            }
+            if !bytes.starts_with(WASM_MAGIC) {
+                return Err(AirdropError::InvalidContractBytes);
+            }
+            estimated_gas = estimated_gas.saturating_add(
+                Gas::from_gas((bytes.len() as u64).saturating_mul(DEPLOY_GAS_PER_BYTE))
+            );
+        }
+        if full_access_keys.count() == 0 {
+            warnings.push("No full access key is added, the account can't be managed".to_string());
+        }
+
+        Ok(ValidationReport {
+            warnings,
+            minimum_deposit: self.get_account_creation_cost(Some(options.clone())),
+            estimated_gas,
+        })
+    }
+
+    fn assert_required_suffix(&self, account_id: &AccountId) {
+        if let Some(suffix) = &self.required_suffix {
+            assert!(
+                account_id.as_str().ends_with(suffix.as_str()),
+                "Account id must end with {}",
+                suffix
+            );
+        }
+    }
+
+    fn assert_not_paused(&self) {
+        assert!(!self.paused, "Contract is paused");
+    }
+
+    fn assert_funding_enabled(&self) {
+        assert!(self.funding_enabled, "Funding is disabled");
+    }
+
+    /// Creates the account and claims the balance of the key this tx is signed with to it, for
+    /// `create_account_and_claim` and `create_account_and_claim_derived`, which is `method_name`.
+    fn internal_create_account_and_claim(
+        &mut self,
+        new_account_id: AccountId,
+        new_public_key: PublicKey,
+        staking: Option<StakingIntent>,
+        options: Option<CreateAccountOptions>,
+        method_name: &str,
+    ) -> PromiseOrValue<ClaimAmountOutcome> {
+        if let Err(error) = self.check_claim_context(method_name) {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+        }
+        assert!(
+            env::is_valid_account_id(new_account_id.as_bytes()),
+            "Invalid account id"
+        );
+        assert!(
+            !is_implicit_account_id(&new_account_id),
+            "Implicit accounts can't be created, claim to them instead"
+        );
+        self.assert_not_blocked(&new_account_id);
+        self.assert_required_suffix(&new_account_id);
+        let options = options.map(|mut options| {
+            assert!(
+                options.ft_registrations.as_ref().is_none_or(|ft_registrations| ft_registrations.is_empty()),
+                "FT registrations can't be added by create_account_and_claim"
+            );
+            if options.keyless != Some(true) {
+                options.full_access_keys.get_or_insert_with(Vec::new).insert(0, new_public_key.clone());
+            }
+            if let Err(error) = self.internal_validate_create_account_options(&new_account_id, &options) {
+                panic!("{}", error);
+            }
+            options
+        });
+        if let Err(error) = self.internal_track_claim() {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(error));
+        }
+        if self.claim_locks.contains_key(&key_hash(&env::signer_account_pk())) {
+            return PromiseOrValue::Value(ClaimAmountOutcome::Rejected(ClaimError::ClaimInProgress));
+        }
+        assert!(
+            !self.drips.contains_key(&key_hash(&env::signer_account_pk())),
+            "Drip keys can only be claimed with claim"
+        );
+        self.assert_claimable(&env::signer_account_pk());
+        if let Some(reservation) = self.reservations.get(&env::signer_account_pk()) {
+            assert_eq!(
+                reservation.account_id, new_account_id,
+                "Key is reserved for {}", reservation.account_id
+            );
+        }
+        let amount = self
+            .internal_key_balance(&env::signer_account_pk())
+            .expect("Unexpected public key");
+        let mut callback_gas = MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS;
+        let mut transfer = amount;
+        if let Some(staking) = &staking {
+            assert!(staking.amount.0 > 0, "Staking amount must be positive");
+            let account_cost = env::storage_byte_cost()
+                .checked_mul(u128::from(
+                    ACCOUNT_STORAGE_BYTES + access_key_storage_bytes(new_public_key.as_bytes().len() as u64, None),
+                ))
+                .unwrap_or_else(|| overflow("account creation cost"));
+            transfer = amount
+                .checked_sub(UncToken::from_attounc(staking.amount.0))
+                .filter(|transfer| *transfer >= account_cost)
+                .expect("Staking amount must leave the storage cost of the new account");
+            callback_gas = callback_gas
+                .saturating_add(DEPOSIT_AND_STAKE_GAS)
+                .saturating_add(FT_TRANSFER_GAS)
+                .saturating_add(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS);
+        }
+        // The key stays locked with its balance until the callback resolves the creation
+        self.claim_locks.insert(key_hash(&env::signer_account_pk()), env::block_height());
+        self.charge_claim_allowance(&env::signer_account_pk());
+        assert_creation_gas();
+        let promise = Promise::new(new_account_id.clone()).create_account();
+        let promise = match &options {
+            Some(options) => build_creation_batch(promise, options),
+            None => promise.add_full_access_key(new_public_key),
+        };
+        promise
+            .transfer(transfer)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(callback_gas)
+                    .with_unused_gas_weight(1)
+                    .on_account_created_and_claimed(new_account_id, amount, staking)
+            )
+            .into()
+    }
+
+    /// Takes the balance of the key to claim it to `account_id`, or the next drip of it.
+    /// Returns the amount to pay out, with the storage stake once the key is fully claimed and removed.
+    fn internal_claim_balance(&mut self, public_key: PublicKey, account_id: &AccountId) -> UncToken {
+        let balance = self
+            .internal_take_key_balance(&public_key)
+            .expect("Unexpected public key");
+        self.internal_release_tokens(&public_key, account_id);
+        let (amount, payout) = self.internal_compute_claim(&public_key, balance);
+        self.internal_record_drip(&public_key);
+        self.internal_record_claim(public_key.clone(), account_id.clone(), amount, false);
+        let remaining = balance.checked_sub(amount).unwrap_or_else(|| overflow("claim"));
+        if remaining.is_zero() {
+            self.internal_count_key_claimed(&public_key);
+            let storage_stake = self.internal_forget_key(&public_key);
+            debug_assert_eq!(payout, amount.saturating_add(storage_stake));
+            Promise::new(env::current_account_id()).delete_key(public_key);
+        } else {
+            // The key stays to claim the next drips
+            self.internal_set_key_balance(&public_key, remaining);
+        }
+        payout
+    }
+
+    fn internal_record_claim(
+        &mut self,
+        public_key: PublicKey,
+        account_id: AccountId,
+        amount: UncToken,
+        account_created: bool,
+    ) {
+        if let Some(drop_id) = self.key_drops.get(&key_hash(&public_key)) {
+            let drop = self.drop_configs.get_mut(drop_id).unwrap();
+            drop.remaining = drop.remaining.checked_sub(amount).unwrap_or_else(|| overflow("drop remaining"));
+            drop.claimed = drop.claimed.checked_add(amount).unwrap_or_else(|| overflow("drop claimed"));
+            self.drop_claims.insert((*drop_id, drop.claims), self.claim_history.len());
+            drop.claims += 1;
+        }
+        self.claim_history.push(ClaimRecord {
+            public_key,
+            account_id,
+            amount: U128(amount.as_attounc()),
+            timestamp: env::block_timestamp(),
+            block_height: env::block_height(),
+            account_created,
+        });
+        self.stats.total_unc_claimed.0 = self.stats.total_unc_claimed.0
+            .checked_add(amount.as_attounc())
+            .unwrap_or_else(|| overflow("total claimed"));
+    }
+
+    /// Records a failed operation in `failures`, overwriting the oldest record once MAX_FAILURE_RECORDS
+    /// are kept, and logs it.
+    fn internal_record_failure(
+        &mut self,
+        kind: FailureKind,
+        public_key: Option<PublicKey>,
+        account_id: Option<AccountId>,
+        amount: UncToken,
+    ) {
+        AirDropEvent::OperationFailed {
+            kind,
+            public_key: public_key.as_ref(),
+            account_id: account_id.as_ref(),
+            amount: U128(amount.as_attounc()),
+        }
+        .emit();
+        let record = FailureRecord {
+            kind,
+            public_key,
+            account_id,
+            amount: U128(amount.as_attounc()),
+            timestamp: env::block_timestamp(),
+        };
+        if (self.failures.len() as u64) < MAX_FAILURE_RECORDS {
+            self.failures.push(record);
+        } else {
+            self.failures.set((self.failure_count % MAX_FAILURE_RECORDS) as u32, record);
+        }
+        self.failure_count += 1;
+    }
+
+    /// Counts a key whose whole balance was claimed, in the stats and in its drop if it has one.
+    fn internal_count_key_claimed(&mut self, key: &PublicKey) {
+        self.stats.total_drops_claimed += 1;
+        if let Some(drop_id) = self.key_drops.get(&key_hash(key)) {
+            self.drop_configs.get_mut(drop_id).unwrap().keys_claimed += 1;
+        }
+    }
+
+    /// Sets the tag of the key and adds the key to the index of the tag.
+    fn internal_set_key_tag(&mut self, key: &PublicKey, tag: String) {
+        self.internal_remove_key_tag(key);
+        self.keys_by_tag.entry(tag.clone()).or_default().push(key.clone());
+        self.key_tags.insert(key.clone(), tag);
+    }
+
+    /// Removes the tag of the key and the key from the index of the tag. Returns the removed tag.
+    fn internal_remove_key_tag(&mut self, key: &PublicKey) -> Option<String> {
+        let tag = self.key_tags.remove(key)?;
+        if let Some(keys) = self.keys_by_tag.get_mut(&tag) {
+            keys.retain(|tagged| tagged != key);
+            if keys.is_empty() {
+                self.keys_by_tag.remove(&tag);
+            }
+        }
+        Some(tag)
+    }
+
+    fn internal_remove_funder_drop(&mut self, funder: &AccountId, drop_id: u64) {
+        if let Some(drop_ids) = self.drops_by_funder.get_mut(funder) {
+            drop_ids.retain(|id| *id != drop_id);
+            if drop_ids.is_empty() {
+                self.drops_by_funder.remove(funder);
+            }
+        }
+    }
+
+    /// Deletes the drop and its claim index if it has `auto_withdraw` set and no key left, and refunds the
+    /// storage the drop freed and what is left of its balance to the funder.
+    fn internal_complete_drop(&mut self, drop_id: u64) {
+        let drop = &self.drop_configs[&drop_id];
+        if !drop.auto_withdraw || drop.keys_left() > 0 {
+            return;
+        }
+        // Pending writes are flushed so that the storage usage only changes by the removed records
+        self.drop_configs.flush();
+        self.drops_by_funder.flush();
+        let storage_before = env::storage_usage();
+        let drop = self.drop_configs.remove(&drop_id).unwrap();
+        self.internal_remove_funder_drop(&drop.funder, drop_id);
+        self.drop_configs.flush();
+        self.drops_by_funder.flush();
+        let freed_bytes = storage_before.saturating_sub(env::storage_usage());
+        // The claim index was stored at the expense of the contract, so it isn't refunded
+        for index in 0..drop.claims {
+            self.drop_claims.remove(&(drop_id, index));
+        }
+        let refunded = env::storage_byte_cost()
+            .checked_mul(freed_bytes.into())
+            .and_then(|storage_cost| storage_cost.checked_add(drop.remaining))
+            .unwrap_or_else(|| overflow("drop refund"));
+        AirDropEvent::DropCompleted {
+            drop_id,
+            funder: &drop.funder,
+            refunded: U128(refunded.as_attounc()),
+        }
+        .emit();
+        if !refunded.is_zero() {
+            Promise::new(drop.funder).transfer(refunded);
+        }
+    }
+
+    /// Removes an unclaimed key and returns its balance with its storage stake, counting the balance as
+    /// refunded. The caller deletes the access key.
+    fn internal_reclaim_key(&mut self, key: &PublicKey) -> UncToken {
+        let balance = self.internal_take_key_balance(key).unwrap_or(UncToken::from_attounc(0));
+        self.stats.total_drops_refunded += 1;
+        self.stats.total_unc_refunded.0 = self.stats.total_unc_refunded.0
+            .checked_add(balance.as_attounc())
+            .unwrap_or_else(|| overflow("total refunded"));
+        if let Some(drop_id) = self.key_drops.get(&key_hash(key)) {
+            let drop = self.drop_configs.get_mut(drop_id).unwrap();
+            drop.keys_reclaimed += 1;
+            drop.remaining = drop.remaining.checked_sub(balance).unwrap_or_else(|| overflow("drop remaining"));
+            drop.refunded = drop.refunded.checked_add(balance).unwrap_or_else(|| overflow("drop refunded"));
+        }
+        let storage_stake = self.internal_forget_key(key);
+        balance.checked_add(storage_stake).unwrap_or_else(|| overflow("reclaim"))
+    }
+
+    /// Returns the methods the access key of the key can call: `create_account_and_claim` for a reserved key
+    /// or a key for new accounts only, the `methods` it was funded with, those allowed by its drop, or the
+    /// default claim methods.
+    fn internal_key_method_names(&self, key: &PublicKey) -> String {
+        if self.reservations.contains_key(key) {
+            return "create_account_and_claim".to_string();
+        }
+        if self.internal_new_accounts_only(key) {
+            return NEW_ACCOUNT_CLAIM_METHODS.join(",");
+        }
+        if let Some(methods) = self.key_methods.get(&key_hash(key)) {
+            return methods.clone();
+        }
+        self.key_drops
+            .get(&key_hash(key))
+            .and_then(|drop_id| self.drop_configs[drop_id].allowed_methods.clone())
+            .unwrap_or_else(|| self.get_default_claim_methods())
+    }
+
+    /// Returns whether the key can only be claimed to a new account, set by `send` or by its drop.
+    fn internal_new_accounts_only(&self, key: &PublicKey) -> bool {
+        self.new_account_keys.contains(&key_hash(key))
+            || self
+                .key_drops
+                .get(&key_hash(key))
+                .is_some_and(|drop_id| self.drop_configs[drop_id].new_accounts_only)
+    }
+
+    /// Asserts that the claim method `method_name` of CLAIM_METHODS is called by the contract itself, i.e.
+    /// with one of its access keys, and that the access key is allowed to call it. Every claim method calls
+    /// this first, so a new one only has to be added to CLAIM_METHODS.
+    fn check_claim_context(&self, method_name: &str) -> Result<(), ClaimError> {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Claim only can come from this account"
+        );
+        debug_assert!(CLAIM_METHODS.contains(&method_name), "{} is not a claim method", method_name);
+        // Only a transaction signed with an access key of the contract account itself is a claim by a drop key.
+        // In a call the contract makes to itself, the signer key is that of whoever started the chain.
+        let public_key = env::signer_account_pk();
+        if env::signer_account_id() != env::current_account_id()
+            || self.admin_keys.contains(&public_key)
+            || self.internal_key_balance(&public_key).is_none()
+        {
+            return Err(ClaimError::NotADropKey);
+        }
+        if !NEW_ACCOUNT_CLAIM_METHODS.contains(&method_name) && self.internal_new_accounts_only(&public_key) {
+            return Err(ClaimError::NewAccountRequired);
+        }
+        self.assert_method_allowed(&public_key, method_name);
+        Ok(())
+    }
+
+    /// Asserts that the access key of the key is allowed to call `method_name`. The runtime already
+    /// enforces the methods of the access key, this keeps the contract from relying on it alone.
+    fn assert_method_allowed(&self, key: &PublicKey, method_name: &str) {
+        let method_names = self.internal_key_method_names(key);
+        assert!(
+            method_names.split(',').any(|allowed| allowed == method_name),
+            "Method {} is not allowed for this key",
+            method_name
+        );
+    }
+
+    /// Adds `amount` tokens of `ft_contract_id` to the key.
+    fn internal_credit_tokens(&mut self, key: &PublicKey, ft_contract_id: AccountId, amount: u128) {
+        let tokens = self.ft_balances.entry(key_hash(key)).or_insert(FtBalance {
+            ft_contract_id: ft_contract_id.clone(),
+            amount: U128(0),
+        });
+        assert_eq!(
+            tokens.ft_contract_id, ft_contract_id,
+            "Key already holds the tokens of another contract"
+        );
+        tokens.amount.0 = tokens.amount.0.checked_add(amount).unwrap_or_else(|| overflow("token balance"));
+    }
+
+    /// Transfers the tokens of the key, if any, to `receiver_id`, whom the balance of the key is paid out to.
+    /// They are stranded if the transfer fails, e.g. when `receiver_id` isn't registered on the FT contract,
+    /// so that the claim of the balance doesn't depend on the FT contract.
+    fn internal_release_tokens(&mut self, key: &PublicKey, receiver_id: &AccountId) {
+        if let Some(tokens) = self.ft_balances.remove(&key_hash(key)) {
+            internal_transfer_tokens(tokens.ft_contract_id, receiver_id.clone(), tokens.amount.0);
+        }
+    }
+
+    /// Adds tokens that couldn't be released to `stranded_tokens`.
+    fn internal_strand_tokens(&mut self, ft_contract_id: AccountId, amount: u128) {
+        AirDropEvent::TokensStranded { ft_contract_id: &ft_contract_id, amount: U128(amount) }.emit();
+        let stranded = self.stranded_tokens.entry(ft_contract_id).or_insert(0);
+        *stranded = stranded.checked_add(amount).unwrap_or_else(|| overflow("stranded tokens"));
+    }
+
+    /// Returns the drop `drop_id`, asserting that the caller is its funder.
+    fn internal_funded_drop(&mut self, drop_id: u64) -> &mut DropConfig {
+        let drop = self.drop_configs.get_mut(&drop_id).expect("No such drop");
+        if drop.funder != env::predecessor_account_id() {
+            panic!("{}", AirdropError::NotDropFunder);
+        }
+        drop
+    }
+
+    /// Minimum balance of a new key: `min_drop_amount`, but at least `MIN_NET_CREDIT`.
+    fn internal_min_drop_amount(&self) -> UncToken {
+        std::cmp::max(self.min_drop_amount, MIN_NET_CREDIT)
+    }
+
+    /// Returns the part of the attached deposit `send` uses under `over_deposit_policy`, and refunds the
+    /// rest to the sender.
+    fn internal_send_deposit(&self) -> UncToken {
+        let deposit = env::attached_deposit();
+        let OverDepositPolicy::RefundExcess(max_deposit) = &self.over_deposit_policy else {
+            return deposit;
+        };
+        let max_deposit = UncToken::from_attounc(max_deposit.0);
+        if deposit <= max_deposit {
+            return deposit;
+        }
+        Promise::new(env::predecessor_account_id()).transfer(deposit.saturating_sub(max_deposit));
+        max_deposit
+    }
+
+    /// Deposit `send` requires for a new key: the allowance of its curve and the minimum balance of the key,
+    /// so a deposit equal to the allowance is always rejected.
+    fn internal_min_send_deposit(&self, key: &PublicKey) -> UncToken {
+        self.internal_curve_allowance(key)
+            .checked_add(self.internal_min_drop_amount())
+            .unwrap_or_else(|| overflow("minimum deposit"))
+    }
+
+    /// Returns the allowance of the access key of a new key: `max_claim_attempts` times the allowance of a
+    /// claim attempt of its curve.
+    fn internal_curve_allowance(&self, key: &PublicKey) -> UncToken {
+        self.internal_attempts_allowance(self.internal_attempt_allowance(key))
+    }
+
+    /// Returns the allowance of the access key of a key funded with `deposit`. A drip key gets the allowance
+    /// of a claim attempt for each of its drips if there are more than `max_claim_attempts`.
+    fn internal_new_key_allowance(&self, key: &PublicKey, deposit: UncToken) -> UncToken {
+        let Some(drip) = self.drips.get(&key_hash(key)) else {
+            return self.internal_curve_allowance(key);
+        };
+        self.internal_drip_allowance(key, drip.amount, deposit)
+            .unwrap_or_else(|| overflow("access key allowance"))
+    }
+
+    /// Returns the allowance of the access key of a drip key funded with `deposit`, or None if it doesn't
+    /// fit in a balance.
+    fn internal_drip_allowance(&self, key: &PublicKey, drip_amount: UncToken, deposit: UncToken) -> Option<UncToken> {
+        let attempt_allowance = self.internal_attempt_allowance(key);
+        // Each drip also takes the allowance of its claim from the deposit, so the balance left is at most
+        // this many drips. The storage stake isn't known yet, which can only add a drip.
+        let per_drip = drip_amount.checked_add(attempt_allowance)?;
+        let drips = deposit.as_attounc().div_ceil(per_drip.as_attounc());
+        let attempts = std::cmp::max(drips, self.max_claim_attempts.into());
+        attempt_allowance.checked_mul(attempts)
+    }
+
+    /// Returns the allowance of a claim attempt of the key, depending on its curve.
+    fn internal_attempt_allowance(&self, key: &PublicKey) -> UncToken {
+        match key.curve_type() {
+            CurveType::ED25519 => self.ed25519_allowance,
+            CurveType::SECP256K1 => self.secp256k1_allowance,
+        }
+    }
+
+    fn internal_attempts_allowance(&self, attempt_allowance: UncToken) -> UncToken {
+        attempt_allowance
+            .checked_mul(self.max_claim_attempts.into())
+            .unwrap_or_else(|| overflow("access key allowance"))
+    }
+
+    /// Flushes the collections a new key writes to and returns the storage usage, so that the difference
+    /// with a later call only counts the records of the key.
+    fn internal_key_storage_usage(&mut self) -> u64 {
+        self.key_balances.flush();
+        self.allowances.flush();
+        self.key_tags.flush();
+        self.keys_by_tag.flush();
+        self.key_expirations.flush();
+        self.funded_at.flush();
+        self.storage_stakes.flush();
+        env::storage_usage()
+    }
+
+    /// Stores the balance of a new key: the attached deposit minus `fees` and its storage stake, the cost of
+    /// the storage added since `storage_before` plus `extra_bytes` (e.g. its access key). Asserts that the
+    /// balance is at least the minimum balance of a new key and returns it.
+    fn internal_fund_new_key(
+        &mut self,
+        key: &PublicKey,
+        deposit: UncToken,
+        fees: UncToken,
+        storage_before: u64,
+        extra_bytes: u64,
+    ) -> UncToken {
+        self.assert_key_capacity();
+        self.funded_at.insert(key_hash(key), env::block_timestamp());
+        // The placeholders take as many bytes as the final values
+        self.storage_stakes.insert(key_hash(key), UncToken::from_attounc(0));
+        self.internal_set_key_balance(key, UncToken::from_attounc(0));
+        let storage_bytes = self.internal_key_storage_usage().saturating_sub(storage_before) + extra_bytes;
+        let storage_stake = env::storage_byte_cost()
+            .checked_mul(storage_bytes.into())
+            .unwrap_or_else(|| overflow("storage stake"));
+        let required = self
+            .internal_min_drop_amount()
+            .checked_add(fees)
+            .and_then(|required| required.checked_add(storage_stake))
+            .unwrap_or_else(|| overflow("minimum deposit"));
+        assert!(
+            deposit >= required,
+            "Attached deposit must be at least {} attoUNC, got {}",
+            required.as_attounc(),
+            deposit.as_attounc()
+        );
+        let balance = deposit
+            .checked_sub(fees)
+            .and_then(|balance| balance.checked_sub(storage_stake))
+            .unwrap_or_else(|| overflow("send fee"));
+        self.internal_take_key_balance(key);
+        self.internal_set_key_balance(key, balance);
+        self.storage_stakes.insert(key_hash(key), storage_stake);
+        balance
+    }
+
+    /// Applies the settings of `config` given to `new`, checked like their setters check them.
+    fn internal_apply_config(&mut self, config: Config) {
+        if let Some(allowance) = config.ed25519_allowance {
+            assert!(allowance.0 > 0, "Allowance must be positive");
+            self.ed25519_allowance = UncToken::from_attounc(allowance.0);
+        }
+        if let Some(allowance) = config.secp256k1_allowance {
+            assert!(allowance.0 > 0, "Allowance must be positive");
+            self.secp256k1_allowance = UncToken::from_attounc(allowance.0);
+        }
+        if let Some(max_claim_attempts) = config.max_claim_attempts {
+            assert!(max_claim_attempts > 0, "Max claim attempts must be positive");
+            self.max_claim_attempts = max_claim_attempts;
+        }
+        if let Some(min_drop_amount) = config.min_drop_amount {
+            self.min_drop_amount = UncToken::from_attounc(min_drop_amount.0);
+        }
+        if let Some(fee_bps) = config.fee_bps {
+            assert!(fee_bps <= MAX_FEE_BPS, "Fee must be at most {} basis points", MAX_FEE_BPS);
+            self.fee_bps = fee_bps;
+        }
+        if let Some(claim_cooldown_ns) = config.claim_cooldown_ns {
+            self.internal_set_claim_cooldown(claim_cooldown_ns);
+        }
+        self.default_expiry_ns = config.default_expiry_ns.filter(|expiry_ns| *expiry_ns > 0);
+        self.timelock_delay_ns = config.timelock_delay_ns.unwrap_or(self.timelock_delay_ns);
+    }
+
+    /// Sets the claim cooldown, which only applies to the keys funded from now on.
+    fn internal_set_claim_cooldown(&mut self, claim_cooldown_ns: u64) {
+        self.claim_cooldown_ns = claim_cooldown_ns;
+        self.claim_cooldown_set_at = env::block_timestamp();
+    }
+
+    /// Returns `fee_bps` of `amount`.
+    fn internal_fee(&self, amount: UncToken) -> UncToken {
+        let amount = amount.as_attounc();
+        let fee_bps = u128::from(self.fee_bps);
+        // Split so that the product can't overflow
+        UncToken::from_attounc(amount / 10_000 * fee_bps + amount % 10_000 * fee_bps / 10_000)
+    }
+
+    /// Adds `fee_bps` of `amount` to `fees_collected` and returns it.
+    fn internal_collect_fee(&mut self, amount: UncToken) -> UncToken {
+        let fee = self.internal_fee(amount);
+        self.fees_collected = self.fees_collected.checked_add(fee).unwrap_or_else(|| overflow("fees collected"));
+        fee
+    }
+
+    /// Returns the account that funded the key, if it is recorded: the funder of its drop or its reservation.
+    fn internal_key_funder(&self, key: &PublicKey) -> Option<AccountId> {
+        if let Some(reservation) = self.reservations.get(key) {
+            return Some(reservation.funder.clone());
+        }
+        self.key_drops
+            .get(&key_hash(key))
+            .map(|drop_id| self.drop_configs[drop_id].funder.clone())
+    }
+
+    /// Asserts that the caller can top up the key, for `top_up` and `send`: the key has no claim in progress,
+    /// and the keys of a drop or a reservation can only be topped up by their funder.
+    fn assert_can_top_up(&self, key: &PublicKey) {
+        assert!(
+            !self.claim_locks.contains_key(&key_hash(key)),
+            "Key has a claim in progress"
+        );
+        if let Some(funder) = self.internal_key_funder(key) {
+            assert!(funder == env::predecessor_account_id(), "Only the funder of the key can top it up");
+        }
+    }
+
+    /// Credits `deposit` less the fee to the key whose balance `old_balance` was taken, and to the drop of the
+    /// key if it has one. Returns the new balance of the key.
+    fn internal_top_up(&mut self, key: &PublicKey, old_balance: UncToken, deposit: UncToken) -> UncToken {
+        let fee = self.internal_collect_fee(deposit);
+        let credited = deposit.checked_sub(fee).unwrap_or_else(|| overflow("top-up fee"));
+        let new_balance = old_balance.checked_add(credited).unwrap_or_else(|| overflow("top-up"));
+        self.internal_set_key_balance(key, new_balance);
+        self.internal_record_drop(credited, false);
+        if let Some(drop_id) = self.key_drops.get(&key_hash(key)).copied() {
+            let drop = self.drop_configs.get_mut(&drop_id).unwrap();
+            drop.remaining = drop.remaining.checked_add(credited).unwrap_or_else(|| overflow("drop remaining"));
+        }
+        AirDropEvent::DropToppedUp {
+            public_key: key,
+            old_balance: U128(old_balance.as_attounc()),
+            new_balance: U128(new_balance.as_attounc()),
+        }
+        .emit();
+        new_balance
+    }
+
+    /// Counts `amount` as dropped, and a new drop if `is_new` is set (otherwise it tops up an existing one).
+    fn internal_record_drop(&mut self, amount: UncToken, is_new: bool) {
+        if is_new {
+            self.stats.total_drops_created += 1;
+        }
+        self.stats.total_unc_dropped.0 = self.stats.total_unc_dropped.0
+            .checked_add(amount.as_attounc())
+            .unwrap_or_else(|| overflow("total dropped"));
+    }
+
+    /// Moves what is tracked about a key, except its balance and allowance, to `new_key`, for `rotate_key`.
+    /// Its failed claims are forgotten. Keep in sync with `internal_forget_key`.
+    fn internal_move_key_state(&mut self, key: &PublicKey, new_key: &PublicKey) {
+        if let Some(storage_stake) = self.storage_stakes.remove(&key_hash(key)) {
+            self.storage_stakes.insert(key_hash(new_key), storage_stake);
+        }
+        if let Some(tag) = self.internal_remove_key_tag(key) {
+            self.internal_set_key_tag(new_key, tag);
+        }
+        if let Some(drip) = self.drips.remove(&key_hash(key)) {
+            self.drips.insert(key_hash(new_key), drip);
+        }
+        if let Some(funded_at) = self.funded_at.remove(&key_hash(key)) {
+            self.funded_at.insert(key_hash(new_key), funded_at);
+        }
+        if let Some(expiration) = self.key_expirations.remove(key) {
+            self.key_expirations.insert(new_key.clone(), expiration);
+        }
+        self.failed_attempts.remove(&key_hash(key));
+        if let Some(reservation) = self.reservations.remove(key) {
+            self.reservations.insert(new_key.clone(), reservation);
+        }
+        if self.new_account_keys.remove(&key_hash(key)) {
+            self.new_account_keys.insert(key_hash(new_key));
+        }
+        if let Some(methods) = self.key_methods.remove(&key_hash(key)) {
+            self.key_methods.insert(key_hash(new_key), methods);
+        }
+        if let Some(tokens) = self.ft_balances.remove(&key_hash(key)) {
+            self.ft_balances.insert(key_hash(new_key), tokens);
+        }
+        if let Some(drop_id) = self.key_drops.remove(&key_hash(key)) {
+            self.key_drops.insert(key_hash(new_key), drop_id);
+        }
+    }
+
+    /// Removes what is tracked about a key once its balance has been claimed.
+    /// Returns the storage stake of the key, which the caller pays out with the balance.
+    /// Keep in sync with `internal_move_key_state`.
+    #[must_use]
+    fn internal_forget_key(&mut self, key: &PublicKey) -> UncToken {
+        let storage_stake = self.storage_stakes.remove(&key_hash(key)).unwrap_or(UncToken::from_attounc(0));
+        self.internal_remove_allowance(key);
+        self.internal_remove_key_tag(key);
+        self.drips.remove(&key_hash(key));
+        self.funded_at.remove(&key_hash(key));
+        self.key_expirations.remove(key);
+        self.failed_attempts.remove(&key_hash(key));
+        self.reservations.remove(key);
+        self.new_account_keys.remove(&key_hash(key));
+        self.key_methods.remove(&key_hash(key));
+        // Tokens the claim didn't release, e.g. of a reclaimed expired key, stay with the owner
+        if let Some(tokens) = self.ft_balances.remove(&key_hash(key)) {
+            self.internal_strand_tokens(tokens.ft_contract_id, tokens.amount.0);
+        }
+        if let Some(drop_id) = self.key_drops.remove(&key_hash(key)) {
+            self.internal_complete_drop(drop_id);
+        }
+        storage_stake
+    }
+
+    /// Asserts that the key isn't locked or expired, that its drop is claimable and that its claim cooldown
+    /// has passed. Keys funded while
+    /// no cooldown was set are claimable right away.
+    fn assert_claimable(&self, key: &PublicKey) {
+        if let Some(error) = self.internal_claim_error(key) {
+            panic!("{}", error);
+        }
+    }
+
+    /// Returns why the key can't be claimed right now, if it can't: it is locked, expired, its drop
+    /// doesn't accept claims or its cooldown hasn't elapsed.
+    fn internal_claim_error(&self, key: &PublicKey) -> Option<String> {
+        if self.claim_locks.contains_key(&key_hash(key)) {
+            return Some("Key has a claim in progress".to_string());
+        }
+        if self.failed_attempts.get(&key_hash(key)).is_some_and(|attempts| *attempts >= MAX_FAILED_CLAIM_ATTEMPTS) {
+            return Some("Key is locked after too many failed claims".to_string());
+        }
+        if self.key_expirations.get(key).is_some_and(|expiration| env::block_timestamp() >= *expiration) {
+            return Some("Key has expired".to_string());
+        }
+        if let Some(drop_id) = self.key_drops.get(&key_hash(key)) {
+            if let Some(error) = self.drop_configs[drop_id].claim_error(env::block_timestamp()) {
+                return Some(error.to_string());
+            }
+        }
+        if self.claim_cooldown_ns > 0 && self.funded_at.get(&key_hash(key))
+            .filter(|funded_at| **funded_at >= self.claim_cooldown_set_at)
+            .is_some_and(|funded_at| env::block_timestamp() < funded_at.saturating_add(self.claim_cooldown_ns))
+        {
+            return Some("Key not yet claimable".to_string());
+        }
+        None
+    }
+
+    /// Returns the status of the key, checked in the order of `internal_claim_error`.
+    fn internal_key_status(&self, key: &PublicKey) -> KeyStatus {
+        if let Some(since_block) = self.claim_locks.get(&key_hash(key)) {
+            return KeyStatus::ClaimInProgress { since_block: *since_block };
+        }
+        if self.failed_attempts.get(&key_hash(key)).is_some_and(|attempts| *attempts >= MAX_FAILED_CLAIM_ATTEMPTS) {
+            return KeyStatus::Disabled;
+        }
+        if self.key_expirations.get(key).is_some_and(|expiration| env::block_timestamp() >= *expiration) {
+            return KeyStatus::Expired;
+        }
+        if let Some(drop_id) = self.key_drops.get(&key_hash(key)) {
+            match self.drop_configs[drop_id].claim_error(env::block_timestamp()) {
+                Some(AirdropError::DropPaused) => return KeyStatus::Disabled,
+                Some(AirdropError::DropEnded) => return KeyStatus::Expired,
+                Some(_) => return KeyStatus::NotYetClaimable,
+                None => {}
+            }
+        }
+        if self.internal_claim_error(key).is_some()
+            || self.drips.get(&key_hash(key)).is_some_and(|drip| env::block_timestamp() < drip.next_claim_ns())
+        {
+            return KeyStatus::NotYetClaimable;
+        }
+        KeyStatus::Available
+    }
+
+    /// Returns how much of `balance` a claim of the key releases, all of it or the next drip if the key has a
+    /// drip, and what the claim pays out: the amount released, with the storage stake of the key if it uses
+    /// up the balance. Shared by the claims and `simulate_claim`.
+    fn internal_compute_claim(&self, key: &PublicKey, balance: UncToken) -> (UncToken, UncToken) {
+        let amount = match self.drips.get(&key_hash(key)) {
+            Some(drip) => std::cmp::min(drip.amount, balance),
+            None => balance,
+        };
+        if amount < balance {
+            return (amount, amount);
+        }
+        let storage_stake = self.storage_stakes.get(&key_hash(key)).copied().unwrap_or(UncToken::from_attounc(0));
+        (amount, amount.checked_add(storage_stake).unwrap_or_else(|| overflow("claim")))
+    }
+
+    /// Asserts that the drip interval of a drip key has elapsed and starts the next one.
+    fn internal_record_drip(&mut self, key: &PublicKey) {
+        self.assert_drip_due(key);
+        if let Some(drip) = self.drips.get_mut(&key_hash(key)) {
+            drip.last_claim_ns = Some(env::block_timestamp());
+        }
+    }
+
+    /// Asserts that the drip interval of the key has elapsed, if it is a drip key.
+    fn assert_drip_due(&self, key: &PublicKey) {
+        if let Some(drip) = self.drips.get(&key_hash(key)) {
+            assert!(
+                env::block_timestamp() >= drip.next_claim_ns(),
+                "Drip interval has not elapsed yet"
+            );
+        }
+    }
+
+    /// Decrements the allowance estimate of the key by the cost of the prepaid gas of this claim attempt.
+    fn charge_claim_allowance(&mut self, key: &PublicKey) {
+        if let Some(allowance) = self.allowances.get(key) {
+            let cost = UncToken::from_attounc(
+                u128::from(env::prepaid_gas().as_gas()).saturating_mul(CLAIM_GAS_PRICE_ESTIMATE)
+            );
+            // The estimate is 0 once the gas of the claims used up the allowance
+            self.internal_set_allowance(key, allowance.saturating_sub(cost));
+        }
+    }
+
+    fn internal_add_liability(&mut self, amount: UncToken) {
+        self.other_liability = self.other_liability.checked_add(amount).unwrap_or_else(|| overflow("other liability"));
+    }
+
+    fn internal_release_liability(&mut self, amount: UncToken) {
+        self.other_liability = self.other_liability.checked_sub(amount).unwrap_or_else(|| overflow("other liability"));
+    }
+
+    /// Returns the balance not owed to anyone: what is left once the storage, the keys, the sponsor pool,
+    /// the other liabilities and the fees are set aside.
+    fn internal_available_balance(&self) -> u128 {
+        let breakdown = self.get_balance_breakdown();
+        // Saturates since a balance below what is owed leaves nothing available
+        breakdown.account_balance.0
+            .saturating_sub(breakdown.locked.0)
+            .saturating_sub(breakdown.key_liability.0)
+            .saturating_sub(breakdown.reserved_allowances.0)
+            .saturating_sub(breakdown.sponsor_pool.0)
+            .saturating_sub(breakdown.other_liability.0)
+            .saturating_sub(self.fees_collected.as_attounc())
+    }
+
+    /// Makes `new_owner` the pending owner, shared by `propose_owner` and the `SetOwner` action.
+    fn internal_propose_owner(&mut self, new_owner: AccountId) {
+        assert_ne!(new_owner, self.owner_id, "Account is already the owner");
+        assert_ne!(new_owner, env::current_account_id(), "The contract can't be the owner");
+        AirDropEvent::OwnerProposed { owner_id: &self.owner_id, new_owner: &new_owner }.emit();
+        self.pending_owner = Some(new_owner);
+    }
+
+    /// Sets the allowance estimate of the key, keeping `reserved_allowances` in sync.
+    fn internal_set_allowance(&mut self, key: &PublicKey, allowance: UncToken) {
+        self.internal_remove_allowance(key);
+        self.allowances.insert(key.clone(), allowance);
+        self.reserved_allowances = self.reserved_allowances.checked_add(allowance).unwrap_or_else(|| overflow("reserved allowances"));
+    }
+
+    fn internal_remove_allowance(&mut self, key: &PublicKey) {
+        if let Some(allowance) = self.allowances.remove(key) {
+            // Allowances stored before `reserved_allowances` was tracked were never added to it
+            self.reserved_allowances = self.reserved_allowances.saturating_sub(allowance);
+        }
+    }
+
+    /// Asserts that a new key can be funded without exceeding `max_keys`.
+    fn assert_key_capacity(&self) {
+        assert!(self.max_keys.is_none_or(|max_keys| self.key_count < max_keys), "Key capacity reached");
+    }
+
+    fn assert_not_claim_locked(&self, key: &PublicKey) {
+        assert!(
+            !self.claim_locks.contains_key(&key_hash(key)),
+            "Key has a claim in progress"
+        );
+    }
+
+    fn assert_not_blocked(&self, account_id: &AccountId) {
+        assert!(
+            !self.blocked_accounts.contains(account_id),
+            "Account is blocked"
+        );
+    }
+
+    fn assert_drop_metadata(&self, metadata: &Option<String>) {
+        if let Some(metadata) = metadata {
+            assert!(
+                metadata.len() <= MAX_DROP_METADATA_LENGTH,
+                "Drop metadata must be at most {} bytes long",
+                MAX_DROP_METADATA_LENGTH
+            );
+        }
+    }
+
+    fn assert_owner(&self) {
+        if env::predecessor_account_id() != self.owner_id {
+            panic!("{}", AirdropError::NotOwner);
+        }
+    }
+
+    fn assert_owner_or_operator(&self) {
+        let caller = env::predecessor_account_id();
+        if caller != self.owner_id && !self.operators.contains(&caller) {
+            panic!("{}", AirdropError::NotOwnerOrOperator);
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32"), not(feature = "interface-only")))]
+mod serde_tests;
+
+#[cfg(all(test, not(target_arch = "wasm32"), not(feature = "interface-only")))]
+mod tests {
+
+    use super::*;
+
+    use proptest::prelude::*;
+    use unc_sdk::mock::MockAction;
+    use unc_sdk::test_utils::{get_created_receipts, get_logs, VMContextBuilder};
+    use unc_sdk::{test_vm_config, testing_env, CurveType, RuntimeFeesConfig};
+
+    /// Allowance `send` gives a new key by default.
+    const ACCESS_KEY_ALLOWANCE: UncToken = CLAIM_ATTEMPT_ALLOWANCE.saturating_mul(DEFAULT_MAX_CLAIM_ATTEMPTS as u128);
+
+    fn airdrop() -> AccountId {
+        "airdrop".parse().unwrap()
+    }
+
+    fn bob() -> AccountId {
+        "bob".parse().unwrap()
+    }
+
+    fn carol() -> AccountId {
+        "carol".parse().unwrap()
+    }
+
+    fn advanced_options() -> CreateAccountOptions {
+        CreateAccountOptions {
+            full_access_keys: Some(vec!["qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz".parse().unwrap()]),
+            limited_access_keys: None,
+            contract_bytes: None,
+            ft_registrations: None,
+            keyless: None,
+        }
+    }
+
+    /// Mocks the blockchain for a callback on the airdrop contract with the given promise result.
+    fn callback_env(promise_result: PromiseResult) {
+        testing_env!(
+            VMContextBuilder::new()
+                .current_account_id(airdrop())
+                .predecessor_account_id(airdrop())
+                .context.clone(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![promise_result]
+        );
+    }
+
+    #[test]
+    fn test_create_account() {
+        // Create a new instance of the airdrop contract
+        let mut contract = AirDrop::new_default();
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to an extremely small amount
+        let deposit = UncToken::from_attounc(1_000_000);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account with the PK
+        contract.create_account(bob(), pk, None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_create_invalid_account() {
+        // Create a new instance of the airdrop contract
+        let mut contract = AirDrop::new_default();
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to an extremely small amount
+        let deposit = UncToken::from_attounc(1_000_000);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Attempt to create an invalid account with the PK
+        contract.create_account("XYZ".parse().unwrap(), pk, None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_missing_balance_panics() {
+        // Create a new instance of the airdrop contract
+        let contract = AirDrop::new_default();
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .context.clone()
+        );
+
+        contract.get_key_balance(pk);
+    }
+
+    #[test]
+    fn test_get_missing_balance_success() {
+        // Create a new instance of the airdrop contract
+        let mut contract = AirDrop::new_default();
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE.saturating_mul(100);
+        
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the airdrop
+        contract.send(pk.clone(), None, None, None, None, None);
+
+        // try getting the balance of the key
+        let balance:u128 = contract.get_key_balance(pk).as_attounc();
+        assert_eq!(
+            balance,
+            deposit.saturating_sub(ACCESS_KEY_ALLOWANCE).saturating_sub(key_storage_stake()).as_attounc()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_claim_invalid_account() {
+        // Create a new instance of the airdrop contract
+        let mut contract = AirDrop::new_default();
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE.saturating_mul(100);
+        
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the airdrop, which returns the balance of the key
+        assert_eq!(
+            contract.send(pk.clone(), None, None, None, None, None),
+            U128(deposit.as_attounc() - ACCESS_KEY_ALLOWANCE.as_attounc() - key_storage_stake().as_attounc())
+        );
+
+        // Now, send new transaction to airdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .signer_account_id(airdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Create the second public key
+        let pk2 = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Attempt to create the account and claim
+        contract.create_account_and_claim("XYZ".parse().unwrap(), pk2, None, None);
+    }
+
+    #[test]
+    fn test_drop_claim() {
+        // Create a new instance of the airdrop contract
+        let mut contract = AirDrop::new_default();
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE.saturating_mul(100);
+        
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the airdrop
+        contract.send(pk.clone(), None, None, None, None, None);
+
+        // Now, send new transaction to airdrop contract and reinitialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .signer_account_id(airdrop())
+            .signer_account_pk(pk)
+            .account_balance(deposit)
+            .context.clone()
+        );
+
+        // Create the second public key
+        let pk2 = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
+            .parse()
+            .unwrap();
+        // Attempt to create the account and claim
+        contract.create_account_and_claim(bob(), pk2, None, None);
+    }
+
+    #[test]
+    fn test_send_two_times() {
+        // Create a new instance of the airdrop contract
+        let mut contract = AirDrop::new_default();
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to be 100 times the access key allowance
+        let deposit = ACCESS_KEY_ALLOWANCE.saturating_mul(100);
+        
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create the airdrop
+        contract.send(pk.clone(), None, None, None, None, None);
+        assert_eq!(
+            contract.get_key_balance(pk.clone()),
+            deposit.saturating_sub(ACCESS_KEY_ALLOWANCE).saturating_sub(key_storage_stake())
+        );
+
+        // Re-initialize the mocked blockchain with new params
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .account_balance(deposit)
+            .attached_deposit(deposit.saturating_add(UncToken::from_attounc(1)))
+            .context.clone()
+        );
+
+        // Send to the same key again, which tops it up with the full deposit
+        contract.send(pk.clone(), None, None, None, None, None);
+        assert_eq!(
+            contract.get_key_balance(pk).as_attounc(),
+            deposit.as_attounc() + deposit.as_attounc() + 1
+                - ACCESS_KEY_ALLOWANCE.as_attounc()
+                - key_storage_stake().as_attounc()
+        );
+    }
+
+    #[test]
+    fn test_top_up_skips_access_key() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1_000))
+            .context.clone()
+        );
+
+        assert_eq!(
+            contract.send(pk.clone(), None, None, None, None, None),
+            U128(ACCESS_KEY_ALLOWANCE.as_attounc() + 1_000)
+        );
+        // No access key is added on a top-up
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(
+            contract.get_key_balance(pk.clone()).as_attounc(),
+            ACCESS_KEY_ALLOWANCE.as_attounc() + 1_000
+        );
+        assert_eq!(contract.allowances.get(&pk), Some(&ACCESS_KEY_ALLOWANCE));
+    }
+
+    #[test]
+    fn test_create_advanced_account() {
+        // Create a new instance of the airdrop contract
+        let mut contract = AirDrop::new_default();
+        // Create the public key to be used in the test
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        // Default the deposit to an extremely small amount
+        let deposit = UncToken::from_attounc(1_000_000);
+
+        // Create options for the advanced account creation
+        let options: CreateAccountOptions = CreateAccountOptions {
+            full_access_keys: Some(vec![pk.clone()]),
+            limited_access_keys: Some(vec![LimitedAccessKey {
+                public_key: PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![1; 32]).unwrap(),
+                allowance: UncToken::from_attounc(100),
+                receiver_id: airdrop(),
+                method_names: "send".to_string(),
+            }]),
+            contract_bytes: Some(include_bytes!("../target/wasm32-unknown-unknown/release/airdrop.wasm").to_vec()),
+            ft_registrations: None,
+            keyless: None,
+        };
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account with the advanced options
+        contract.create_account_advanced(bob(), options);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_create_advanced_account_no_options() {
+        // Create a new instance of the airdrop contract
+        let mut contract = AirDrop::new_default();
+        // Default the deposit to an extremely small amount
+        let deposit = UncToken::from_attounc(1_000_000);
+
+        // Initialize the mocked blockchain
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // Create bob's account with the advanced options
+        contract.create_account_advanced(bob(), CreateAccountOptions { full_access_keys: None, limited_access_keys: None, contract_bytes: None, ft_registrations: None, keyless: None });
+    }
+
+    #[test]
+    fn test_failed_advanced_creation_is_queued_and_retried() {
+        // Initialize the mocked blockchain with the airdrop account as the owner
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        let deposit = UncToken::from_unc(1);
+
+        // The account creation for carol fails
+        callback_env(PromiseResult::Failed);
+        assert!(!contract.on_account_created_advanced(bob(), carol(), deposit, advanced_options(), FT_STORAGE_DEPOSIT, 0));
+
+        // The creation is queued instead of refunded, its storage paid from the deposit
+        let pending = contract.pending_creations.get(&carol()).expect("Creation should be queued");
+        assert_eq!(pending.funder, bob());
+        assert!(!pending.storage_cost.is_zero());
+        assert_eq!(pending.amount.saturating_add(pending.storage_cost), deposit);
+        assert_eq!(pending.retries, 0);
+        assert_eq!(transferred_to(&bob()), 0);
+        assert_eq!(contract.get_balance_breakdown().other_liability, U128(deposit.as_attounc()));
+
+        // The owner retries the creation
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .prepaid_gas(Gas::from_tgas(300))
+            .context.clone()
+        );
+        contract.retry_create_account(carol());
+        assert_eq!(transferred_to(&carol()), deposit.as_attounc());
+        assert_eq!(contract.get_balance_breakdown().other_liability, U128(0));
+
+        // The retry succeeds and nothing is left pending
+        callback_env(PromiseResult::Successful(vec![]));
+        assert!(contract.on_account_created_advanced(bob(), carol(), deposit, advanced_options(), FT_STORAGE_DEPOSIT, 1));
+        assert!(contract.pending_creations.get(&carol()).is_none());
+    }
+
+    #[test]
+    fn test_failed_advanced_creation_out_of_retries() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+
+        // The last allowed retry fails, so the funder is refunded instead of queued
+        callback_env(PromiseResult::Failed);
+        contract.on_account_created_advanced(
+            bob(),
+            carol(),
+            UncToken::from_attounc(1_000_000),
+            advanced_options(),
+            FT_STORAGE_DEPOSIT,
+            MAX_CREATE_ACCOUNT_RETRIES,
+        );
+        assert!(contract.pending_creations.get(&carol()).is_none());
+    }
+
+    #[test]
+    fn test_failed_advanced_creation_below_storage_cost() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+
+        // A deposit that doesn't cover the storage of the queued creation is refunded
+        callback_env(PromiseResult::Failed);
+        contract.on_account_created_advanced(bob(), carol(), UncToken::from_attounc(1_000_000), advanced_options(), FT_STORAGE_DEPOSIT, 0);
+        assert!(contract.pending_creations.get(&carol()).is_none());
+        assert_eq!(transferred_to(&bob()), 1_000_000);
+    }
+
+    #[test]
+    fn test_cancel_pending_creation() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        callback_env(PromiseResult::Failed);
+        contract.on_account_created_advanced(bob(), carol(), UncToken::from_unc(1), advanced_options(), FT_STORAGE_DEPOSIT, 0);
+        assert!(contract.pending_creations.get(&carol()).is_some());
+
+        operator_env(carol());
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.cancel_pending_creation(carol()); }),
+            "Only the funder of the creation can cancel it"
+        );
+        operator_env(bob());
+        contract.cancel_pending_creation(carol());
+        assert!(contract.pending_creations.get(&carol()).is_none());
+        assert_eq!(transferred_to(&bob()), UncToken::from_unc(1).as_attounc());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn test_retry_create_account_not_owner() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+
+        callback_env(PromiseResult::Failed);
+        contract.on_account_created_advanced(bob(), carol(), UncToken::from_attounc(1_000_000), advanced_options(), FT_STORAGE_DEPOSIT, 0);
+
+        // Bob is not the owner, so the retry is rejected
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.retry_create_account(carol());
+    }
+
+    /// Builds a Merkle drop for bob and carol, returning the root and the proof of each of them.
+    fn merkle_tree() -> (Base58CryptoHash, Vec<Base58CryptoHash>, Vec<Base58CryptoHash>) {
+        let bob_leaf = merkle::leaf_hash(&bob(), 100);
+        let carol_leaf = merkle::leaf_hash(&carol(), 200);
+        let root = merkle::hash_pair(&bob_leaf, &carol_leaf);
+        (root.into(), vec![carol_leaf.into()], vec![bob_leaf.into()])
+    }
+
+    /// Returns the index in the claim bitmap of bob's leaf in `merkle_tree`.
+    fn bob_merkle_index() -> u64 {
+        let (root, bob_proof, _) = merkle_tree();
+        let proof: Vec<CryptoHash> = bob_proof.into_iter().map(CryptoHash::from).collect();
+        merkle::verify_proof(&root.into(), merkle::leaf_hash(&bob(), 100), &proof).unwrap()
+    }
+
+    fn merkle_drop_env(timestamp: u64) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(UncToken::from_unc(1))
+            .block_timestamp(timestamp)
+            .context.clone()
+        );
+    }
+
+    #[test]
+    fn test_merkle_drop_claim() {
+        merkle_drop_env(0);
+        let mut contract = AirDrop::new_default();
+        let (root, bob_proof, carol_proof) = merkle_tree();
+        let drop_id = contract.create_merkle_drop(root, U128(300), 2, None);
+
+        contract.claim_merkle(drop_id, bob(), U128(100), bob_proof);
+        contract.claim_merkle(drop_id, carol(), U128(200), carol_proof);
+        assert_eq!(contract.get_merkle_drop(drop_id).unwrap().claimed, U128(300));
+    }
+
+    #[test]
+    #[should_panic(expected = "Already claimed")]
+    fn test_merkle_drop_double_claim() {
+        merkle_drop_env(0);
+        let mut contract = AirDrop::new_default();
+        let (root, bob_proof, _) = merkle_tree();
+        // Fund more than needed so only the bitmap can stop the second claim
+        let drop_id = contract.create_merkle_drop(root, U128(300), 2, None);
+
+        contract.claim_merkle(drop_id, bob(), U128(100), bob_proof.clone());
+        contract.claim_merkle(drop_id, bob(), U128(100), bob_proof);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid proof")]
+    fn test_merkle_drop_forged_proof() {
+        merkle_drop_env(0);
+        let mut contract = AirDrop::new_default();
+        let (root, bob_proof, _) = merkle_tree();
+        let drop_id = contract.create_merkle_drop(root, U128(300), 2, None);
+
+        // Bob tries to claim carol's amount with his own proof
+        contract.claim_merkle(drop_id, bob(), U128(200), bob_proof);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must cover the total of the drop and its storage")]
+    fn test_merkle_drop_underfunded() {
+        merkle_drop_env(0);
+        let mut contract = AirDrop::new_default();
+        let (root, _, _) = merkle_tree();
+        contract.create_merkle_drop(root, U128(UncToken::from_unc(1).as_attounc()), 2, None);
+    }
+
+    #[test]
+    fn test_merkle_drop_charges_storage_and_refunds_excess() {
+        merkle_drop_env(0);
+        let mut contract = AirDrop::new_default();
+        let (root, bob_proof, _) = merkle_tree();
+        let drop_id = contract.create_merkle_drop(root, U128(300), 2, Some(1_000));
+        let reserve = contract.merkle_drops[&drop_id].storage_reserve;
+        let word_cost = env::storage_byte_cost().saturating_mul(u128::from(MERKLE_WORD_STORAGE_BYTES));
+        assert!(reserve > word_cost.saturating_mul(2));
+        assert_eq!(transferred_to(&bob()), UncToken::from_unc(1).as_attounc() - 300 - reserve.as_attounc());
+
+        // The first claim of a word of the bitmap pays for it
+        contract.claim_merkle(drop_id, bob(), U128(100), bob_proof);
+        assert_eq!(contract.merkle_drops[&drop_id].storage_reserve, reserve.saturating_sub(word_cost));
+
+        // The refund returns the remainder with the unused reserve
+        merkle_drop_env(1_000);
+        contract.refund_merkle_drop(drop_id);
+        assert_eq!(transferred_to(&bob()), 200 + reserve.saturating_sub(word_cost).as_attounc());
+    }
+
+    #[test]
+    fn test_failed_merkle_claim_can_be_claimed_again() {
+        merkle_drop_env(0);
+        let mut contract = AirDrop::new_default();
+        let (root, bob_proof, _) = merkle_tree();
+        let drop_id = contract.create_merkle_drop(root, U128(300), 2, None);
+        contract.claim_merkle(drop_id, bob(), U128(100), bob_proof.clone());
+        let index = bob_merkle_index();
+
+        callback_env(PromiseResult::Failed);
+        assert!(!contract.on_merkle_claimed(drop_id, bob(), bob(), index, U128(100)));
+        assert_eq!(contract.get_merkle_drop(drop_id).unwrap().claimed, U128(0));
+        assert_eq!(contract.get_stats().total_unc_claimed, U128(0));
+
+        merkle_drop_env(0);
+        contract.claim_merkle(drop_id, bob(), U128(100), bob_proof);
+        assert_eq!(contract.get_merkle_drop(drop_id).unwrap().claimed, U128(100));
+    }
+
+    #[test]
+    fn test_merkle_drop_refund_after_expiration() {
+        merkle_drop_env(0);
+        let mut contract = AirDrop::new_default();
+        let (root, bob_proof, _) = merkle_tree();
+        let drop_id = contract.create_merkle_drop(root, U128(300), 2, Some(1_000));
+        contract.claim_merkle(drop_id, bob(), U128(100), bob_proof);
+
+        // Once expired, the remainder can be refunded
+        merkle_drop_env(1_000);
+        contract.refund_merkle_drop(drop_id);
+        assert!(contract.get_merkle_drop(drop_id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Drop has expired")]
+    fn test_merkle_drop_claim_after_expiration() {
+        merkle_drop_env(0);
+        let mut contract = AirDrop::new_default();
+        let (root, bob_proof, _) = merkle_tree();
+        let drop_id = contract.create_merkle_drop(root, U128(300), 2, Some(1_000));
+
+        merkle_drop_env(1_000);
+        contract.claim_merkle(drop_id, bob(), U128(100), bob_proof);
+    }
+
+    #[test]
+    #[should_panic(expected = "Drop has not expired yet")]
+    fn test_merkle_drop_refund_before_expiration() {
+        merkle_drop_env(0);
+        let mut contract = AirDrop::new_default();
+        let (root, _, _) = merkle_tree();
+        let drop_id = contract.create_merkle_drop(root, U128(300), 2, Some(1_000));
+        contract.refund_merkle_drop(drop_id);
+    }
+
+    #[test]
+    fn test_key_information_fields() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .block_timestamp(1_000)
+            .context.clone()
+        );
+        contract.send(drop_pk(), Some("gold".to_string()), None, Some(2_000), None, None);
+        let stake = contract.storage_stakes[&key_hash(&drop_pk())];
+
+        let info = contract.get_key_information(drop_pk()).unwrap();
+        assert_eq!(info.balance, U128(ACCESS_KEY_ALLOWANCE.saturating_sub(stake).as_attounc()));
+        assert_eq!(info.allowance_remaining, U128(ACCESS_KEY_ALLOWANCE.as_attounc()));
+        assert_eq!(info.tag, Some("gold".to_string()));
+        assert_eq!(info.failed_attempts, 0);
+        assert_eq!(info.expiry_ns, Some(2_000));
+        assert!(!info.disabled);
+        assert_eq!(info.created_at_ns, 1_000);
+        assert!(info.claimable);
+
+        contract.failed_attempts.insert(key_hash(&drop_pk()), MAX_FAILED_CLAIM_ATTEMPTS);
+        let info = contract.get_key_information(drop_pk()).unwrap();
+        assert!(info.disabled);
+        assert!(!info.claimable);
+    }
+
+    #[test]
+    fn test_key_status() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .block_timestamp(1_000)
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, Some(2_000), None, None);
+        let status = |contract: &AirDrop| contract.get_key_information(drop_pk()).unwrap().status;
+        assert_eq!(status(&contract), KeyStatus::Available);
+
+        contract.claim_cooldown_ns = 500;
+        assert_eq!(status(&contract), KeyStatus::NotYetClaimable);
+        contract.claim_cooldown_ns = 0;
+
+        contract.claim_locks.insert(key_hash(&drop_pk()), 7);
+        assert_eq!(status(&contract), KeyStatus::ClaimInProgress { since_block: 7 });
+        // The batch view returns the same status
+        assert_eq!(
+            contract.get_keys_information(vec![drop_pk()])[0].as_ref().unwrap().status,
+            KeyStatus::ClaimInProgress { since_block: 7 }
+        );
+        contract.claim_locks.remove(&key_hash(&drop_pk()));
+
+        contract.key_expirations.insert(drop_pk(), 1_000);
+        assert_eq!(status(&contract), KeyStatus::Expired);
+
+        contract.failed_attempts.insert(key_hash(&drop_pk()), MAX_FAILED_CLAIM_ATTEMPTS);
+        assert_eq!(status(&contract), KeyStatus::Disabled);
+    }
+
+    #[test]
+    fn test_key_status_json() {
+        for (status, json) in [
+            (KeyStatus::Available, r#""available""#),
+            (KeyStatus::ClaimInProgress { since_block: 42 }, r#"{"claim_in_progress":{"since_block":42}}"#),
+            (KeyStatus::Disabled, r#""disabled""#),
+            (KeyStatus::Expired, r#""expired""#),
+            (KeyStatus::NotYetClaimable, r#""not_yet_claimable""#),
+        ] {
+            assert_eq!(unc_sdk::serde_json::to_string(&status).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn test_key_information_allowance() {
+        let mut contract = AirDrop::new_default();
+        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
+            .parse()
+            .unwrap();
+        let deposit = ACCESS_KEY_ALLOWANCE.saturating_mul(100);
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+
+        // After funding, the whole allowance is available
+        contract.send(pk.clone(), None, None, None, None, None);
+        let info = contract.get_key_information(pk.clone()).unwrap();
+        assert_eq!(info.allowance_remaining, U128(ACCESS_KEY_ALLOWANCE.as_attounc()));
+
+        // A claim attempt burns an estimate of its prepaid gas
+        let prepaid_gas = Gas::from_tgas(100);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .signer_account_id(airdrop())
+            .signer_account_pk(pk.clone())
+            .prepaid_gas(prepaid_gas)
+            .account_balance(deposit)
+            .context.clone()
+        );
+        contract.create_account_and_claim(bob(), "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap(), None, None);
+        assert_eq!(
+            contract.allowances.get(&pk).unwrap().as_attounc(),
+            ACCESS_KEY_ALLOWANCE.as_attounc() - u128::from(prepaid_gas.as_gas()) * CLAIM_GAS_PRICE_ESTIMATE
+        );
+    }
+
+    const CODE: &str = "7Hq2-Xv9k-PbL4-Wm3z";
+
+    fn code_env() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(UncToken::from_attounc(1_000_000))
+            .context.clone()
+        );
+    }
+
+    /// Commits the claim of `code` to `account_id`, then moves to the next block to claim it.
+    fn commit_code(contract: &mut AirDrop, code: &str, account_id: &AccountId) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(code_commitment_cost())
+            .block_height(1)
+            .context.clone()
+        );
+        contract.commit_code(code_commitment(code, account_id).into());
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .block_height(2)
+            .context.clone()
+        );
+    }
+
+    #[test]
+    fn test_claim_with_code() {
+        code_env();
+        let mut contract = AirDrop::new_default();
+        contract.send_with_code(env::sha256_array(CODE.as_bytes()).into());
+
+        commit_code(&mut contract, CODE, &carol());
+        contract.claim_with_code(CODE.to_string(), carol());
+        assert!(contract.codes.get(&env::sha256_array(CODE.as_bytes())).is_none());
+        assert_eq!(transferred_to(&carol()), 1_000_000 + code_commitment_cost().as_attounc());
+    }
+
+    #[test]
+    #[should_panic(expected = "Claim must be committed with commit_code first")]
+    fn test_claim_with_code_redirected() {
+        code_env();
+        let mut contract = AirDrop::new_default();
+        contract.send_with_code(env::sha256_array(CODE.as_bytes()).into());
+
+        commit_code(&mut contract, CODE, &carol());
+        contract.claim_with_code(CODE.to_string(), bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Claim must be committed in an earlier block")]
+    fn test_claim_with_code_same_block() {
+        code_env();
+        let mut contract = AirDrop::new_default();
+        contract.send_with_code(env::sha256_array(CODE.as_bytes()).into());
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(code_commitment_cost())
+            .context.clone()
+        );
+        contract.commit_code(code_commitment(CODE, &carol()).into());
+        contract.claim_with_code(CODE.to_string(), carol());
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must be at least 1650000000000000000000 attoUNC, got 0")]
+    fn test_commit_code_without_deposit() {
+        code_env();
+        let mut contract = AirDrop::new_default();
+        testing_env!(VMContextBuilder::new().current_account_id(airdrop()).context.clone());
+        contract.commit_code(code_commitment(CODE, &carol()).into());
+    }
+
+    /// Moves to the block at which the commitment made by `commit_code` can be cancelled, as `predecessor`.
+    fn cancel_commitment_env(predecessor: AccountId, block_height: u64) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(predecessor)
+            .block_height(block_height)
+            .context.clone()
+        );
+    }
+
+    #[test]
+    fn test_cancel_commitment() {
+        code_env();
+        let mut contract = AirDrop::new_default();
+        commit_code(&mut contract, CODE, &carol());
+        assert_eq!(contract.other_liability, code_commitment_cost());
+
+        cancel_commitment_env(bob(), 1 + CODE_COMMITMENT_TIMEOUT_BLOCKS);
+        contract.cancel_commitment(code_commitment(CODE, &carol()).into());
+        assert!(!contract.code_commitments.contains_key(&code_commitment(CODE, &carol())));
+        assert!(contract.other_liability.is_zero());
+        assert_eq!(transferred_to(&bob()), code_commitment_cost().as_attounc());
+    }
+
+    #[test]
+    #[should_panic(expected = "Commitment has not timed out yet")]
+    fn test_cancel_commitment_before_timeout() {
+        code_env();
+        let mut contract = AirDrop::new_default();
+        commit_code(&mut contract, CODE, &carol());
+
+        cancel_commitment_env(bob(), CODE_COMMITMENT_TIMEOUT_BLOCKS);
+        contract.cancel_commitment(code_commitment(CODE, &carol()).into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the committer can cancel the commitment")]
+    fn test_cancel_commitment_not_committer() {
+        code_env();
+        let mut contract = AirDrop::new_default();
+        commit_code(&mut contract, CODE, &carol());
+
+        cancel_commitment_env(carol(), 1 + CODE_COMMITMENT_TIMEOUT_BLOCKS);
+        contract.cancel_commitment(code_commitment(CODE, &carol()).into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown code")]
+    fn test_claim_with_wrong_code() {
+        code_env();
+        let mut contract = AirDrop::new_default();
+        contract.send_with_code(env::sha256_array(CODE.as_bytes()).into());
+
+        contract.claim_with_code("7Hq2-Xv9k-PbL4-Wm3y".to_string(), carol());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown code")]
+    fn test_claim_with_code_replay() {
+        code_env();
+        let mut contract = AirDrop::new_default();
+        contract.send_with_code(env::sha256_array(CODE.as_bytes()).into());
+
+        commit_code(&mut contract, CODE, &carol());
+        contract.claim_with_code(CODE.to_string(), carol());
+        contract.claim_with_code(CODE.to_string(), carol());
+    }
+
+    #[test]
+    #[should_panic(expected = "Code must be at least 16 bytes long")]
+    fn test_claim_with_short_code() {
+        code_env();
+        let mut contract = AirDrop::new_default();
+        contract.send_with_code(env::sha256_array(b"1234").into());
+
+        contract.claim_with_code("1234".to_string(), carol());
+    }
+
+    /// Creates a contract owned by the airdrop account with bob blocked and a drop funded for `pk`.
+    fn blocked_contract(pk: &PublicKey) -> AirDrop {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_unc(100))
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        contract.send(pk.clone(), None, None, None, None, None);
+        owner_env();
+        contract.block_account(bob());
+
+        // Switch to a call made with the drop key
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .signer_account_id(airdrop())
+            .signer_account_pk(pk.clone())
+            .attached_deposit(UncToken::from_attounc(1_000_000))
+            .context.clone()
+        );
+        contract
+    }
+
+    /// Sets up a call from the airdrop account, which owns the contracts it creates, with the 1 attoUNC
+    /// owner methods require.
+    fn owner_env() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+    }
+
+    fn drop_pk() -> PublicKey {
+        "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz".parse().unwrap()
+    }
+
+    /// Storage stake `send` takes from the deposit of a new ED25519 key without tag, expiration or cooldown.
+    fn key_storage_stake() -> UncToken {
+        let access_key_bytes =
+            access_key_storage_bytes(ED25519_PUBLIC_KEY_BYTES, Some((&airdrop(), &CLAIM_METHODS.join(","))));
+        env::storage_byte_cost().saturating_mul((KEY_STORAGE_BYTES + access_key_bytes).into())
+    }
+
+    #[test]
+    fn test_block_and_unblock_account() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+
+        assert!(contract.block_account(bob()));
+        assert!(contract.is_blocked(bob()));
+        assert_eq!(contract.get_blocked(None, None), vec![bob()]);
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"airdrop","version":"1.0.0","event":"account_blocked","data":{"account_id":"bob"}}"#]
+        );
+
+        assert!(contract.unblock_account(bob()));
+        assert!(!contract.is_blocked(bob()));
+        assert!(contract.get_blocked(None, None).is_empty());
+        assert_eq!(
+            get_logs()[1],
+            r#"EVENT_JSON:{"standard":"airdrop","version":"1.0.0","event":"account_unblocked","data":{"account_id":"bob"}}"#
+        );
+    }
+
+    #[test]
+    fn test_get_blocked_pagination() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        contract.block_account(bob());
+        contract.block_account(carol());
+
+        assert_eq!(contract.get_blocked(Some(1), Some(1)), vec![carol()]);
+        assert_eq!(contract.get_blocked(None, Some(1)), vec![bob()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner or an operator can call this method")]
+    fn test_block_account_not_owner() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.block_account(carol());
+    }
+
+    #[test]
+    #[should_panic(expected = "Account is blocked")]
+    fn test_claim_to_blocked_account() {
+        let mut contract = blocked_contract(&drop_pk());
+        claim_resolved(&mut contract, bob());
+    }
+
+    #[test]
+    fn test_claim_to_other_account_while_blocked() {
+        let pk = drop_pk();
+        let mut contract = blocked_contract(&pk);
+
+        // The balance of the key is untouched and can go to an unblocked account
+        claim_resolved(&mut contract, carol());
+        assert!(contract.internal_key_balance(&pk).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Account is blocked")]
+    fn test_create_account_and_claim_blocked_account() {
+        let mut contract = blocked_contract(&drop_pk());
+        contract.create_account_and_claim(bob(), "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account is blocked")]
+    fn test_create_blocked_account() {
+        let mut contract = blocked_contract(&drop_pk());
+        contract.create_account(bob(), "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account is blocked")]
+    fn test_create_advanced_blocked_account() {
+        let mut contract = blocked_contract(&drop_pk());
+        contract.create_account_advanced(bob(), advanced_options());
+    }
+
+    fn sweep_env(timestamp: u64) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .block_timestamp(timestamp)
+            .context.clone()
+        );
+    }
+
+    #[test]
+    fn test_sweep_after_delay() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        contract.propose_sweep(bob());
+        assert_eq!(contract.get_pending_sweep().unwrap().proposed_at, 1_000);
+
+        sweep_env(1_000 + DEFAULT_SWEEP_DELAY_NS);
+        contract.confirm_sweep();
+        assert!(contract.get_pending_sweep().is_none());
+    }
+
+    #[test]
+    fn test_sweep_leaves_funded_keys() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        fund_keys(&mut contract, 0..2);
+        sweep_env(1_000);
+        contract.propose_sweep(bob());
+
+        sweep_env(1_000 + DEFAULT_SWEEP_DELAY_NS);
+        let breakdown = contract.get_balance_breakdown();
+        assert!(breakdown.key_liability.0 > 0);
+        contract.confirm_sweep();
+        assert_eq!(
+            transferred_to(&bob()),
+            breakdown.account_balance.0
+                - breakdown.locked.0
+                - breakdown.key_liability.0
+                - breakdown.reserved_allowances.0
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Sweep delay has not passed yet")]
+    fn test_sweep_before_delay() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        contract.propose_sweep(bob());
+
+        sweep_env(1_000 + DEFAULT_SWEEP_DELAY_NS - 1);
+        contract.confirm_sweep();
+    }
+
+    #[test]
+    #[should_panic(expected = "No pending sweep")]
+    fn test_sweep_cancelled() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        contract.propose_sweep(bob());
+        contract.cancel_sweep();
+
+        sweep_env(1_000 + DEFAULT_SWEEP_DELAY_NS);
+        contract.confirm_sweep();
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn test_sweep_not_owner() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        contract.propose_sweep(bob());
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .block_timestamp(1_000 + DEFAULT_SWEEP_DELAY_NS)
+            .context.clone()
+        );
+        contract.confirm_sweep();
+    }
+
+    #[test]
+    fn test_action_executed_after_eta() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        let config = OwnerAction::SetTimelockDelay { timelock_delay_ns: 0 };
+        let id = contract.schedule_action(config.clone(), 1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+        let owner = contract.schedule_action(OwnerAction::SetOwner { new_owner: bob() }, 2_000 + DEFAULT_TIMELOCK_DELAY_NS);
+        assert_eq!(contract.get_scheduled_actions(None, None).len(), 2);
+        assert_eq!(contract.get_scheduled_actions(None, Some(1))[0].action, config);
+
+        sweep_env(1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+        contract.execute_action(id);
+        assert_eq!(contract.timelock_delay_ns, 0);
+        assert_eq!(contract.get_scheduled_actions(None, None)[0].id, owner);
+
+        sweep_env(2_000 + DEFAULT_TIMELOCK_DELAY_NS);
+        contract.execute_action(owner);
+        assert!(contract.get_scheduled_actions(None, None).is_empty());
+        // The new owner still has to accept
+        assert_eq!(contract.get_owner_id(), &airdrop());
+        assert_eq!(contract.get_pending_owner(), Some(&bob()));
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+        contract.accept_ownership();
+        assert_eq!(contract.get_owner_id(), &bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "The contract can't be the owner")]
+    fn test_action_set_owner_to_contract() {
+        let carol_env = |timestamp| testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(carol())
+            .attached_deposit(UncToken::from_attounc(1))
+            .block_timestamp(timestamp)
+            .context.clone()
+        );
+        carol_env(1_000);
+        let mut contract = AirDrop::new(carol(), None);
+        let id = contract.schedule_action(OwnerAction::SetOwner { new_owner: airdrop() }, 1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+
+        carol_env(1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+        contract.execute_action(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Action is still timelocked")]
+    fn test_action_executed_before_eta() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        let id = contract.schedule_action(OwnerAction::SetOwner { new_owner: bob() }, 1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+
+        sweep_env(1_000 + DEFAULT_TIMELOCK_DELAY_NS - 1);
+        contract.execute_action(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Eta is earlier than the timelock delay")]
+    fn test_action_scheduled_before_delay() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        contract.schedule_action(OwnerAction::SetOwner { new_owner: bob() }, 999 + DEFAULT_TIMELOCK_DELAY_NS);
+    }
+
+    #[test]
+    #[should_panic(expected = "No scheduled action with this id")]
+    fn test_action_cancelled() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        let id = contract.schedule_action(OwnerAction::SetOwner { new_owner: bob() }, 1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+        contract.cancel_action(id);
+        assert!(contract.get_scheduled_actions(None, None).is_empty());
+
+        sweep_env(1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+        contract.execute_action(id);
+    }
+
+    #[test]
+    fn test_withdraw_treasury_and_stage_upgrade() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        let code_hash: Base58CryptoHash = [7; 32].into();
+        let withdraw = contract.schedule_action(
+            OwnerAction::WithdrawTreasury { to: bob(), amount: U128(5) },
+            1_000 + DEFAULT_TIMELOCK_DELAY_NS,
+        );
+        let upgrade = contract.schedule_action(OwnerAction::StageUpgrade { code_hash }, 1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+
+        sweep_env(1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+        contract.execute_action(withdraw);
+        contract.execute_action(upgrade);
+        assert_eq!(contract.get_staged_code_hash(), Some(code_hash));
+        assert!(get_created_receipts().iter().any(|receipt| receipt.receiver_id == bob()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount exceeds the funds not owed to the keys")]
+    fn test_withdraw_treasury_owed_to_keys() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        let balance = env::account_balance().as_attounc();
+        let id = contract.schedule_action(
+            OwnerAction::WithdrawTreasury { to: bob(), amount: U128(balance) },
+            1_000 + DEFAULT_TIMELOCK_DELAY_NS,
+        );
+
+        sweep_env(1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+        contract.execute_action(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount exceeds the funds not owed to the keys")]
+    fn test_withdraw_treasury_owed_to_merkle_drop() {
+        sweep_env(1_000);
+        let mut contract = AirDrop::new_default();
+        let id = contract.schedule_action(
+            OwnerAction::WithdrawTreasury { to: bob(), amount: U128(0) },
+            1_000 + DEFAULT_TIMELOCK_DELAY_NS,
+        );
+        merkle_drop_env(1_000);
+        contract.create_merkle_drop(merkle_tree().0, U128(300), 2, None);
+
+        sweep_env(1_000 + DEFAULT_TIMELOCK_DELAY_NS);
+        // One more than what isn't owed to the drop
+        let breakdown = contract.get_balance_breakdown();
+        let amount = breakdown.account_balance.0 - breakdown.locked.0 - breakdown.other_liability.0 + 1;
+        contract.scheduled_actions.get_mut(&id).unwrap().action = OwnerAction::WithdrawTreasury { to: bob(), amount: U128(amount) };
+        contract.execute_action(id);
+    }
+
+    #[test]
+    fn test_other_liability() {
+        merkle_drop_env(0);
+        let mut contract = AirDrop::new_default();
+        let (root, bob_proof, _) = merkle_tree();
+        let drop_id = contract.create_merkle_drop(root, U128(300), 2, None);
+        let reserve = contract.merkle_drops[&drop_id].storage_reserve.as_attounc();
+        assert_eq!(contract.get_balance_breakdown().other_liability, U128(300 + reserve));
+        // A claim releases its amount and the storage of the bitmap word it writes
+        contract.claim_merkle(drop_id, bob(), U128(100), bob_proof);
+        let reserve = contract.merkle_drops[&drop_id].storage_reserve.as_attounc();
+        assert_eq!(contract.get_balance_breakdown().other_liability, U128(200 + reserve));
+
+        code_env();
+        contract.send_with_code(env::sha256_array(CODE.as_bytes()).into());
+        commit_code(&mut contract, CODE, &carol());
+        assert_eq!(
+            contract.get_balance_breakdown().other_liability,
+            U128(200 + reserve + 1_000_000 + code_commitment_cost().as_attounc())
+        );
+        contract.claim_with_code(CODE.to_string(), carol());
+        assert_eq!(contract.get_balance_breakdown().other_liability, U128(200 + reserve));
+
+        // The rest goes back to the funder once the drop is refunded
+        contract.merkle_drops.get_mut(&drop_id).unwrap().expiration = Some(0);
+        contract.refund_merkle_drop(drop_id);
+        assert_eq!(contract.get_balance_breakdown().other_liability, U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Arithmetic overflow in top-up")]
+    fn test_send_top_up_overflow() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .account_balance(UncToken::from_attounc(0))
+            .attached_deposit(UncToken::from_attounc(u128::MAX - 1))
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+        assert_eq!(
+            contract.get_key_balance(drop_pk()),
+            UncToken::from_attounc(u128::MAX - 1).saturating_sub(ACCESS_KEY_ALLOWANCE).saturating_sub(key_storage_stake())
+        );
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .account_balance(UncToken::from_attounc(0))
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Arithmetic overflow in key liability")]
+    fn test_send_liability_overflow() {
+        let mut contract = AirDrop::new_default();
+        for pk in [drop_pk(), bob_pk()] {
+            testing_env!(
+                VMContextBuilder::new()
+                .current_account_id(airdrop())
+                .account_balance(UncToken::from_attounc(0))
+                .attached_deposit(UncToken::from_attounc(
+                    u128::MAX / 2 + ACCESS_KEY_ALLOWANCE.as_attounc() + key_storage_stake().as_attounc() + 1
+                ))
+                .context.clone()
+            );
+            contract.send(pk, None, None, None, None, None);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Arithmetic overflow in required deposit")]
+    fn test_required_deposit_overflow() {
+        let contract = AirDrop::new_default();
+        contract.get_required_deposit(2, U128(u128::MAX / 2), 0);
+    }
+
+    #[test]
+    fn test_allowance_estimate_saturates_at_zero() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(3))
+            .context.clone()
+        );
+        contract.send_drip(drop_pk(), U128(ACCESS_KEY_ALLOWANCE.as_attounc()), 0, None);
+        contract.internal_set_allowance(&drop_pk(), UncToken::from_attounc(1));
+
+        drip_env(&drop_pk(), 0);
+        claim_resolved(&mut contract, bob());
+        assert_eq!(contract.get_key_information(drop_pk()).unwrap().allowance_remaining, U128(0));
+        assert!(contract.reserved_allowances.is_zero());
+    }
+
+    #[test]
+    fn test_untracked_allowance_removed() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+        // As if the allowance was stored before `reserved_allowances` was tracked
+        contract.reserved_allowances = UncToken::from_attounc(0);
+
+        claim_env(&drop_pk(), 0);
+        claim_resolved(&mut contract, bob());
+        assert!(contract.reserved_allowances.is_zero());
+    }
+
+    #[test]
+    fn test_drop_remaining_after_top_up() {
+        let (mut contract, _, open_drop) = campaign_contract();
+        drop_env(carol(), 0);
+        contract.send(bob_pk(), None, None, None, None, None);
+
+        drip_env(&bob_pk(), 0);
+        claim_resolved(&mut contract, bob());
+        assert!(contract.get_drop(open_drop).unwrap().remaining.is_zero());
+    }
+
+    #[test]
+    fn test_key_count() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+        contract.send(bob_pk(), None, None, None, None, None);
+        // A top-up doesn't add a key
+        contract.send(bob_pk(), None, None, None, None, None);
+        assert_eq!(contract.get_key_count(), 2);
+
+        claim_env(&drop_pk(), 0);
+        claim_resolved(&mut contract, bob());
+        assert_eq!(contract.get_key_count(), 1);
+
+        let balance = contract.get_key_balance(bob_pk());
+        claim_env(&bob_pk(), 0);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        // The key is only removed once the creation succeeded
+        assert_eq!(contract.get_key_count(), 1);
+        claim_callback_env(&bob_pk(), PromiseResult::Failed);
+        contract.on_account_created_and_claimed(carol(), balance, None);
+        assert_eq!(contract.get_key_count(), 1);
+
+        claim_env(&bob_pk(), 0);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        claim_callback_env(&bob_pk(), PromiseResult::Successful(vec![]));
+        contract.on_account_created_and_claimed(carol(), balance, None);
+        assert_eq!(contract.get_key_count(), 0);
+    }
+
+    #[test]
+    fn test_claim_in_progress() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_env(&pk, 0);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        assert!(!contract.can_claim(pk.clone()));
+
+        // A second claim landing before the callback is rejected without paying out
+        claim_env(&pk, 1);
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::ClaimInProgress))));
+        assert!(matches!(
+            contract.create_account_and_claim(bob(), drop_pk(), None, None),
+            PromiseOrValue::Value(ClaimAmountOutcome::Rejected(ClaimError::ClaimInProgress))
+        ));
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(contract.get_key_balance(pk.clone()), ACCESS_KEY_ALLOWANCE);
+
+        // A failed creation unlocks the key with its balance
+        claim_callback_env(&pk, PromiseResult::Failed);
+        contract.on_account_created_and_claimed(carol(), ACCESS_KEY_ALLOWANCE, None);
+        assert!(contract.can_claim(pk.clone()));
+        claim_env(&pk, 2);
+        claim_resolved(&mut contract, bob());
+        assert!(!contract.key_exists(pk));
+    }
+
+    #[test]
+    fn test_create_account_and_claim_derived() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 7..8).remove(0);
+        let expected: AccountId = format!("{}.airdrop", "07".repeat(DERIVED_ACCOUNT_ID_KEY_BYTES)).parse().unwrap();
+        assert_eq!(derived_account_id(&pk, &airdrop()), expected);
+
+        claim_env(&pk, 0);
+        contract.create_account_and_claim_derived(airdrop(), drop_pk());
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| receipt.receiver_id == expected
+            && matches!(receipt.actions[0], MockAction::CreateAccount { .. })));
+        assert!(!contract.can_claim(pk));
+    }
+
+    /// Returns the names of the actions of the receipts to `account_id`, e.g. `"AddKeyWithFullAccess"`.
+    fn action_names(account_id: &AccountId) -> Vec<String> {
+        get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == *account_id)
+            .flat_map(|receipt| receipt.actions)
+            .map(|action| format!("{:?}", action).split([' ', '(', '{']).next().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_build_creation_batch() {
+        testing_env!(VMContextBuilder::new().current_account_id(airdrop()).context.clone());
+        let _ = build_creation_batch(Promise::new(bob()).create_account(), &validation_options());
+        assert_eq!(
+            action_names(&bob()),
+            vec!["CreateAccount", "AddKeyWithFullAccess", "AddKeyWithFunctionCall", "DeployContract"]
+        );
+    }
+
+    #[test]
+    fn test_create_account_and_claim_with_options() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        let mut options = validation_options();
+        options.full_access_keys = None;
+        claim_env(&pk, 0);
+        contract.create_account_and_claim(carol(), bob_pk(), None, Some(options));
+        // `new_public_key` is added with the keys and the contract of the options
+        assert_eq!(
+            action_names(&carol()),
+            vec!["CreateAccount", "AddKeyWithFullAccess", "AddKeyWithFunctionCall", "DeployContract", "Transfer"]
+        );
+        assert!(get_created_receipts().iter().flat_map(|receipt| &receipt.actions).any(|action| matches!(
+            action,
+            MockAction::AddKeyWithFullAccess { public_key, .. } if public_key.to_string() == String::from(&bob_pk())
+        )));
+    }
+
+    #[test]
+    fn test_create_account_and_claim_keyless() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_env(&pk, 0);
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                let options = CreateAccountOptions { keyless: Some(true), ..validation_options() };
+                contract.create_account_and_claim(carol(), bob_pk(), None, Some(options));
+            }),
+            "A keyless account can't have full access keys"
+        );
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                let options = CreateAccountOptions { ft_registrations: Some(vec![token()]), ..validation_options() };
+                contract.create_account_and_claim(carol(), bob_pk(), None, Some(options));
+            }),
+            "FT registrations can't be added by create_account_and_claim"
+        );
+
+        // Opted into, the account only gets the limited key and the contract
+        let options = CreateAccountOptions { full_access_keys: None, keyless: Some(true), ..validation_options() };
+        contract.create_account_and_claim(carol(), bob_pk(), None, Some(options));
+        assert_eq!(
+            action_names(&carol()),
+            vec!["CreateAccount", "AddKeyWithFunctionCall", "DeployContract", "Transfer"]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid account id")]
+    fn test_derived_account_id_parent_too_long() {
+        // 33 characters of hex and dot leave 31 for the parent
+        derived_account_id(&drop_pk(), &"a".repeat(32).parse().unwrap());
+    }
+
+    /// Funds the key with seed 7 with `reserve_account` for `account_id` from bob, expiring at 2_000.
+    fn reserved_contract(account_id: AccountId) -> (AirDrop, PublicKey) {
+        let mut contract = AirDrop::new_default();
+        let pk = PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![7; 32]).unwrap();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2).saturating_add(key_storage_stake()))
+            .block_timestamp(1_000)
+            .context.clone()
+        );
+        contract.reserve_account(account_id, pk.clone(), Some(2_000));
+        (contract, pk)
+    }
+
+    #[test]
+    fn test_reserved_account_exact_match() {
+        let (mut contract, pk) = reserved_contract(carol());
+        assert_eq!(contract.get_reserved_name(pk.clone()), Some(carol()));
+        // The access key can only create the account
+        assert!(get_created_receipts()[0].actions.iter().any(|action| matches!(
+            action,
+            MockAction::AddKeyWithFunctionCall { method_names, .. } if method_names == &["create_account_and_claim".to_string()]
+        )));
+
+        claim_env(&pk, 0);
+        let balance = contract.get_key_balance(pk.clone());
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        assert!(get_created_receipts().iter().any(|receipt| receipt.receiver_id == carol()
+            && matches!(receipt.actions[0], MockAction::CreateAccount { .. })));
+
+        claim_callback_env(&pk, PromiseResult::Successful(vec![]));
+        contract.on_account_created_and_claimed(carol(), balance, None);
+        assert_eq!(contract.get_reserved_name(pk), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Key is reserved for carol")]
+    fn test_reserved_account_mismatch() {
+        let (mut contract, pk) = reserved_contract(carol());
+        claim_env(&pk, 0);
+        contract.create_account_and_claim(bob(), drop_pk(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Method claim is not allowed for this key")]
+    fn test_reserved_key_cannot_claim() {
+        let (mut contract, pk) = reserved_contract(carol());
+        claim_env(&pk, 0);
+        claim_resolved(&mut contract, carol());
+    }
+
+    #[test]
+    fn test_key_without_reservation() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        assert_eq!(contract.get_reserved_name(pk.clone()), None);
+        claim_env(&pk, 0);
+        contract.create_account_and_claim(bob(), drop_pk(), None, None);
+        assert!(get_created_receipts().iter().any(|receipt| receipt.receiver_id == bob()));
+    }
+
+    #[test]
+    fn test_reservation_expires_with_key() {
+        let (contract, pk) = reserved_contract(carol());
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .block_timestamp(2_000)
+            .context.clone()
+        );
+        assert_eq!(contract.get_reserved_name(pk), None);
+    }
+
+    #[test]
+    fn test_refund_reservation() {
+        let (mut contract, pk) = reserved_contract(carol());
+        let refund = contract.get_key_balance(pk.clone()).saturating_add(contract.storage_stakes[&key_hash(&pk)]);
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                testing_env!(VMContextBuilder::new().current_account_id(airdrop()).predecessor_account_id(carol()).context.clone());
+                let pk = PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![7; 32]).unwrap();
+                contract.refund_reservation(pk);
+            }),
+            "Only the funder of the reservation can refund it"
+        );
+
+        testing_env!(VMContextBuilder::new().current_account_id(airdrop()).predecessor_account_id(bob()).context.clone());
+        contract.refund_reservation(pk.clone());
+        assert!(!contract.key_exists(pk.clone()));
+        assert_eq!(contract.get_reserved_name(pk), None);
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| receipt.receiver_id == bob()
+            && matches!(receipt.actions[..], [MockAction::Transfer { deposit, .. }]
+                if deposit == refund)));
+    }
+
+    fn staking_intent(amount: UncToken) -> Option<StakingIntent> {
+        Some(StakingIntent { pool_id: pool(), amount: U128(amount.as_attounc()) })
+    }
+
+    #[test]
+    fn test_create_account_and_claim_staking() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        let stake = UncToken::from_milliunc(600);
+        claim_env(&pk, 0);
+        contract.create_account_and_claim(carol(), drop_pk(), staking_intent(stake), None);
+        // The new account gets the rest of the balance
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| receipt.receiver_id == carol()
+            && receipt.actions.iter().any(|action| matches!(
+                action,
+                MockAction::Transfer { deposit, .. } if *deposit == ACCESS_KEY_ALLOWANCE.saturating_sub(stake)
+            ))));
+
+        // Once created, the amount is staked for the contract
+        claim_callback_env(&pk, PromiseResult::Successful(vec![]));
+        assert_eq!(
+            contract.on_account_created_and_claimed(carol(), ACCESS_KEY_ALLOWANCE, staking_intent(stake)),
+            ClaimAmountOutcome::Claimed(U128(ACCESS_KEY_ALLOWANCE.as_attounc()))
+        );
+        assert!(!contract.key_exists(pk));
+        let calls = function_calls();
+        assert!(calls.contains(&(pool(), "deposit_and_stake".to_string(), stake)));
+        assert!(calls.contains(&(airdrop(), "on_staked".to_string(), UncToken::from_attounc(0))));
+
+        // And the minted shares are transferred to the new account
+        callback_env(PromiseResult::Successful(b"\"42\"".to_vec()));
+        assert!(contract.on_staked(carol(), pool(), stake));
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, pool());
+        let MockAction::FunctionCallWeight { method_name, args, attached_deposit, .. } = &receipts[0].actions[0] else {
+            panic!("Expected a function call");
+        };
+        assert_eq!(method_name, b"ft_transfer");
+        assert_eq!(*attached_deposit, UncToken::from_attounc(1));
+        let args: unc_sdk::serde_json::Value = unc_sdk::serde_json::from_slice(args).unwrap();
+        assert_eq!(args["receiver_id"], "carol");
+        assert_eq!(args["amount"], "42");
+    }
+
+    #[test]
+    fn test_staking_falls_back_to_transfer() {
+        let stake = UncToken::from_milliunc(600);
+        callback_env(PromiseResult::Failed);
+        let mut contract = AirDrop::new_default();
+        assert!(!contract.on_staked(carol(), pool(), stake));
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, carol());
+        assert!(matches!(receipts[0].actions[..], [MockAction::Transfer { deposit, .. }] if deposit == stake));
+    }
+
+    #[test]
+    fn test_staking_amount_validation() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_env(&pk, 0);
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.create_account_and_claim(carol(), drop_pk(), staking_intent(UncToken::from_attounc(0)), None);
+            }),
+            "Staking amount must be positive"
+        );
+        // The new account must get at least the storage cost of its record and key
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.create_account_and_claim(carol(), drop_pk(), staking_intent(ACCESS_KEY_ALLOWANCE), None);
+            }),
+            "Staking amount must leave the storage cost of the new account"
+        );
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.create_account_and_claim(carol(), drop_pk(), staking_intent(UncToken::from_unc(2)), None);
+            }),
+            "Staking amount must leave the storage cost of the new account"
+        );
+        assert!(contract.can_claim(pk));
+    }
+
+    #[test]
+    fn test_has_claimed() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_env(&pk, 0);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        // Not until the account is created
+        assert!(!contract.has_claimed(carol()));
+        claim_callback_env(&pk, PromiseResult::Failed);
+        contract.on_account_created_and_claimed(carol(), ACCESS_KEY_ALLOWANCE, None);
+        assert!(!contract.has_claimed(carol()));
+
+        claim_env(&pk, 1);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        claim_callback_env(&pk, PromiseResult::Successful(vec![]));
+        contract.on_account_created_and_claimed(carol(), ACCESS_KEY_ALLOWANCE, None);
+        assert!(contract.has_claimed(carol()));
+        assert!(!contract.has_claimed(bob()));
+
+        // Nor until the transfer of a claim succeeds
+        let pk = fund_keys(&mut contract, 1..2).remove(0);
+        claim_env(&pk, 2);
+        contract.claim(bob());
+        assert!(!contract.has_claimed(bob()));
+        resolve_claim(&mut contract, PromiseResult::Failed);
+        assert!(!contract.has_claimed(bob()));
+        claim_env(&pk, 3);
+        claim_resolved(&mut contract, bob());
+        assert!(contract.has_claimed(bob()));
+    }
+
+    #[test]
+    fn test_top_up_during_claim_in_progress() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_env(&pk, 0);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE)
+            .context.clone()
+        );
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                let pk = PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![0; 32]).unwrap();
+                contract.send(pk, None, None, None, None, None);
+            }),
+            "Key has a claim in progress"
+        );
+
+        claim_callback_env(&pk, PromiseResult::Successful(vec![]));
+        contract.on_account_created_and_claimed(carol(), ACCESS_KEY_ALLOWANCE, None);
+        assert!(contract.internal_key_balance(&pk).is_none());
+    }
+
+    /// Sets up an owner call at `block_height` to a contract whose funded key is locked at block 10.
+    fn stale_claim_contract(block_height: u64) -> (AirDrop, PublicKey) {
+        let mut contract = AirDrop::new_default();
+        contract.owner_id = airdrop();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_env(&pk, 10);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .block_height(block_height)
+            .context.clone()
+        );
+        (contract, pk)
+    }
+
+    #[test]
+    fn test_unlock_stale_claim() {
+        let (mut contract, pk) = stale_claim_contract(10 + CLAIM_LOCK_TIMEOUT_BLOCKS);
+        assert_eq!(
+            panic_message(&mut contract, |contract| contract.unlock_stale_claim(drop_pk())),
+            "Key has no claim in progress"
+        );
+        contract.unlock_stale_claim(pk.clone());
+        assert!(contract.can_claim(pk));
+    }
+
+    #[test]
+    #[should_panic(expected = "Claim lock has not timed out yet")]
+    fn test_unlock_claim_before_timeout() {
+        let (mut contract, pk) = stale_claim_contract(10 + CLAIM_LOCK_TIMEOUT_BLOCKS - 1);
+        contract.unlock_stale_claim(pk);
+    }
+
+    #[test]
+    fn test_max_keys() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_max_keys(Some(2));
+        assert_eq!(contract.get_max_keys(), Some(2));
+        let keys = fund_keys(&mut contract, 0..2);
+        let pk = PublicKey::from_parts(CurveType::ED25519, vec![2; 32]).unwrap();
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(2));
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.send(PublicKey::from_parts(CurveType::ED25519, vec![2; 32]).unwrap(), None, None, None, None, None);
+            }),
+            "Key capacity reached"
+        );
+        // Top-ups don't add keys
+        contract.send(keys[0].clone(), None, None, None, None, None);
+        assert_eq!(contract.get_key_count(), 2);
+
+        // A claimed key frees its slot
+        claim_env(&keys[1], 0);
+        claim_resolved(&mut contract, bob());
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(2));
+        contract.send(pk, None, None, None, None, None);
+        assert_eq!(contract.get_key_count(), 2);
+    }
+
+    #[test]
+    fn test_over_deposit_policy() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        assert_eq!(contract.get_over_deposit_policy(), OverDepositPolicy::CreditAll);
+        let key = |seed| PublicKey::from_parts(CurveType::ED25519, vec![seed; 32]).unwrap();
+
+        // The whole deposit goes to the key
+        send_env(UncToken::from_unc(1_000));
+        let credited = contract.send(key(0), None, None, None, None, None);
+        assert!(credited.0 > UncToken::from_unc(990).as_attounc());
+        assert_eq!(transferred_to(&env::predecessor_account_id()), 0);
+        send_env(UncToken::from_unc(10));
+        let expected = contract.send(key(1), None, None, None, None, None);
+
+        // Only 10 UNC of the deposit are used, the rest is refunded
+        owner_env();
+        contract.set_over_deposit_policy(OverDepositPolicy::RefundExcess(U128(UncToken::from_unc(10).as_attounc())));
+        send_env(UncToken::from_unc(1_000));
+        assert_eq!(contract.send(key(2), None, None, None, None, None), expected);
+        assert_eq!(transferred_to(&env::predecessor_account_id()), UncToken::from_unc(990).as_attounc());
+        send_env(UncToken::from_unc(1_000));
+        assert_eq!(
+            contract.send(key(2), None, None, None, None, None),
+            U128(expected.0 + UncToken::from_unc(10).as_attounc())
+        );
+        assert_eq!(transferred_to(&env::predecessor_account_id()), UncToken::from_unc(990).as_attounc());
+        send_env(UncToken::from_unc(5));
+        contract.send(key(3), None, None, None, None, None);
+        assert_eq!(transferred_to(&env::predecessor_account_id()), 0);
+
+        owner_env();
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.set_over_deposit_policy(OverDepositPolicy::RefundExcess(U128(0)));
+            }),
+            "Maximum deposit must be greater than 0"
+        );
+    }
+
+    #[test]
+    fn test_key_count_after_reclaim() {
+        let mut contract = expiring_contract();
+        contract.owner_id = airdrop();
+        assert_eq!(contract.get_key_count(), 3);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .block_timestamp(3_000)
+            .context.clone()
+        );
+        contract.reclaim_all_expired(10, bob());
+        assert_eq!(contract.get_key_count(), 1);
+        contract.owner_reclaim(limited_key(3, "").public_key, bob());
+        assert_eq!(contract.get_key_count(), 0);
+    }
+
+    fn send_env(deposit: UncToken) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must be at least 2010000000000000000000000 attoUNC, got 2009999999999999999999999")]
+    fn test_send_below_min_drop_amount() {
+        let mut contract = AirDrop::new_default();
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_add(DEFAULT_MIN_DROP_AMOUNT).saturating_sub(UncToken::from_attounc(1)));
+        contract.send(drop_pk(), None, None, None, None, None);
+    }
+
+    #[test]
+    fn test_send_min_drop_amount() {
+        let mut contract = AirDrop::new_default();
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_add(DEFAULT_MIN_DROP_AMOUNT).saturating_add(key_storage_stake()));
+        contract.send(drop_pk(), None, None, None, None, None);
+        assert_eq!(contract.get_key_balance(drop_pk()), DEFAULT_MIN_DROP_AMOUNT);
+    }
+
+    #[test]
+    fn test_send_below_storage_stake() {
+        let mut contract = AirDrop::new_default();
+        // Enough for the allowance and the minimum, but not for the storage of the key on top
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_add(DEFAULT_MIN_DROP_AMOUNT));
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.send(drop_pk(), None, None, None, None, None); }),
+            format!(
+                "Attached deposit must be at least {} attoUNC, got {}",
+                ACCESS_KEY_ALLOWANCE.saturating_add(DEFAULT_MIN_DROP_AMOUNT).saturating_add(key_storage_stake()).as_attounc(),
+                ACCESS_KEY_ALLOWANCE.saturating_add(DEFAULT_MIN_DROP_AMOUNT).as_attounc()
+            )
+        );
+    }
+
+    /// Balance of the contract not staked for storage, owed to the keys or reserved for their allowances.
+    fn free_balance(contract: &mut AirDrop) -> u128 {
+        contract.internal_key_storage_usage();
+        let breakdown = contract.get_balance_breakdown();
+        breakdown.account_balance.0 - breakdown.locked.0 - breakdown.key_liability.0 - breakdown.reserved_allowances.0
+    }
+
+    #[test]
+    fn test_send_claim_keeps_free_balance() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .account_balance(UncToken::from_unc(100))
+            .context.clone()
+        );
+        let free_before = free_balance(&mut contract);
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .account_balance(UncToken::from_unc(100))
+            .storage_usage(env::storage_usage())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+        contract.send(drop_pk(), Some("campaign".to_string()), None, None, None, None);
+        // The storage of the key is paid by the deposit
+        let free_after_send = free_balance(&mut contract);
+        assert!(free_after_send >= free_before);
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .signer_account_id(airdrop())
+            .signer_account_pk(drop_pk())
+            .account_balance(env::account_balance())
+            .storage_usage(env::storage_usage())
+            .context.clone()
+        );
+        contract.claim(bob());
+        assert_eq!(
+            get_created_receipts()
+                .iter()
+                .flat_map(|receipt| &receipt.actions)
+                .filter_map(|action| match action {
+                    MockAction::Transfer { deposit, .. } => Some(*deposit),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            vec![ACCESS_KEY_ALLOWANCE]
+        );
+        resolve_claim(&mut contract, PromiseResult::Successful(vec![]));
+        // The storage stake is paid out with the balance once the storage is freed
+        assert!(free_balance(&mut contract) >= free_after_send);
+    }
+
+    #[test]
+    fn test_send_deposit_equal_to_allowance() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_min_drop_amount(U128(0));
+
+        // Even without a minimum a deposit equal to the allowance leaves nothing to claim
+        send_env(ACCESS_KEY_ALLOWANCE);
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.send(drop_pk(), None, None, None, None, None); }),
+            format!(
+                "Attached deposit must be more than the access key allowance of {} attoUNC, got {}",
+                ACCESS_KEY_ALLOWANCE.as_attounc(),
+                ACCESS_KEY_ALLOWANCE.as_attounc()
+            )
+        );
+
+        // One attoUNC above the allowance doesn't cover the storage stake of the key
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_add(MIN_NET_CREDIT));
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.send(drop_pk(), None, None, None, None, None); }),
+            format!(
+                "Attached deposit must be at least {} attoUNC, got {}",
+                ACCESS_KEY_ALLOWANCE.saturating_add(key_storage_stake()).as_attounc() + MIN_NET_CREDIT.as_attounc(),
+                ACCESS_KEY_ALLOWANCE.as_attounc() + MIN_NET_CREDIT.as_attounc()
+            )
+        );
+
+        // The failed calls aren't rolled back here, so the next ones fund other keys
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_add(key_storage_stake()).saturating_add(MIN_NET_CREDIT));
+        assert_eq!(contract.send(bob_pk(), None, None, None, None, None), U128(MIN_NET_CREDIT.as_attounc()));
+
+        let deposit = UncToken::from_unc(10);
+        send_env(deposit);
+        assert_eq!(
+            contract.send(PublicKey::from_parts(CurveType::ED25519, vec![1; 32]).unwrap(), None, None, None, None, None),
+            U128(deposit.as_attounc() - ACCESS_KEY_ALLOWANCE.as_attounc() - key_storage_stake().as_attounc())
+        );
+    }
+
+    /// Funds `key` with `deposit` and returns its balance and the allowance of the access key added for it.
+    fn send_and_get_allowance(contract: &mut AirDrop, key: &PublicKey, deposit: UncToken) -> (UncToken, Option<UncToken>) {
+        send_env(deposit);
+        contract.send(key.clone(), None, None, None, None, None);
+        let allowance = get_created_receipts()[0].actions.iter().find_map(|action| match action {
+            MockAction::AddKeyWithFunctionCall { allowance, .. } => Some(*allowance),
+            _ => None,
+        });
+        (contract.get_key_balance(key.clone()), allowance.unwrap())
+    }
+
+    #[test]
+    fn test_allowance_per_curve() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        let ed25519_allowance = UncToken::from_milliunc(200);
+        let secp256k1_allowance = UncToken::from_milliunc(500);
+        contract.set_ed25519_allowance(U128(ed25519_allowance.as_attounc()));
+        contract.set_secp256k1_allowance(U128(secp256k1_allowance.as_attounc()));
+        let deposit = UncToken::from_unc(2);
+        // The access key gets the allowance of a claim attempt for each of the DEFAULT_MAX_CLAIM_ATTEMPTS
+        let ed25519_allowance = ed25519_allowance.saturating_mul(DEFAULT_MAX_CLAIM_ATTEMPTS.into());
+        let secp256k1_allowance = secp256k1_allowance.saturating_mul(DEFAULT_MAX_CLAIM_ATTEMPTS.into());
+
+        let ed25519_key = PublicKey::from_parts(CurveType::ED25519, vec![1; 32]).unwrap();
+        let (balance, allowance) = send_and_get_allowance(&mut contract, &ed25519_key, deposit);
+        assert_eq!(allowance, Some(ed25519_allowance));
+        assert_eq!(contract.allowances[&ed25519_key], ed25519_allowance);
+        let stake = contract.storage_stakes[&key_hash(&ed25519_key)];
+        assert_eq!(balance, deposit.saturating_sub(ed25519_allowance).saturating_sub(stake));
+
+        let secp256k1_key = PublicKey::from_parts(CurveType::SECP256K1, vec![1; 64]).unwrap();
+        let (balance, allowance) = send_and_get_allowance(&mut contract, &secp256k1_key, deposit);
+        assert_eq!(allowance, Some(secp256k1_allowance));
+        assert_eq!(contract.allowances[&secp256k1_key], secp256k1_allowance);
+        let stake = contract.storage_stakes[&key_hash(&secp256k1_key)];
+        assert_eq!(balance, deposit.saturating_sub(secp256k1_allowance).saturating_sub(stake));
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must be more than the access key allowance of 1000000000000000000000000 attoUNC")]
+    fn test_send_below_secp256k1_allowance() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_ed25519_allowance(U128(UncToken::from_milliunc(200).as_attounc()));
+        contract.set_secp256k1_allowance(U128(UncToken::from_milliunc(500).as_attounc()));
+        // Enough for an ED25519 key, not for a SECP256K1 one
+        send_env(UncToken::from_milliunc(500));
+        contract.send(PublicKey::from_parts(CurveType::SECP256K1, vec![1; 64]).unwrap(), None, None, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Allowance must be positive")]
+    fn test_set_zero_allowance() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_secp256k1_allowance(U128(0));
+    }
+
+    #[test]
+    fn test_allowance_for_max_claim_attempts() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_max_claim_attempts(3);
+        assert_eq!(contract.get_max_claim_attempts(), 3);
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        assert_eq!(contract.allowances[&pk], CLAIM_ATTEMPT_ALLOWANCE.saturating_mul(3));
+        assert_eq!(contract.get_key_information(pk.clone()).unwrap().remaining_attempts, 3);
+
+        // A failed creation takes an attempt, and the key can be claimed with the next one
+        claim_env(&pk, 0);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        claim_callback_env(&pk, PromiseResult::Failed);
+        contract.on_account_created_and_claimed(carol(), ACCESS_KEY_ALLOWANCE, None);
+        let info = contract.get_key_information(pk.clone()).unwrap();
+        assert_eq!(info.remaining_attempts, 2);
+        assert!(info.claimable);
+        claim_env(&pk, 1);
+        contract.create_account_and_claim(bob(), drop_pk(), None, None);
+        assert_eq!(get_created_receipts().len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Max claim attempts must be positive")]
+    fn test_set_zero_max_claim_attempts() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_max_claim_attempts(0);
+    }
+
+    #[test]
+    fn test_top_up() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_fee_bps(250);
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        let old_balance = contract.get_key_balance(pk.clone());
+        let allowance = contract.allowances[&pk];
+        let fees = contract.get_fees_collected().0;
+
+        send_env(UncToken::from_unc(4));
+        let new_balance = contract.top_up(pk.clone());
+        // 2.5% of the deposit goes to the fees, the rest to the key, without another allowance or access key
+        let fee = UncToken::from_unc(4).as_attounc() / 40;
+        assert_eq!(new_balance, U128(old_balance.as_attounc() + UncToken::from_unc(4).as_attounc() - fee));
+        assert_eq!(contract.get_key_balance(pk.clone()).as_attounc(), new_balance.0);
+        assert_eq!(contract.get_fees_collected(), U128(fees + fee));
+        assert_eq!(contract.allowances[&pk], allowance);
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"airdrop","version":"1.0.0","event":"drop_topped_up","data":{{"public_key":"{}","old_balance":"{}","new_balance":"{}"}}}}"#,
+                String::from(&pk),
+                old_balance.as_attounc(),
+                new_balance.0
+            )]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Key is missing")]
+    fn test_top_up_missing_key() {
+        let mut contract = AirDrop::new_default();
+        send_env(UncToken::from_unc(1));
+        contract.top_up(drop_pk());
+    }
+
+    #[test]
+    fn test_top_up_drop_key() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        let remaining = contract.get_drop(window_drop).unwrap().remaining;
+        drop_env(bob(), 0);
+        contract.top_up(drop_pk());
+        assert_eq!(
+            contract.get_drop(window_drop).unwrap().remaining,
+            remaining.saturating_add(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the key can top it up")]
+    fn test_top_up_not_funder() {
+        let (mut contract, _, _) = campaign_contract();
+        drop_env(carol(), 0);
+        contract.top_up(drop_pk());
+    }
+
+    #[test]
+    fn test_send_top_up_drop_key() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        let remaining = contract.get_drop(window_drop).unwrap().remaining;
+        drop_env(bob(), 0);
+        contract.send(drop_pk(), None, None, None, None, None);
+        assert_eq!(
+            contract.get_drop(window_drop).unwrap().remaining,
+            remaining.saturating_add(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the key can top it up")]
+    fn test_send_top_up_not_funder() {
+        let (mut contract, _, _) = campaign_contract();
+        drop_env(carol(), 0);
+        contract.send(drop_pk(), None, None, None, None, None);
+    }
+
+    /// Creates a contract with bob as a relayer and 10 UNC in the sponsor pool.
+    fn sponsor_contract() -> AirDrop {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.add_relayer(bob());
+        send_env(UncToken::from_unc(10));
+        assert_eq!(contract.fund_sponsor_pool(), U128(UncToken::from_unc(10).as_attounc()));
+        contract
+    }
+
+    #[test]
+    fn test_sponsor_key() {
+        let mut contract = sponsor_contract();
+        assert!(contract.is_relayer(bob()));
+        let amount = ACCESS_KEY_ALLOWANCE.saturating_mul(2);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.sponsor_key(drop_pk(), U128(amount.as_attounc()));
+
+        let stake = contract.storage_stakes[&key_hash(&drop_pk())];
+        let balance = amount.saturating_sub(ACCESS_KEY_ALLOWANCE).saturating_sub(stake);
+        assert_eq!(contract.get_key_balance(drop_pk()), balance);
+        assert_eq!(contract.allowances[&drop_pk()], ACCESS_KEY_ALLOWANCE);
+        let allowance = get_created_receipts()[0].actions.iter().find_map(|action| match action {
+            MockAction::AddKeyWithFunctionCall { allowance, .. } => Some(*allowance),
+            _ => None,
+        });
+        assert_eq!(allowance, Some(Some(ACCESS_KEY_ALLOWANCE)));
+        // The amount moves from the pool to what is owed to the key
+        let breakdown = contract.get_balance_breakdown();
+        assert_eq!(breakdown.sponsor_pool, U128(UncToken::from_unc(10).saturating_sub(amount).as_attounc()));
+        assert_eq!(breakdown.key_liability, U128(balance.as_attounc()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only relayers can sponsor keys")]
+    fn test_sponsor_key_not_relayer() {
+        let mut contract = sponsor_contract();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(carol())
+            .context.clone()
+        );
+        contract.sponsor_key(drop_pk(), U128(ACCESS_KEY_ALLOWANCE.saturating_mul(2).as_attounc()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Sponsor pool doesn't cover the amount")]
+    fn test_sponsor_key_above_pool() {
+        let mut contract = sponsor_contract();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.sponsor_key(drop_pk(), U128(UncToken::from_unc(11).as_attounc()));
+    }
+
+    #[test]
+    fn test_send_fee() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_fee_bps(250);
+
+        let sent = UncToken::from_unc(4);
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_add(sent).saturating_add(key_storage_stake()));
+        contract.send(drop_pk(), None, None, None, None, None);
+        // 2.5% of what is left once the allowance is taken, the storage stake included
+        let fee = sent.saturating_add(key_storage_stake()).as_attounc() / 40;
+        assert_eq!(contract.get_fees_collected(), U128(fee));
+        assert_eq!(contract.get_key_balance(drop_pk()).as_attounc(), sent.as_attounc() - fee);
+        // The fees aren't owed to the keys
+        assert_eq!(contract.get_balance_breakdown().key_liability, U128(sent.as_attounc() - fee));
+
+        // A top-up pays the fee on the whole deposit
+        send_env(UncToken::from_unc(2));
+        contract.send(drop_pk(), None, None, None, None, None);
+        let top_up_fee = UncToken::from_unc(2).as_attounc() / 40;
+        assert_eq!(contract.get_fees_collected(), U128(fee + top_up_fee));
+        assert_eq!(
+            contract.get_key_balance(drop_pk()).as_attounc(),
+            sent.as_attounc() - fee + UncToken::from_unc(2).as_attounc() - top_up_fee
+        );
+        assert_eq!(contract.get_stats().total_unc_dropped, U128(contract.get_key_balance(drop_pk()).as_attounc()));
+    }
+
+    #[test]
+    fn test_fee_rounds_down() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_fee_bps(MAX_FEE_BPS);
+        contract.internal_collect_fee(UncToken::from_attounc(19_999));
+        assert_eq!(contract.get_fees_collected(), U128(1_999));
+        // No overflow on the largest amounts
+        contract.fees_collected = UncToken::from_attounc(0);
+        contract.internal_collect_fee(UncToken::from_attounc(u128::MAX));
+        assert_eq!(contract.get_fees_collected(), U128(u128::MAX / 10));
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee must be at most 1000 basis points")]
+    fn test_set_fee_bps_above_max() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_fee_bps(MAX_FEE_BPS + 1);
+    }
+
+    #[test]
+    fn test_withdraw_fees() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_fee_bps(100);
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_add(UncToken::from_unc(1)));
+        contract.send(drop_pk(), None, None, None, None, None);
+        let fees = contract.get_fees_collected();
+        assert!(fees.0 > 0);
+
+        owner_env();
+        contract.withdraw_fees(carol());
+        assert_eq!(contract.get_fees_collected(), U128(0));
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, carol());
+        assert!(matches!(receipts[0].actions[0], MockAction::Transfer { deposit, .. } if deposit.as_attounc() == fees.0));
+
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.withdraw_fees(carol()); }),
+            "No fees to withdraw"
+        );
+    }
+
+    fn signing_key() -> ed25519_dalek::SigningKey {
+        ed25519_dalek::SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn signing_pk() -> PublicKey {
+        PublicKey::from_parts(CurveType::ED25519, signing_key().verifying_key().to_bytes().to_vec()).unwrap()
+    }
+
+    fn sign_claim(account_id: &AccountId, nonce: u64) -> Vec<u8> {
+        use ed25519_dalek::Signer;
+        signing_key().sign(&signed_claim_message(&airdrop(), account_id, nonce)).to_bytes().to_vec()
+    }
+
+    /// Creates a contract with `signing_pk` funded with `send_signed` with 2 allowances.
+    fn signed_contract() -> AirDrop {
+        let mut contract = AirDrop::new_default();
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(2));
+        let balance = contract.send_signed(signing_pk());
+        // The storage stake of the key is taken from the deposit
+        let stake = contract.storage_stakes[&key_hash(&signing_pk())];
+        assert!(!stake.is_zero());
+        assert_eq!(balance.0 + stake.as_attounc(), ACCESS_KEY_ALLOWANCE.saturating_mul(2).as_attounc());
+        // No access key is added
+        assert!(get_created_receipts().is_empty());
+        contract
+    }
+
+    #[test]
+    fn test_claim_signed() {
+        let mut contract = signed_contract();
+        testing_env!(VMContextBuilder::new().current_account_id(airdrop()).predecessor_account_id(carol()).context.clone());
+        contract.claim_signed(bob(), signing_pk(), sign_claim(&bob(), 1), 1);
+
+        // The storage stake is paid out with the balance
+        let payout = ACCESS_KEY_ALLOWANCE.saturating_mul(2);
+        assert_eq!(transferred_to(&bob()), payout.as_attounc());
+        // The key keeps its balance until the transfer succeeds
+        assert!(contract.key_exists(signing_pk()));
+        callback_env(PromiseResult::Successful(vec![]));
+        assert_eq!(
+            contract.on_signed_claimed(bob(), vec![signing_pk()], payout),
+            ClaimAmountOutcome::Claimed(U128(payout.as_attounc()))
+        );
+        assert!(!contract.key_exists(signing_pk()));
+        assert_eq!(contract.get_claim_history(None, None).len(), 1);
+    }
+
+    #[test]
+    fn test_claim_signed_failed_transfer() {
+        let mut contract = signed_contract();
+        let balance = contract.get_key_balance(signing_pk());
+        contract.claim_signed(bob(), signing_pk(), sign_claim(&bob(), 1), 1);
+
+        callback_env(PromiseResult::Failed);
+        assert_eq!(
+            contract.on_signed_claimed(bob(), vec![signing_pk()], ACCESS_KEY_ALLOWANCE.saturating_mul(2)),
+            ClaimAmountOutcome::Claimed(U128(0))
+        );
+        // The key is unlocked with its balance, and nothing is counted as claimed
+        assert_eq!(contract.get_key_balance(signing_pk()), balance);
+        assert!(!contract.claim_locks.contains_key(&key_hash(&signing_pk())));
+        assert_eq!(contract.get_stats().total_unc_claimed, U128(0));
+        assert!(contract.get_claim_history(None, None).is_empty());
+        // It can be claimed again with a new nonce
+        contract.claim_signed(bob(), signing_pk(), sign_claim(&bob(), 2), 2);
+        assert!(transferred_to(&bob()) > 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Nonce already used")]
+    fn test_claim_signed_replayed_nonce() {
+        let mut contract = signed_contract();
+        contract.claim_signed(bob(), signing_pk(), sign_claim(&bob(), 1), 1);
+
+        // Funding the key again doesn't allow replaying the signature
+        send_env(ACCESS_KEY_ALLOWANCE);
+        contract.send_signed(signing_pk());
+        contract.claim_signed(bob(), signing_pk(), sign_claim(&bob(), 1), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_claim_signed_bad_signature() {
+        let mut contract = signed_contract();
+        // Signed for another account
+        contract.claim_signed(carol(), signing_pk(), sign_claim(&bob(), 1), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_claim_signed_other_contract() {
+        let mut contract = signed_contract();
+        // Signed for a deployment of the contract on another account
+        let signature = {
+            use ed25519_dalek::Signer;
+            signing_key().sign(&signed_claim_message(&bob(), &bob(), 1)).to_bytes().to_vec()
+        };
+        contract.claim_signed(bob(), signing_pk(), signature, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_claim_signed_wrong_nonce() {
+        let mut contract = signed_contract();
+        contract.claim_signed(bob(), signing_pk(), sign_claim(&bob(), 1), 2);
+    }
+
+    #[test]
+    fn test_claim_all() {
+        let mut contract = signed_contract();
+        let other_key = ed25519_dalek::SigningKey::from_bytes(&[8; 32]);
+        let other_pk = PublicKey::from_parts(CurveType::ED25519, other_key.verifying_key().to_bytes().to_vec()).unwrap();
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(3));
+        contract.send(other_pk.clone(), None, None, None, None, None);
+        let other_signature = {
+            use ed25519_dalek::Signer;
+            other_key.sign(&signed_claim_message(&airdrop(), &bob(), 1)).to_bytes().to_vec()
+        };
+
+        testing_env!(VMContextBuilder::new().current_account_id(airdrop()).predecessor_account_id(bob()).context.clone());
+        // The key that was never funded is skipped
+        contract.claim_all(
+            bob(),
+            vec![signing_pk(), bob_pk(), other_pk.clone()],
+            vec![sign_claim(&bob(), 1), vec![0; 64], other_signature],
+            1,
+        );
+
+        let receipts = get_created_receipts();
+        // Both balances with their storage stakes go out in one transfer
+        let transfers: Vec<UncToken> = receipts
+            .iter()
+            .filter(|receipt| receipt.receiver_id == bob())
+            .flat_map(|receipt| &receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::Transfer { deposit, .. } => Some(*deposit),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(transfers, vec![ACCESS_KEY_ALLOWANCE.saturating_mul(4)]);
+
+        callback_env(PromiseResult::Successful(vec![]));
+        contract.on_signed_claimed(bob(), vec![signing_pk(), other_pk.clone()], ACCESS_KEY_ALLOWANCE.saturating_mul(4));
+        assert!(!contract.key_exists(signing_pk()));
+        assert!(!contract.key_exists(other_pk));
+        // The access key of the key funded with `send` is deleted
+        assert!(get_created_receipts().iter().any(|receipt| matches!(receipt.actions[..], [MockAction::DeleteKey { .. }])));
+        assert_eq!(contract.get_claim_history(None, None).len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_claim_all_bad_signature() {
+        let mut contract = signed_contract();
+        contract.claim_all(carol(), vec![signing_pk()], vec![sign_claim(&bob(), 1)], 1);
+    }
+
+    #[test]
+    fn test_send_with_restricted_methods() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, Some("create_account_and_claim".to_string()), None);
+        contract.send(bob_pk(), None, None, None, None, None);
+
+        let method_names: Vec<String> = get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::AddKeyWithFunctionCall { method_names, .. } => Some(
+                    method_names.join(",")
+                ),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(method_names, vec!["create_account_and_claim".to_string(), CLAIM_METHODS.join(",")]);
+    }
+
+    #[test]
+    fn test_get_key_methods() {
+        let mut contract = AirDrop::new_default();
+        assert_eq!(contract.get_key_methods(drop_pk()), None);
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(2));
+        contract.send(drop_pk(), None, None, None, Some("claim,claim_wrapped".to_string()), None);
+        contract.send(bob_pk(), None, None, None, None, None);
+        assert_eq!(contract.get_key_methods(drop_pk()), Some("claim,claim_wrapped".to_string()));
+        assert_eq!(contract.get_key_methods(bob_pk()), Some(CLAIM_METHODS.join(",")));
+
+        // The stored methods are enforced by the contract too
+        claim_env(&drop_pk(), 0);
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.claim_and_call(bob(), token(), "deposit".to_string(), Base64VecU8(vec![]), Gas::from_tgas(10));
+            }),
+            "Method claim_and_call is not allowed for this key"
+        );
+        claim_resolved(&mut contract, bob());
+        assert_eq!(contract.get_key_methods(drop_pk()), None);
+        assert!(!contract.key_methods.contains_key(&key_hash(&drop_pk())));
+    }
+
+    #[test]
+    #[should_panic(expected = "Method send can't be allowed on drop keys")]
+    fn test_send_with_non_claim_method() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, Some("claim,send".to_string()), None);
+    }
+
+    type ContractCall = fn(&mut AirDrop);
+
+    /// Calls each method of CLAIM_METHODS, in the same order.
+    fn claim_calls() -> Vec<(&'static str, ContractCall)> {
+        vec![
+            ("claim", |contract| { contract.claim(bob()); }),
+            ("create_account_and_claim", |contract| {
+                contract.create_account_and_claim(carol(), bob_pk(), None, None);
+            }),
+            ("claim_ft_with_registration", |contract| { contract.claim_ft_with_registration(bob()); }),
+            ("claim_ft_call", |contract| { contract.claim_ft_call(bob(), String::new()); }),
+            ("claim_and_call", |contract| {
+                contract.claim_and_call(bob(), token(), "deposit".to_string(), Base64VecU8(vec![]), Gas::from_tgas(10));
+            }),
+            ("create_account_and_claim_derived", |contract| {
+                contract.create_account_and_claim_derived(airdrop(), bob_pk());
+            }),
+            ("claim_wrapped", |contract| { contract.claim_wrapped(bob()); }),
+        ]
+    }
+
+    #[test]
+    fn test_claim_methods_check_claim_context() {
+        let names: Vec<&str> = claim_calls().iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, CLAIM_METHODS);
+
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .signer_account_pk(pk.clone())
+            .context.clone()
+        );
+        for (name, call) in claim_calls() {
+            assert!(panic_message(&mut contract, call).contains("Claim only can come from this account"), "{}", name);
+        }
+
+        // Keys funded once the default is restricted can only call the default methods
+        contract.default_claim_methods = Some("claim".to_string());
+        let pk = fund_keys(&mut contract, 1..2).remove(0);
+        claim_env(&pk, 0);
+        for (name, call) in claim_calls().into_iter().filter(|(name, _)| *name != "claim") {
+            assert_eq!(panic_message(&mut contract, call), format!("Method {} is not allowed for this key", name));
+        }
+    }
+
+    #[test]
+    fn test_admin_key_collision() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        // A full access key of the owner that got funded by mistake
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        owner_env();
+        assert!(contract.add_admin_key(pk.clone()));
+        assert!(!contract.add_admin_key(pk.clone()));
+        assert_eq!(contract.get_admin_keys(), vec![pk.clone()]);
+
+        claim_env(&pk, 0);
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::NotADropKey))));
+        for (name, call) in claim_calls() {
+            call(&mut contract);
+            assert!(get_created_receipts().is_empty(), "{}", name);
+        }
+        assert_eq!(contract.claims_in_window, 0);
+        assert!(contract.key_exists(pk.clone()));
+
+        owner_env();
+        assert!(contract.remove_admin_key(pk.clone()));
+        claim_env(&pk, 0);
+        assert!(matches!(claim_resolved(&mut contract, bob()), ClaimOutcome::Claimed(_)));
+        assert!(!contract.key_exists(pk));
+    }
+
+    #[test]
+    fn test_claim_not_a_drop_key() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_env(&bob_pk(), 0);
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::NotADropKey))));
+
+        // In a call the contract makes to itself, the signer key isn't the claimer's
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .signer_account_id(bob())
+            .signer_account_pk(pk.clone())
+            .context.clone()
+        );
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::NotADropKey))));
+        assert!(contract.key_exists(pk));
+    }
+
+    #[test]
+    fn test_max_admin_keys() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        for seed in 0..MAX_ADMIN_KEYS as u8 {
+            contract.add_admin_key(PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![seed; 32]).unwrap());
+        }
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.add_admin_key(PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![255; 32]).unwrap());
+            }),
+            format!("At most {} admin keys can be registered", MAX_ADMIN_KEYS)
+        );
+        // Registering a key again doesn't need room
+        assert!(!contract.add_admin_key(PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![0; 32]).unwrap()));
+    }
+
+    #[test]
+    fn test_set_default_claim_methods() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        assert_eq!(contract.get_default_claim_methods(), CLAIM_METHODS.join(","));
+        assert_eq!(
+            panic_message(&mut contract, |contract| contract.set_default_claim_methods(Some("claim,send".to_string()))),
+            "Method send can't be allowed on drop keys"
+        );
+        contract.set_default_claim_methods(Some("claim,claim_and_call".to_string()));
+        assert_eq!(contract.get_default_claim_methods(), "claim,claim_and_call");
+
+        fund_keys(&mut contract, 0..1);
+        let method_names = get_created_receipts().into_iter().flat_map(|receipt| receipt.actions).find_map(|action| {
+            match action {
+                MockAction::AddKeyWithFunctionCall { method_names, .. } => Some(method_names.join(",")),
+                _ => None,
+            }
+        });
+        assert_eq!(method_names.unwrap(), "claim,claim_and_call");
+
+        owner_env();
+        contract.set_default_claim_methods(None);
+        assert_eq!(contract.get_default_claim_methods(), CLAIM_METHODS.join(","));
+    }
+
+    #[test]
+    fn test_new_defaults() {
+        owner_env();
+        let contract = AirDrop::new(bob(), None);
+        assert_eq!(contract.get_owner_id(), &bob());
+        assert_eq!(contract.ed25519_allowance, CLAIM_ATTEMPT_ALLOWANCE);
+        assert_eq!(contract.secp256k1_allowance, CLAIM_ATTEMPT_ALLOWANCE);
+        assert_eq!(contract.get_max_claim_attempts(), DEFAULT_MAX_CLAIM_ATTEMPTS);
+        assert_eq!(contract.min_drop_amount, DEFAULT_MIN_DROP_AMOUNT);
+        assert_eq!(contract.get_fee_bps(), 0);
+        assert_eq!(contract.claim_cooldown_ns, 0);
+        assert_eq!(contract.get_default_expiry_ns(), None);
+        assert_eq!(contract.timelock_delay_ns, DEFAULT_TIMELOCK_DELAY_NS);
+        // The zero-argument constructor makes the caller the owner
+        assert_eq!(AirDrop::new_default().get_owner_id(), &airdrop());
+
+        let contract = AirDrop::new(bob(), Some(Config {
+            ed25519_allowance: Some(U128(5)),
+            fee_bps: Some(100),
+            default_expiry_ns: Some(1_000),
+            ..Config::default()
+        }));
+        assert_eq!(contract.ed25519_allowance, UncToken::from_attounc(5));
+        assert_eq!(contract.secp256k1_allowance, CLAIM_ATTEMPT_ALLOWANCE);
+        assert_eq!(contract.get_fee_bps(), 100);
+        assert_eq!(contract.get_default_expiry_ns(), Some(1_000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee must be at most 1000 basis points")]
+    fn test_new_invalid_config() {
+        owner_env();
+        AirDrop::new(bob(), Some(Config { fee_bps: Some(MAX_FEE_BPS + 1), ..Config::default() }));
+    }
+
+    #[test]
+    #[should_panic(expected = "The contract is already initialized")]
+    fn test_new_twice() {
+        owner_env();
+        env::state_write(&AirDrop::new_default());
+        AirDrop::new(bob(), None);
+    }
+
+    #[test]
+    fn test_ping() {
+        owner_env();
+        let status = AirDrop::ping();
+        assert!(!status.initialized);
+        assert_eq!(status.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(status.keys, 0);
+
+        let mut contract = AirDrop::new_default();
+        fund_keys(&mut contract, 0..2);
+        env::state_write(&contract);
+        assert_eq!(
+            AirDrop::ping(),
+            ContractStatus {
+                initialized: true,
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                paused: false,
+                keys: 2,
+                total_unclaimed: U128(contract.key_liability.as_attounc()),
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The contract is not initialized, call `new` or `new_default` first")]
+    fn test_uninitialized() {
+        owner_env();
+        AirDrop::default();
+    }
+
+    #[test]
+    fn test_default_expiry() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_default_expiry_ns(Some(5_000));
+        assert_eq!(contract.get_default_expiry_ns(), Some(5_000));
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .block_timestamp(1_000)
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+        let info = contract.get_key_information(drop_pk()).unwrap();
+        assert_eq!(info.expiry_ns, Some(info.created_at_ns + 5_000));
+        // An expiration given to the call overrides the default
+        contract.send(bob_pk(), None, None, Some(3_000), None, None);
+        assert_eq!(contract.get_key_information(bob_pk()).unwrap().expiry_ns, Some(3_000));
+
+        owner_env();
+        contract.set_default_expiry_ns(Some(0));
+        assert_eq!(contract.get_default_expiry_ns(), None);
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        assert_eq!(contract.get_key_information(pk).unwrap().expiry_ns, None);
+    }
+
+    #[test]
+    fn test_get_keys_by_tag() {
+        let mut contract = AirDrop::new_default();
+        let gold: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz".parse().unwrap();
+        let silver: PublicKey = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca".parse().unwrap();
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(100))
+            .context.clone()
+        );
+        contract.send(gold.clone(), Some("gold".to_string()), None, None, None, None);
+        contract.send(silver.clone(), Some("silver".to_string()), None, None, None, None);
+
+        assert_eq!(contract.get_keys_by_tag("gold".to_string(), None, None), vec![gold.clone()]);
+        assert_eq!(contract.get_keys_by_tag("silver".to_string(), None, None), vec![silver]);
+        assert!(contract.get_keys_by_tag("bronze".to_string(), None, None).is_empty());
+        assert_eq!(contract.get_key_information(gold).unwrap().tag, Some("gold".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the key can change its tag")]
+    fn test_send_retag_not_funder() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(100))
+            .context.clone()
+        );
+        contract.send(drop_pk(), Some("gold".to_string()), None, None, None, None);
+        contract.send(drop_pk(), Some("silver".to_string()), None, None, None, None);
+    }
+
+    #[test]
+    fn test_send_retag_drop_key() {
+        let (mut contract, _, _) = campaign_contract();
+        drop_env(bob(), 0);
+        contract.send(drop_pk(), Some("gold".to_string()), None, None, None, None);
+        contract.send(drop_pk(), Some("silver".to_string()), None, None, None, None);
+        assert!(contract.get_keys_by_tag("gold".to_string(), None, None).is_empty());
+        assert_eq!(contract.get_keys_by_tag("silver".to_string(), None, None), vec![drop_pk()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tag must be at most 64 bytes long")]
+    fn test_send_tag_too_long() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(100))
+            .context.clone()
+        );
+        contract.send(drop_pk(), Some("x".repeat(MAX_TAG_LENGTH + 1)), None, None, None, None);
+    }
+
+    /// Funds a distinct key for each of the given seeds and returns the keys.
+    fn fund_keys(contract: &mut AirDrop, seeds: std::ops::Range<u8>) -> Vec<PublicKey> {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2).saturating_add(key_storage_stake()))
+            .context.clone()
+        );
+        seeds
+            .map(|seed| {
+                let pk = PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![seed; 32]).unwrap();
+                contract.send(pk.clone(), None, None, None, None, None);
+                pk
+            })
+            .collect()
+    }
+
+    fn claim_env(pk: &PublicKey, block_height: u64) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .signer_account_id(airdrop())
+            .signer_account_pk(pk.clone())
+            .block_height(block_height)
+            .context.clone()
+        );
+    }
+
+    /// Resolves the transfer of the `claim` just made in the current context with `promise_result`, keeping the
+    /// rest of the context for the callback.
+    fn resolve_claim(contract: &mut AirDrop, promise_result: PromiseResult) -> ClaimOutcome {
+        let args = get_created_receipts().into_iter().flat_map(|receipt| receipt.actions).find_map(|action| {
+            match action {
+                MockAction::FunctionCallWeight { method_name, args, .. } if method_name == b"on_claimed" => Some(args),
+                _ => None,
+            }
+        }).unwrap();
+        let args: unc_sdk::serde_json::Value = unc_sdk::serde_json::from_slice(&args).unwrap();
+        testing_env!(
+            VMContextBuilder::new()
+                .current_account_id(env::current_account_id())
+                .predecessor_account_id(env::predecessor_account_id())
+                .signer_account_id(env::signer_account_id())
+                .signer_account_pk(env::signer_account_pk())
+                .block_height(env::block_height())
+                .block_timestamp(env::block_timestamp())
+                .prepaid_gas(env::prepaid_gas())
+                .account_balance(env::account_balance())
+                .storage_usage(env::storage_usage())
+                .context.clone(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![promise_result]
+        );
+        contract.on_claimed(
+            unc_sdk::serde_json::from_value(args["account_id"].clone()).unwrap(),
+            unc_sdk::serde_json::from_value(args["amount"].clone()).unwrap(),
+            unc_sdk::serde_json::from_value(args["public_key"].clone()).unwrap(),
+            args["timestamp"].as_u64().unwrap(),
+        )
+    }
+
+    /// Calls `claim` in the current context and resolves its transfer successfully.
+    fn claim_resolved(contract: &mut AirDrop, account_id: AccountId) -> ClaimOutcome {
+        if let PromiseOrValue::Value(outcome) = contract.claim(account_id) {
+            return outcome;
+        }
+        resolve_claim(contract, PromiseResult::Successful(vec![]))
+    }
+
+    #[test]
+    fn test_claim_resolves_receipt() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        let payout = contract.get_key_balance(pk.clone()).saturating_add(contract.storage_stakes[&key_hash(&pk)]);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .signer_account_id(airdrop())
+            .signer_account_pk(pk.clone())
+            .block_timestamp(5_000)
+            .context.clone()
+        );
+        contract.claim(bob());
+        let args = get_created_receipts().into_iter().flat_map(|receipt| receipt.actions).find_map(|action| {
+            match action {
+                MockAction::FunctionCallWeight { method_name, args, .. } if method_name == b"on_claimed" => Some(args),
+                _ => None,
+            }
+        }).unwrap();
+        let args: unc_sdk::serde_json::Value = unc_sdk::serde_json::from_slice(&args).unwrap();
+        assert_eq!(
+            args,
+            unc_sdk::serde_json::json!({
+                "account_id": "bob",
+                "amount": payout.as_attounc().to_string(),
+                "public_key": String::from(&pk),
+                "timestamp": 5_000,
+            })
+        );
+
+        callback_env(PromiseResult::Successful(vec![]));
+        assert_eq!(
+            contract.on_claimed(bob(), payout, pk.clone(), 5_000),
+            ClaimOutcome::Claimed(ClaimReceipt {
+                account_id: bob(),
+                amount: U128(payout.as_attounc()),
+                public_key: pk.clone(),
+                timestamp: 5_000,
+            })
+        );
+        callback_env(PromiseResult::Failed);
+        assert!(matches!(
+            contract.on_claimed(bob(), payout, pk, 5_000),
+            ClaimOutcome::Claimed(ClaimReceipt { amount: U128(0), .. })
+        ));
+    }
+
+    #[test]
+    fn test_claim_failed_transfer() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        let balance = contract.get_key_balance(pk.clone());
+        claim_env(&pk, 0);
+        contract.claim(bob());
+        // The key is locked with its balance while the transfer is in flight
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::ClaimInProgress))));
+
+        assert!(matches!(
+            resolve_claim(&mut contract, PromiseResult::Failed),
+            ClaimOutcome::Claimed(ClaimReceipt { amount: U128(0), .. })
+        ));
+        assert_eq!(contract.get_key_balance(pk.clone()), balance);
+        assert!(!contract.claim_locks.contains_key(&key_hash(&pk)));
+        assert_eq!(contract.failed_attempts[&key_hash(&pk)], 1);
+        assert_eq!(contract.get_recent_failures(None, None)[0].kind, FailureKind::Claim);
+        let stats = contract.get_stats();
+        assert_eq!(stats.total_unc_claimed, U128(0));
+        assert_eq!(stats.total_drops_claimed, 0);
+        assert!(contract.get_claim_history(None, None).is_empty());
+        assert!(get_created_receipts().is_empty());
+
+        // The key can be claimed again
+        claim_env(&pk, 0);
+        assert!(matches!(claim_resolved(&mut contract, bob()), ClaimOutcome::Claimed(_)));
+        assert!(!contract.key_exists(pk));
+        assert_eq!(contract.get_stats().total_drops_claimed, 1);
+    }
+
+    #[test]
+    fn test_claims_rate_limited_and_auto_paused() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        contract.set_claim_limits(Some(2), Some(4));
+        let keys = fund_keys(&mut contract, 0..5);
+
+        // A burst of claims in the same window
+        let block_height = CLAIM_WINDOW_BLOCKS * 3;
+        for pk in &keys[..2] {
+            claim_env(pk, block_height);
+            assert!(matches!(contract.claim(bob()), PromiseOrValue::Promise(_)));
+        }
+        for pk in &keys[2..4] {
+            claim_env(pk, block_height + 1);
+            assert!(matches!(contract.claim(bob()), PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::RateLimited))));
+        }
+        claim_env(&keys[4], block_height + 2);
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::AutoPaused))));
+        assert!(contract.is_paused());
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"airdrop","version":"1.0.0","event":"auto_paused","data":{"window":3,"claims":5}}"#]
+        );
+
+        // Rejected keys keep their balance
+        assert!(contract.internal_key_balance(&keys[4]).is_some());
+    }
+
+    #[test]
+    fn test_claim_limits_reset_across_windows() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        contract.set_claim_limits(Some(1), None);
+        let keys = fund_keys(&mut contract, 0..3);
+
+        claim_env(&keys[0], CLAIM_WINDOW_BLOCKS - 1);
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Promise(_)));
+        claim_env(&keys[1], CLAIM_WINDOW_BLOCKS - 1);
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::RateLimited))));
+
+        // The next window starts from zero
+        claim_env(&keys[1], CLAIM_WINDOW_BLOCKS);
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Promise(_)));
+        assert_eq!(contract.claims_in_window, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_claim_while_paused() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        let keys = fund_keys(&mut contract, 0..1);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+        contract.pause();
+
+        claim_env(&keys[0], 0);
+        claim_resolved(&mut contract, bob());
+    }
+
+    #[test]
+    fn test_claim_with_funding_disabled() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        let keys = fund_keys(&mut contract, 0..1);
+        owner_env();
+        contract.set_funding_enabled(false);
+        assert!(!contract.is_funding_enabled());
+
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(2).saturating_add(key_storage_stake()));
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.send(bob_pk(), None, None, None, None, None); }),
+            "Funding is disabled"
+        );
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.send_signed(bob_pk()); }),
+            "Funding is disabled"
+        );
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.send_with_code(Base58CryptoHash::from([0; 32])); }),
+            "Funding is disabled"
+        );
+
+        // The funded keys can still be claimed
+        claim_env(&keys[0], 0);
+        contract.claim(bob());
+        assert!(get_created_receipts().iter().any(|receipt| receipt.receiver_id == bob()));
+        resolve_claim(&mut contract, PromiseResult::Successful(vec![]));
+        assert!(!contract.key_exists(keys[0].clone()));
+
+        owner_env();
+        contract.set_funding_enabled(true);
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(2).saturating_add(key_storage_stake()));
+        contract.send(bob_pk(), None, None, None, None, None);
+        assert!(contract.key_exists(bob_pk()));
+    }
+
+    fn pool() -> AccountId {
+        "pool".parse().unwrap()
+    }
+
+    /// Creates a contract with a funded key and `pool` allowed for `claim_and_call`.
+    fn claim_and_call_contract() -> (AirDrop, PublicKey) {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.add_call_receiver(pool(), "receiver_id".to_string());
+        let keys = fund_keys(&mut contract, 0..1);
+        (contract, keys[0].clone())
+    }
+
+    #[test]
+    fn test_claim_and_call() {
+        let (mut contract, pk) = claim_and_call_contract();
+        assert_eq!(contract.get_call_receivers(None, None), vec![(pool(), "receiver_id".to_string())]);
+        let payout = contract.get_key_balance(pk.clone()).saturating_add(contract.storage_stakes[&key_hash(&pk)]);
+
+        claim_env(&pk, 0);
+        contract.claim_and_call(
+            bob(),
+            pool(),
+            "deposit_and_stake".to_string(),
+            Base64VecU8(br#"{"validator": "carol"}"#.to_vec()),
+            Gas::from_tgas(50),
+        );
+        assert!(!contract.key_exists(pk));
+        let receipts = get_created_receipts();
+        let call = receipts
+            .iter()
+            .filter(|receipt| receipt.receiver_id == pool())
+            .flat_map(|receipt| &receipt.actions)
+            .find_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, args, attached_deposit, prepaid_gas, .. } => {
+                    Some((method_name.clone(), args.clone(), *attached_deposit, *prepaid_gas))
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(call.0, b"deposit_and_stake");
+        let args: unc_sdk::serde_json::Value = unc_sdk::serde_json::from_slice(&call.1).unwrap();
+        assert_eq!(args, unc_sdk::serde_json::json!({ "validator": "carol", "receiver_id": "bob" }));
+        assert_eq!(call.2, payout);
+        assert_eq!(call.3, Gas::from_tgas(50));
+        assert!(receipts.iter().any(|receipt| receipt.actions.iter().any(|action| matches!(
+            action,
+            MockAction::FunctionCallWeight { method_name, .. } if method_name == b"on_claim_called"
+        ))));
+
+        callback_env(PromiseResult::Successful(vec![]));
+        assert_eq!(contract.on_claim_called(bob(), payout), ClaimAmountOutcome::Claimed(U128(payout.as_attounc())));
+        assert!(get_created_receipts().is_empty());
+    }
+
+    #[test]
+    fn test_claim_and_call_falls_back_to_transfer() {
+        let (mut contract, pk) = claim_and_call_contract();
+        claim_env(&pk, 0);
+        contract.claim_and_call(bob(), pool(), "deposit".to_string(), Base64VecU8(b"{}".to_vec()), Gas::from_tgas(50));
+
+        // The failed call is refunded to the contract, which transfers the balance to the account
+        callback_env(PromiseResult::Failed);
+        assert_eq!(contract.on_claim_called(bob(), UncToken::from_attounc(1_000)), ClaimAmountOutcome::Claimed(U128(1_000)));
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, bob());
+        assert!(matches!(receipts[0].actions[..], [MockAction::Transfer { deposit, .. }] if deposit.as_attounc() == 1_000));
+    }
+
+    fn wrap() -> AccountId {
+        "wrap".parse().unwrap()
+    }
+
+    #[test]
+    fn test_claim_wrapped() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1)[0].clone();
+        claim_env(&pk, 0);
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.claim_wrapped(bob()); }),
+            "Wrapped claims are not enabled"
+        );
+        owner_env();
+        contract.set_wrap_contract(Some(wrap()));
+        assert_eq!(contract.get_wrap_contract(), Some(wrap()));
+        let payout = contract.get_key_balance(pk.clone()).saturating_add(contract.storage_stakes[&key_hash(&pk)]);
+
+        // The balance is deposited in the wrap contract, then the wrapped tokens are forwarded
+        claim_env(&pk, 0);
+        contract.claim_wrapped(bob());
+        assert!(!contract.key_exists(pk));
+        assert_eq!(
+            function_calls(),
+            vec![
+                (wrap(), "unc_deposit".to_string(), payout),
+                (airdrop(), "on_wrapped".to_string(), UncToken::from_attounc(0)),
+            ]
+        );
+        callback_env(PromiseResult::Successful(vec![]));
+        contract.on_wrapped(bob(), wrap(), payout);
+        assert_eq!(
+            function_calls(),
+            vec![
+                (wrap(), "ft_transfer".to_string(), UncToken::from_attounc(1)),
+                (airdrop(), "on_wrapped_transferred".to_string(), UncToken::from_attounc(0)),
+            ]
+        );
+        callback_env(PromiseResult::Successful(vec![]));
+        assert_eq!(contract.on_wrapped_transferred(bob(), payout), ClaimAmountOutcome::Claimed(U128(payout.as_attounc())));
+        assert!(contract.get_recent_failures(None, None).is_empty());
+
+        // A failed transfer leaves the wrapped tokens on the contract
+        callback_env(PromiseResult::Failed);
+        assert_eq!(contract.on_wrapped_transferred(bob(), payout), ClaimAmountOutcome::Claimed(U128(0)));
+        assert_eq!(contract.get_recent_failures(None, None)[0].kind, FailureKind::ClaimWrapped);
+
+        // A failed deposit is refunded to the contract, which transfers the balance to the account
+        callback_env(PromiseResult::Failed);
+        contract.on_wrapped(bob(), wrap(), payout);
+        assert_eq!(transferred_to(&bob()), payout.as_attounc());
+        assert_eq!(contract.get_recent_failures(None, None).len(), 2);
+    }
+
+    #[test]
+    fn test_claim_and_call_receiver_not_allowed() {
+        let (mut contract, pk) = claim_and_call_contract();
+        claim_env(&pk, 0);
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.claim_and_call(bob(), carol(), "deposit".to_string(), Base64VecU8(b"{}".to_vec()), Gas::from_tgas(50));
+            }),
+            "Receiver contract is not allowed"
+        );
+
+        owner_env();
+        assert!(contract.remove_call_receiver(pool()));
+        claim_env(&pk, 0);
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.claim_and_call(bob(), pool(), "deposit".to_string(), Base64VecU8(b"{}".to_vec()), Gas::from_tgas(50));
+            }),
+            "Receiver contract is not allowed"
+        );
+        assert!(contract.key_exists(pk));
+    }
+
+    #[test]
+    #[should_panic(expected = "Args must be a JSON object")]
+    fn test_claim_and_call_args_not_object() {
+        let (mut contract, pk) = claim_and_call_contract();
+        claim_env(&pk, 0);
+        contract.claim_and_call(bob(), pool(), "deposit".to_string(), Base64VecU8(b"[]".to_vec()), Gas::from_tgas(50));
+    }
+
+    #[test]
+    fn test_force_claim() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        let keys = fund_keys(&mut contract, 0..1);
+
+        // The owner recovers the balance without the key signing anything
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+        contract.force_claim(keys[0].clone(), bob());
+        assert!(contract.internal_key_balance(&keys[0]).is_none());
+        assert!(!contract.has_claimed(bob()));
+        callback_env(PromiseResult::Successful(vec![]));
+        assert!(contract.on_force_claimed(bob()));
+        assert!(contract.has_claimed(bob()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn test_force_claim_not_owner() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        let keys = fund_keys(&mut contract, 0..1);
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.force_claim(keys[0].clone(), bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough gas attached for the account creation callback")]
+    fn test_create_account_not_enough_gas() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1_000_000))
+            .prepaid_gas(MIN_ON_CREATE_ACCOUNT_CALLBACK_GAS)
+            .context.clone()
+        );
+        contract.create_account(bob(), drop_pk(), None);
+    }
+
+    #[test]
+    fn test_on_account_created_result() {
+        let mut contract = AirDrop::new_default();
+        let amount = UncToken::from_attounc(1_000_000);
+
+        callback_env(PromiseResult::Successful(vec![]));
+        assert_eq!(
+            contract.on_account_created(bob(), carol(), amount),
+            CreationResult { account_id: carol(), amount: U128(1_000_000), success: true }
+        );
+
+        callback_env(PromiseResult::Failed);
+        let result = contract.on_account_created(bob(), carol(), amount);
+        assert_eq!(
+            unc_sdk::serde_json::to_string(&result).unwrap(),
+            r#"{"account_id":"carol","amount":"1000000","success":false}"#
+        );
+    }
+
+    #[test]
+    fn test_create_account_refund_to() {
+        let mut contract = AirDrop::new_default();
+        let amount = UncToken::from_attounc(1_000_000);
+        // Called by a relayer on behalf of carol
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(amount)
+            .context.clone()
+        );
+        contract.create_account("new.airdrop".parse().unwrap(), drop_pk(), Some(carol()));
+        let args = get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .find_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, args, .. } if method_name == b"on_account_created" => Some(args),
+                _ => None,
+            })
+            .unwrap();
+        let args: unc_sdk::serde_json::Value = unc_sdk::serde_json::from_slice(&args).unwrap();
+        assert_eq!(args["refund_to"], "carol");
+
+        callback_env(PromiseResult::Failed);
+        contract.on_account_created(carol(), "new.airdrop".parse().unwrap(), amount);
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, carol());
+        assert!(matches!(receipts[0].actions[..], [MockAction::Transfer { deposit, .. }] if deposit == amount));
+    }
+
+    fn create_account_requests(amounts: &[u128]) -> Vec<CreateAccountRequest> {
+        amounts
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| CreateAccountRequest {
+                new_account_id: format!("new{}.airdrop", i).parse().unwrap(),
+                new_public_key: drop_pk(),
+                amount: U128(*amount),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_create_accounts() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .attached_deposit(UncToken::from_attounc(300))
+            .context.clone()
+        );
+        assert_eq!(contract.create_accounts(create_account_requests(&[100, 200])), 2);
+
+        let receipts = get_created_receipts();
+        for (i, amount) in [100, 200].into_iter().enumerate() {
+            let new_account_id: AccountId = format!("new{}.airdrop", i).parse().unwrap();
+            assert!(receipts.iter().any(|receipt| receipt.receiver_id == new_account_id
+                && receipt.actions.iter().any(|action| matches!(action, MockAction::Transfer { deposit, .. } if deposit.as_attounc() == amount))));
+        }
+        // Every creation has its own callback carrying its own amount
+        let amounts: Vec<_> = receipts
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, args, .. } if method_name == b"on_account_created" => {
+                    let args: unc_sdk::serde_json::Value = unc_sdk::serde_json::from_slice(&args).unwrap();
+                    assert_eq!(args["refund_to"], "bob");
+                    Some(args["amount"].clone())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(amounts, vec!["100", "200"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amounts must add up to the attached deposit")]
+    fn test_create_accounts_deposit_mismatch() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(299))
+            .context.clone()
+        );
+        contract.create_accounts(create_account_requests(&[100, 200]));
+    }
+
+    #[test]
+    #[should_panic(expected = "More than 3 accounts can't be created with the gas attached")]
+    fn test_create_accounts_batch_too_large() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(4))
+            .prepaid_gas(Gas::from_tgas(35))
+            .context.clone()
+        );
+        contract.create_accounts(create_account_requests(&[1, 1, 1, 1]));
+    }
+
+    #[test]
+    fn test_storage_keys_keep_old_prefixes() {
+        assert_eq!(borsh::to_vec(&StorageKey::Accounts).unwrap(), b"a");
+        assert_eq!(borsh::to_vec(&StorageKey::BlockedAccounts).unwrap(), b"b");
+    }
+
+    #[test]
+    fn test_storage_keys_distinct() {
+        let prefixes: Vec<Vec<u8>> = [
+            StorageKey::Accounts,
+            StorageKey::Allowances,
+            StorageKey::Codes,
+            StorageKey::KeyTags,
+            StorageKey::PendingCreations,
+            StorageKey::MerkleDrops,
+            StorageKey::MerkleClaimed,
+            StorageKey::BlockedAccounts,
+            StorageKey::KeyBalances,
+            StorageKey::ClaimHistory,
+            StorageKey::Drips,
+            StorageKey::IdempotencyKeys,
+            StorageKey::FundedAt,
+            StorageKey::KeyExpirations,
+            StorageKey::DropConfigs,
+            StorageKey::KeyDrops,
+            StorageKey::DropsByFunder,
+            StorageKey::Operators,
+            StorageKey::FtBalances,
+            StorageKey::FailedAttempts,
+            StorageKey::ScheduledActions,
+            StorageKey::UsedNonces,
+            StorageKey::StorageStakes,
+            StorageKey::ClaimLocks,
+            StorageKey::CallReceivers,
+            StorageKey::ClaimedAccounts,
+            StorageKey::Reservations,
+            StorageKey::Failures,
+            StorageKey::Relayers,
+            StorageKey::DropClaims,
+            StorageKey::AdminKeys,
+            StorageKey::NewAccountKeys,
+            StorageKey::KeyMethods,
+            StorageKey::FtContracts,
+            StorageKey::StrandedTokens,
+            StorageKey::CodeCommitments,
+            StorageKey::KeysByTag,
+        ]
+        .iter()
+        .map(|key| borsh::to_vec(key).unwrap())
+        .collect();
+        for (i, prefix) in prefixes.iter().enumerate() {
+            assert!(!prefixes[..i].contains(prefix), "Prefix {:?} is used twice", prefix);
+        }
+    }
+
+    #[test]
+    fn test_collections_with_distinct_keys_dont_interfere() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .context.clone()
+        );
+        // Two maps with the same key and value types only differ by their storage key
+        let mut accounts: LookupMap<PublicKey, UncToken> = LookupMap::new(StorageKey::Accounts);
+        let mut allowances: LookupMap<PublicKey, UncToken> = LookupMap::new(StorageKey::Allowances);
+        accounts.insert(drop_pk(), UncToken::from_attounc(1));
+        allowances.insert(drop_pk(), UncToken::from_attounc(2));
+        accounts.flush();
+        allowances.flush();
+
+        let accounts: LookupMap<PublicKey, UncToken> = LookupMap::new(StorageKey::Accounts);
+        let mut allowances: LookupMap<PublicKey, UncToken> = LookupMap::new(StorageKey::Allowances);
+        assert_eq!(accounts.get(&drop_pk()), Some(&UncToken::from_attounc(1)));
+        assert_eq!(allowances.get(&drop_pk()), Some(&UncToken::from_attounc(2)));
+
+        allowances.remove(&drop_pk());
+        allowances.flush();
+        let accounts: LookupMap<PublicKey, UncToken> = LookupMap::new(StorageKey::Accounts);
+        assert_eq!(accounts.get(&drop_pk()), Some(&UncToken::from_attounc(1)));
+    }
+
+    #[test]
+    fn test_migrate_baseline_state() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .context.clone()
+        );
+        // A balance stored by the first version, whose state is only the `b"a"` prefix of `accounts`
+        let mut old_accounts: LookupMap<PublicKey, UncToken> = LookupMap::new(b"a");
+        old_accounts.insert(drop_pk(), UncToken::from_attounc(42));
+        old_accounts.flush();
+        let state = [1, 0, 0, 0, b'a'];
+        assert_eq!(borsh::to_vec(&OldAirDrop { accounts: old_accounts }).unwrap(), state);
+        env::storage_write(STATE_KEY, &state);
+        assert!(AirDrop::try_from_slice(&state).is_err());
+
+        let contract = AirDrop::migrate();
+        assert_eq!(contract.get_key_balance(drop_pk()), UncToken::from_attounc(42));
+        assert_eq!(contract.get_owner_id(), &airdrop());
+        assert_eq!(contract.get_max_claim_attempts(), DEFAULT_MAX_CLAIM_ATTEMPTS);
+    }
+
+    #[test]
+    fn test_migrate_current_state() {
+        owner_env();
+        let mut contract = AirDrop::new(bob(), None);
+        contract.accounts.insert(drop_pk(), UncToken::from_attounc(42));
+        contract.accounts.flush();
+        contract.paused = true;
+        env::state_write(&contract);
+
+        let contract = AirDrop::migrate();
+        assert_eq!(contract.get_key_balance(drop_pk()), UncToken::from_attounc(42));
+        assert_eq!(contract.get_owner_id(), &bob());
+        assert!(contract.paused);
+    }
+
+    #[test]
+    fn test_migrate_chunk_resumes() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        // Claims recorded before `claimed_accounts` existed
+        let accounts: Vec<AccountId> = (0..5).map(|i| format!("user{}.airdrop", i).parse().unwrap()).collect();
+        for account_id in &accounts {
+            contract.claim_history.push(ClaimRecord {
+                public_key: drop_pk(),
+                account_id: account_id.clone(),
+                amount: U128(1),
+                timestamp: 0,
+                block_height: 0,
+                account_created: false,
+            });
+        }
+
+        assert!(!contract.migrate_chunk(2));
+        assert!(contract.has_claimed(accounts[1].clone()));
+        assert!(!contract.has_claimed(accounts[2].clone()));
+        // A claim between chunks is recorded as usual
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_env(&pk, 0);
+        claim_resolved(&mut contract, bob());
+        assert!(contract.has_claimed(bob()));
+
+        owner_env();
+        assert!(!contract.migrate_chunk(2));
+        assert!(contract.migrate_chunk(2));
+        assert_eq!(contract.migration_cursor, 6);
+        assert!(accounts.into_iter().all(|account_id| contract.has_claimed(account_id)));
+        // Nothing is left once complete
+        assert!(contract.migrate_chunk(2));
+    }
+
+    #[test]
+    fn test_legacy_key_balance_moved_on_send() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        let balance = contract.key_balances.remove(&key_hash(&pk)).unwrap();
+        contract.accounts.insert(pk.clone(), UncToken::from_attounc(balance));
+
+        contract.send(pk.clone(), None, None, None, None, None);
+        assert!(contract.accounts.get(&pk).is_none());
+        // The top-up credits the whole deposit
+        assert_eq!(
+            contract.get_key_balance(pk.clone()).as_attounc(),
+            balance + env::attached_deposit().as_attounc()
+        );
+
+        claim_env(&pk, 0);
+        claim_resolved(&mut contract, bob());
+        assert!(contract.internal_key_balance(&pk).is_none());
+    }
+
+    #[test]
+    fn test_legacy_key_balance_claimed() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        let balance = contract.key_balances.remove(&key_hash(&pk)).unwrap();
+        contract.accounts.insert(pk.clone(), UncToken::from_attounc(balance));
+
+        claim_env(&pk, 0);
+        claim_resolved(&mut contract, bob());
+        assert!(contract.accounts.get(&pk).is_none());
+    }
+
+    #[test]
+    fn test_key_balance_storage_usage() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        let pk = drop_pk();
+
+        // Legacy entry: `b"a"` + borsh public key (4 + 33 bytes) -> 16 bytes of UncToken
+        let before = env::storage_usage();
+        contract.accounts.insert(pk.clone(), ACCESS_KEY_ALLOWANCE);
+        contract.accounts.flush();
+        let legacy = env::storage_usage() - before;
+        contract.accounts.remove(&pk);
+        contract.accounts.flush();
+
+        // Packed entry: `b"k"` + 32 bytes of hash -> 16 bytes of u128
+        let before = env::storage_usage();
+        contract.internal_set_key_balance(&pk, ACCESS_KEY_ALLOWANCE);
+        contract.key_balances.flush();
+        let packed = env::storage_usage() - before;
+
+        assert_eq!(legacy, 40 + 38 + 16);
+        assert_eq!(packed, 40 + 33 + 16);
+    }
+
+    fn suffix_contract() -> AirDrop {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_required_suffix(Some(".airdrop".to_string()));
+        contract
+    }
+
+    #[test]
+    fn test_create_account_with_required_suffix() {
+        let mut contract = suffix_contract();
+        contract.create_account("bob.airdrop".parse().unwrap(), drop_pk(), None);
+        contract.create_account_advanced("carol.airdrop".parse().unwrap(), advanced_options());
+    }
+
+    #[test]
+    #[should_panic(expected = "Account id must end with .airdrop")]
+    fn test_create_account_without_required_suffix() {
+        let mut contract = suffix_contract();
+        contract.create_account(bob(), drop_pk(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account id must end with .airdrop")]
+    fn test_create_advanced_account_without_required_suffix() {
+        let mut contract = suffix_contract();
+        contract.create_account_advanced(bob(), advanced_options());
+    }
+
+    #[test]
+    #[should_panic(expected = "Account id must end with .airdrop")]
+    fn test_create_account_and_claim_without_required_suffix() {
+        let mut contract = suffix_contract();
+        let keys = fund_keys(&mut contract, 0..1);
+        claim_env(&keys[0], 0);
+        contract.create_account_and_claim(bob(), drop_pk(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn test_set_required_suffix_not_owner() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.set_required_suffix(Some(".airdrop".to_string()));
+    }
+
+    #[test]
+    fn test_claim_history() {
+        let mut contract = AirDrop::new_default();
+        let keys = fund_keys(&mut contract, 0..3);
+
+        claim_env(&keys[0], 0);
+        claim_resolved(&mut contract, bob());
+        claim_env(&keys[1], 1);
+        claim_resolved(&mut contract, carol());
+
+        let history = contract.get_claim_history(None, None);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].public_key, keys[0]);
+        assert_eq!(history[0].account_id, bob());
+        assert_eq!(history[0].amount, U128(ACCESS_KEY_ALLOWANCE.as_attounc()));
+        assert_eq!(history[1].public_key, keys[1]);
+        assert_eq!(history[1].account_id, carol());
+
+        let page = contract.get_claim_history(Some(1), Some(1));
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].account_id, carol());
+    }
+
+    #[test]
+    fn test_storage_constants_match_usage() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+
+        let before = env::storage_usage();
+        contract.send(drop_pk(), None, None, None, None, None);
+        contract.key_balances.flush();
+        contract.allowances.flush();
+        assert_eq!(env::storage_usage() - before, KEY_STORAGE_BYTES);
+
+        // The first key of a tag also adds the index of the tag
+        let tag = "campaign".to_string();
+        contract.send(PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![1; 32]).unwrap(), Some(tag.clone()), None, None, None, None);
+        contract.key_balances.flush();
+        contract.allowances.flush();
+        contract.key_tags.flush();
+        contract.keys_by_tag.flush();
+        let before = env::storage_usage();
+        contract.send(PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![2; 32]).unwrap(), Some(tag.clone()), None, None, None, None);
+        contract.key_balances.flush();
+        contract.allowances.flush();
+        contract.key_tags.flush();
+        contract.keys_by_tag.flush();
+        assert_eq!(
+            env::storage_usage() - before,
+            KEY_STORAGE_BYTES + TAG_STORAGE_BYTES + tag.len() as u64
+        );
+    }
+
+    #[test]
+    fn test_get_required_deposit() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .context.clone()
+        );
+        let contract = AirDrop::new_default();
+        let byte_cost = env::storage_byte_cost().as_attounc();
+        // The access key of a drop: 74 bytes, the 33 bytes of the key, "airdrop" and the seven method names
+        let access_key_bytes: u128 =
+            74 + 33 + 7 + (4 + 5) + (4 + 24) + (4 + 26) + (4 + 13) + (4 + 14) + (4 + 32) + (4 + 13);
+        let per_key = 1_000 + ACCESS_KEY_ALLOWANCE.as_attounc() + (353 + access_key_bytes) * byte_cost;
+        assert_eq!(contract.get_required_deposit(3, U128(1_000), 0), U128(3 * per_key));
+        assert_eq!(
+            contract.get_required_deposit(3, U128(1_000), 10),
+            U128(3 * (per_key + (201 + 10) * byte_cost))
+        );
+    }
+
+    #[test]
+    fn test_preview_batch_split_matches_send() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        contract.fee_bps = 250;
+        let total = 3 * (ACCESS_KEY_ALLOWANCE.as_attounc() + key_storage_stake().as_attounc() + UncToken::from_unc(1).as_attounc()) + 2;
+        let preview = contract.preview_batch_split(3, U128(total));
+        assert_eq!(preview.remainder, U128(2));
+        assert_eq!(preview.allowance_total, U128(3 * ACCESS_KEY_ALLOWANCE.as_attounc()));
+
+        let share = (total - preview.remainder.0) / 3;
+        for seed in 0..3 {
+            testing_env!(
+                VMContextBuilder::new()
+                .current_account_id(airdrop())
+                .attached_deposit(UncToken::from_attounc(share))
+                .context.clone()
+            );
+            let pk = PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![seed; 32]).unwrap();
+            assert_eq!(contract.send(pk, None, None, None, None, None), preview.per_key_net);
+        }
+        assert_eq!(contract.get_balance_breakdown().reserved_allowances, preview.allowance_total);
+    }
+
+    #[test]
+    #[should_panic(expected = "Count must be greater than 0")]
+    fn test_preview_batch_split_no_keys() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .context.clone()
+        );
+        AirDrop::new_default().preview_batch_split(0, U128(1_000));
+    }
+
+    #[test]
+    fn test_get_account_creation_cost() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .context.clone()
+        );
+        let contract = AirDrop::new_default();
+        let byte_cost = env::storage_byte_cost().as_attounc();
+        assert_eq!(contract.get_account_creation_cost(None), U128(100 * byte_cost));
+
+        let options = CreateAccountOptions {
+            full_access_keys: Some(vec![drop_pk()]),
+            limited_access_keys: Some(vec![LimitedAccessKey {
+                public_key: drop_pk(),
+                allowance: UncToken::from_attounc(100),
+                receiver_id: bob(),
+                method_names: "a,bc".to_string(),
+            }]),
+            contract_bytes: Some(vec![0; 1_000]),
+            ft_registrations: None,
+            keyless: None,
+        };
+        let bytes = 100 + (49 + 33) + (74 + 33 + 3 + (4 + 1) + (4 + 2)) + 1_000;
+        assert_eq!(contract.get_account_creation_cost(Some(options)), U128(bytes * byte_cost));
+    }
+
+    fn validation_options() -> CreateAccountOptions {
+        CreateAccountOptions {
+            full_access_keys: Some(vec![drop_pk()]),
+            limited_access_keys: Some(vec![LimitedAccessKey {
+                public_key: PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![1; 32]).unwrap(),
+                allowance: UncToken::from_attounc(100),
+                receiver_id: airdrop(),
+                method_names: "claim,send".to_string(),
+            }]),
+            contract_bytes: Some(b"\0asm\x01\0\0\0".to_vec()),
+            ft_registrations: None,
+            keyless: None,
+        }
+    }
+
+    fn validation_contract() -> AirDrop {
+        let mut contract = suffix_contract();
+        contract.block_account("blocked.airdrop".parse().unwrap());
+        contract
+    }
+
+    #[test]
+    fn test_validate_create_account_options() {
+        let contract = validation_contract();
+        let options = validation_options();
+        let report = contract
+            .validate_create_account_options("bob.airdrop".parse().unwrap(), options.clone())
+            .unwrap();
+        assert!(report.warnings.is_empty());
+        assert_eq!(report.minimum_deposit, contract.get_account_creation_cost(Some(options)));
+        assert_eq!(
+            report.estimated_gas,
+            CREATE_ACCOUNT_ADVANCED_GAS.saturating_add(Gas::from_gas(8 * DEPLOY_GAS_PER_BYTE))
+        );
+    }
+
+    #[test]
+    fn test_validate_create_account_options_warnings() {
+        let contract = validation_contract();
+        let mut options = validation_options();
+        options.full_access_keys = None;
+        options.limited_access_keys.as_mut().unwrap()[0].method_names = String::new();
+        options.limited_access_keys.as_mut().unwrap()[0].allowance = UncToken::from_attounc(0);
+        let report = contract
+            .validate_create_account_options("bob.airdrop".parse().unwrap(), options)
+            .unwrap();
+        assert_eq!(report.warnings.len(), 3);
+    }
+
+    #[test]
+    fn test_validate_blocked_account() {
+        let contract = validation_contract();
+        assert_eq!(
+            contract.validate_create_account_options("blocked.airdrop".parse().unwrap(), validation_options()),
+            Err(AirdropError::AccountBlocked)
+        );
+    }
+
+    #[test]
+    fn test_validate_missing_suffix() {
+        let contract = validation_contract();
+        assert_eq!(
+            contract.validate_create_account_options(bob(), validation_options()),
+            Err(AirdropError::MissingSuffix(".airdrop".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_validate_no_options() {
+        let contract = validation_contract();
+        let options = CreateAccountOptions { full_access_keys: None, limited_access_keys: None, contract_bytes: None, ft_registrations: None, keyless: None };
+        assert_eq!(
+            contract.validate_create_account_options("bob.airdrop".parse().unwrap(), options),
+            Err(AirdropError::NoOptions)
+        );
+    }
+
+    #[test]
+    fn test_validate_duplicate_key() {
+        let contract = validation_contract();
+        let mut options = validation_options();
+        options.limited_access_keys.as_mut().unwrap()[0].public_key = drop_pk();
+        assert_eq!(
+            contract.validate_create_account_options("bob.airdrop".parse().unwrap(), options),
+            Err(AirdropError::DuplicateKey(drop_pk()))
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid_contract_bytes() {
+        let contract = validation_contract();
+        let mut options = validation_options();
+        options.contract_bytes = Some(b"not wasm".to_vec());
+        assert_eq!(
+            contract.validate_create_account_options("bob.airdrop".parse().unwrap(), options),
+            Err(AirdropError::InvalidContractBytes)
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid_method_names() {
+        let contract = validation_contract();
+        for method_names in ["claim,", "claim, send", "cl-aim"] {
+            let mut options = validation_options();
+            options.limited_access_keys.as_mut().unwrap()[0].method_names = method_names.to_string();
+            assert_eq!(
+                contract.validate_create_account_options("bob.airdrop".parse().unwrap(), options),
+                Err(AirdropError::InvalidMethodNames(method_names.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract bytes are not a wasm module")]
+    fn test_create_advanced_account_validates_options() {
+        let mut contract = validation_contract();
+        let mut options = validation_options();
+        options.contract_bytes = Some(b"not wasm".to_vec());
+        contract.create_account_advanced("bob.airdrop".parse().unwrap(), options);
+    }
+
+    #[test]
+    fn test_estimate_claim_gas() {
+        let contract = AirDrop::new_default();
+        assert_eq!(contract.estimate_claim_gas(), Gas::from_tgas(20));
+    }
+
+    fn limited_key(seed: u8, method_names: &str) -> LimitedAccessKey {
+        LimitedAccessKey {
+            public_key: PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![seed; 32]).unwrap(),
+            allowance: UncToken::from_attounc(100),
+            receiver_id: airdrop(),
+            method_names: method_names.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_create_advanced_account_within_limits() {
+        let mut contract = validation_contract();
+        contract.set_create_account_limits(3, 8);
+        let options = CreateAccountOptions {
+            full_access_keys: Some(vec![drop_pk()]),
+            limited_access_keys: Some(vec![limited_key(1, "claim"), limited_key(2, "claim,send")]),
+            contract_bytes: Some(b"\0asm\x01\0\0\0".to_vec()),
+            ft_registrations: None,
+            keyless: None,
+        };
+        contract.create_account_advanced("bob.airdrop".parse().unwrap(), options);
+    }
+
+    fn ft_registration_options() -> CreateAccountOptions {
+        let mut options = advanced_options();
+        options.ft_registrations = Some(vec![token(), pool()]);
+        options
+    }
+
+    #[test]
+    fn test_create_account_advanced_ft_registrations() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        let options = ft_registration_options();
+        let registrations = FT_STORAGE_DEPOSIT.saturating_mul(2);
+        let cost = contract.get_account_creation_cost(Some(options.clone()));
+        assert_eq!(
+            cost.0,
+            contract.get_account_creation_cost(Some(advanced_options())).0 + registrations.as_attounc()
+        );
+
+        // The registration deposits are kept on the contract until the account is created
+        let deposit = UncToken::from_attounc(cost.0 + 1_000);
+        send_env(deposit);
+        contract.create_account_advanced(carol(), options.clone());
+        assert!(get_created_receipts().iter().any(|receipt| receipt.receiver_id == carol()
+            && receipt.actions.iter().any(|action| matches!(
+                action,
+                MockAction::Transfer { deposit: transfer, .. } if *transfer == deposit.saturating_sub(registrations)
+            ))));
+
+        callback_env(PromiseResult::Successful(vec![]));
+        assert!(contract.on_account_created_advanced(bob(), carol(), deposit, options, FT_STORAGE_DEPOSIT, 0));
+        assert_eq!(
+            function_calls(),
+            vec![
+                (token(), "storage_deposit".to_string(), FT_STORAGE_DEPOSIT),
+                (airdrop(), "on_ft_registered".to_string(), UncToken::from_attounc(0)),
+                (pool(), "storage_deposit".to_string(), FT_STORAGE_DEPOSIT),
+                (airdrop(), "on_ft_registered".to_string(), UncToken::from_attounc(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ft_registration_deposit_is_configurable() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        assert_eq!(contract.get_ft_registration_deposit(), U128(FT_STORAGE_DEPOSIT.as_attounc()));
+        contract.set_ft_registration_deposit(U128(5_000));
+        let cost = contract.get_account_creation_cost(Some(ft_registration_options()));
+        assert_eq!(cost.0, contract.get_account_creation_cost(Some(advanced_options())).0 + 10_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attached deposit must cover the FT registrations and the storage of the account")]
+    fn test_create_account_advanced_ft_registrations_deposit_too_low() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        let cost = contract.get_account_creation_cost(Some(ft_registration_options()));
+        send_env(UncToken::from_attounc(cost.0 - 1));
+        contract.create_account_advanced(carol(), ft_registration_options());
+    }
+
+    #[test]
+    fn test_validate_too_many_ft_registrations() {
+        let contract = validation_contract();
+        let mut options = validation_options();
+        options.ft_registrations = Some(vec![token(); MAX_FT_REGISTRATIONS as usize]);
+        let report = contract
+            .validate_create_account_options("bob.airdrop".parse().unwrap(), options.clone())
+            .unwrap();
+        assert_eq!(
+            report.estimated_gas,
+            CREATE_ACCOUNT_ADVANCED_GAS
+                .saturating_add(Gas::from_gas(8 * DEPLOY_GAS_PER_BYTE))
+                .saturating_add(Gas::from_tgas(15 * MAX_FT_REGISTRATIONS))
+        );
+
+        options.ft_registrations.as_mut().unwrap().push(pool());
+        assert_eq!(
+            contract.validate_create_account_options("bob.airdrop".parse().unwrap(), options),
+            Err(AirdropError::TooManyFtRegistrations(MAX_FT_REGISTRATIONS))
+        );
+    }
+
+    #[test]
+    fn test_ft_registration_failure_keeps_account() {
+        let mut contract = AirDrop::new_default();
+        callback_env(PromiseResult::Failed);
+        assert!(!contract.on_ft_registered(carol(), token(), FT_STORAGE_DEPOSIT));
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"airdrop","version":"1.0.0","event":"ft_registration_failed","data":{"account_id":"carol","ft_contract_id":"token"}}"#]
+        );
+        // The deposit goes to the new account
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].receiver_id, carol());
+        assert!(matches!(receipts[0].actions[..], [MockAction::Transfer { deposit, .. }] if deposit == FT_STORAGE_DEPOSIT));
+    }
+
+    #[test]
+    #[should_panic(expected = "is added more than once")]
+    fn test_create_advanced_account_duplicate_key() {
+        let mut contract = validation_contract();
+        let mut options = validation_options();
+        options.limited_access_keys = Some(vec![limited_key(1, "claim"), limited_key(1, "send")]);
+        contract.create_account_advanced("bob.airdrop".parse().unwrap(), options);
+    }
+
+    #[test]
+    fn test_validate_too_many_keys() {
+        let contract = validation_contract();
+        let mut options = validation_options();
+        options.limited_access_keys = Some((1..=DEFAULT_MAX_KEYS_PER_ACCOUNT as u8).map(|seed| limited_key(seed, "claim")).collect());
+        assert_eq!(
+            contract.validate_create_account_options("bob.airdrop".parse().unwrap(), options),
+            Err(AirdropError::TooManyKeys(DEFAULT_MAX_KEYS_PER_ACCOUNT))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "More than 2 keys")]
+    fn test_create_advanced_account_too_many_keys() {
+        let mut contract = validation_contract();
+        contract.set_create_account_limits(2, DEFAULT_MAX_CONTRACT_BYTES);
+        let mut options = validation_options();
+        options.limited_access_keys = Some(vec![limited_key(1, "claim"), limited_key(2, "claim")]);
+        contract.create_account_advanced("bob.airdrop".parse().unwrap(), options);
+    }
+
+    #[test]
+    fn test_validate_contract_too_large() {
+        let mut contract = validation_contract();
+        contract.set_create_account_limits(DEFAULT_MAX_KEYS_PER_ACCOUNT, 4);
+        assert_eq!(
+            contract.validate_create_account_options("bob.airdrop".parse().unwrap(), validation_options()),
+            Err(AirdropError::ContractTooLarge(4))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is larger than 4 bytes")]
+    fn test_create_advanced_account_contract_too_large() {
+        let mut contract = validation_contract();
+        contract.set_create_account_limits(DEFAULT_MAX_KEYS_PER_ACCOUNT, 4);
+        contract.create_account_advanced("bob.airdrop".parse().unwrap(), validation_options());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid method names: claim, send")]
+    fn test_create_advanced_account_method_names_with_spaces() {
+        let mut contract = validation_contract();
+        let mut options = validation_options();
+        options.limited_access_keys = Some(vec![limited_key(1, "claim, send")]);
+        contract.create_account_advanced("bob.airdrop".parse().unwrap(), options);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this method")]
+    fn test_set_create_account_limits_not_owner() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(bob())
+            .context.clone()
+        );
+        contract.set_create_account_limits(1, 1);
+    }
+
+    /// Every owner-only method, called with arguments that would succeed for the owner.
+    fn owner_calls() -> Vec<fn(&mut AirDrop)> {
+        vec![
+            |contract| { contract.force_claim(drop_pk(), bob()); },
+            |contract| { contract.rotate_key(drop_pk(), bob_pk()); },
+            |contract| { contract.move_balance(drop_pk(), bob_pk(), U128(1)); },
+            |contract| { contract.retry_create_account(carol()); },
+            |contract| { contract.add_operator(bob()); },
+            |contract| { contract.add_admin_key(bob_pk()); },
+            |contract| { contract.remove_admin_key(bob_pk()); },
+            |contract| { contract.add_relayer(bob()); },
+            |contract| contract.set_default_claim_methods(None),
+            |contract| contract.set_default_expiry_ns(None),
+            |contract| contract.set_max_keys(None),
+            |contract| contract.set_wrap_contract(None),
+            |contract| { contract.add_ft_contract(token()); },
+            |contract| { contract.remove_ft_contract(token()); },
+            |contract| { contract.withdraw_stranded_tokens(token(), bob()); },
+            |contract| contract.set_over_deposit_policy(OverDepositPolicy::CreditAll),
+            |contract| { contract.remove_relayer(bob()); },
+            |contract| { contract.withdraw_sponsor_pool(U128(0), bob()); },
+            |contract| { contract.remove_operator(bob()); },
+            |contract| contract.propose_owner(carol()),
+            |contract| contract.reset_key_attempts(drop_pk()),
+            |contract| contract.unlock_stale_claim(drop_pk()),
+            |contract| { contract.owner_reclaim(drop_pk(), bob()); },
+            |contract| contract.propose_sweep(bob()),
+            |contract| { contract.confirm_sweep(); },
+            |contract| contract.cancel_sweep(),
+            |contract| contract.set_claim_limits(None, None),
+            |contract| contract.set_claim_cooldown(0),
+            |contract| contract.set_min_drop_amount(U128(0)),
+            |contract| contract.set_fee_bps(0),
+            |contract| contract.set_funding_enabled(true),
+            |contract| contract.set_ft_registration_deposit(U128(0)),
+            |contract| contract.set_ed25519_allowance(U128(1)),
+            |contract| contract.set_secp256k1_allowance(U128(1)),
+            |contract| contract.set_max_claim_attempts(1),
+            |contract| contract.set_ft_storage_deposit(U128(0), Gas::from_tgas(1)),
+            |contract| { contract.migrate_chunk(1); },
+            |contract| contract.add_call_receiver(bob(), "account_id".to_string()),
+            |contract| { contract.remove_call_receiver(bob()); },
+            |contract| { contract.withdraw_fees(bob()); },
+            |contract| { contract.reclaim_all_expired(0, bob()); },
+            |contract| contract.set_create_account_limits(1, 1),
+            |contract| contract.set_required_suffix(None),
+            |contract| contract.set_source_metadata(None),
+            |contract| { contract.schedule_action(OwnerAction::SetOwner { new_owner: bob() }, u64::MAX); },
+            |contract| contract.execute_action(0),
+            |contract| contract.cancel_action(0),
+        ]
+    }
+
+    /// Every method the operators can call on top of the owner.
+    fn operator_calls() -> Vec<fn(&mut AirDrop)> {
+        vec![
+            |contract| { contract.block_account(bob()); },
+            |contract| { contract.unblock_account(bob()); },
+            |contract| contract.pause(),
+            |contract| contract.unpause(),
+        ]
+    }
+
+    fn panic_message(contract: &mut AirDrop, call: fn(&mut AirDrop)) -> String {
+        let error = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| call(contract)))
+            .expect_err("Call should have panicked");
+        match error.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => error.downcast_ref::<String>().cloned().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_owner_methods_require_one_atto() {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .context.clone()
+        );
+        let mut contract = AirDrop::new_default();
+        for call in owner_calls().into_iter().chain(operator_calls()) {
+            assert_eq!(
+                panic_message(&mut contract, call),
+                "Requires attached deposit of exactly 1 attoUNC"
+            );
+        }
+    }
+
+    #[test]
+    fn test_owner_methods_reject_other_callers() {
+        let mut contract = AirDrop::new_default();
+        owner_env();
+        for call in owner_calls() {
+            assert_eq!(
+                panic_message(&mut contract, call),
+                "Only the owner can call this method"
+            );
+        }
+        for call in operator_calls() {
+            assert_eq!(
+                panic_message(&mut contract, call),
+                "Only the owner or an operator can call this method"
+            );
+        }
+    }
+
+    fn operator_env(operator: AccountId) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(operator)
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+    }
+
+    #[test]
+    fn test_operator_permissions() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        assert!(contract.add_operator(carol()));
+        assert!(!contract.add_operator(carol()));
+        assert!(contract.is_operator(carol()));
+        assert_eq!(contract.get_operators(None, None), vec![carol()]);
+
+        // Operators can call the operator methods
+        operator_env(carol());
+        for call in operator_calls() {
+            call(&mut contract);
+        }
+        // But none of the owner-only ones
+        for call in owner_calls() {
+            assert_eq!(
+                panic_message(&mut contract, call),
+                "Only the owner can call this method"
+            );
+        }
+
+        // Strangers can call neither
+        operator_env(bob());
+        for call in operator_calls() {
+            assert_eq!(
+                panic_message(&mut contract, call),
+                "Only the owner or an operator can call this method"
+            );
+        }
+
+        owner_env();
+        assert!(contract.remove_operator(carol()));
+        assert!(!contract.is_operator(carol()));
+        operator_env(carol());
+        assert_eq!(
+            panic_message(&mut contract, |contract| contract.pause()),
+            "Only the owner or an operator can call this method"
+        );
+    }
+
+    #[test]
+    fn test_views_need_no_deposit() {
+        let contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(carol())
+            .context.clone()
+        );
+        assert_ne!(contract.get_owner_id(), &carol());
+        assert!(!contract.is_paused());
+        assert!(!contract.is_blocked(bob()));
+        assert!(contract.get_pending_sweep().is_none());
+        assert!(contract.get_claim_history(None, None).is_empty());
+        assert!(contract.get_key_information(drop_pk()).is_err());
+        contract.estimate_claim_gas();
+    }
+
+    fn drip_env(pk: &PublicKey, timestamp: u64) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .signer_account_id(airdrop())
+            .signer_account_pk(pk.clone())
+            .block_timestamp(timestamp)
+            .context.clone()
+        );
+    }
+
+    /// Creates a contract with a key releasing 2.5 allowances in drips of one allowance per 1000 ns.
+    /// The access key gets the allowance of 3 claim attempts, one per drip.
+    fn drip_contract() -> AirDrop {
+        let mut contract = AirDrop::new_default();
+        let balance = UncToken::from_attounc(ACCESS_KEY_ALLOWANCE.as_attounc() * 5 / 2);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(balance.saturating_add(CLAIM_ATTEMPT_ALLOWANCE.saturating_mul(3)).saturating_add(key_storage_stake()))
+            .context.clone()
+        );
+        contract.send_drip(drop_pk(), U128(ACCESS_KEY_ALLOWANCE.as_attounc()), 1_000, None);
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Key already has a balance, top it up with top_up")]
+    fn test_send_drip_funded_key() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        // Anyone could otherwise slow the claims of the key down to 1 attoUNC per interval
+        contract.send_drip(pk, U128(1), u64::MAX, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Drip amount is too small: the allowance of a claim per drip would take the whole deposit")]
+    fn test_send_drip_amount_too_small() {
+        let mut contract = AirDrop::new_default();
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(3));
+        contract.send_drip(drop_pk(), U128(1), 0, None);
+    }
+
+    #[test]
+    fn test_drip_claims() {
+        let mut contract = drip_contract();
+        let drip = ACCESS_KEY_ALLOWANCE.as_attounc();
+
+        drip_env(&drop_pk(), 5_000);
+        assert_eq!(contract.time_until_next_drip(drop_pk()), 0);
+        claim_resolved(&mut contract, bob());
+        assert_eq!(contract.get_key_balance(drop_pk()).as_attounc(), drip * 3 / 2);
+        assert_eq!(contract.time_until_next_drip(drop_pk()), 1_000);
+
+        drip_env(&drop_pk(), 6_000);
+        claim_resolved(&mut contract, bob());
+        assert_eq!(contract.get_key_balance(drop_pk()).as_attounc(), drip / 2);
+
+        // The last drip releases what is left and removes the key
+        drip_env(&drop_pk(), 7_000);
+        claim_resolved(&mut contract, bob());
+        assert!(contract.internal_key_balance(&drop_pk()).is_none());
+        assert!(contract.drips.get(&key_hash(&drop_pk())).is_none());
+        assert_eq!(contract.get_claim_history(None, None).len(), 3);
+    }
+
+    #[test]
+    fn test_max_per_claim() {
+        let mut contract = AirDrop::new_default();
+        let cap = UncToken::from_unc(1);
+        // 2.5 claims of the cap, plus the allowance of a claim attempt for each of the 3 claims
+        send_env(UncToken::from_milliunc(2_500).saturating_add(CLAIM_ATTEMPT_ALLOWANCE.saturating_mul(3)).saturating_add(key_storage_stake()));
+        contract.send_drip(drop_pk(), U128(cap.as_attounc()), 0, None);
+        assert_eq!(contract.allowances[&drop_pk()], CLAIM_ATTEMPT_ALLOWANCE.saturating_mul(3));
+        let info = contract.get_key_information(drop_pk()).unwrap();
+        assert_eq!(info.balance, U128(UncToken::from_milliunc(2_500).as_attounc()));
+        assert_eq!(info.max_per_claim, Some(U128(cap.as_attounc())));
+
+        let stake = contract.storage_stakes[&key_hash(&drop_pk())];
+        for (claim, (payout, remaining)) in [(cap, 1_500), (cap, 500), (UncToken::from_milliunc(500).saturating_add(stake), 0)]
+            .into_iter()
+            .enumerate()
+        {
+            drip_env(&drop_pk(), claim as u64);
+            contract.claim(bob());
+            assert!(get_created_receipts().iter().flat_map(|receipt| &receipt.actions).any(|action| matches!(
+                action,
+                MockAction::Transfer { deposit, .. } if *deposit == payout
+            )));
+            resolve_claim(&mut contract, PromiseResult::Successful(vec![]));
+            let receipts = get_created_receipts();
+            if remaining > 0 {
+                // The access key stays for the next claim
+                assert!(!receipts.iter().flat_map(|receipt| &receipt.actions).any(|action| matches!(
+                    action,
+                    MockAction::DeleteKey { .. }
+                )));
+                assert_eq!(contract.get_key_balance(drop_pk()), UncToken::from_milliunc(remaining));
+            }
+        }
+        // The last claim releases what is left, below the cap, and removes the key
+        assert!(!contract.key_exists(drop_pk()));
+        assert!(get_created_receipts().iter().any(|receipt| receipt
+            .actions
+            .iter()
+            .any(|action| matches!(action, MockAction::DeleteKey { .. }))));
+        assert_eq!(contract.get_claim_history(None, None).len(), 3);
+    }
+
+    /// Returns the attounc$ transferred to `account_id` by the receipts of the last call.
+    fn transferred_to(account_id: &AccountId) -> u128 {
+        get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == *account_id)
+            .flat_map(|receipt| receipt.actions)
+            .map(|action| match action {
+                MockAction::Transfer { deposit, .. } => deposit.as_attounc(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_simulate_claim() {
+        let mut contract = drip_contract();
+        drip_env(&drop_pk(), 1_000);
+        let simulation = contract.simulate_claim(drop_pk(), Some(bob())).unwrap();
+        assert_eq!(
+            simulation,
+            ClaimSimulation {
+                payout: U128(ACCESS_KEY_ALLOWANCE.as_attounc()),
+                requires_new_account: false,
+                errors: vec![],
+            }
+        );
+        contract.claim(bob());
+        assert_eq!(transferred_to(&bob()), simulation.payout.0);
+        resolve_claim(&mut contract, PromiseResult::Successful(vec![]));
+
+        // The next drip isn't due yet
+        drip_env(&drop_pk(), 1_500);
+        assert_eq!(
+            contract.simulate_claim(drop_pk(), None).unwrap().errors,
+            vec!["Drip interval has not elapsed yet".to_string()]
+        );
+
+        // The last claim pays out what is left with the storage stake
+        contract.blocked_accounts.insert(carol());
+        drip_env(&drop_pk(), 3_000);
+        claim_resolved(&mut contract, bob());
+        drip_env(&drop_pk(), 4_000);
+        let simulation = contract.simulate_claim(drop_pk(), Some(carol())).unwrap();
+        assert_eq!(simulation.errors, vec!["Account is blocked".to_string()]);
+        contract.claim(bob());
+        assert_eq!(transferred_to(&bob()), simulation.payout.0);
+        resolve_claim(&mut contract, PromiseResult::Successful(vec![]));
+        assert!(!contract.key_exists(drop_pk()));
+
+        assert_eq!(contract.simulate_claim(drop_pk(), None), Err(AirdropError::KeyMissing));
+    }
+
+    #[test]
+    fn test_simulate_claim_requires_new_account() {
+        let contract = restricted_drop_contract();
+        claim_env(&drop_pk(), 0);
+        let simulation = contract.simulate_claim(drop_pk(), None).unwrap();
+        assert!(simulation.requires_new_account);
+        assert_eq!(
+            simulation.payout,
+            U128(contract.get_key_balance(drop_pk()).saturating_add(contract.storage_stakes[&key_hash(&drop_pk())]).as_attounc())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Drip interval has not elapsed yet")]
+    fn test_drip_claim_too_early() {
+        let mut contract = drip_contract();
+        drip_env(&drop_pk(), 5_000);
+        claim_resolved(&mut contract, bob());
+
+        drip_env(&drop_pk(), 5_999);
+        claim_resolved(&mut contract, bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Drip keys can only be claimed with claim")]
+    fn test_drip_create_account_and_claim() {
+        let mut contract = drip_contract();
+        drip_env(&drop_pk(), 5_000);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+    }
+
+    #[test]
+    fn test_send_with_idempotency_key() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2).saturating_add(key_storage_stake()))
+            .context.clone()
+        );
+
+        contract.send(drop_pk(), None, Some("order-1".to_string()), None, None, None);
+        // The retry refunds the deposit instead of crediting it again
+        assert_eq!(
+            contract.send(drop_pk(), None, Some("order-1".to_string()), None, None, None),
+            U128(ACCESS_KEY_ALLOWANCE.as_attounc())
+        );
+        assert_eq!(contract.get_key_balance(drop_pk()), ACCESS_KEY_ALLOWANCE);
+
+        // Another idempotency key is a new send
+        contract.send(drop_pk(), None, Some("order-2".to_string()), None, None, None);
+        assert_eq!(
+            contract.get_key_balance(drop_pk()),
+            ACCESS_KEY_ALLOWANCE.saturating_mul(3).saturating_add(key_storage_stake())
+        );
+    }
+
+    #[test]
+    fn test_source_metadata() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        assert_eq!(
+            unc_sdk::serde_json::to_string(&contract.get_source_metadata()).unwrap(),
+            r#"{"version":"0.1.0","link":"https://github.com/zwong91/contracts-examples","standards":[{"standard":"nep330","version":"1.1.0"},{"standard":"nep452","version":"1.0.0"}]}"#
+        );
+
+        contract.set_source_metadata(Some("https://example.org/airdrop".to_string()));
+        assert_eq!(
+            contract.get_source_metadata().link.as_deref(),
+            Some("https://example.org/airdrop")
+        );
+    }
+
+    /// Returns the ABI generated by `unc_bindgen` for the methods of the contract.
+    #[cfg(feature = "abi")]
+    fn contract_abi() -> unc_sdk::serde_json::Value {
+        #[allow(improper_ctypes)]
+        extern "C" {
+            fn __unc_abi_new() -> (*const u8, usize);
+        }
+        let abi = unsafe {
+            let (ptr, len) = __unc_abi_new();
+            std::slice::from_raw_parts(ptr, len)
+        };
+        unc_sdk::serde_json::from_slice(abi).unwrap()
+    }
+
+    #[cfg(feature = "abi")]
+    #[test]
+    fn test_abi_methods() {
+        let abi = contract_abi();
+        let functions = abi["functions"].as_array().unwrap();
+        let names: Vec<&str> = functions.iter().map(|function| function["name"].as_str().unwrap()).collect();
+        for name in [
+            "new", "new_default", "ping", "migrate", "send", "claim", "send_drip", "time_until_next_drip", "force_claim", "rotate_key", "move_balance",
+            "send_with_code", "commit_code", "cancel_commitment", "claim_with_code", "cancel_pending_creation", "create_account_and_claim", "create_account",
+            "create_account_advanced", "retry_create_account", "on_account_created",
+            "on_account_created_advanced", "on_account_created_and_claimed", "create_merkle_drop",
+            "claim_merkle", "on_merkle_claimed", "refund_merkle_drop", "get_merkle_drop", "get_key_balance", "block_account",
+            "unblock_account", "is_blocked", "get_blocked", "propose_sweep", "confirm_sweep", "cancel_sweep",
+            "get_pending_sweep", "get_keys_by_tag", "set_claim_limits", "pause", "unpause", "is_paused",
+            "set_create_account_limits", "set_required_suffix", "get_claim_history", "estimate_claim_gas",
+            "get_required_deposit", "preview_batch_split", "get_account_creation_cost", "validate_create_account_options",
+            "get_source_metadata", "set_source_metadata", "get_owner_id", "get_key_information",
+            "get_keys_information", "key_exists", "set_claim_cooldown", "get_stats", "get_balance_breakdown", "get_keys_expiring_before", "reclaim_all_expired", "add_operator",
+            "remove_operator", "is_operator", "get_operators",
+            "ft_on_transfer", "claim_ft_with_registration", "on_ft_storage_deposited", "on_ft_transferred",
+            "claim_ft_call", "on_ft_transfer_called",
+            "get_key_tokens", "propose_owner",
+            "accept_ownership", "cancel_ownership_proposal", "get_pending_owner", "reset_key_attempts", "create_drop",
+            "send_to_drop", "pause_drop", "unpause_drop", "update_drop_metadata", "get_drop",
+            "transfer_drop_ownership", "get_drops_by_funder", "schedule_action", "execute_action", "cancel_action",
+            "get_scheduled_actions", "get_staged_code_hash", "can_claim", "owner_reclaim", "get_key_count", "set_min_drop_amount", "send_signed",
+            "claim_signed", "on_signed_claimed", "on_force_claimed", "unlock_stale_claim", "set_fee_bps", "get_fee_bps", "get_fees_collected", "withdraw_fees",
+            "create_accounts", "set_funding_enabled", "is_funding_enabled", "claim_and_call", "on_claim_called", "on_claimed",
+            "create_account_and_claim_derived", "on_staked", "has_claimed",
+            "add_call_receiver", "remove_call_receiver", "get_call_receivers",
+            "set_ft_registration_deposit", "get_ft_registration_deposit", "on_ft_registered",
+            "reserve_account", "refund_reservation", "get_reserved_name",
+            "set_ed25519_allowance", "set_secp256k1_allowance", "migrate_chunk", "get_recent_failures",
+            "set_max_claim_attempts", "get_max_claim_attempts", "set_ft_storage_deposit", "get_ft_storage_deposit",
+            "get_ft_storage_deposit_gas", "top_up", "sponsor_key", "add_relayer", "remove_relayer", "is_relayer",
+            "fund_sponsor_pool", "withdraw_sponsor_pool", "get_sponsor_pool", "claim_all",
+            "set_default_claim_methods", "get_default_claim_methods", "set_default_expiry_ns", "get_default_expiry_ns", "set_max_keys", "get_max_keys", "claim_wrapped", "on_wrapped", "on_wrapped_transferred", "set_wrap_contract", "get_wrap_contract", "add_admin_key", "remove_admin_key", "get_admin_keys", "set_over_deposit_policy", "get_over_deposit_policy", "get_key_methods", "export_claims", "get_drop_totals", "add_ft_contract", "remove_ft_contract", "is_ft_contract", "get_stranded_tokens", "withdraw_stranded_tokens", "on_tokens_released",
+            "simulate_claim", "get_storage_usage", "get_storage_cost",
+        ] {
+            assert!(names.contains(&name), "Missing method {}", name);
+        }
+        for name in CLAIM_METHODS {
+            assert!(names.contains(name), "Missing claim method {}", name);
+        }
+
+        let create_account_advanced = functions
+            .iter()
+            .find(|function| function["name"] == "create_account_advanced")
+            .unwrap();
+        assert_eq!(
+            create_account_advanced["params"]["args"],
+            unc_sdk::serde_json::json!([
+                { "name": "new_account_id", "type_schema": { "$ref": "#/definitions/AccountId" } },
+                { "name": "options", "type_schema": { "$ref": "#/definitions/CreateAccountOptions" } },
+            ])
+        );
+
+        let definitions = &abi["root_schema"]["definitions"];
+        for definition in ["CreateAccountOptions", "LimitedAccessKey", "KeyInfo", "KeyStatus", "OverDepositPolicy", "ValidationReport", "ClaimRecord"] {
+            assert!(definitions.get(definition).is_some(), "Missing definition of {}", definition);
+        }
+        assert_eq!(
+            definitions["LimitedAccessKey"]["required"],
+            unc_sdk::serde_json::json!(["allowance", "method_names", "public_key", "receiver_id"])
+        );
+    }
+
+    fn bob_pk() -> PublicKey {
+        PublicKey::from_parts(unc_sdk::CurveType::ED25519, vec![2; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_move_balance() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        let keys = fund_keys(&mut contract, 0..1);
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(4));
+        contract.top_up(keys[0].clone());
+        let balance = contract.get_key_balance(keys[0].clone());
+        let liability = contract.key_liability;
+        let half = U128(balance.as_attounc() / 2);
+        let moved = half.0 - ACCESS_KEY_ALLOWANCE.as_attounc() - key_storage_stake().as_attounc();
+
+        owner_env();
+        assert_eq!(contract.move_balance(keys[0].clone(), drop_pk(), half), U128(moved));
+        assert_eq!(contract.storage_stakes[&key_hash(&drop_pk())], key_storage_stake());
+        assert_eq!(contract.get_key_balance(keys[0].clone()).as_attounc(), balance.as_attounc() - half.0);
+        assert_eq!(contract.get_key_balance(drop_pk()).as_attounc(), moved);
+        // The allowance and the storage stake of the new key are taken from the moved amount
+        assert_eq!(contract.key_liability, liability.saturating_sub(ACCESS_KEY_ALLOWANCE).saturating_sub(key_storage_stake()));
+        assert_eq!(contract.get_key_count(), 2);
+        // The new key gets an access key with a fresh allowance
+        assert_eq!(contract.allowances[&drop_pk()], ACCESS_KEY_ALLOWANCE);
+        assert!(get_created_receipts().into_iter().flat_map(|receipt| receipt.actions).any(|action| {
+            matches!(action, MockAction::AddKeyWithFunctionCall { public_key, .. } if public_key.to_string() == String::from(&drop_pk()))
+        }));
+
+        // Moving to a funded key adds to its balance
+        owner_env();
+        assert_eq!(contract.move_balance(drop_pk(), keys[0].clone(), U128(1)), U128(balance.as_attounc() - half.0 + 1));
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.move_balance(drop_pk(), bob_pk(), U128(u128::MAX));
+            }),
+            format!("Source key has {} attoUNC, less than {}", moved - 1, u128::MAX)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount doesn't cover the allowance of the new access key")]
+    fn test_move_balance_below_allowance() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        let keys = fund_keys(&mut contract, 0..1);
+        owner_env();
+        contract.move_balance(keys[0].clone(), drop_pk(), U128(ACCESS_KEY_ALLOWANCE.as_attounc() / 2));
+    }
+
+    #[test]
+    fn test_move_whole_balance() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        let keys = fund_keys(&mut contract, 0..2);
+        let balance = contract.get_key_balance(keys[0].clone());
+        let storage_stake = contract.storage_stakes[&key_hash(&keys[0])];
+        let to_balance = contract.get_key_balance(keys[1].clone());
+
+        owner_env();
+        let new_balance = contract.move_balance(keys[0].clone(), keys[1].clone(), U128(balance.as_attounc()));
+        // The storage stake of the forgotten key moves with its balance
+        assert_eq!(new_balance.0, to_balance.as_attounc() + balance.as_attounc() + storage_stake.as_attounc());
+        assert!(contract.internal_key_balance(&keys[0]).is_none());
+        assert!(!contract.allowances.contains_key(&keys[0]));
+        assert_eq!(contract.get_key_count(), 1);
+        assert!(get_created_receipts().into_iter().flat_map(|receipt| receipt.actions).any(|action| {
+            matches!(action, MockAction::DeleteKey { public_key, .. } if public_key.to_string() == String::from(&keys[0]))
+        }));
+    }
+
+    #[test]
+    fn test_rotate_key() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+        contract.send(drop_pk(), Some("gold".to_string()), None, None, None, None);
+        let stake = contract.storage_stakes[&key_hash(&drop_pk())];
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(contract.owner_id.clone())
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+        contract.rotate_key(drop_pk(), bob_pk());
+
+        assert!(contract.get_key_information(drop_pk()).is_err());
+        let info = contract.get_key_information(bob_pk()).unwrap();
+        assert_eq!(info.balance, U128(ACCESS_KEY_ALLOWANCE.saturating_sub(stake).as_attounc()));
+        // The storage stake moves with the balance
+        assert!(contract.storage_stakes.get(&key_hash(&drop_pk())).is_none());
+        assert_eq!(contract.storage_stakes[&key_hash(&bob_pk())], stake);
+        assert_eq!(info.allowance_remaining, U128(ACCESS_KEY_ALLOWANCE.as_attounc()));
+        assert_eq!(info.tag.as_deref(), Some("gold"));
+        assert!(contract.allowances.get(&drop_pk()).is_none());
+    }
+
+    #[test]
+    fn test_rotate_key_moves_all_state() {
+        let mut contract = ft_contract();
+        contract.key_methods.insert(key_hash(&drop_pk()), "claim".to_string());
+        contract.internal_set_key_tag(&drop_pk(), "gold".to_string());
+        // The claims of the old key used part of its allowance
+        let allowance = contract.allowances[&drop_pk()];
+        contract.internal_set_allowance(&drop_pk(), allowance.saturating_sub(UncToken::from_attounc(100)));
+        let balance = contract.get_key_balance(drop_pk());
+
+        owner_env();
+        contract.rotate_key(drop_pk(), bob_pk());
+        assert!(contract.get_key_tokens(drop_pk()).is_none());
+        assert_eq!(contract.get_key_tokens(bob_pk()).unwrap().amount, U128(1_000));
+        assert_eq!(contract.get_key_methods(bob_pk()), Some("claim".to_string()));
+        assert_eq!(contract.get_keys_by_tag("gold".to_string(), None, None), vec![bob_pk()]);
+        // The new access key gets a full allowance, topped up from the balance
+        assert_eq!(contract.allowances[&bob_pk()], allowance);
+        assert_eq!(contract.get_key_balance(bob_pk()), balance.saturating_sub(UncToken::from_attounc(100)));
+        assert_eq!(contract.reserved_allowances, allowance);
+    }
+
+    #[test]
+    #[should_panic(expected = "New public key already has a balance")]
+    fn test_rotate_key_to_funded_key() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+        contract.send(bob_pk(), None, None, None, None, None);
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(contract.owner_id.clone())
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+        contract.rotate_key(drop_pk(), bob_pk());
+    }
+
+    #[test]
+    fn test_get_keys_information() {
+        let mut contract = AirDrop::new_default();
+        let keys = fund_keys(&mut contract, 0..2);
+        let missing = drop_pk();
+
+        let infos = contract.get_keys_information(vec![
+            keys[1].clone(),
+            missing.clone(),
+            keys[0].clone(),
+            keys[1].clone(),
+        ]);
+        assert_eq!(infos.len(), 4);
+        assert_eq!(infos[0].as_ref().unwrap().balance, U128(ACCESS_KEY_ALLOWANCE.as_attounc()));
+        assert!(infos[1].is_none());
+        assert!(infos[2].is_some());
+        assert_eq!(infos[3].as_ref().unwrap().balance, infos[0].as_ref().unwrap().balance);
+
+        assert!(contract.key_exists(keys[0].clone()));
+        assert!(!contract.key_exists(missing));
+    }
+
+    #[test]
+    #[should_panic(expected = "At most 100 keys can be queried at once")]
+    fn test_get_keys_information_over_limit() {
+        let contract = AirDrop::new_default();
+        contract.get_keys_information(vec![drop_pk(); MAX_KEYS_PER_QUERY + 1]);
+    }
+
+    /// Creates a contract with a claim cooldown of 1000 ns and a key funded at timestamp 5000.
+    fn cooldown_contract() -> AirDrop {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.set_claim_cooldown(1_000);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .block_timestamp(5_000)
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+        contract
+    }
+
+    #[test]
+    fn test_can_claim() {
+        let mut contract = cooldown_contract();
+        drip_env(&drop_pk(), 6_000);
+        assert!(contract.can_claim(drop_pk()));
+        assert!(!contract.can_claim(bob_pk()));
+
+        contract.paused = true;
+        assert!(!contract.can_claim(drop_pk()));
+        contract.paused = false;
+
+        contract.failed_attempts.insert(key_hash(&drop_pk()), MAX_FAILED_CLAIM_ATTEMPTS);
+        assert!(!contract.can_claim(drop_pk()));
+        contract.failed_attempts.remove(&key_hash(&drop_pk()));
+
+        contract.key_expirations.insert(drop_pk(), 6_000);
+        assert!(!contract.can_claim(drop_pk()));
+        contract.key_expirations.remove(&drop_pk());
+        assert!(contract.can_claim(drop_pk()));
+    }
+
+    #[test]
+    fn test_cooldown_skips_keys_funded_before() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        // Funded at 0, before the cooldown is set
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .block_timestamp(2_000)
+            .context.clone()
+        );
+        contract.set_claim_cooldown(1_000);
+        drip_env(&pk, 2_000);
+        assert!(contract.can_claim(pk));
+    }
+
+    #[test]
+    fn test_can_claim_during_cooldown() {
+        let contract = cooldown_contract();
+        drip_env(&drop_pk(), 5_999);
+        assert!(!contract.can_claim(drop_pk()));
+    }
+
+    #[test]
+    fn test_can_claim_outside_drop_window() {
+        let (mut contract, _, open_drop) = campaign_contract();
+        drip_env(&drop_pk(), 999);
+        assert!(!contract.can_claim(drop_pk()));
+        drip_env(&drop_pk(), 1_000);
+        assert!(contract.can_claim(drop_pk()));
+        drip_env(&drop_pk(), 2_000);
+        assert!(!contract.can_claim(drop_pk()));
+
+        contract.drop_configs.get_mut(&open_drop).unwrap().paused = true;
+        assert!(!contract.can_claim(bob_pk()));
+    }
+
+    #[test]
+    fn test_can_claim_between_drips() {
+        let mut contract = drip_contract();
+        drip_env(&drop_pk(), 5_000);
+        claim_resolved(&mut contract, bob());
+        drip_env(&drop_pk(), 5_999);
+        assert!(!contract.can_claim(drop_pk()));
+        drip_env(&drop_pk(), 6_000);
+        assert!(contract.can_claim(drop_pk()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Key not yet claimable")]
+    fn test_claim_during_cooldown() {
+        let mut contract = cooldown_contract();
+        drip_env(&drop_pk(), 5_999);
+        claim_resolved(&mut contract, bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Key not yet claimable")]
+    fn test_create_account_and_claim_during_cooldown() {
+        let mut contract = cooldown_contract();
+        drip_env(&drop_pk(), 5_999);
+        contract.create_account_and_claim(bob(), bob_pk(), None, None);
+    }
+
+    #[test]
+    fn test_claim_after_cooldown() {
+        let mut contract = cooldown_contract();
+        drip_env(&drop_pk(), 6_000);
+        claim_resolved(&mut contract, bob());
+        assert!(!contract.key_exists(drop_pk()));
+        assert!(contract.funded_at.get(&key_hash(&drop_pk())).is_none());
+    }
+
+    fn claim_callback_env(pk: &PublicKey, promise_result: PromiseResult) {
+        testing_env!(
+            VMContextBuilder::new()
+                .current_account_id(airdrop())
+                .predecessor_account_id(airdrop())
+                .signer_account_id(airdrop())
+                .signer_account_pk(pk.clone())
+                .context.clone(),
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![promise_result]
+        );
+    }
+
+    #[test]
+    fn test_stats_send_claim_refund() {
+        let mut contract = AirDrop::new_default();
+        let deposit = ACCESS_KEY_ALLOWANCE.saturating_mul(2).saturating_add(key_storage_stake());
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(deposit)
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+        contract.send(bob_pk(), None, None, None, None, None);
+        let stats = contract.get_stats();
+        assert_eq!(stats.total_drops_created, 2);
+        assert_eq!(stats.total_unc_dropped, U128(ACCESS_KEY_ALLOWANCE.saturating_mul(2).as_attounc()));
+
+        claim_env(&drop_pk(), 0);
+        claim_resolved(&mut contract, bob());
+        let stats = contract.get_stats();
+        assert_eq!(stats.total_drops_claimed, 1);
+        assert_eq!(stats.total_unc_claimed, U128(ACCESS_KEY_ALLOWANCE.as_attounc()));
+        assert_eq!(stats.total_accounts_created, 0);
+
+        // A failed account creation puts the balance back without counting a claim
+        claim_env(&bob_pk(), 0);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        claim_callback_env(&bob_pk(), PromiseResult::Failed);
+        assert_eq!(
+            contract.on_account_created_and_claimed(carol(), ACCESS_KEY_ALLOWANCE, None),
+            ClaimAmountOutcome::Claimed(U128(0))
+        );
+        assert_eq!(contract.get_stats().total_drops_claimed, 1);
+        assert_eq!(contract.get_stats().total_unc_claimed, U128(ACCESS_KEY_ALLOWANCE.as_attounc()));
+        assert_eq!(contract.get_stats().total_accounts_created, 0);
+
+        claim_env(&bob_pk(), 0);
+        contract.create_account_and_claim(carol(), drop_pk(), None, None);
+        claim_callback_env(&bob_pk(), PromiseResult::Successful(vec![]));
+        assert_eq!(
+            contract.on_account_created_and_claimed(carol(), ACCESS_KEY_ALLOWANCE, None),
+            ClaimAmountOutcome::Claimed(U128(ACCESS_KEY_ALLOWANCE.as_attounc()))
+        );
+        let stats = contract.get_stats();
+        assert_eq!(stats.total_drops_claimed, 2);
+        assert_eq!(stats.total_unc_claimed, U128(ACCESS_KEY_ALLOWANCE.saturating_mul(2).as_attounc()));
+        assert_eq!(stats.total_accounts_created, 1);
+
+        merkle_drop_env(0);
+        let (root, bob_proof, _) = merkle_tree();
+        let drop_id = contract.create_merkle_drop(root, U128(300), 2, Some(1_000));
+        contract.claim_merkle(drop_id, bob(), U128(100), bob_proof);
+        let index = bob_merkle_index();
+        callback_env(PromiseResult::Successful(vec![]));
+        contract.on_merkle_claimed(drop_id, bob(), bob(), index, U128(100));
+        merkle_drop_env(1_000);
+        contract.refund_merkle_drop(drop_id);
+        let stats = contract.get_stats();
+        assert_eq!(stats.total_drops_created, 3);
+        assert_eq!(stats.total_drops_claimed, 2);
+        assert_eq!(stats.total_drops_refunded, 1);
+        assert_eq!(stats.total_accounts_created, 1);
+        assert_eq!(stats.total_unc_dropped, U128(ACCESS_KEY_ALLOWANCE.saturating_mul(2).as_attounc() + 300));
+        assert_eq!(stats.total_unc_claimed, U128(ACCESS_KEY_ALLOWANCE.saturating_mul(2).as_attounc() + 100));
+    }
+
+    #[test]
+    fn test_failures_recorded_by_callbacks() {
+        let mut contract = AirDrop::new_default();
+        let amount = UncToken::from_attounc(1_000_000);
+        callback_env(PromiseResult::Successful(vec![]));
+        contract.on_account_created(bob(), carol(), amount);
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_callback_env(&pk, PromiseResult::Successful(vec![]));
+        contract.on_claim_called(carol(), amount);
+        // Success paths write nothing
+        assert!(contract.get_recent_failures(None, None).is_empty());
+        assert!(get_logs().is_empty());
+
+        callback_env(PromiseResult::Failed);
+        contract.on_account_created(bob(), carol(), amount);
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"airdrop","version":"1.0.0","event":"operation_failed","data":{"kind":"account_creation","public_key":null,"account_id":"carol","amount":"1000000"}}"#]
+        );
+        claim_callback_env(&pk, PromiseResult::Failed);
+        contract.on_account_created_and_claimed(carol(), ACCESS_KEY_ALLOWANCE, None);
+        claim_callback_env(&pk, PromiseResult::Failed);
+        contract.on_claim_called(bob(), amount);
+        claim_callback_env(&pk, PromiseResult::Failed);
+        contract.on_claimed(carol(), amount, pk.clone(), 0);
+
+        let failures = contract.get_recent_failures(None, None);
+        assert_eq!(
+            failures.iter().map(|failure| failure.kind).collect::<Vec<_>>(),
+            vec![
+                FailureKind::Claim,
+                FailureKind::ClaimAndCall,
+                FailureKind::CreateAccountAndClaim,
+                FailureKind::AccountCreation,
+            ]
+        );
+        assert_eq!(failures[0].public_key, Some(pk.clone()));
+        assert_eq!(failures[0].account_id, Some(carol()));
+        assert_eq!(failures[1].account_id, Some(bob()));
+        assert_eq!(failures[2].public_key, Some(pk));
+        assert_eq!(failures[2].amount, U128(ACCESS_KEY_ALLOWANCE.as_attounc()));
+        assert_eq!(failures[3].public_key, None);
+    }
+
+    #[test]
+    fn test_failure_records_wrap() {
+        let mut contract = AirDrop::new_default();
+        for amount in 0..MAX_FAILURE_RECORDS + 2 {
+            // A fresh context every 50 records, since each one is logged
+            if amount % 50 == 0 {
+                testing_env!(VMContextBuilder::new().current_account_id(airdrop()).context.clone());
+            }
+            contract.internal_record_failure(FailureKind::AccountCreation, None, None, UncToken::from_attounc(amount.into()));
+        }
+        assert_eq!(contract.failures.len() as u64, MAX_FAILURE_RECORDS);
+        let amounts = |failures: Vec<&FailureRecord>| failures.iter().map(|failure| failure.amount.0).collect::<Vec<_>>();
+        let max = u128::from(MAX_FAILURE_RECORDS);
+        assert_eq!(amounts(contract.get_recent_failures(None, Some(3))), vec![max + 1, max, max - 1]);
+        assert_eq!(contract.get_recent_failures(None, None).len() as u64, DEFAULT_PAGE_LIMIT);
+        // The two oldest records were overwritten
+        let oldest = contract.get_recent_failures(Some(MAX_FAILURE_RECORDS - 2), None);
+        assert_eq!(amounts(oldest), vec![3, 2]);
+        assert!(contract.get_recent_failures(Some(MAX_FAILURE_RECORDS), None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Arithmetic overflow in total dropped")]
+    fn test_stats_overflow() {
+        let mut contract = AirDrop::new_default();
+        contract.stats.total_unc_dropped = U128(u128::MAX);
+        fund_keys(&mut contract, 0..1);
+    }
+
+    #[test]
+    fn test_stats_count_only_successful_creations() {
+        let mut contract = AirDrop::new_default();
+        let amount = UncToken::from_attounc(1_000_000);
+        callback_env(PromiseResult::Failed);
+        contract.on_account_created(bob(), carol(), amount);
+        contract.on_account_created_advanced(bob(), carol(), amount, advanced_options(), FT_STORAGE_DEPOSIT, 0);
+        assert_eq!(contract.get_stats().total_accounts_created, 0);
+
+        callback_env(PromiseResult::Successful(vec![]));
+        contract.on_account_created(bob(), carol(), amount);
+        contract.on_account_created_advanced(bob(), carol(), amount, advanced_options(), FT_STORAGE_DEPOSIT, 0);
+        assert_eq!(contract.get_stats().total_accounts_created, 2);
+    }
+
+    #[test]
+    fn test_stats_survive_migrate() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.stats.total_drops_created = 7;
+        contract.stats.total_unc_claimed = U128(42);
+        env::state_write(&contract);
+
+        let contract = AirDrop::migrate();
+        assert_eq!(contract.get_stats().total_drops_created, 7);
+        assert_eq!(contract.get_stats().total_unc_claimed, U128(42));
+    }
+
+    fn expiring_contract() -> AirDrop {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .block_timestamp(1_000)
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, Some(2_000), None, None);
+        contract.send(bob_pk(), None, None, Some(3_000), None, None);
+        contract.send(limited_key(3, "").public_key, None, None, Some(4_000), None, None);
+        contract
+    }
+
+    #[test]
+    fn test_get_keys_expiring_before() {
+        let contract = expiring_contract();
+        let stake = contract.storage_stakes[&key_hash(&drop_pk())];
+        let balance = U128(ACCESS_KEY_ALLOWANCE.saturating_sub(stake).as_attounc());
+        let mut expiring = contract.get_keys_expiring_before(3_500, None, None);
+        expiring.sort_by_key(|(key, _)| String::from(key));
+        let mut expected = vec![(drop_pk(), balance), (bob_pk(), balance)];
+        expected.sort_by_key(|(key, _)| String::from(key));
+        assert_eq!(expiring, expected);
+
+        assert_eq!(contract.get_keys_expiring_before(2_000, None, None), vec![]);
+        assert_eq!(contract.get_keys_expiring_before(5_000, Some(1), Some(1)).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Key has expired")]
+    fn test_claim_expired_key() {
+        let mut contract = expiring_contract();
+        drip_env(&drop_pk(), 2_000);
+        claim_resolved(&mut contract, bob());
+    }
+
+    #[test]
+    fn test_claim_forgets_expiration() {
+        let mut contract = expiring_contract();
+        drip_env(&drop_pk(), 1_999);
+        claim_resolved(&mut contract, bob());
+        assert_eq!(contract.get_keys_expiring_before(u64::MAX, None, None).len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expiration must be in the future")]
+    fn test_send_expiration_in_the_past() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .block_timestamp(1_000)
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, Some(1_000), None, None);
+    }
+
+    fn drop_env(predecessor: AccountId, timestamp: u64) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(predecessor)
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .block_timestamp(timestamp)
+            .context.clone()
+        );
+    }
+
+    /// Creates a drop of bob claimable from 1000 to 2000 with `drop_pk`, and a drop of carol without window
+    /// with `bob_pk`.
+    fn campaign_contract() -> (AirDrop, u64, u64) {
+        drop_env(bob(), 0);
+        let mut contract = AirDrop::new_default();
+        let window_drop = contract.create_drop(Some("spring".to_string()), Some(1_000), Some(2_000), None, None, None);
+        contract.send_to_drop(window_drop, drop_pk());
+        drop_env(carol(), 0);
+        let open_drop = contract.create_drop(None, None, None, None, None, None);
+        contract.send_to_drop(open_drop, bob_pk());
+        (contract, window_drop, open_drop)
+    }
+
+    #[test]
+    #[should_panic(expected = "Drop has not started yet")]
+    fn test_drop_claim_before_start() {
+        let (mut contract, _, _) = campaign_contract();
+        drip_env(&drop_pk(), 999);
+        claim_resolved(&mut contract, bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Drop has ended")]
+    fn test_drop_claim_at_end() {
+        let (mut contract, _, _) = campaign_contract();
+        drip_env(&drop_pk(), 2_000);
+        claim_resolved(&mut contract, bob());
+    }
+
+    #[test]
+    #[should_panic(expected = "Drop is paused")]
+    fn test_paused_drop_claim() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        drop_env(bob(), 1_000);
+        contract.pause_drop(window_drop);
+        drip_env(&drop_pk(), 1_000);
+        contract.create_account_and_claim(carol(), bob_pk(), None, None);
+    }
+
+    #[test]
+    fn test_drop_claim_in_window() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        drip_env(&drop_pk(), 1_000);
+        claim_resolved(&mut contract, bob());
+        let drop = contract.get_drop(window_drop).unwrap();
+        assert_eq!(drop.keys_added, 1);
+        assert_eq!(drop.keys_claimed, 1);
+        assert_eq!(drop.remaining, UncToken::from_attounc(0));
+    }
+
+    #[test]
+    fn test_paused_drop_doesnt_affect_other_drops() {
+        let (mut contract, window_drop, open_drop) = campaign_contract();
+        drop_env(bob(), 0);
+        contract.pause_drop(window_drop);
+        drip_env(&bob_pk(), 0);
+        claim_resolved(&mut contract, bob());
+        assert_eq!(contract.get_drop(open_drop).unwrap().keys_claimed, 1);
+
+        drop_env(bob(), 0);
+        contract.unpause_drop(window_drop);
+        assert!(!contract.get_drop(window_drop).unwrap().paused);
+    }
+
+    #[test]
+    fn test_drop_counters_and_metadata() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        drop_env(bob(), 0);
+        contract.send_to_drop(window_drop, drop_pk());
+        contract.send_to_drop(window_drop, limited_key(3, "").public_key);
+        contract.update_drop_metadata(window_drop, Some("summer".to_string()));
+        let drop = contract.get_drop(window_drop).unwrap();
+        assert_eq!(drop.funder, bob());
+        assert_eq!(drop.metadata, Some("summer".to_string()));
+        assert_eq!((drop.starts_at, drop.ends_at), (Some(1_000), Some(2_000)));
+        assert_eq!(drop.keys_added, 2);
+        // The top-up credits the whole deposit, the new keys the deposit without the allowance and storage stake
+        let stakes = contract.storage_stakes[&key_hash(&drop_pk())]
+            .saturating_add(contract.storage_stakes[&key_hash(&limited_key(3, "").public_key)]);
+        assert_eq!(drop.remaining, ACCESS_KEY_ALLOWANCE.saturating_mul(4).saturating_sub(stakes));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the drop can call this method")]
+    fn test_pause_drop_not_funder() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        drop_env(carol(), 0);
+        contract.pause_drop(window_drop);
+    }
+
+    #[test]
+    #[should_panic(expected = "Key belongs to another drop")]
+    fn test_send_to_drop_key_of_other_drop() {
+        let (mut contract, _, open_drop) = campaign_contract();
+        drop_env(carol(), 0);
+        contract.send_to_drop(open_drop, drop_pk());
+    }
+
+    #[test]
+    fn test_balance_breakdown() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .account_balance(UncToken::from_unc(100))
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(3))
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+        let breakdown = contract.get_balance_breakdown();
+        // The attached deposit is part of the balance
+        assert_eq!(
+            breakdown.account_balance,
+            U128(UncToken::from_unc(100).saturating_add(ACCESS_KEY_ALLOWANCE.saturating_mul(3)).as_attounc())
+        );
+        assert_eq!(
+            breakdown.locked,
+            U128(env::storage_byte_cost().saturating_mul(u128::from(env::storage_usage())).as_attounc())
+        );
+        let stake = key_storage_stake();
+        assert_eq!(breakdown.key_liability, U128(ACCESS_KEY_ALLOWANCE.saturating_mul(2).saturating_sub(stake).as_attounc()));
+        assert_eq!(breakdown.reserved_allowances, U128(ACCESS_KEY_ALLOWANCE.as_attounc()));
+
+        // Topping the key up only adds to the liability
+        contract.send(drop_pk(), None, None, None, None, None);
+        let breakdown = contract.get_balance_breakdown();
+        assert_eq!(breakdown.key_liability, U128(ACCESS_KEY_ALLOWANCE.saturating_mul(5).saturating_sub(stake).as_attounc()));
+        assert_eq!(breakdown.reserved_allowances, U128(ACCESS_KEY_ALLOWANCE.as_attounc()));
+
+        claim_env(&drop_pk(), 0);
+        claim_resolved(&mut contract, bob());
+        let breakdown = contract.get_balance_breakdown();
+        assert_eq!(breakdown.key_liability, U128(0));
+        assert_eq!(breakdown.reserved_allowances, U128(0));
+    }
+
+    #[test]
+    fn test_storage_usage_grows_on_send() {
+        let mut contract = AirDrop::new_default();
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(2));
+        let usage = contract.get_storage_usage();
+        contract.send(drop_pk(), Some("gold".to_string()), None, None, None, None);
+        assert!(contract.get_storage_usage() > usage);
+        assert_eq!(
+            contract.get_storage_cost(),
+            U128(env::storage_byte_cost().as_attounc() * u128::from(contract.get_storage_usage()))
+        );
+    }
+
+    fn transfer_env(predecessor: AccountId) {
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(predecessor)
+            .attached_deposit(UncToken::from_attounc(1))
+            .context.clone()
+        );
+    }
+
+    #[test]
+    fn test_transfer_drop_ownership() {
+        let (mut contract, window_drop, open_drop) = campaign_contract();
+        assert_eq!(contract.get_drops_by_funder(bob()), vec![window_drop]);
+        assert_eq!(contract.get_drops_by_funder(carol()), vec![open_drop]);
+
+        transfer_env(bob());
+        contract.transfer_drop_ownership(window_drop, carol());
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"airdrop","version":"1.0.0","event":"drop_ownership_transferred","data":{"drop_id":0,"old_funder":"bob","new_funder":"carol"}}"#]
+        );
+        assert_eq!(contract.get_drop(window_drop).unwrap().funder, carol());
+        assert!(contract.get_drops_by_funder(bob()).is_empty());
+        assert_eq!(contract.get_drops_by_funder(carol()), vec![open_drop, window_drop]);
+
+        // The new funder can manage the drop right away
+        transfer_env(carol());
+        contract.pause_drop(window_drop);
+        assert!(contract.get_drop(window_drop).unwrap().paused);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the drop can call this method")]
+    fn test_old_funder_loses_rights_after_transfer() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        transfer_env(bob());
+        contract.transfer_drop_ownership(window_drop, carol());
+        contract.pause_drop(window_drop);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the funder of the drop can call this method")]
+    fn test_transfer_drop_ownership_not_funder() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        transfer_env(carol());
+        contract.transfer_drop_ownership(window_drop, carol());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot transfer a drop to the contract")]
+    fn test_transfer_drop_ownership_to_contract() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        transfer_env(bob());
+        contract.transfer_drop_ownership(window_drop, airdrop());
+    }
+
+    #[test]
+    #[should_panic(expected = "Account is blocked")]
+    fn test_transfer_drop_ownership_to_blocked_account() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        contract.blocked_accounts.insert(carol());
+        transfer_env(bob());
+        contract.transfer_drop_ownership(window_drop, carol());
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 attoUNC")]
+    fn test_transfer_drop_ownership_without_one_atto() {
+        let (mut contract, window_drop, _) = campaign_contract();
+        drop_env(bob(), 0);
+        contract.transfer_drop_ownership(window_drop, carol());
+    }
+
+    #[test]
+    fn test_auto_withdraw_refunds_freed_storage() {
+        drop_env(bob(), 0);
+        let mut contract = AirDrop::new_default();
+        let drop_id = contract.create_drop(None, None, None, Some(true), None, None);
+        contract.send_to_drop(drop_id, drop_pk());
+        contract.send_to_drop(drop_id, bob_pk());
+        contract.drop_configs.flush();
+        contract.drops_by_funder.flush();
+
+        // The drop stays while one of its keys is left
+        drip_env(&drop_pk(), 0);
+        claim_resolved(&mut contract, bob());
+        assert!(contract.get_drop(drop_id).is_some());
+        assert!(get_logs().is_empty());
+
+        // Measured once the env is set up, which resets the storage usage
+        drip_env(&bob_pk(), 0);
+        let storage_before = env::storage_usage();
+        claim_resolved(&mut contract, bob());
+        let freed_bytes = storage_before - env::storage_usage();
+        assert!(freed_bytes > 0);
+        assert!(contract.get_drop(drop_id).is_none());
+        assert!(contract.get_drops_by_funder(bob()).is_empty());
+        // The claim index of the drop goes with it
+        assert!(!contract.drop_claims.contains_key(&(drop_id, 0)));
+        assert!(!contract.drop_claims.contains_key(&(drop_id, 1)));
+        let refunded = env::storage_byte_cost().saturating_mul(freed_bytes.into());
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"airdrop","version":"1.0.0","event":"drop_completed","data":{{"drop_id":0,"funder":"bob","refunded":"{}"}}}}"#,
+                refunded.as_attounc()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_drop_without_auto_withdraw_kept() {
+        let (mut contract, _, open_drop) = campaign_contract();
+        drip_env(&bob_pk(), 0);
+        claim_resolved(&mut contract, bob());
+        assert_eq!(contract.get_drop(open_drop).unwrap().keys_left(), 0);
+        assert!(get_logs().is_empty());
+    }
+
+    fn implicit_account() -> AccountId {
+        "0a1b2c3d4e5f6789abcdef0123456789abcdef0123456789abcdef0123456789".parse().unwrap()
+    }
+
+    #[test]
+    fn test_claim_to_implicit_account() {
+        let mut contract = AirDrop::new_default();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+
+        claim_env(&drop_pk(), 0);
+        claim_resolved(&mut contract, implicit_account());
+        assert!(!contract.key_exists(drop_pk()));
+        assert_eq!(contract.get_claim_history(None, None)[0].account_id, implicit_account());
+    }
+
+    #[test]
+    #[should_panic(expected = "Implicit accounts can't be created, claim to them instead")]
+    fn test_create_implicit_account_and_claim() {
+        let mut contract = AirDrop::new_default();
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_env(&pk, 0);
+        contract.create_account_and_claim(implicit_account(), bob_pk(), None, None);
+    }
+
+    #[test]
+    fn test_is_implicit_account_id() {
+        assert!(is_implicit_account_id(&implicit_account()));
+        assert!(!is_implicit_account_id(&bob()));
+        // 64 characters, but not all hex
+        assert!(!is_implicit_account_id(&"g".repeat(64).parse().unwrap()));
+        assert!(!is_implicit_account_id(&"a".repeat(63).parse().unwrap()));
+    }
+
+    #[test]
+    fn test_reclaim_all_expired() {
+        let mut contract = expiring_contract();
+        contract.owner_id = airdrop();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .block_timestamp(3_000)
+            .context.clone()
+        );
+        // The keys expiring at 2000 and 3000 are reclaimed, the one expiring at 4000 is left
+        assert_eq!(contract.reclaim_all_expired(10, carol()), 2);
+        assert!(!contract.key_exists(drop_pk()));
+        assert!(!contract.key_exists(bob_pk()));
+        assert!(contract.key_exists(limited_key(3, "").public_key));
+        assert_eq!(contract.get_stats().total_drops_refunded, 2);
+        let stake = contract.storage_stakes[&key_hash(&limited_key(3, "").public_key)];
+        assert_eq!(
+            contract.get_balance_breakdown().key_liability,
+            U128(ACCESS_KEY_ALLOWANCE.saturating_sub(stake).as_attounc())
+        );
+        assert_eq!(contract.reclaim_all_expired(10, carol()), 0);
+    }
+
+    #[test]
+    fn test_reclaim_all_expired_limit() {
+        let mut contract = expiring_contract();
+        contract.owner_id = airdrop();
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .block_timestamp(5_000)
+            .context.clone()
+        );
+        assert_eq!(contract.reclaim_all_expired(1, carol()), 1);
+        assert_eq!(contract.get_keys_expiring_before(u64::MAX, None, None).len(), 2);
+    }
+
+    #[test]
+    fn test_reclaim_all_expired_skips_unexpired() {
+        let mut contract = expiring_contract();
+        contract.owner_id = airdrop();
+        // The first key with an expiration no longer expires before the others
+        contract.key_expirations.insert(drop_pk(), 5_000);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .block_timestamp(1_500)
+            .context.clone()
+        );
+        assert_eq!(contract.reclaim_all_expired(1, carol()), 0);
+        assert!(get_created_receipts().is_empty());
+
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .block_timestamp(3_000)
+            .context.clone()
+        );
+        assert_eq!(contract.reclaim_all_expired(1, carol()), 1);
+        assert!(!contract.key_exists(bob_pk()));
+        let amount = transferred_to(&carol());
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"airdrop","version":"1.0.0","event":"reclaimed","data":{{"public_key":"{}","to":"carol","amount":"{}"}}}}"#,
+                String::from(&bob_pk()),
+                amount
+            )]
+        );
+    }
+
+    #[test]
+    fn test_reclaim_completes_auto_withdraw_drop() {
+        drop_env(bob(), 0);
+        let mut contract = AirDrop::new_default();
+        contract.owner_id = airdrop();
+        let drop_id = contract.create_drop(None, None, None, Some(true), None, None);
+        contract.send_to_drop(drop_id, drop_pk());
+        contract.key_expirations.insert(drop_pk(), 1_000);
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(airdrop())
+            .attached_deposit(UncToken::from_attounc(1))
+            .block_timestamp(1_000)
+            .context.clone()
+        );
+        assert_eq!(contract.reclaim_all_expired(10, carol()), 1);
+        assert!(contract.get_drop(drop_id).is_none());
+    }
+
+    #[test]
+    fn test_export_claims_and_drop_totals() {
+        drop_env(bob(), 0);
+        let mut contract = AirDrop::new_default();
+        contract.owner_id = airdrop();
+        let drop_id = contract.create_drop(None, None, None, None, None, None);
+        let keys: Vec<PublicKey> = (0..4)
+            .map(|seed| PublicKey::from_parts(CurveType::ED25519, vec![seed; 32]).unwrap())
+            .collect();
+        for key in &keys {
+            contract.send_to_drop(drop_id, key.clone());
+        }
+        let balance = contract.get_key_balance(keys[0].clone());
+
+        claim_env(&keys[0], 5);
+        claim_resolved(&mut contract, bob());
+        claim_env(&keys[1], 6);
+        contract.create_account_and_claim(carol(), bob_pk(), None, None);
+        claim_callback_env(&keys[1], PromiseResult::Successful(vec![]));
+        contract.on_account_created_and_claimed(carol(), balance, None);
+        owner_env();
+        contract.owner_reclaim(keys[2].clone(), airdrop());
+
+        let rows = contract.export_claims(drop_id, 0, 10);
+        assert_eq!(
+            rows,
+            vec![
+                ClaimExportRow {
+                    account_id: bob(),
+                    amount: U128(balance.as_attounc()),
+                    timestamp: 0,
+                    block_height: 5,
+                    account_created: false,
+                },
+                ClaimExportRow {
+                    account_id: carol(),
+                    amount: U128(balance.as_attounc()),
+                    timestamp: 0,
+                    block_height: 0,
+                    account_created: true,
+                },
+            ]
+        );
+        // Pages are taken in insertion order
+        assert_eq!(contract.export_claims(drop_id, 1, 10), rows[1..]);
+        assert_eq!(contract.export_claims(drop_id, 0, 1), rows[..1]);
+        assert!(contract.export_claims(drop_id, 2, 10).is_empty());
+
+        let totals = contract.get_drop_totals(drop_id);
+        assert_eq!(totals.claimed.0, rows.iter().map(|row| row.amount.0).sum::<u128>());
+        assert_eq!(totals.refunded, U128(balance.as_attounc()));
+        assert_eq!(totals.outstanding, U128(contract.get_key_balance(keys[3].clone()).as_attounc()));
+    }
+
+    fn restricted_drop_contract() -> AirDrop {
+        drop_env(bob(), 0);
+        let mut contract = AirDrop::new_default();
+        let drop_id = contract.create_drop(None, None, None, None, Some("create_account_and_claim".to_string()), None);
+        contract.send_to_drop(drop_id, drop_pk());
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Method claim is not allowed for this key")]
+    fn test_restricted_drop_claim() {
+        let mut contract = restricted_drop_contract();
+        claim_env(&drop_pk(), 0);
+        claim_resolved(&mut contract, bob());
+    }
+
+    #[test]
+    fn test_restricted_drop_create_account_and_claim() {
+        let mut contract = restricted_drop_contract();
+        assert_eq!(contract.internal_key_method_names(&drop_pk()), "create_account_and_claim");
+        claim_env(&drop_pk(), 0);
+        contract.create_account_and_claim(carol(), bob_pk(), None, None);
     }
 
-    /// Returns the balance associated with given key.
-    pub fn get_key_balance(&self, key: PublicKey) -> &UncToken {
-        self.accounts.get(&key.into()).expect("Key is missing").into()
+    #[test]
+    #[should_panic(expected = "Method send can't be allowed on drop keys")]
+    fn test_create_drop_invalid_allowed_method() {
+        drop_env(bob(), 0);
+        let mut contract = AirDrop::new_default();
+        contract.create_drop(None, None, None, None, Some("claim,send".to_string()), None);
     }
 
-    /// Returns information associated with a given key.
-    /// Part of the airdrop NEP
-    #[handle_result]
-    pub fn get_key_information(&self, key: PublicKey) -> Result<KeyInfo, &'static str> {
-        match self.accounts.get(&key) {
-            Some(balance) => Ok(KeyInfo { balance: U128::from(balance.as_attounc()) }),
-            None => Err("Key is missing"),
+    #[test]
+    fn test_new_accounts_only_send() {
+        let mut contract = AirDrop::new_default();
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(2));
+        contract.send(drop_pk(), None, None, None, None, Some(true));
+        assert!(contract.get_key_information(drop_pk()).unwrap().new_accounts_only);
+        assert_eq!(contract.internal_key_method_names(&drop_pk()), NEW_ACCOUNT_CLAIM_METHODS.join(","));
+
+        claim_env(&drop_pk(), 0);
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::NewAccountRequired))));
+        for (name, call) in claim_calls().into_iter().filter(|(name, _)| !NEW_ACCOUNT_CLAIM_METHODS.contains(name)) {
+            call(&mut contract);
+            assert!(get_created_receipts().is_empty(), "{}", name);
         }
+        assert!(contract.simulate_claim(drop_pk(), None).unwrap().requires_new_account);
+
+        contract.create_account_and_claim(carol(), bob_pk(), None, None);
+        assert!(get_created_receipts().iter().any(|receipt| receipt.receiver_id == carol()));
     }
-}
 
-#[cfg(all(test, not(target_arch = "wasm32")))]
-mod tests {
+    #[test]
+    fn test_new_accounts_only_derived() {
+        let mut contract = AirDrop::new_default();
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(2));
+        contract.send(drop_pk(), None, None, None, None, Some(true));
 
-    use super::*;
+        claim_env(&drop_pk(), 0);
+        let promise = contract.create_account_and_claim_derived(airdrop(), bob_pk());
+        assert!(matches!(promise, PromiseOrValue::Promise(_)));
+        drop(promise);
+        let new_account_id = derived_account_id(&drop_pk(), &airdrop());
+        assert!(get_created_receipts().iter().any(|receipt| receipt.receiver_id == new_account_id));
+    }
 
-    use unc_sdk::test_utils::VMContextBuilder;
-    use unc_sdk::testing_env;
+    #[test]
+    fn test_new_accounts_only_drop() {
+        drop_env(bob(), 0);
+        let mut contract = AirDrop::new_default();
+        let drop_id = contract.create_drop(None, None, None, None, None, Some(true));
+        assert!(contract.drop_configs[&drop_id].new_accounts_only);
+        contract.send_to_drop(drop_id, drop_pk());
+        assert!(contract.get_key_information(drop_pk()).unwrap().new_accounts_only);
+        assert_eq!(contract.internal_key_method_names(&drop_pk()), NEW_ACCOUNT_CLAIM_METHODS.join(","));
 
-    fn airdrop() -> AccountId {
-        "airdrop".parse().unwrap()
+        claim_env(&drop_pk(), 0);
+        assert!(matches!(contract.claim(bob()), PromiseOrValue::Value(ClaimOutcome::Rejected(ClaimError::NewAccountRequired))));
+        assert!(contract.key_exists(drop_pk()));
+        contract.create_account_and_claim(carol(), bob_pk(), None, None);
+        assert!(get_created_receipts().iter().any(|receipt| receipt.receiver_id == carol()));
     }
 
-    fn bob() -> AccountId {
-        "bob".parse().unwrap()
+    #[test]
+    #[should_panic(expected = "Key can only be claimed to a new account with create_account_and_claim or create_account_and_claim_derived")]
+    fn test_new_accounts_only_claim_signed() {
+        let mut contract = signed_contract();
+        contract.new_account_keys.insert(key_hash(&signing_pk()));
+        contract.claim_signed(bob(), signing_pk(), sign_claim(&bob(), 1), 1);
     }
 
     #[test]
-    fn test_create_account() {
-        // Create a new instance of the airdrop contract
-        let mut contract = AirDrop::new();
-        // Create the public key to be used in the test
-        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
-            .parse()
-            .unwrap();
-        // Default the deposit to an extremely small amount
-        let deposit = 1_000_000;
+    #[should_panic(expected = "Methods can't be set on a key for new accounts only")]
+    fn test_new_accounts_only_with_methods() {
+        let mut contract = AirDrop::new_default();
+        send_env(ACCESS_KEY_ALLOWANCE.saturating_mul(2));
+        contract.send(drop_pk(), None, None, None, Some("claim".to_string()), Some(true));
+    }
 
-        // Initialize the mocked blockchain
+    #[test]
+    fn test_event_envelope() {
+        testing_env!(VMContextBuilder::new().current_account_id(airdrop()).context.clone());
+        AirDropEvent::AutoPaused { window: 1, claims: 2 }.emit();
+        let logs = get_logs();
+        let json = logs[0].strip_prefix("EVENT_JSON:").expect("Event logs start with EVENT_JSON:");
+        let event: unc_sdk::serde_json::Value = unc_sdk::serde_json::from_str(json).unwrap();
+        assert_eq!(event["standard"], "airdrop");
+        assert_eq!(event["version"], "1.0.0");
+        assert_eq!(event["event"], "auto_paused");
+        assert_eq!(event["data"]["window"], 1);
+        assert_eq!(event["data"]["claims"], 2);
+    }
+
+    #[test]
+    fn test_owner_reclaim() {
+        let mut contract = AirDrop::new_default();
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(airdrop())
-            .attached_deposit(deposit)
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(3))
             .context.clone()
         );
+        contract.send(drop_pk(), None, None, None, None, None);
+        contract.owner_id = airdrop();
 
-        // Create bob's account with the PK
-        contract.create_account(bob(), pk);
+        owner_env();
+        contract.owner_reclaim(drop_pk(), carol());
+        assert!(!contract.key_exists(drop_pk()));
+        assert!(contract.key_liability.is_zero());
+        let stats = contract.get_stats();
+        assert_eq!(stats.total_drops_refunded, 1);
+        assert_eq!(
+            stats.total_unc_refunded,
+            U128(ACCESS_KEY_ALLOWANCE.saturating_mul(2).saturating_sub(key_storage_stake()).as_attounc())
+        );
+
+        // The storage stake is reclaimed with the balance
+        let event: unc_sdk::serde_json::Value =
+            unc_sdk::serde_json::from_str(get_logs()[0].strip_prefix("EVENT_JSON:").unwrap()).unwrap();
+        assert_eq!(event["event"], "reclaimed");
+        assert_eq!(event["data"]["public_key"], String::from(&drop_pk()));
+        assert_eq!(event["data"]["to"], "carol");
+        assert_eq!(event["data"]["amount"], ACCESS_KEY_ALLOWANCE.saturating_mul(2).as_attounc().to_string());
+
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| receipt.receiver_id == carol()));
+        assert!(receipts.iter().flat_map(|receipt| &receipt.actions).any(|action| matches!(action, MockAction::DeleteKey { .. })));
     }
 
     #[test]
-    #[should_panic]
-    fn test_create_invalid_account() {
-        // Create a new instance of the airdrop contract
-        let mut contract = AirDrop::new();
-        // Create the public key to be used in the test
-        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
-            .parse()
-            .unwrap();
-        // Default the deposit to an extremely small amount
-        let deposit = 1_000_000;
+    #[should_panic(expected = "Unexpected public key")]
+    fn test_owner_reclaim_unknown_key() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.owner_reclaim(drop_pk(), carol());
+    }
 
-        // Initialize the mocked blockchain
+    fn token() -> AccountId {
+        "token".parse().unwrap()
+    }
+
+    /// Creates a contract with `drop_pk` funded with 2 allowances and 1000 tokens of `token`.
+    fn ft_contract() -> AirDrop {
+        owner_env();
+        let mut contract = AirDrop::new_default();
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(airdrop())
-            .attached_deposit(deposit)
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(3).saturating_add(key_storage_stake()))
+            .context.clone()
+        );
+        contract.send(drop_pk(), None, None, None, None, None);
+        contract.ft_contracts.insert(token());
+        testing_env!(
+            VMContextBuilder::new()
+            .current_account_id(airdrop())
+            .predecessor_account_id(token())
             .context.clone()
         );
+        contract.ft_on_transfer(bob(), U128(1_000), String::from(&drop_pk()));
+        contract
+    }
 
-        // Attempt to create an invalid account with the PK
-        contract.create_account("XYZ".parse().unwrap(), pk);
+    fn function_calls() -> Vec<(AccountId, String, UncToken)> {
+        get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| {
+                let receiver_id = receipt.receiver_id.clone();
+                receipt.actions.into_iter().filter_map(move |action| match action {
+                    MockAction::FunctionCallWeight { method_name, attached_deposit, .. } => Some((
+                        receiver_id.clone(),
+                        String::from_utf8(method_name).unwrap(),
+                        attached_deposit,
+                    )),
+                    _ => None,
+                })
+            })
+            .collect()
     }
 
     #[test]
-    #[should_panic]
-    fn test_get_missing_balance_panics() {
-        // Create a new instance of the airdrop contract
-        let contract = AirDrop::new();
-        // Create the public key to be used in the test
-        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
-            .parse()
-            .unwrap();
+    fn test_ft_on_transfer() {
+        let mut contract = ft_contract();
+        contract.ft_on_transfer(bob(), U128(500), String::from(&drop_pk()));
+        let tokens = contract.get_key_tokens(drop_pk()).unwrap();
+        assert_eq!(tokens.ft_contract_id, token());
+        assert_eq!(tokens.amount, U128(1_500));
+    }
 
-        // Initialize the mocked blockchain
+    #[test]
+    #[should_panic(expected = "Key must be funded with send first")]
+    fn test_ft_on_transfer_unfunded_key() {
+        let mut contract = ft_contract();
+        contract.ft_on_transfer(bob(), U128(500), String::from(&bob_pk()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token contract is not allowed")]
+    fn test_ft_on_transfer_unlisted_token() {
+        let mut contract = ft_contract();
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(airdrop())
+            .predecessor_account_id(carol())
             .context.clone()
         );
+        contract.ft_on_transfer(bob(), U128(500), String::from(&drop_pk()));
+    }
 
-        contract.get_key_balance(pk);
+    #[test]
+    fn test_ft_contract_allowlist() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        assert!(!contract.is_ft_contract(token()));
+        assert!(contract.add_ft_contract(token()));
+        assert!(!contract.add_ft_contract(token()));
+        assert!(contract.is_ft_contract(token()));
+        assert!(contract.remove_ft_contract(token()));
+        assert!(!contract.is_ft_contract(token()));
     }
 
     #[test]
-    fn test_get_missing_balance_success() {
-        // Create a new instance of the airdrop contract
-        let mut contract = AirDrop::new();
-        // Create the public key to be used in the test
-        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
-            .parse()
-            .unwrap();
-        // Default the deposit to be 100 times the access key allowance
-        let deposit = ACCESS_KEY_ALLOWANCE.saturating_mul(100);
-        
-        // Initialize the mocked blockchain
+    #[should_panic(expected = "Key already holds the tokens of another contract")]
+    fn test_ft_on_transfer_other_token() {
+        let mut contract = ft_contract();
+        contract.ft_contracts.insert(carol());
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(airdrop())
-            .attached_deposit(deposit)
+            .predecessor_account_id(carol())
             .context.clone()
         );
+        contract.ft_on_transfer(bob(), U128(500), String::from(&drop_pk()));
+    }
 
-        // Create the airdrop
-        contract.send(pk.clone());
+    #[test]
+    fn test_claim_ft_with_registration() {
+        let mut contract = ft_contract();
+        claim_env(&drop_pk(), 0);
+        contract.claim_ft_with_registration(bob());
+        assert_eq!(
+            function_calls(),
+            vec![
+                (token(), "storage_deposit".to_string(), FT_STORAGE_DEPOSIT),
+                (airdrop(), "on_ft_storage_deposited".to_string(), UncToken::from_attounc(0)),
+            ]
+        );
+        assert!(contract.get_key_tokens(drop_pk()).is_none());
+        assert_eq!(
+            contract.get_key_balance(drop_pk()),
+            ACCESS_KEY_ALLOWANCE.saturating_mul(2).saturating_sub(FT_STORAGE_DEPOSIT)
+        );
 
-        // try getting the balance of the key
-        let balance:u128 = contract.get_key_balance(pk).as_attounc();
+        // Once registered, the tokens are transferred
+        callback_env(PromiseResult::Successful(vec![]));
+        contract.on_ft_storage_deposited(drop_pk(), bob(), token(), U128(1_000), FT_STORAGE_DEPOSIT);
         assert_eq!(
-            balance,
-            u128::from((deposit.saturating_sub(ACCESS_KEY_ALLOWANCE)).as_attounc())
+            function_calls(),
+            vec![
+                (token(), "ft_transfer".to_string(), UncToken::from_attounc(1)),
+                (airdrop(), "on_ft_transferred".to_string(), UncToken::from_attounc(0)),
+            ]
         );
+        callback_env(PromiseResult::Successful(vec![]));
+        assert_eq!(contract.on_ft_transferred(drop_pk(), token(), U128(1_000)), ClaimAmountOutcome::Claimed(U128(1_000)));
+        assert!(contract.get_key_tokens(drop_pk()).is_none());
     }
 
     #[test]
-    #[should_panic]
-    fn test_claim_invalid_account() {
-        // Create a new instance of the airdrop contract
-        let mut contract = AirDrop::new();
-        // Create the public key to be used in the test
-        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
-            .parse()
-            .unwrap();
-        // Default the deposit to be 100 times the access key allowance
-        let deposit = ACCESS_KEY_ALLOWANCE.saturating_mul(100);
-        
-        // Initialize the mocked blockchain
+    fn test_claim_ft_with_registration_from_drop() {
+        drop_env(bob(), 0);
+        let mut contract = AirDrop::new_default();
+        let drop_id = contract.create_drop(None, None, None, None, None, None);
+        contract.send_to_drop(drop_id, drop_pk());
+        contract.ft_contracts.insert(token());
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(airdrop())
-            .attached_deposit(deposit)
+            .predecessor_account_id(token())
             .context.clone()
         );
+        contract.ft_on_transfer(bob(), U128(1_000), String::from(&drop_pk()));
+        let remaining = contract.get_drop(drop_id).unwrap().remaining;
 
-        // Create the airdrop
-        contract.send(pk.clone());
+        // The storage deposit leaves the drop with the key balance it is taken from
+        claim_env(&drop_pk(), 0);
+        contract.claim_ft_with_registration(bob());
+        assert_eq!(contract.get_drop(drop_id).unwrap().remaining, remaining.saturating_sub(FT_STORAGE_DEPOSIT));
 
-        // Now, send new transaction to airdrop contract and reinitialize the mocked blockchain with new params
+        // and comes back with the refund if the registration fails
+        callback_env(PromiseResult::Failed);
+        contract.on_ft_storage_deposited(drop_pk(), bob(), token(), U128(1_000), FT_STORAGE_DEPOSIT);
+        assert_eq!(contract.get_drop(drop_id).unwrap().remaining, remaining);
+    }
+
+    #[test]
+    fn test_claim_ft_with_configured_storage_deposit() {
+        let mut contract = ft_contract();
+        let deposit = UncToken::from_attounc(2_350_000_000_000_000_000_000);
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(airdrop())
-            .predecessor_account_id(airdrop())
-            .signer_account_pk(pk.into())
-            .account_balance(deposit)
+            .predecessor_account_id(contract.get_owner_id().clone())
+            .attached_deposit(UncToken::from_attounc(1))
             .context.clone()
         );
+        contract.set_ft_storage_deposit(U128(deposit.as_attounc()), Gas::from_tgas(7));
+        assert_eq!(contract.get_ft_storage_deposit(), U128(deposit.as_attounc()));
+        assert_eq!(contract.get_ft_storage_deposit_gas(), Gas::from_tgas(7));
 
-        // Create the second public key
-        let pk2 = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
-            .parse()
+        claim_env(&drop_pk(), 0);
+        contract.claim_ft_with_registration(bob());
+        let (attached_deposit, prepaid_gas) = get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id == token())
+            .flat_map(|receipt| receipt.actions)
+            .find_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, attached_deposit, prepaid_gas, .. }
+                    if method_name == b"storage_deposit" => Some((attached_deposit, prepaid_gas)),
+                _ => None,
+            })
             .unwrap();
-        // Attempt to create the account and claim
-        contract.create_account_and_claim("XYZ".parse().unwrap(), pk2);
+        assert_eq!(attached_deposit, deposit);
+        assert_eq!(prepaid_gas, Gas::from_tgas(7));
+        assert_eq!(
+            contract.get_key_balance(drop_pk()),
+            ACCESS_KEY_ALLOWANCE.saturating_mul(2).saturating_sub(deposit)
+        );
+
+        // A failed registration puts back the deposit the claim was made with
+        callback_env(PromiseResult::Failed);
+        contract.on_ft_storage_deposited(drop_pk(), bob(), token(), U128(1_000), deposit);
+        assert_eq!(contract.get_key_balance(drop_pk()), ACCESS_KEY_ALLOWANCE.saturating_mul(2));
     }
 
     #[test]
-    fn test_drop_claim() {
-        // Create a new instance of the airdrop contract
-        let mut contract = AirDrop::new();
-        // Create the public key to be used in the test
-        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
-            .parse()
+    fn test_claim_ft_rolled_back_on_failure() {
+        let mut contract = ft_contract();
+        claim_env(&drop_pk(), 0);
+        contract.claim_ft_with_registration(bob());
+
+        // A failed registration puts back the tokens and the storage deposit
+        callback_env(PromiseResult::Failed);
+        contract.on_ft_storage_deposited(drop_pk(), bob(), token(), U128(1_000), FT_STORAGE_DEPOSIT);
+        assert_eq!(contract.get_key_tokens(drop_pk()).unwrap().amount, U128(1_000));
+        assert_eq!(contract.get_key_balance(drop_pk()), ACCESS_KEY_ALLOWANCE.saturating_mul(2));
+
+        // A failed transfer puts back the tokens
+        claim_env(&drop_pk(), 0);
+        contract.claim_ft_with_registration(bob());
+        callback_env(PromiseResult::Failed);
+        assert_eq!(contract.on_ft_transferred(drop_pk(), token(), U128(1_000)), ClaimAmountOutcome::Claimed(U128(0)));
+        assert_eq!(contract.get_key_tokens(drop_pk()).unwrap().amount, U128(1_000));
+    }
+
+    #[test]
+    fn test_claim_ft_call() {
+        let mut contract = ft_contract();
+        claim_env(&drop_pk(), 0);
+        contract.claim_ft_call(bob(), "deposit".to_string());
+        let receipts = get_created_receipts();
+        let (method_name, args) = receipts
+            .iter()
+            .filter(|receipt| receipt.receiver_id == token())
+            .flat_map(|receipt| &receipt.actions)
+            .find_map(|action| match action {
+                MockAction::FunctionCallWeight { method_name, args, .. } => Some((method_name.clone(), args.clone())),
+                _ => None,
+            })
             .unwrap();
-        // Default the deposit to be 100 times the access key allowance
-        let deposit = ACCESS_KEY_ALLOWANCE.saturating_mul(100);
-        
-        // Initialize the mocked blockchain
-        testing_env!(
-            VMContextBuilder::new()
-            .current_account_id(airdrop())
-            .attached_deposit(deposit)
-            .context.clone()
+        assert_eq!(method_name, b"ft_transfer_call");
+        let args: unc_sdk::serde_json::Value = unc_sdk::serde_json::from_slice(&args).unwrap();
+        assert_eq!(args["receiver_id"], "bob");
+        assert_eq!(args["amount"], "1000");
+        assert_eq!(args["msg"], "deposit");
+        assert!(contract.get_key_tokens(drop_pk()).is_none());
+        // The native balance stays on the key
+        assert_eq!(contract.get_key_balance(drop_pk()), ACCESS_KEY_ALLOWANCE.saturating_mul(2));
+
+        // The receiver used only part of the tokens, the rest goes back to the key
+        callback_env(PromiseResult::Successful(b"\"400\"".to_vec()));
+        assert_eq!(contract.on_ft_transfer_called(drop_pk(), token(), U128(1_000)), ClaimAmountOutcome::Claimed(U128(400)));
+        let tokens = contract.get_key_tokens(drop_pk()).unwrap();
+        assert_eq!(tokens.ft_contract_id, token());
+        assert_eq!(tokens.amount, U128(600));
+    }
+
+    #[test]
+    fn test_claim_ft_call_rolled_back_on_failure() {
+        let mut contract = ft_contract();
+        claim_env(&drop_pk(), 0);
+        contract.claim_ft_call(bob(), "deposit".to_string());
+        callback_env(PromiseResult::Failed);
+        assert_eq!(contract.on_ft_transfer_called(drop_pk(), token(), U128(1_000)), ClaimAmountOutcome::Claimed(U128(0)));
+        assert_eq!(contract.get_key_tokens(drop_pk()).unwrap().amount, U128(1_000));
+
+        // Everything used leaves nothing on the key
+        claim_env(&drop_pk(), 0);
+        contract.claim_ft_call(bob(), "deposit".to_string());
+        callback_env(PromiseResult::Successful(b"\"1000\"".to_vec()));
+        assert_eq!(contract.on_ft_transfer_called(drop_pk(), token(), U128(1_000)), ClaimAmountOutcome::Claimed(U128(1_000)));
+        assert!(contract.get_key_tokens(drop_pk()).is_none());
+    }
+
+    #[test]
+    fn test_reclaim_during_claim_rejected() {
+        let mut contract = ft_contract();
+        contract.owner_id = airdrop();
+        claim_env(&drop_pk(), 0);
+        contract.claim_ft_call(bob(), "deposit".to_string());
+        owner_env();
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.owner_reclaim(drop_pk(), carol()); }),
+            "Key has a claim in progress"
         );
 
-        // Create the airdrop
-        contract.send(pk.clone());
+        // The callback unlocks the key
+        callback_env(PromiseResult::Successful(b"\"1000\"".to_vec()));
+        contract.on_ft_transfer_called(drop_pk(), token(), U128(1_000));
+        owner_env();
+        contract.owner_reclaim(drop_pk(), carol());
+        assert!(!contract.key_exists(drop_pk()));
 
-        // Now, send new transaction to airdrop contract and reinitialize the mocked blockchain with new params
-        testing_env!(
-            VMContextBuilder::new()
-            .current_account_id(airdrop())
-            .predecessor_account_id(airdrop())
-            .signer_account_pk(pk.into())
-            .account_balance(deposit)
-            .context.clone()
+        // Same for a key whose account creation is in flight
+        let pk = fund_keys(&mut contract, 0..1).remove(0);
+        claim_env(&pk, 0);
+        contract.create_account_and_claim(carol(), bob_pk(), None, None);
+        owner_env();
+        assert_eq!(
+            panic_message(&mut contract, |contract| {
+                contract.owner_reclaim(PublicKey::from_parts(CurveType::ED25519, vec![0; 32]).unwrap(), carol());
+            }),
+            "Key has a claim in progress"
         );
+    }
 
-        // Create the second public key
-        let pk2 = "2S87aQ1PM9o6eBcEXnTR5yBAVRTiNmvj8J8ngZ6FzSca"
-            .parse()
-            .unwrap();
-        // Attempt to create the account and claim
-        contract.create_account_and_claim(bob(), pk2);
+    #[test]
+    fn test_claim_releases_tokens() {
+        let mut contract = ft_contract();
+        claim_env(&drop_pk(), 0);
+        contract.claim(bob());
+        assert!(transferred_to(&bob()) > 0);
+        // The tokens are released once the transfer succeeds
+        resolve_claim(&mut contract, PromiseResult::Successful(vec![]));
+        assert!(contract.get_key_tokens(drop_pk()).is_none());
+        assert!(function_calls().contains(&(token(), "ft_transfer".to_string(), UncToken::from_attounc(1))));
+
+        // A failed transfer strands the tokens for the owner
+        callback_env(PromiseResult::Failed);
+        assert!(!contract.on_tokens_released(token(), U128(1_000)));
+        assert_eq!(contract.get_stranded_tokens(token()), U128(1_000));
+        owner_env();
+        contract.withdraw_stranded_tokens(token(), carol());
+        assert_eq!(contract.get_stranded_tokens(token()), U128(0));
+        assert!(function_calls().contains(&(token(), "ft_transfer".to_string(), UncToken::from_attounc(1))));
     }
 
     #[test]
-    fn test_send_two_times() {
-        // Create a new instance of the airdrop contract
-        let mut contract = AirDrop::new();
-        // Create the public key to be used in the test
-        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
-            .parse()
-            .unwrap();
-        // Default the deposit to be 100 times the access key allowance
-        let deposit = ACCESS_KEY_ALLOWANCE.saturating_mul(100);
-        
-        // Initialize the mocked blockchain
-        testing_env!(
-            VMContextBuilder::new()
-            .current_account_id(airdrop())
-            .attached_deposit(deposit)
-            .context.clone()
-        );
+    fn test_owner_reclaim_releases_tokens() {
+        let mut contract = ft_contract();
+        owner_env();
+        contract.owner_reclaim(drop_pk(), carol());
+        assert!(contract.get_key_tokens(drop_pk()).is_none());
+        assert_eq!(contract.get_stranded_tokens(token()), U128(0));
+        assert!(function_calls().contains(&(token(), "ft_transfer".to_string(), UncToken::from_attounc(1))));
+    }
 
-        // Create the airdrop
-        contract.send(pk.clone());
-        assert_eq!(contract.get_key_balance(pk.clone()), (deposit.saturating_sub(ACCESS_KEY_ALLOWANCE)).into());
+    #[test]
+    fn test_ownership_handshake() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.propose_owner(bob());
+        // A second proposal replaces the first one
+        contract.propose_owner(carol());
+        assert_eq!(contract.get_pending_owner(), Some(&carol()));
+        assert_eq!(contract.get_owner_id(), &airdrop());
 
-        // Re-initialize the mocked blockchain with new params
-        testing_env!(
-            VMContextBuilder::new()
-            .current_account_id(airdrop())
-            .account_balance(deposit)
-            .attached_deposit(deposit + 1)
-            .context.clone()
+        operator_env(carol());
+        contract.accept_ownership();
+        assert_eq!(contract.get_owner_id(), &carol());
+        assert!(contract.get_pending_owner().is_none());
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"airdrop","version":"1.0.0","event":"ownership_accepted","data":{"old_owner":"airdrop","new_owner":"carol"}}"#]
         );
 
-        // Attempt to recreate the same airdrop twice
-        contract.send(pk.clone());
+        // The new owner has the owner rights, the old one lost them
+        contract.propose_owner(bob());
+        owner_env();
         assert_eq!(
-            contract.accounts.get(&pk.into()).unwrap().as_attounc(),
-            deposit.as_attounc() + deposit.as_attounc() + 1 - 2 * ACCESS_KEY_ALLOWANCE.as_attounc()
+            panic_message(&mut contract, |contract| contract.cancel_ownership_proposal()),
+            "Only the owner can call this method"
         );
     }
 
     #[test]
-    fn test_create_advanced_account() {
-        // Create a new instance of the airdrop contract
-        let mut contract = AirDrop::new();
-        // Create the public key to be used in the test
-        let pk: PublicKey = "qSq3LoufLvTCTNGC3LJePMDGrok8dHMQ5A1YD9psbiz"
-            .parse()
-            .unwrap();
-        // Default the deposit to an extremely small amount
-        let deposit = 1_000_000;
+    #[should_panic(expected = "Only the proposed owner can accept the ownership")]
+    fn test_stranger_accepts_ownership() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.propose_owner(carol());
+        operator_env(bob());
+        contract.accept_ownership();
+    }
 
-        // Create options for the advanced account creation
-        let options: CreateAccountOptions = CreateAccountOptions {
-            full_access_keys: Some(vec![pk.clone()]),
-            limited_access_keys: Some(vec![LimitedAccessKey {
-                public_key: pk.clone(),
-                allowance: UncToken::from_attounc(100),
-                receiver_id: airdrop(),
-                method_names: "send".to_string(),
-            }]),
-            contract_bytes: Some(include_bytes!("../target/wasm32-unknown-unknown/release/airdrop.wasm").to_vec()),
-        };
+    #[test]
+    fn test_cancel_ownership_proposal() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.propose_owner(carol());
+        contract.cancel_ownership_proposal();
+        assert!(contract.get_pending_owner().is_none());
+        assert_eq!(
+            get_logs()[1],
+            r#"EVENT_JSON:{"standard":"airdrop","version":"1.0.0","event":"owner_proposal_cancelled","data":{"new_owner":"carol"}}"#
+        );
+        operator_env(carol());
+        assert!(panic_message(&mut contract, |contract| contract.accept_ownership())
+            .contains("Only the proposed owner can accept the ownership"));
+    }
 
-        // Initialize the mocked blockchain
+    #[test]
+    #[should_panic(expected = "Account is already the owner")]
+    fn test_propose_current_owner() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.propose_owner(airdrop());
+    }
+
+    #[test]
+    #[should_panic(expected = "The contract can't be the owner")]
+    fn test_propose_contract_as_owner() {
+        owner_env();
+        let mut contract = AirDrop::new_default();
+        contract.owner_id = bob();
+        operator_env(bob());
+        contract.propose_owner(airdrop());
+    }
+
+    #[test]
+    fn test_failed_claims_lock_key() {
+        let mut contract = AirDrop::new_default();
+        contract.owner_id = airdrop();
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(airdrop())
-            .attached_deposit(deposit)
+            .attached_deposit(ACCESS_KEY_ALLOWANCE.saturating_mul(2))
             .context.clone()
         );
+        contract.send(drop_pk(), None, None, None, None, None);
+        for attempt in 1..=MAX_FAILED_CLAIM_ATTEMPTS {
+            claim_env(&drop_pk(), 0);
+            contract.create_account_and_claim(carol(), bob_pk(), None, None);
+            claim_callback_env(&drop_pk(), PromiseResult::Failed);
+            contract.on_account_created_and_claimed(carol(), ACCESS_KEY_ALLOWANCE, None);
+            assert_eq!(contract.get_key_information(drop_pk()).unwrap().failed_attempts, attempt);
+        }
 
-        // Create bob's account with the advanced options
-        contract.create_account_advanced(bob(), options);
+        claim_env(&drop_pk(), 0);
+        assert_eq!(
+            panic_message(&mut contract, |contract| { contract.claim(bob()); }),
+            "Key is locked after too many failed claims"
+        );
+
+        owner_env();
+        contract.reset_key_attempts(drop_pk());
+        assert_eq!(contract.get_key_information(drop_pk()).unwrap().failed_attempts, 0);
+        claim_env(&drop_pk(), 0);
+        claim_resolved(&mut contract, bob());
+        assert!(!contract.key_exists(drop_pk()));
     }
 
-    #[test]
-    #[should_panic]
-    fn test_create_advanced_account_no_options() {
-        // Create a new instance of the airdrop contract
-        let mut contract = AirDrop::new();
-        // Default the deposit to an extremely small amount
-        let deposit = 1_000_000;
+    /// An operation of `test_balance_conservation` on the key at index `key` of the generated keys.
+    #[derive(Debug, Clone)]
+    enum Op {
+        /// Funds the key, or tops it up if it already has a balance.
+        Send { key: usize, amount: u128, expiring: bool },
+        Claim { key: usize },
+        /// Claims with `create_account_and_claim` and fails the account creation, which puts the balance back.
+        FailedCreation { key: usize },
+        /// Advances the clock, which expires the keys funded with `expiring` before.
+        Tick,
+        /// Reclaims the balances of the expired keys.
+        Reclaim,
+    }
 
-        // Initialize the mocked blockchain
+    /// What the contract should store for a key.
+    struct ModelKey {
+        balance: u128,
+        expiration: Option<u64>,
+        failed_attempts: u32,
+    }
+
+    impl ModelKey {
+        fn is_claimable(&self, timestamp: u64) -> bool {
+            self.failed_attempts < MAX_FAILED_CLAIM_ATTEMPTS
+                && self.expiration.is_none_or(|expiration| timestamp < expiration)
+        }
+    }
+
+    const NUM_MODEL_KEYS: usize = 4;
+
+    fn keys_strategy() -> impl Strategy<Value = Vec<PublicKey>> {
+        prop::collection::btree_set(any::<[u8; 32]>(), NUM_MODEL_KEYS).prop_map(|keys| {
+            keys.into_iter()
+                .map(|key| PublicKey::from_parts(CurveType::ED25519, key.to_vec()).unwrap())
+                .collect()
+        })
+    }
+
+    fn op_strategy() -> impl Strategy<Value = Op> {
+        let allowance = ACCESS_KEY_ALLOWANCE.as_attounc();
+        prop_oneof![
+            (0..NUM_MODEL_KEYS, any::<u128>(), any::<bool>())
+                .prop_map(|(key, amount, expiring)| Op::Send { key, amount, expiring }),
+            // Deposits close to the allowance, which are the common ones
+            (0..NUM_MODEL_KEYS, allowance..allowance * 3, any::<bool>())
+                .prop_map(|(key, amount, expiring)| Op::Send { key, amount, expiring }),
+            (0..NUM_MODEL_KEYS).prop_map(|key| Op::Claim { key }),
+            (0..NUM_MODEL_KEYS).prop_map(|key| Op::FailedCreation { key }),
+            Just(Op::Tick),
+            Just(Op::Reclaim),
+        ]
+    }
+
+    /// Sets up a call signed with `pk` to a contract holding `balance` on top of the attached `deposit`.
+    fn model_env(pk: &PublicKey, balance: u128, deposit: u128, timestamp: u64) {
         testing_env!(
             VMContextBuilder::new()
             .current_account_id(airdrop())
-            .attached_deposit(deposit)
+            .predecessor_account_id(airdrop())
+            .signer_account_id(airdrop())
+            .signer_account_pk(pk.clone())
+            .account_balance(UncToken::from_attounc(balance))
+            .attached_deposit(UncToken::from_attounc(deposit))
+            .block_timestamp(timestamp)
             .context.clone()
         );
+    }
 
-        // Create bob's account with the advanced options
-        contract.create_account_advanced(bob(), CreateAccountOptions { full_access_keys: None, limited_access_keys: None, contract_bytes: None });
+    proptest! {
+        /// Runs random operations against a model of the key balances and checks after every one of them
+        /// that the balances stored add up to what was funded and not claimed or refunded, and that no key
+        /// is left with a zero balance.
+        #[test]
+        fn test_balance_conservation(keys in keys_strategy(), ops in prop::collection::vec(op_strategy(), 1..50)) {
+            model_env(&keys[0], 0, 0, 1);
+            let mut contract = AirDrop::new_default();
+            let mut model: Vec<Option<ModelKey>> = keys.iter().map(|_| None).collect();
+            // Only the difference of the cumulative totals is compared to the balances
+            let (mut funded, mut claimed, mut refunded) = (0u128, 0u128, 0u128);
+            let mut now = 1;
+            // What the contract holds for the keys: their balances and storage stakes
+            let mut held = 0u128;
+            for op in ops {
+                match op {
+                    Op::Send { key, amount, expiring } => {
+                        // The expiration of a new key adds to its storage stake, twice the stake of a plain key
+                        // is above what it can add
+                        let min_new_deposit = ACCESS_KEY_ALLOWANCE
+                            .saturating_add(DEFAULT_MIN_DROP_AMOUNT)
+                            .saturating_add(key_storage_stake().saturating_mul(2));
+                        match &model[key] {
+                            Some(_) if amount > 0 => (),
+                            None if amount >= min_new_deposit.as_attounc() => (),
+                            _ => continue,
+                        }
+                        // The contract can't hold a deposit on top of what it owes if the sum doesn't fit in a balance,
+                        // and rejects deposits its cumulative totals can't count
+                        if held.checked_add(amount).is_none()
+                            || contract.stats.total_unc_dropped.0.checked_add(amount).is_none()
+                        {
+                            continue;
+                        }
+                        let expiration = expiring.then_some(now + 1);
+                        model_env(&keys[key], held, amount, now);
+                        contract.send(keys[key].clone(), None, None, expiration, None, None);
+                        let credited = match &model[key] {
+                            Some(_) => amount,
+                            None => {
+                                let stake = contract.storage_stakes[&key_hash(&keys[key])].as_attounc();
+                                prop_assert!(stake >= key_storage_stake().as_attounc());
+                                amount - ACCESS_KEY_ALLOWANCE.as_attounc() - stake
+                            }
+                        };
+                        let entry = model[key].get_or_insert(ModelKey { balance: 0, expiration: None, failed_attempts: 0 });
+                        entry.balance += credited;
+                        entry.expiration = expiration.or(entry.expiration);
+                        funded = funded.wrapping_add(credited);
+                    }
+                    Op::Claim { key } => {
+                        if model[key].as_ref().is_some_and(|entry| entry.is_claimable(now)) {
+                            model_env(&keys[key], held, 0, now);
+                            claim_resolved(&mut contract, bob());
+                            claimed = claimed.wrapping_add(model[key].take().unwrap().balance);
+                        }
+                    }
+                    Op::FailedCreation { key } => {
+                        if let Some(entry) = model[key].as_mut().filter(|entry| entry.is_claimable(now)) {
+                            model_env(&keys[key], held, 0, now);
+                            contract.create_account_and_claim(carol(), bob_pk(), None, None);
+                            claim_callback_env(&keys[key], PromiseResult::Failed);
+                            contract.on_account_created_and_claimed(carol(), UncToken::from_attounc(entry.balance), None);
+                            entry.failed_attempts += 1;
+                        }
+                    }
+                    Op::Tick => now += 1,
+                    Op::Reclaim => {
+                        model_env(&keys[0], held, 1, now);
+                        contract.reclaim_all_expired(u64::MAX, bob());
+                        for entry in model.iter_mut() {
+                            if entry.as_ref().is_some_and(|entry| entry.expiration.is_some_and(|expiration| expiration <= now)) {
+                                refunded = refunded.wrapping_add(entry.take().unwrap().balance);
+                            }
+                        }
+                    }
+                }
+
+                let mut owed = 0u128;
+                held = 0;
+                for (key, entry) in keys.iter().zip(&model) {
+                    let balance = contract.internal_key_balance(key).map(|balance| balance.as_attounc());
+                    prop_assert_eq!(balance, entry.as_ref().map(|entry| entry.balance));
+                    prop_assert_ne!(balance, Some(0), "Key left with a zero balance");
+                    prop_assert_eq!(contract.storage_stakes.contains_key(&key_hash(key)), entry.is_some());
+                    owed += balance.unwrap_or(0);
+                    held += balance.unwrap_or(0) + contract.storage_stakes.get(&key_hash(key)).map_or(0, |stake| stake.as_attounc());
+                }
+                prop_assert_eq!(owed, funded.wrapping_sub(claimed).wrapping_sub(refunded));
+                prop_assert_eq!(contract.key_liability.as_attounc(), owed);
+                prop_assert_eq!(contract.get_key_count(), model.iter().flatten().count() as u64);
+            }
+        }
     }
 }
@@ -0,0 +1,138 @@
+use unc_sdk::{env, AccountId, CryptoHash};
+
+/// Byte prepended to the leaves before hashing them, so that a leaf can't be passed off as a node.
+pub const LEAF_PREFIX: u8 = 0;
+
+/// Byte prepended to the pairs of sibling nodes before hashing them.
+pub const NODE_PREFIX: u8 = 1;
+
+/// Hash of a Merkle drop leaf: `sha256(0x00 || account_id || amount)`, with the amount
+/// encoded as 16 little-endian bytes.
+pub fn leaf_hash(account_id: &AccountId, amount: u128) -> CryptoHash {
+    let mut bytes = vec![LEAF_PREFIX];
+    bytes.extend_from_slice(account_id.as_bytes());
+    bytes.extend_from_slice(&amount.to_le_bytes());
+    env::sha256_array(&bytes)
+}
+
+/// Hash of two sibling nodes: `sha256(0x01 || left || right)`. The pair is sorted before hashing
+/// so proofs don't need to carry the side of each sibling.
+pub fn hash_pair(a: &CryptoHash, b: &CryptoHash) -> CryptoHash {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    let mut bytes = vec![NODE_PREFIX];
+    bytes.extend_from_slice(left);
+    bytes.extend_from_slice(right);
+    env::sha256_array(&bytes)
+}
+
+/// Verifies that `leaf` is part of the tree with the given `root`.
+/// Returns the index of the leaf in the tree if the proof is valid.
+///
+/// The index is numbered like a binary heap (the root is 1, the children of `i` are
+/// `2i` and `2i + 1`) and is derived from the side the leaf ends up on at every level,
+/// so every leaf of a tree gets a distinct index.
+pub fn verify_proof(root: &CryptoHash, leaf: CryptoHash, proof: &[CryptoHash]) -> Option<u64> {
+    if proof.len() >= 64 {
+        return None;
+    }
+    let mut index: u64 = 1 << proof.len();
+    let mut node = leaf;
+    for (level, sibling) in proof.iter().enumerate() {
+        if *sibling < node {
+            index |= 1 << level;
+        }
+        node = hash_pair(&node, sibling);
+    }
+    if &node == root {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    fn hash(hex: &str) -> CryptoHash {
+        let mut hash = [0u8; 32];
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        hash
+    }
+
+    fn leaves() -> Vec<CryptoHash> {
+        vec![
+            leaf_hash(&"alice".parse().unwrap(), 100),
+            leaf_hash(&"bob".parse().unwrap(), 200),
+            leaf_hash(&"carol".parse().unwrap(), 300),
+            leaf_hash(&"dave".parse().unwrap(), 400),
+        ]
+    }
+
+    fn root() -> CryptoHash {
+        hash("6faa1e35675073f0dfea5ccccc1d83606f3828724cd6f513f988b1fdf73188c1")
+    }
+
+    #[test]
+    fn test_leaf_hash_vectors() {
+        let leaves = leaves();
+        assert_eq!(leaves[0], hash("eede5599bfa7af308a11728018cc0822bd4fa4d8bfccf896ebaf4ca05edfb12f"));
+        assert_eq!(leaves[1], hash("f33a3eb2855c06dc6faece0dfee5a26f73b23145a0ecf41fc73fc0f892c54314"));
+        assert_eq!(leaves[2], hash("d2c075a838c4a57a774892fa19739117756e27fe7dca6e53ad9995180a9570d1"));
+        assert_eq!(leaves[3], hash("0b08605325383755f5db6591f84a77033045bae1b89803ebe30535636cbab209"));
+    }
+
+    #[test]
+    fn test_leaf_and_node_hashes_are_domain_separated() {
+        let leaves = leaves();
+        // The same bytes hash differently as a leaf and as a pair of nodes
+        let bytes = [leaves[0], leaves[1]].concat();
+        let mut leaf_bytes = vec![LEAF_PREFIX];
+        leaf_bytes.extend_from_slice(&bytes);
+        let mut node_bytes = vec![NODE_PREFIX];
+        node_bytes.extend_from_slice(&bytes);
+        assert_ne!(env::sha256_array(&leaf_bytes), env::sha256_array(&node_bytes));
+        assert_eq!(hash_pair(&leaves[0], &leaves[1]), env::sha256_array(&node_bytes));
+    }
+
+    #[test]
+    fn test_hash_pair_vectors() {
+        let leaves = leaves();
+        let expected = hash("43019d29df92035d6e6a85efd484557050d305ed929858637cb5e9540c1e7a09");
+        assert_eq!(hash_pair(&leaves[0], &leaves[1]), expected);
+        // The order of the siblings doesn't matter
+        assert_eq!(hash_pair(&leaves[1], &leaves[0]), expected);
+
+        let right = hash_pair(&leaves[2], &leaves[3]);
+        assert_eq!(right, hash("b1eb9cf406e200351cff550182c5c0b4f887844939f28a9431a27e2c3a2d61bc"));
+        assert_eq!(hash_pair(&expected, &right), root());
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        let leaves = leaves();
+        let left = hash_pair(&leaves[0], &leaves[1]);
+        let right = hash_pair(&leaves[2], &leaves[3]);
+
+        assert_eq!(verify_proof(&root(), leaves[0], &[leaves[1], right]), Some(4));
+        assert_eq!(verify_proof(&root(), leaves[1], &[leaves[0], right]), Some(5));
+        assert_eq!(verify_proof(&root(), leaves[2], &[leaves[3], left]), Some(7));
+        assert_eq!(verify_proof(&root(), leaves[3], &[leaves[2], left]), Some(6));
+    }
+
+    #[test]
+    fn test_forged_proof_rejected() {
+        let leaves = leaves();
+        let right = hash_pair(&leaves[2], &leaves[3]);
+
+        // Claiming more than the amount in the tree
+        let forged_leaf = leaf_hash(&"alice".parse().unwrap(), 1_000);
+        assert_eq!(verify_proof(&root(), forged_leaf, &[leaves[1], right]), None);
+        // Using the wrong sibling
+        assert_eq!(verify_proof(&root(), leaves[0], &[leaves[2], right]), None);
+        // Truncated proof
+        assert_eq!(verify_proof(&root(), leaves[0], &[leaves[1]]), None);
+    }
+}
@@ -1,4 +1,7 @@
 use unc_sdk::serde::{Serialize, Deserialize};
+use unc_sdk::FunctionError;
+#[cfg(feature = "abi")]
+use unc_sdk::schemars::JsonSchema;
 
 use crate::*;
 
@@ -6,15 +9,61 @@ use crate::*;
 /// Part of the airdrop NEP
 #[derive(Serialize)]
 #[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
 pub struct KeyInfo {
     /// attounc$ amount that will be sent to the claiming account (either new or existing)
     /// when the key is successfully used.
     pub balance: U128,
+    /// Estimate of the attounc$ left in the access key allowance to pay for claim gas.
+    pub allowance_remaining: U128,
+    /// Tag the key was funded with, e.g. to segment campaigns.
+    pub tag: Option<String>,
+    /// Number of consecutive failed claims of the key. The key is locked once it reaches
+    /// MAX_FAILED_CLAIM_ATTEMPTS.
+    pub failed_attempts: u32,
+    /// Number of claim attempts the allowance of the key was sized for, less the failed ones.
+    pub remaining_attempts: u32,
+    /// Maximum attounc$ released by a claim if the key was funded with `send_drip`, the rest of the balance
+    /// staying on the key.
+    pub max_per_claim: Option<U128>,
+    /// Block timestamp in nanoseconds from which the key can no longer be claimed, if any.
+    pub expiry_ns: Option<u64>,
+    /// Whether the key is locked after too many failed claims or its drop is paused, until the owner or
+    /// the funder of the drop enables it again.
+    pub disabled: bool,
+    /// Block timestamp in nanoseconds at which the key was funded, 0 if funded before it was recorded.
+    pub created_at_ns: u64,
+    /// Whether the key can be claimed right now, see `can_claim`.
+    pub claimable: bool,
+    /// Why the key can or can't be claimed right now, leaving out a pause of the whole contract.
+    pub status: KeyStatus,
+    /// Whether the key can only be claimed to a new account, with `create_account_and_claim` or
+    /// `create_account_and_claim_derived`.
+    pub new_accounts_only: bool,
+}
+
+/// State of a key, returned in `KeyInfo`. Unit variants serialize as a bare string, e.g. `"available"`,
+/// and `ClaimInProgress` as `{"claim_in_progress":{"since_block":..}}`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde", rename_all = "snake_case")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub enum KeyStatus {
+    /// The key can be claimed.
+    Available,
+    /// A claim of the key is waiting for its callback, locked since the block height `since_block`.
+    ClaimInProgress { since_block: u64 },
+    /// The key is locked after too many failed claims, or its drop is paused.
+    Disabled,
+    /// The key or its drop has expired.
+    Expired,
+    /// The drop of the key hasn't started, or its cooldown or drip interval hasn't elapsed yet.
+    NotYetClaimable,
 }
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, BorshDeserialize, BorshSerialize, Clone)]
 #[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
 /// Information about any limited access keys that are being added to the account as part of `create_account_advanced`.
 pub struct LimitedAccessKey {
     /// The public key of the limited access key.
@@ -27,12 +76,636 @@ pub struct LimitedAccessKey {
     pub method_names: String,
 }
     
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, BorshDeserialize, BorshSerialize, Clone)]
 #[serde(crate = "unc_sdk::serde")]
-/// Options for `create_account_advanced`.
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+/// Options for `create_account_advanced` and `create_account_and_claim`.
 pub struct CreateAccountOptions {
     pub full_access_keys: Option<Vec<PublicKey>>,
     pub limited_access_keys: Option<Vec<LimitedAccessKey>>,
     pub contract_bytes: Option<Vec<u8>>,
+    /// FT contracts to register the new account with once it's created, at most MAX_FT_REGISTRATIONS.
+    pub ft_registrations: Option<Vec<AccountId>>,
+    /// Opts into an account without any full access key, e.g. one only controlled by its contract.
+    /// `create_account_and_claim` then doesn't add `new_public_key` either.
+    pub keyless: Option<bool>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+/// An account id reserved for a key by `reserve_account`.
+pub struct Reservation {
+    /// The only account the key can create.
+    pub account_id: AccountId,
+    /// The account that funded the key and can refund it with `refund_reservation`.
+    pub funder: AccountId,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+/// A `create_account_advanced` call whose account creation failed and that can be retried by the owner.
+pub struct PendingCreation {
+    /// The account that attached the deposit and gets refunded once the retries are exhausted.
+    pub funder: AccountId,
+    /// The amount of attounc$ to transfer to the new account, without `storage_cost`.
+    pub amount: UncToken,
+    /// The options the account should be created with.
+    pub options: CreateAccountOptions,
+    /// How many times the creation has been retried so far.
+    pub retries: u32,
+    /// Cost of the storage of this entry, taken from the deposit while it is queued.
+    pub storage_cost: UncToken,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+/// A commitment made with `commit_code` to claim a code to an account.
+pub struct CodeCommitment {
+    /// The account that attached the deposit and can cancel the commitment with `cancel_commitment`.
+    pub committer: AccountId,
+    /// Block height at which the commitment was made.
+    pub committed_at: u64,
+    /// attounc$ attached to pay for the storage of the commitment, paid out with the claim.
+    pub deposit: UncToken,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+/// A drop distributed to a list of accounts committed to by a Merkle root, see `create_merkle_drop`.
+pub struct MerkleDrop {
+    /// The account that funded the drop and gets the unclaimed remainder back after expiration.
+    pub funder: AccountId,
+    /// Root of the Merkle tree of `sha256(0x00 || account_id || amount)` leaves.
+    pub root: CryptoHash,
+    /// Total attounc$ available to the claims of the drop.
+    pub total: UncToken,
+    /// Attounc$ claimed so far.
+    pub claimed: UncToken,
+    /// Block timestamp in nanoseconds after which the drop can no longer be claimed.
+    pub expiration: Option<u64>,
+    /// Part of the deposit paying for the storage of the drop and the words of its claim bitmap not written
+    /// yet, refunded with the remainder.
+    pub storage_reserve: UncToken,
+}
+
+/// Information about a Merkle drop. Returned by the `get_merkle_drop` view method.
+#[derive(Serialize)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct MerkleDropInfo {
+    pub funder: AccountId,
+    pub root: Base58CryptoHash,
+    pub total: U128,
+    pub claimed: U128,
+    pub expiration: Option<u64>,
+}
+
+impl From<&MerkleDrop> for MerkleDropInfo {
+    fn from(drop: &MerkleDrop) -> Self {
+        Self {
+            funder: drop.funder.clone(),
+            root: drop.root.into(),
+            total: U128(drop.total.as_attounc()),
+            claimed: U128(drop.claimed.as_attounc()),
+            expiration: drop.expiration,
+        }
+    }
+}
+
+/// A sweep of the contract funds proposed by the owner, see `propose_sweep`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct PendingSweep {
+    /// The account the funds are sent to.
+    pub to: AccountId,
+    /// Block timestamp in nanoseconds at which the sweep was proposed.
+    pub proposed_at: u64,
+}
+
+/// Settings given to `new`, each one left to its default when missing.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct Config {
+    /// Allowance of a claim attempt of the ED25519 keys, see `set_ed25519_allowance`.
+    pub ed25519_allowance: Option<U128>,
+    /// Allowance of a claim attempt of the SECP256K1 keys, see `set_secp256k1_allowance`.
+    pub secp256k1_allowance: Option<U128>,
+    /// See `set_max_claim_attempts`.
+    pub max_claim_attempts: Option<u32>,
+    /// See `set_min_drop_amount`.
+    pub min_drop_amount: Option<U128>,
+    /// See `set_fee_bps`.
+    pub fee_bps: Option<u16>,
+    /// See `set_claim_cooldown`.
+    pub claim_cooldown_ns: Option<u64>,
+    /// See `set_default_expiry_ns`.
+    pub default_expiry_ns: Option<u64>,
+    /// Minimum delay of the actions scheduled with `schedule_action`.
+    pub timelock_delay_ns: Option<u64>,
+}
+
+/// What `send` does with a deposit larger than needed, see `set_over_deposit_policy`. Serializes as
+/// `"credit_all"` or `{"refund_excess":"<max deposit>"}`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde", rename_all = "snake_case")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub enum OverDepositPolicy {
+    /// The whole deposit goes to the key.
+    CreditAll,
+    /// `send` uses at most this many attoUNC of the deposit and refunds the rest to the sender.
+    RefundExcess(U128),
+}
+
+/// A destructive owner operation that only takes effect once its timelock has passed, see `schedule_action`.
+/// The owner setters, e.g. `set_claim_cooldown` or `set_fee_bps`, aren't timelocked.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub enum OwnerAction {
+    /// Sets the minimum delay of the actions scheduled from then on.
+    SetTimelockDelay { timelock_delay_ns: u64 },
+    /// Sends `amount` of the funds not owed to the keys to `to`. The fees and the sponsor pool aren't part of
+    /// them, they are withdrawn with `withdraw_fees` and `withdraw_sponsor_pool`.
+    WithdrawTreasury { to: AccountId, amount: U128 },
+    /// Records the sha256 of the code the contract is going to be upgraded to.
+    StageUpgrade { code_hash: Base58CryptoHash },
+    /// Proposes `new_owner` as the next owner like `propose_owner`: it becomes the owner once it calls
+    /// `accept_ownership`.
+    SetOwner { new_owner: AccountId },
+}
+
+/// An owner action waiting for its timelock, see `schedule_action`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct ScheduledAction {
+    pub id: u64,
+    pub action: OwnerAction,
+    /// Block timestamp in nanoseconds from which the action can be executed.
+    pub eta: u64,
+}
+
+/// Reason a claim was rejected without panicking, so that it still counts towards the claim limits.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub enum ClaimError {
+    /// More than `max_claims_per_window` claims were made in the current window.
+    RateLimited,
+    /// More than `auto_pause_threshold` claims were made in the current window and the contract got paused.
+    AutoPaused,
+    /// The account creation of a previous `create_account_and_claim` of the key hasn't resolved yet.
+    ClaimInProgress,
+    /// The transaction isn't signed with a funded key of the contract account, or it is signed with one
+    /// of its admin keys. It doesn't count towards the claim limits.
+    NotADropKey,
+    /// The key can only be claimed to a new account, with `create_account_and_claim` or
+    /// `create_account_and_claim_derived`. It doesn't count towards the claim limits.
+    NewAccountRequired,
+}
+
+/// What a claim of a key would pay out right now, returned by `simulate_claim`.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct ClaimSimulation {
+    /// attounc$ the claim sends: the balance or the next drip, with the storage stake of the key if the claim
+    /// removes it.
+    pub payout: U128,
+    /// Whether the key can only be claimed to a new account, with `create_account_and_claim` or
+    /// `create_account_and_claim_derived`.
+    pub requires_new_account: bool,
+    /// Why the claim would fail right now, empty if it would succeed.
+    pub errors: Vec<String>,
+}
+
+/// Operation whose failure is kept in a `FailureRecord`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde", rename_all = "snake_case")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub enum FailureKind {
+    /// The account creation of `create_account` or `create_accounts`.
+    AccountCreation,
+    /// The account creation of `create_account_and_claim`.
+    CreateAccountAndClaim,
+    /// The transfer of `claim`.
+    Claim,
+    /// The call of `claim_and_call`.
+    ClaimAndCall,
+    /// The deposit or the transfer of the wrapped tokens of `claim_wrapped`.
+    ClaimWrapped,
+    /// The transfer of `claim_merkle`.
+    MerkleClaim,
+}
+
+/// A failed operation, kept for support. Returned by `get_recent_failures`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct FailureRecord {
+    pub kind: FailureKind,
+    /// The key that was claimed, if any.
+    pub public_key: Option<PublicKey>,
+    /// The account that was created or paid.
+    pub account_id: Option<AccountId>,
+    /// attounc$ refunded to the funder, kept on the key or transferred to the account instead.
+    pub amount: U128,
+    /// Block timestamp in nanoseconds of the failure.
+    pub timestamp: u64,
+}
+
+/// Part of the balance claimed with `create_account_and_claim` to stake in `pool_id` for the new account.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct StakingIntent {
+    pub pool_id: AccountId,
+    pub amount: U128,
+}
+
+/// An account to create with `create_accounts`, funded with `amount`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct CreateAccountRequest {
+    pub new_account_id: AccountId,
+    pub new_public_key: PublicKey,
+    pub amount: U128,
+}
+
+/// Outcome of `create_account`, returned by its `on_account_created` callback.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct CreationResult {
+    /// The account that was to be created.
+    pub account_id: AccountId,
+    /// attounc$ transferred to the new account, or refunded to the caller on failure.
+    pub amount: U128,
+    /// Whether the account was created.
+    pub success: bool,
+}
+
+/// A successful key claim, kept in the claim history for audits. Returned by `get_claim_history`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct ClaimRecord {
+    /// The key whose balance was claimed.
+    pub public_key: PublicKey,
+    /// The account that received the balance.
+    pub account_id: AccountId,
+    /// attounc$ sent to the account.
+    pub amount: U128,
+    /// Block timestamp in nanoseconds of the claim.
+    pub timestamp: u64,
+    /// Block height of the claim.
+    pub block_height: u64,
+    /// Whether the account was created by the claim, with `create_account_and_claim`.
+    pub account_created: bool,
+}
+
+/// What `claim` resolves to once its transfer is done, for the caller to read from the transaction outcome.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct ClaimReceipt {
+    /// The account the balance was sent to.
+    pub account_id: AccountId,
+    /// attounc$ sent to the account, 0 if the transfer failed.
+    pub amount: U128,
+    /// The key that was claimed.
+    pub public_key: PublicKey,
+    /// Block timestamp in nanoseconds of the claim.
+    pub timestamp: u64,
+}
+
+/// What `claim` returns: the receipt of the transfer once it is done, or why the claim was rejected.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub enum ClaimOutcome {
+    /// The balance was transferred, or the transfer failed and the receipt shows 0.
+    Claimed(ClaimReceipt),
+    /// The claim was rejected without paying out.
+    Rejected(ClaimError),
+}
+
+/// What the claims other than `claim` return: the amount claimed once the claim resolves, or why the claim
+/// was rejected.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub enum ClaimAmountOutcome {
+    /// attounc$ claimed, or the tokens for the FT claims. 0 if the claim failed.
+    Claimed(U128),
+    /// The claim was rejected without paying out.
+    Rejected(ClaimError),
+}
+
+/// A claim of a drop as exported by `export_claims`.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct ClaimExportRow {
+    /// The account that received the balance.
+    pub account_id: AccountId,
+    /// attounc$ sent to the account.
+    pub amount: U128,
+    /// Block timestamp in nanoseconds of the claim.
+    pub timestamp: u64,
+    /// Block height of the claim.
+    pub block_height: u64,
+    /// Whether the account was created by the claim.
+    pub account_created: bool,
+}
+
+impl From<&ClaimRecord> for ClaimExportRow {
+    fn from(record: &ClaimRecord) -> Self {
+        Self {
+            account_id: record.account_id.clone(),
+            amount: record.amount,
+            timestamp: record.timestamp,
+            block_height: record.block_height,
+            account_created: record.account_created,
+        }
+    }
+}
+
+/// attounc$ totals of a drop, returned by `get_drop_totals`.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct DropTotals {
+    /// Claimed from the keys of the drop.
+    pub claimed: U128,
+    /// Reclaimed from the unclaimed keys of the drop.
+    pub refunded: U128,
+    /// Left to claim on the keys of the drop.
+    pub outstanding: U128,
+}
+
+/// Self-check of the contract returned by `ping`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct ContractStatus {
+    /// Whether `new` or `new_default` was called.
+    pub initialized: bool,
+    /// Version of the crate the code was built from.
+    pub version: String,
+    pub paused: bool,
+    /// Number of funded keys.
+    pub keys: u64,
+    /// attounc$ owed to the funded keys.
+    pub total_unclaimed: U128,
+}
+
+/// Cumulative counters of the contract activity, only ever incremented. Returned by `get_stats`.
+/// A call whose attounc$ totals would overflow u128::MAX fails.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Default, Clone, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct Stats {
+    /// Keys, redemption codes and Merkle drops funded.
+    pub total_drops_created: u64,
+    /// Keys and redemption codes whose whole balance was claimed.
+    pub total_drops_claimed: u64,
+    /// Expired Merkle drops whose remainder was refunded to the funder, and expired keys reclaimed by the owner.
+    pub total_drops_refunded: u64,
+    /// Accounts successfully created by the contract.
+    pub total_accounts_created: u64,
+    /// attounc$ made claimable by the drops, without the access key allowances.
+    pub total_unc_dropped: U128,
+    /// attounc$ paid out by successful claims.
+    pub total_unc_claimed: U128,
+    /// attounc$ refunded from expired Merkle drops and reclaimed keys.
+    pub total_unc_refunded: U128,
+}
+
+/// Split of the contract balance for reconciliation. Returned by `get_balance_breakdown`.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct BalanceBreakdown {
+    /// attounc$ held by the contract account.
+    pub account_balance: U128,
+    /// attounc$ staked for the storage used by the contract.
+    pub locked: U128,
+    /// attounc$ owed to the funded keys.
+    pub key_liability: U128,
+    /// Estimate of the attounc$ left in the allowances of the funded access keys.
+    pub reserved_allowances: U128,
+    /// attounc$ in the pool `sponsor_key` funds keys from, not owed to any key yet.
+    pub sponsor_pool: U128,
+    /// attounc$ owed outside the keys: the unclaimed amounts and storage reserves of the Merkle drops, the
+    /// balances of the codes, the deposits of the code commitments, the pending creations and the FT
+    /// registration deposits of the creations in flight.
+    pub other_liability: U128,
+}
+
+/// Split of a total deposit sent to new keys with one `send` each. Returned by `preview_batch_split`.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct BatchPreview {
+    /// attounc$ each key is credited: its share of the total less the allowance, the fee and the storage stake.
+    pub per_key_net: U128,
+    /// attounc$ taken by the access key allowances of all the keys.
+    pub allowance_total: U128,
+    /// attounc$ of the total that doesn't split evenly across the keys, left to the sender.
+    pub remainder: U128,
+}
+
+/// Result of `validate_create_account_options` for options that `create_account_advanced` accepts.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct ValidationReport {
+    /// Things that won't make the creation fail but are likely mistakes.
+    pub warnings: Vec<String>,
+    /// Minimum attounc$ to attach to cover the storage of the new account.
+    pub minimum_deposit: U128,
+    /// Estimate of the gas to attach to `create_account_advanced`.
+    pub estimated_gas: Gas,
+}
+
+/// Reason a call is rejected, e.g. options rejected by `create_account_advanced` and `validate_create_account_options`.
+#[derive(FunctionError, Debug, PartialEq)]
+pub enum AirdropError {
+    /// The method can only be called by the owner.
+    NotOwner,
+    /// The method can only be called by the owner or an operator.
+    NotOwnerOrOperator,
+    /// The method must be called with exactly 1 attoUNC attached.
+    OneAttoRequired,
+    InvalidAccountId,
+    AccountBlocked,
+    /// The account id doesn't end with the required suffix, which is included.
+    MissingSuffix(String),
+    NoOptions,
+    /// More keys are added than `max_keys_per_account`, which is included.
+    TooManyKeys(u64),
+    /// The contract bytes are longer than `max_contract_bytes`, which is included.
+    ContractTooLarge(u64),
+    /// The key is added more than once across the full and limited access keys.
+    DuplicateKey(PublicKey),
+    /// The contract bytes don't start with the wasm magic bytes.
+    InvalidContractBytes,
+    /// The method names of a limited access key aren't a comma separated list of method names.
+    InvalidMethodNames(String),
+    /// The method can only be called by the funder of the drop.
+    NotDropFunder,
+    /// The drop of the key is paused by its funder.
+    DropPaused,
+    /// The drop of the key has a start time that isn't reached yet.
+    DropNotStarted,
+    /// The drop of the key has an end time that is reached.
+    DropEnded,
+    /// The result of the named balance operation doesn't fit in a u128.
+    ArithmeticOverflow(String),
+    /// More accounts are created at once than the gas attached allows, which is included.
+    BatchTooLarge(u64),
+    /// More FT contracts are listed than MAX_FT_REGISTRATIONS, which is included.
+    TooManyFtRegistrations(u64),
+    /// The key isn't funded.
+    KeyMissing,
+    /// The options opt into a keyless account but list full access keys.
+    KeylessWithFullAccessKeys,
+}
+
+impl std::fmt::Display for AirdropError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AirdropError::NotOwner => write!(f, "Only the owner can call this method"),
+            AirdropError::NotOwnerOrOperator => write!(f, "Only the owner or an operator can call this method"),
+            AirdropError::OneAttoRequired => write!(f, "Requires attached deposit of exactly 1 attoUNC"),
+            AirdropError::InvalidAccountId => write!(f, "Invalid account id"),
+            AirdropError::AccountBlocked => write!(f, "Account is blocked"),
+            AirdropError::MissingSuffix(suffix) => write!(f, "Account id must end with {}", suffix),
+            AirdropError::NoOptions => write!(f, "Cannot create account with no options. Please specify either contract bytes, full access keys, or limited access keys."),
+            AirdropError::TooManyKeys(max) => write!(f, "More than {} keys can't be added to an account", max),
+            AirdropError::ContractTooLarge(max) => write!(f, "Contract is larger than {} bytes", max),
+            AirdropError::DuplicateKey(key) => write!(f, "Key {} is added more than once", String::from(key)),
+            AirdropError::InvalidContractBytes => write!(f, "Contract bytes are not a wasm module"),
+            AirdropError::InvalidMethodNames(method_names) => write!(f, "Invalid method names: {}", method_names),
+            AirdropError::NotDropFunder => write!(f, "Only the funder of the drop can call this method"),
+            AirdropError::DropPaused => write!(f, "Drop is paused"),
+            AirdropError::DropNotStarted => write!(f, "Drop has not started yet"),
+            AirdropError::DropEnded => write!(f, "Drop has ended"),
+            AirdropError::ArithmeticOverflow(operation) => write!(f, "Arithmetic overflow in {}", operation),
+            AirdropError::BatchTooLarge(max) => write!(f, "More than {} accounts can't be created with the gas attached", max),
+            AirdropError::TooManyFtRegistrations(max) => write!(f, "More than {} FT registrations can't be added to an account", max),
+            AirdropError::KeyMissing => write!(f, "Key is missing"),
+            AirdropError::KeylessWithFullAccessKeys => write!(f, "A keyless account can't have full access keys"),
+        }
+    }
+}
+
+/// A campaign grouping keys funded by the same account, see `create_drop`. Returned by `get_drop`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct DropConfig {
+    /// The account that created the drop and funds its keys.
+    pub funder: AccountId,
+    /// Free-form metadata of the campaign, e.g. a JSON description.
+    pub metadata: Option<String>,
+    /// Block timestamp in nanoseconds from which the keys can be claimed.
+    pub starts_at: Option<u64>,
+    /// Block timestamp in nanoseconds from which the keys can no longer be claimed.
+    pub ends_at: Option<u64>,
+    /// Whether the funder paused the claims of the keys.
+    pub paused: bool,
+    /// Number of keys funded in the drop.
+    pub keys_added: u64,
+    /// Number of keys of the drop whose whole balance was claimed.
+    pub keys_claimed: u64,
+    /// Number of expired keys of the drop reclaimed by the owner.
+    pub keys_reclaimed: u64,
+    /// Number of claims of the keys of the drop, see `export_claims`.
+    pub claims: u64,
+    /// attounc$ claimed from the keys of the drop.
+    pub claimed: UncToken,
+    /// attounc$ reclaimed from the unclaimed keys of the drop.
+    pub refunded: UncToken,
+    /// attounc$ left to claim on the keys of the drop.
+    pub remaining: UncToken,
+    /// Whether the drop is deleted once its last key is removed, refunding its storage and what is left
+    /// of its balance to the funder.
+    pub auto_withdraw: bool,
+    /// Comma separated methods the access keys of the drop can call, `default_claim_methods` if not set.
+    pub allowed_methods: Option<String>,
+    /// Whether the keys of the drop can only be claimed to new accounts, with `create_account_and_claim`.
+    pub new_accounts_only: bool,
+}
+
+impl DropConfig {
+    /// Number of keys of the drop that are still funded.
+    pub fn keys_left(&self) -> u64 {
+        self.keys_added - self.keys_claimed - self.keys_reclaimed
+    }
+
+    /// Returns why the keys of the drop can't be claimed at `timestamp`, if they can't.
+    pub fn claim_error(&self, timestamp: u64) -> Option<AirdropError> {
+        if self.paused {
+            Some(AirdropError::DropPaused)
+        } else if self.starts_at.is_some_and(|starts_at| timestamp < starts_at) {
+            Some(AirdropError::DropNotStarted)
+        } else if self.ends_at.is_some_and(|ends_at| timestamp >= ends_at) {
+            Some(AirdropError::DropEnded)
+        } else {
+            None
+        }
+    }
+}
+
+/// Fungible tokens a key can claim on top of its balance, see `ft_on_transfer`. Returned by `get_key_tokens`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct FtBalance {
+    /// The FT contract of the tokens.
+    pub ft_contract_id: AccountId,
+    /// Amount of tokens, in the smallest unit of the token.
+    pub amount: U128,
+}
+
+/// Schedule of a key whose balance is released in several drips, see `send_drip`.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Drip {
+    /// Maximum attounc$ released by a claim.
+    pub amount: UncToken,
+    /// Minimum time in nanoseconds between two claims.
+    pub interval_ns: u64,
+    /// Block timestamp in nanoseconds of the last claim, if any.
+    pub last_claim_ns: Option<u64>,
+}
+
+impl Drip {
+    /// Block timestamp in nanoseconds from which the next drip can be claimed.
+    pub fn next_claim_ns(&self) -> u64 {
+        self.last_claim_ns.map_or(0, |last_claim_ns| last_claim_ns.saturating_add(self.interval_ns))
+    }
+}
+
+/// Metadata to find the source code of the deployed contract, as defined by NEP-330.
+/// Returned by the `get_source_metadata` view method.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct ContractSourceMetadata {
+    /// Version of the contract crate the code was built from.
+    pub version: Option<String>,
+    /// Link to the source code, e.g. a repository.
+    pub link: Option<String>,
+    /// Standards implemented by the contract.
+    pub standards: Vec<Standard>,
+}
+
+/// A standard implemented by the contract, e.g. `nep330` version `1.1.0`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "unc_sdk::serde")]
+#[cfg_attr(feature = "abi", derive(JsonSchema), schemars(crate = "unc_sdk::schemars"))]
+pub struct Standard {
+    pub standard: String,
+    pub version: String,
 }
-    
\ No newline at end of file
@@ -0,0 +1,350 @@
+//! Pins the JSON clients parse and the borsh layout of the state, so that renaming a field fails a test.
+//! Adding a field means updating its snapshot here.
+
+use super::*;
+
+use unc_sdk::serde::de::DeserializeOwned;
+use unc_sdk::serde::Serialize;
+use unc_sdk::serde_json::{self, json, Value};
+use unc_sdk::test_utils::VMContextBuilder;
+use unc_sdk::{testing_env, CurveType};
+
+/// `ed25519:` followed by the base58 of 32 zero bytes.
+const PUBLIC_KEY: &str = "ed25519:11111111111111111111111111111111";
+
+fn public_key() -> PublicKey {
+    PublicKey::from_parts(CurveType::ED25519, vec![0; 32]).unwrap()
+}
+
+fn account(account_id: &str) -> AccountId {
+    account_id.parse().unwrap()
+}
+
+fn assert_json<T: Serialize>(value: &T, expected: Value) {
+    assert_eq!(serde_json::to_value(value).unwrap(), expected);
+}
+
+/// Asserts that `expected` deserializes and serializes back to itself.
+fn assert_json_round_trip<T: Serialize + DeserializeOwned>(expected: Value) {
+    let value: T = serde_json::from_value(expected.clone()).unwrap();
+    assert_json(&value, expected);
+}
+
+/// Asserts that `value` reads back from its borsh bytes unchanged.
+fn assert_borsh_round_trip<T: BorshSerialize + BorshDeserialize>(value: &T) {
+    let bytes = borsh::to_vec(value).unwrap();
+    let decoded = T::try_from_slice(&bytes).unwrap();
+    assert_eq!(borsh::to_vec(&decoded).unwrap(), bytes);
+}
+
+fn limited_access_key() -> LimitedAccessKey {
+    LimitedAccessKey {
+        public_key: public_key(),
+        allowance: UncToken::from_attounc(100),
+        receiver_id: account("app"),
+        method_names: "claim,vote".to_string(),
+    }
+}
+
+fn create_account_options() -> CreateAccountOptions {
+    CreateAccountOptions {
+        full_access_keys: Some(vec![public_key()]),
+        limited_access_keys: Some(vec![limited_access_key()]),
+        contract_bytes: Some(b"\0asm".to_vec()),
+        ft_registrations: Some(vec![account("token")]),
+        keyless: None,
+    }
+}
+
+fn drop_config() -> DropConfig {
+    DropConfig {
+        funder: account("bob"),
+        metadata: Some("{\"title\":\"launch\"}".to_string()),
+        starts_at: Some(1_000),
+        ends_at: None,
+        paused: false,
+        keys_added: 3,
+        keys_claimed: 1,
+        keys_reclaimed: 0,
+        claims: 2,
+        claimed: UncToken::from_attounc(200),
+        refunded: UncToken::from_attounc(0),
+        remaining: UncToken::from_attounc(300),
+        auto_withdraw: true,
+        allowed_methods: None,
+        new_accounts_only: false,
+    }
+}
+
+#[test]
+fn test_limited_access_key_json() {
+    assert_json_round_trip::<LimitedAccessKey>(json!({
+        "public_key": PUBLIC_KEY,
+        "allowance": "100",
+        "receiver_id": "app",
+        "method_names": "claim,vote",
+    }));
+}
+
+#[test]
+fn test_create_account_options_json() {
+    // The contract bytes are a JSON array of bytes, there is no base64 form
+    let expected = json!({
+        "full_access_keys": [PUBLIC_KEY],
+        "limited_access_keys": [{
+            "public_key": PUBLIC_KEY,
+            "allowance": "100",
+            "receiver_id": "app",
+            "method_names": "claim,vote",
+        }],
+        "contract_bytes": [0, 97, 115, 109],
+        "ft_registrations": ["token"],
+        "keyless": null,
+    });
+    assert_json(&create_account_options(), expected.clone());
+    assert_json_round_trip::<CreateAccountOptions>(expected);
+}
+
+#[test]
+fn test_create_account_options_old_json() {
+    // Options sent before `ft_registrations` existed, or with fields left out, still parse
+    let options: CreateAccountOptions = serde_json::from_value(json!({
+        "full_access_keys": [PUBLIC_KEY],
+        "limited_access_keys": null,
+        "contract_bytes": null,
+    }))
+    .unwrap();
+    assert_eq!(options.full_access_keys, Some(vec![public_key()]));
+    assert!(options.ft_registrations.is_none());
+    assert!(options.keyless.is_none());
+    let options: CreateAccountOptions = serde_json::from_value(json!({})).unwrap();
+    assert!(options.full_access_keys.is_none());
+}
+
+#[test]
+fn test_key_info_json() {
+    let info = KeyInfo {
+        balance: U128(1_000),
+        allowance_remaining: U128(200),
+        tag: Some("gold".to_string()),
+        failed_attempts: 1,
+        remaining_attempts: 1,
+        max_per_claim: None,
+        expiry_ns: Some(5_000),
+        disabled: false,
+        created_at_ns: 1_000,
+        claimable: true,
+        status: KeyStatus::Available,
+        new_accounts_only: true,
+    };
+    assert_json(
+        &info,
+        json!({
+            "balance": "1000",
+            "allowance_remaining": "200",
+            "tag": "gold",
+            "failed_attempts": 1,
+            "remaining_attempts": 1,
+            "max_per_claim": null,
+            "expiry_ns": 5000,
+            "disabled": false,
+            "created_at_ns": 1000,
+            "claimable": true,
+            "status": "available",
+            "new_accounts_only": true,
+        }),
+    );
+}
+
+#[test]
+fn test_config_json() {
+    assert_json_round_trip::<Config>(json!({
+        "ed25519_allowance": "100",
+        "secp256k1_allowance": null,
+        "max_claim_attempts": 3,
+        "min_drop_amount": null,
+        "fee_bps": 50,
+        "claim_cooldown_ns": null,
+        "default_expiry_ns": 1000,
+        "timelock_delay_ns": null,
+    }));
+    // Every field is optional
+    assert_eq!(serde_json::from_value::<Config>(json!({})).unwrap(), Config::default());
+}
+
+#[test]
+fn test_claim_outcome_json() {
+    assert_json(
+        &ClaimOutcome::Claimed(ClaimReceipt {
+            account_id: account("bob"),
+            amount: U128(100),
+            public_key: public_key(),
+            timestamp: 1000,
+        }),
+        json!({
+            "Claimed": { "account_id": "bob", "amount": "100", "public_key": PUBLIC_KEY, "timestamp": 1000 },
+        }),
+    );
+    assert_json(&ClaimOutcome::Rejected(ClaimError::RateLimited), json!({ "Rejected": "RateLimited" }));
+}
+
+#[test]
+fn test_claim_amount_outcome_json() {
+    assert_json(&ClaimAmountOutcome::Claimed(U128(100)), json!({ "Claimed": "100" }));
+    assert_json(&ClaimAmountOutcome::Rejected(ClaimError::ClaimInProgress), json!({ "Rejected": "ClaimInProgress" }));
+}
+
+#[test]
+fn test_over_deposit_policy_json() {
+    assert_json_round_trip::<OverDepositPolicy>(json!("credit_all"));
+    assert_json_round_trip::<OverDepositPolicy>(json!({ "refund_excess": "1000" }));
+}
+
+#[test]
+fn test_drop_config_json() {
+    assert_json(
+        &drop_config(),
+        json!({
+            "funder": "bob",
+            "metadata": "{\"title\":\"launch\"}",
+            "starts_at": 1000,
+            "ends_at": null,
+            "paused": false,
+            "keys_added": 3,
+            "keys_claimed": 1,
+            "keys_reclaimed": 0,
+            "claims": 2,
+            "claimed": "200",
+            "refunded": "0",
+            "remaining": "300",
+            "auto_withdraw": true,
+            "allowed_methods": null,
+            "new_accounts_only": false,
+        }),
+    );
+}
+
+/// Name of each event in the logs. The match fails to compile when an event is added, to add its snapshot.
+fn event_name(event: &AirDropEvent) -> &'static str {
+    match event {
+        AirDropEvent::AccountBlocked { .. } => "account_blocked",
+        AirDropEvent::AccountUnblocked { .. } => "account_unblocked",
+        AirDropEvent::AutoPaused { .. } => "auto_paused",
+        AirDropEvent::DropOwnershipTransferred { .. } => "drop_ownership_transferred",
+        AirDropEvent::DropCompleted { .. } => "drop_completed",
+        AirDropEvent::DropToppedUp { .. } => "drop_topped_up",
+        AirDropEvent::FtRegistrationFailed { .. } => "ft_registration_failed",
+        AirDropEvent::OperationFailed { .. } => "operation_failed",
+        AirDropEvent::OwnerProposed { .. } => "owner_proposed",
+        AirDropEvent::OwnershipAccepted { .. } => "ownership_accepted",
+        AirDropEvent::OwnerProposalCancelled { .. } => "owner_proposal_cancelled",
+        AirDropEvent::Reclaimed { .. } => "reclaimed",
+        AirDropEvent::TokensStranded { .. } => "tokens_stranded",
+    }
+}
+
+#[test]
+fn test_event_json() {
+    let bob = account("bob");
+    let carol = account("carol");
+    let public_key = public_key();
+    let events = [
+        (AirDropEvent::AccountBlocked { account_id: &bob }, json!({ "account_id": "bob" })),
+        (AirDropEvent::AccountUnblocked { account_id: &bob }, json!({ "account_id": "bob" })),
+        (AirDropEvent::AutoPaused { window: 3, claims: 5 }, json!({ "window": 3, "claims": 5 })),
+        (
+            AirDropEvent::DropOwnershipTransferred { drop_id: 0, old_funder: &bob, new_funder: &carol },
+            json!({ "drop_id": 0, "old_funder": "bob", "new_funder": "carol" }),
+        ),
+        (
+            AirDropEvent::DropCompleted { drop_id: 0, funder: &bob, refunded: U128(10) },
+            json!({ "drop_id": 0, "funder": "bob", "refunded": "10" }),
+        ),
+        (
+            AirDropEvent::DropToppedUp { public_key: &public_key, old_balance: U128(1), new_balance: U128(2) },
+            json!({ "public_key": PUBLIC_KEY, "old_balance": "1", "new_balance": "2" }),
+        ),
+        (
+            AirDropEvent::FtRegistrationFailed { account_id: &bob, ft_contract_id: &carol },
+            json!({ "account_id": "bob", "ft_contract_id": "carol" }),
+        ),
+        (
+            AirDropEvent::OperationFailed {
+                kind: FailureKind::CreateAccountAndClaim,
+                public_key: Some(&public_key),
+                account_id: None,
+                amount: U128(7),
+            },
+            json!({ "kind": "create_account_and_claim", "public_key": PUBLIC_KEY, "account_id": null, "amount": "7" }),
+        ),
+        (
+            AirDropEvent::OwnerProposed { owner_id: &bob, new_owner: &carol },
+            json!({ "owner_id": "bob", "new_owner": "carol" }),
+        ),
+        (
+            AirDropEvent::OwnershipAccepted { old_owner: &bob, new_owner: &carol },
+            json!({ "old_owner": "bob", "new_owner": "carol" }),
+        ),
+        (AirDropEvent::OwnerProposalCancelled { new_owner: &carol }, json!({ "new_owner": "carol" })),
+        (
+            AirDropEvent::Reclaimed { public_key: &public_key, to: &bob, amount: U128(5) },
+            json!({ "public_key": PUBLIC_KEY, "to": "bob", "amount": "5" }),
+        ),
+        (
+            AirDropEvent::TokensStranded { ft_contract_id: &carol, amount: U128(3) },
+            json!({ "ft_contract_id": "carol", "amount": "3" }),
+        ),
+    ];
+    for (event, data) in &events {
+        assert_json(event, json!({ "event": event_name(event), "data": data }));
+    }
+}
+
+#[test]
+fn test_state_borsh_round_trip() {
+    assert_borsh_round_trip(&Reservation { account_id: account("bob"), funder: account("carol") });
+    assert_borsh_round_trip(&PendingCreation {
+        funder: account("bob"),
+        amount: UncToken::from_attounc(100),
+        options: create_account_options(),
+        retries: 1,
+        storage_cost: UncToken::from_attounc(10),
+    });
+    assert_borsh_round_trip(&MerkleDrop {
+        funder: account("bob"),
+        root: [7; 32],
+        total: UncToken::from_attounc(100),
+        claimed: UncToken::from_attounc(10),
+        expiration: Some(1_000),
+        storage_reserve: UncToken::from_attounc(5),
+    });
+    assert_borsh_round_trip(&PendingSweep { to: account("bob"), proposed_at: 1_000 });
+    assert_borsh_round_trip(&ScheduledAction {
+        id: 1,
+        action: OwnerAction::SetTimelockDelay { timelock_delay_ns: 60 },
+        eta: 1_000,
+    });
+    assert_borsh_round_trip(&OwnerAction::StageUpgrade { code_hash: [1; 32].into() });
+    assert_borsh_round_trip(&FailureRecord {
+        kind: FailureKind::ClaimAndCall,
+        public_key: Some(public_key()),
+        account_id: None,
+        amount: U128(5),
+        timestamp: 1_000,
+    });
+    assert_borsh_round_trip(&ClaimRecord {
+        public_key: public_key(),
+        account_id: account("bob"),
+        amount: U128(100),
+        timestamp: 1_000,
+        block_height: 10,
+        account_created: true,
+    });
+    assert_borsh_round_trip(&Stats { total_drops_created: 2, total_unc_dropped: U128(100), ..Stats::default() });
+    assert_borsh_round_trip(&drop_config());
+    assert_borsh_round_trip(&FtBalance { ft_contract_id: account("token"), amount: U128(100) });
+    assert_borsh_round_trip(&Drip { amount: UncToken::from_attounc(10), interval_ns: 60, last_claim_ns: Some(1_000) });
+
+    testing_env!(VMContextBuilder::new().current_account_id(account("airdrop")).context.clone());
+    assert_borsh_round_trip(&AirDrop::new(account("bob"), Some(Config { fee_bps: Some(10), ..Config::default() })));
+}
@@ -0,0 +1,13 @@
+use unc_sdk::json_types::U128;
+use unc_sdk::{ext_contract, Gas};
+
+/// Gas attached to `deposit_and_stake`.
+pub const DEPOSIT_AND_STAKE_GAS: Gas = Gas::from_tgas(50);
+
+/// Liquid staking pool interface. The standard `deposit_and_stake` only credits the caller, so the contract
+/// stakes for itself and transfers the minted shares, which the pool issues as a NEP-141 token.
+#[ext_contract(ext_staking_pool)]
+pub trait StakingPool {
+    /// Stakes the attached deposit for the caller. Returns the shares minted for it.
+    fn deposit_and_stake(&mut self) -> U128;
+}
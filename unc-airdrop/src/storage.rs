@@ -0,0 +1,54 @@
+use unc_sdk::AccountId;
+
+/// Bytes counted for every record in storage on top of its key and value.
+pub const RECORD_OVERHEAD_BYTES: u64 = 40;
+
+/// Bytes of contract storage taken by a key funded with `send`: its balance in `key_balances`
+/// (89 bytes), its allowance estimate in `allowances` (94 bytes), its storage stake in
+/// `storage_stakes` (89 bytes) and its funding time in `funded_at` (81 bytes).
+pub const KEY_STORAGE_BYTES: u64 = 353;
+
+/// Bytes of contract storage taken by the tag of a key in `key_tags`, on top of the tag itself,
+/// and by the key in the index of the tag in `keys_by_tag` (37 bytes).
+pub const TAG_STORAGE_BYTES: u64 = 201;
+
+/// Bytes of contract storage taken by a commitment in `code_commitments`: the prefix and the hash (33 bytes),
+/// the committer (at most 68 bytes) and the block height and deposit (24 bytes), on top of the record overhead.
+pub const CODE_COMMITMENT_STORAGE_BYTES: u64 = RECORD_OVERHEAD_BYTES + 33 + 68 + 24;
+
+/// Bytes of contract storage taken by a word of the claim bitmap of a Merkle drop in `merkle_claimed`: the prefix
+/// and the drop id and word index (17 bytes) and the word (8 bytes), on top of the record overhead.
+pub const MERKLE_WORD_STORAGE_BYTES: u64 = RECORD_OVERHEAD_BYTES + 17 + 8;
+
+/// Bytes of an ED25519 public key: the curve type and the key data.
+pub const ED25519_PUBLIC_KEY_BYTES: u64 = 33;
+
+/// Bytes of an account record, without its keys and code.
+pub const ACCOUNT_STORAGE_BYTES: u64 = 100;
+
+/// Bytes of a full access key on top of its public key: the record overhead, the nonce
+/// and the permission.
+pub const FULL_ACCESS_KEY_STORAGE_BYTES: u64 = RECORD_OVERHEAD_BYTES + 8 + 1;
+
+/// Bytes of a function call access key on top of its public key, receiver id and method names:
+/// those of a full access key plus the allowance and the lengths of the receiver id and method list.
+pub const FUNCTION_CALL_ACCESS_KEY_STORAGE_BYTES: u64 = FULL_ACCESS_KEY_STORAGE_BYTES + 17 + 4 + 4;
+
+/// Bytes of storage taken by an access key added to an account, given the length of its public key.
+/// `function_call` is the receiver id and the comma separated method names of a function call key.
+pub fn access_key_storage_bytes(public_key_bytes: u64, function_call: Option<(&AccountId, &str)>) -> u64 {
+    match function_call {
+        None => FULL_ACCESS_KEY_STORAGE_BYTES + public_key_bytes,
+        Some((receiver_id, method_names)) => {
+            let method_names_bytes: u64 = method_names
+                .split(',')
+                .filter(|method_name| !method_name.is_empty())
+                .map(|method_name| 4 + method_name.len() as u64)
+                .sum();
+            FUNCTION_CALL_ACCESS_KEY_STORAGE_BYTES
+                + public_key_bytes
+                + receiver_id.len() as u64
+                + method_names_bytes
+        }
+    }
+}